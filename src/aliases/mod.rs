@@ -0,0 +1,187 @@
+use fs_err as fs;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A tsconfig `paths` entry of the `prefix/*` -> `dir/*` form.
+#[derive(Debug, Clone)]
+pub struct Alias {
+    /// Specifier prefix, e.g. `@/`.
+    pub prefix: String,
+    /// Root-relative directory it maps to, e.g. `src/`.
+    pub target: String,
+}
+
+/// The project's import alias convention, from tsconfig.json (or
+/// jsconfig.json) `compilerOptions.paths`.
+#[derive(Debug, Clone)]
+pub struct PathAliases {
+    /// Most specific target first.
+    pub aliases: Vec<Alias>,
+    /// Where a stray `@/` points when the project defines no aliases:
+    /// `src/` if there is one, else the root.
+    fallback: String,
+}
+
+/// `//` and `/* */` comments outside strings, as tsconfig allows.
+fn strip_comments(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_str = false;
+    let mut escaped = false;
+    while let Some(c) = chars.next() {
+        if in_str {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_str = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_str = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `dir` joined with `rel`, `.`/`..` folded away, `/`-separated.
+fn join(dir: &str, rel: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+    for seg in rel.split('/') {
+        match seg {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            s => parts.push(s),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map(|(d, _)| d).unwrap_or("")
+}
+
+/// Relative specifier from directory `from` to root-relative `to`.
+fn relative(from: &str, to: &str) -> String {
+    let a: Vec<&str> = from.split('/').filter(|p| !p.is_empty()).collect();
+    let b: Vec<&str> = to.split('/').filter(|p| !p.is_empty()).collect();
+    let common = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let ups = "../".repeat(a.len() - common);
+    let rest = b[common..].join("/");
+    if ups.is_empty() {
+        format!("./{}", rest)
+    } else {
+        format!("{}{}", ups, rest)
+    }
+}
+
+fn import_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"((?:from\s+|import\s*\(?\s*|require\(\s*))(['"])([^'"]+)(['"])"#).unwrap())
+}
+
+impl PathAliases {
+    /// Aliases of the project at `root`; `None` without a tsconfig/jsconfig.
+    pub fn load(root: &Path) -> Option<Self> {
+        let text = ["tsconfig.json", "jsconfig.json"].iter().find_map(|f| fs::read_to_string(root.join(f)).ok())?;
+        let v: Value = serde_json::from_str(&crate::provider::jsonfix::repair(&strip_comments(&text))).ok()?;
+        let opts = v.get("compilerOptions");
+        let base = opts.and_then(|o| o.get("baseUrl")).and_then(Value::as_str).unwrap_or(".");
+        let mut aliases: Vec<Alias> = opts
+            .and_then(|o| o.get("paths"))
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, targets)| {
+                let prefix = key.strip_suffix('*')?;
+                let target = targets.as_array()?.first()?.as_str()?.strip_suffix('*')?;
+                let target = join(base, target);
+                let target = if target.is_empty() { target } else { format!("{}/", target) };
+                Some(Alias { prefix: prefix.to_string(), target })
+            })
+            .collect();
+        aliases.sort_by_key(|a| std::cmp::Reverse(a.target.len()));
+        let fallback = if root.join("src").is_dir() { "src/" } else { "" }.to_string();
+        Some(Self { aliases, fallback })
+    }
+
+    /// The convention, phrased for the CODEGEN prompt.
+    pub fn prompt_block(&self) -> String {
+        if self.aliases.is_empty() {
+            return "Import paths: tsconfig defines no path aliases; import project modules with relative paths (`../components/NavBar`), never `@/`.".into();
+        }
+        let map: Vec<String> = self.aliases.iter().map(|a| format!("`{}*` -> `{}*`", a.prefix, a.target)).collect();
+        // the broadest alias (usually `@/`) makes the clearest example
+        let a = &self.aliases[self.aliases.len() - 1];
+        format!(
+            "Import paths: tsconfig maps {}. Import project modules through these aliases (`{}components/NavBar`) instead of `../` relative paths; `./` imports of siblings are fine.",
+            map.join(", "),
+            a.prefix
+        )
+    }
+
+    /// The specifier the convention wants for `spec` imported from `path`,
+    /// if it differs.
+    fn preferred(&self, path: &str, spec: &str) -> Option<String> {
+        let dir = parent(path);
+        if self.aliases.is_empty() {
+            let rest = spec.strip_prefix("@/")?;
+            return Some(relative(dir, &format!("{}{}", self.fallback, rest)));
+        }
+        if !spec.starts_with("../") {
+            return None;
+        }
+        let module = join(dir, spec);
+        self.aliases
+            .iter()
+            .find_map(|a| module.strip_prefix(a.target.as_str()).map(|rest| format!("{}{}", a.prefix, rest)))
+    }
+
+    /// Rewrite the import specifiers in `content` (the file at root-relative
+    /// `path`) to the project's style. Returns the new content and each
+    /// `old -> new` rewrite, or `None` when nothing changed.
+    pub fn normalize(&self, path: &str, content: &str) -> Option<(String, Vec<String>)> {
+        let path = crate::safety::normalize_rel(path);
+        let mut changed = Vec::new();
+        let out = import_re().replace_all(content, |c: &Captures| match self.preferred(&path, &c[3]) {
+            Some(new) => {
+                changed.push(format!("`{}` -> `{}`", &c[3], new));
+                format!("{}{}{}{}", &c[1], &c[2], new, &c[4])
+            }
+            None => c[0].to_string(),
+        });
+        if changed.is_empty() {
+            None
+        } else {
+            Some((out.into_owned(), changed))
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// `text` as a one-line comment in the file type's comment syntax; `None` for
+/// formats without comments (JSON) or unknown extensions.
+pub fn comment(path: &str, text: &str) -> Option<String> {
+    let ext = path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase())?;
+    let (open, close) = match ext.as_str() {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "scss" => ("//", ""),
+        "css" => ("/*", " */"),
+        "md" | "mdx" | "html" => ("<!--", " -->"),
+        "yml" | "yaml" | "sh" | "toml" => ("#", ""),
+        _ => return None,
+    };
+    Some(if text.is_empty() && close.is_empty() { open.to_string() } else { format!("{} {}{}", open, text, close) })
+}
+
+/// Provenance comment for a created file, in the file type's comment syntax.
+pub fn marker(path: &str, tx: Uuid, step_id: &str) -> Option<String> {
+    comment(path, &format!("vibe: tx {} step {}", tx, step_id))
+}
+
+/// `content` with the provenance comment appended as its last line.
+pub fn annotate(path: &str, content: &str, tx: Uuid, step_id: &str) -> String {
+    match marker(path, tx, step_id) {
+        Some(m) => format!("{}\n{}\n", content.trim_end_matches('\n'), m),
+        None => content.to_string(),
+    }
+}
+
+/// The (tx, step id) from the last provenance comment in `content`.
+pub fn find(content: &str) -> Option<(Uuid, String)> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"vibe: tx ([0-9a-fA-F-]{36}) step ([\w.\-]+)").unwrap());
+    let caps = re.captures_iter(content).last()?;
+    let tx = Uuid::parse_str(&caps[1]).ok()?;
+    Some((tx, caps[2].to_string()))
+}
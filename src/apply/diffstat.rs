@@ -0,0 +1,76 @@
+use difflib::sequencematcher::SequenceMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Lines one applied step added to and removed from a file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl FileChange {
+    /// `old`/`new` of `None` mean the file didn't exist before/after.
+    pub fn between(path: &str, old: Option<&str>, new: Option<&str>) -> Self {
+        let a: Vec<&str> = old.map(|s| s.lines().collect()).unwrap_or_default();
+        let b: Vec<&str> = new.map(|s| s.lines().collect()).unwrap_or_default();
+        let (mut added, mut removed) = (0, 0);
+        for op in SequenceMatcher::new(&a, &b).get_opcodes() {
+            if op.tag != "equal" {
+                removed += op.first_end - op.first_start;
+                added += op.second_end - op.second_start;
+            }
+        }
+        Self { path: crate::safety::normalize_rel(path), added, removed }
+    }
+}
+
+/// Diffstat of one route or feature directory.
+#[derive(Debug, Clone, Default)]
+pub struct Area {
+    pub name: String,
+    pub files: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// The route a file under the App Router dir belongs to (`src/app/settings/
+/// profile/page.tsx` -> `/settings`, route groups skipped), else its
+/// top-level feature directory (`src/components/`, `lib/`); root files are
+/// `(root)`.
+pub fn area_of(path: &str) -> String {
+    let path = crate::safety::normalize_rel(path);
+    let rest = path.strip_prefix("src/").unwrap_or(&path);
+    if let Some(app) = rest.strip_prefix("app/") {
+        let dirs = app.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+        let is_group = |s: &str| s.starts_with('(') && s.ends_with(')');
+        let seg = dirs.split('/').find(|s| !s.is_empty() && !is_group(s));
+        return format!("/{}", seg.unwrap_or(""));
+    }
+    match rest.split_once('/') {
+        Some((dir, _)) if path.starts_with("src/") => format!("src/{}/", dir),
+        Some((dir, _)) => format!("{}/", dir),
+        None if path.starts_with("src/") => "src/".to_string(),
+        None => "(root)".to_string(),
+    }
+}
+
+/// `changes` summed per area, most lines changed first. A file changed by
+/// several steps counts once.
+pub fn by_area(changes: &[FileChange]) -> Vec<Area> {
+    let mut areas: BTreeMap<String, (Area, Vec<&str>)> = BTreeMap::new();
+    for c in changes {
+        let name = area_of(&c.path);
+        let (area, paths) = areas.entry(name.clone()).or_insert_with(|| (Area { name, ..Default::default() }, vec![]));
+        if !paths.contains(&c.path.as_str()) {
+            paths.push(&c.path);
+            area.files += 1;
+        }
+        area.added += c.added;
+        area.removed += c.removed;
+    }
+    let mut out: Vec<Area> = areas.into_values().map(|(a, _)| a).collect();
+    out.sort_by_key(|a| std::cmp::Reverse(a.added + a.removed));
+    out
+}
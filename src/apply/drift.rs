@@ -0,0 +1,89 @@
+use anyhow::Result;
+use fs_err as fs;
+use std::path::Path;
+
+use crate::backup::content_hash;
+use crate::safety::normalize_rel;
+use crate::wire::{FileBlob, Step};
+
+/// A file a step writes that changed on disk after it was snapshotted for
+/// codegen, i.e. while the model worked or the plan was reviewed.
+#[derive(Debug, Clone)]
+pub struct Drift {
+    pub path: String,
+    /// Gone from disk since the snapshot.
+    pub deleted: bool,
+    /// Snapshotted content, the merge base; `None` when it was truncated.
+    pub base: Option<String>,
+}
+
+/// Files the file steps of `steps` target whose current hash differs from
+/// the one recorded in `snapshot`. Files not in the snapshot (or without a
+/// hash) can't be checked and are left out.
+pub fn changed_since(root: &Path, steps: &[Step], snapshot: &[FileBlob]) -> Vec<Drift> {
+    let mut out: Vec<Drift> = Vec::new();
+    for step in steps {
+        let (Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. }) = step else { continue };
+        let path = normalize_rel(path);
+        if out.iter().any(|d| d.path == path) {
+            continue;
+        }
+        let Some(blob) = snapshot.iter().find(|b| normalize_rel(&b.path) == path) else { continue };
+        let Some(hash) = &blob.hash else { continue };
+        let now = fs::read(root.join(&path)).ok();
+        if now.as_ref().map(content_hash).as_ref() == Some(hash) {
+            continue;
+        }
+        out.push(Drift { path, deleted: now.is_none(), base: (!blob.truncated).then(|| blob.content.clone()) });
+    }
+    out
+}
+
+/// Three-way merge the generated content of the update steps for `drifted`
+/// (ours) with the files as they are now (theirs), from the snapshot as the
+/// base, and put the result in the steps. Steps are only changed when every
+/// file merges cleanly; otherwise returns the files that didn't, with why.
+pub fn remerge(root: &Path, steps: &mut [Step], drifted: &[Drift]) -> Result<Vec<(String, &'static str)>> {
+    let mut merged = Vec::new();
+    let mut failed = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let (Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. }) = step else { continue };
+        let Some(d) = drifted.iter().find(|d| d.path == normalize_rel(path)) else { continue };
+        let reason = match (step, &d.base) {
+            (_, _) if d.deleted => "deleted since the snapshot",
+            (Step::Update { content: Some(ours), .. }, Some(base)) => {
+                let theirs = fs::read_to_string(root.join(&d.path))?;
+                match crate::git::merge_file(ours, base, &theirs, ["generated", "snapshot", "yours"])? {
+                    (text, true) => {
+                        merged.push((i, text));
+                        continue;
+                    }
+                    (_, false) => "conflicting edits",
+                }
+            }
+            (Step::Update { content: Some(_), .. }, None) => "snapshot was truncated, no merge base",
+            (Step::Update { .. }, _) => "step is a patch, not full content",
+            (Step::Create { .. }, _) => "create step over an edited file",
+            _ => "delete step over an edited file",
+        };
+        failed.push((d.path.clone(), reason));
+    }
+    if failed.is_empty() {
+        for (i, text) in merged {
+            if let Step::Update { content, .. } = &mut steps[i] {
+                *content = Some(text);
+            }
+        }
+    }
+    Ok(failed)
+}
+
+/// What to do about files that changed since the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Merge the edits into the generated content (`remerge`).
+    Remerge,
+    /// Write the generated content over them.
+    Overwrite,
+    Abort,
+}
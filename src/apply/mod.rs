@@ -1,15 +1,22 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use fs_err as fs;
+use serde::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
+use crate::backup::Backup;
 use crate::config::Config;
 use crate::exec::{run_command_allowlisted, CmdResult};
 use crate::merge;
 use crate::wire::Step;
 
-#[derive(Debug, Clone)]
+pub mod diffstat;
+pub mod drift;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ApplySummary {
     pub created: usize,
     pub updated: usize,
@@ -17,8 +24,14 @@ pub struct ApplySummary {
     pub commands: usize,
     pub tests: usize,
     pub skipped: usize,
+    /// One entry per skipped step, so automation can tell the causes apart.
+    pub skipped_steps: Vec<SkippedStep>,
     pub bytes: usize,
     pub command_outputs: Vec<CmdResult>,
+    /// TODO/FIXME markers the generated code introduced.
+    pub follow_ups: Vec<crate::followups::FollowUp>,
+    /// Line counts of every file write/delete, for the per-area diffstat.
+    pub changes: Vec<diffstat::FileChange>,
 }
 
 impl Default for ApplySummary {
@@ -30,39 +43,213 @@ impl Default for ApplySummary {
             commands: 0,
             tests: 0,
             skipped: 0,
+            skipped_steps: vec![],
             bytes: 0,
             command_outputs: vec![],
+            follow_ups: vec![],
+            changes: vec![],
+        }
+    }
+}
+
+/// Why apply left a step alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// Update step with neither `content` nor `patch`.
+    MissingContent,
+    /// Patch-only update; patches are previewed but not applied.
+    PatchOnly,
+    /// Delete of a file that does not exist.
+    DeleteTargetMissing,
+    /// Deletes, commands and tests are blocked in untrusted workspaces.
+    Untrusted,
+    /// Test command not in `command_allowlist`.
+    NotAllowlisted,
+    /// Non-idempotent command that already succeeded earlier in this apply.
+    NonIdempotentRerun,
+}
+
+impl SkipReason {
+    pub fn describe(self) -> &'static str {
+        match self {
+            SkipReason::MissingContent => "no content or patch",
+            SkipReason::PatchOnly => "patch-only update not applied",
+            SkipReason::DeleteTargetMissing => "delete target does not exist",
+            SkipReason::Untrusted => "workspace not trusted",
+            SkipReason::NotAllowlisted => "command not allowlisted",
+            SkipReason::NonIdempotentRerun => "non-idempotent command already ran",
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedStep {
+    pub id: String,
+    pub target: String,
+    pub reason: SkipReason,
+}
+
+impl ApplySummary {
+    fn skip(&mut self, step: &Step, reason: SkipReason) {
+        self.skipped += 1;
+        self.skipped_steps.push(SkippedStep {
+            id: step.id().to_string(),
+            target: step.target().to_string(),
+            reason,
+        });
+    }
+
+    fn change(&mut self, path: &str, old: Option<&str>, new: Option<&str>) {
+        self.changes.push(diffstat::FileChange::between(path, old, new));
+    }
+}
+
+/// Run `steps` in order. With `transactional_apply`, a step failing rolls
+/// back every file written before it (see `rollback`) and the error says so,
+/// naming any commands that ran: their effects beyond package.json and the
+/// lockfiles (node_modules, generated files) are not undone.
 pub fn apply_steps(
     root: &Path,
     steps: &[Step],
     dry_run: bool,
     cfg: &Config,
     task: &str,
+    tx: Uuid,
+    after_step: impl FnMut(&Step, &[diffstat::FileChange]) -> Result<()>,
 ) -> Result<ApplySummary> {
-    let mut summary = ApplySummary::default();
+    apply_journaled(root, steps, dry_run, cfg, task, Journal::new(root, tx, Vec::new()), after_step)
+}
+
+/// `apply_steps` for `vibe tx resume`: `succeeded` are the commands an
+/// earlier, failed apply of the same steps already ran (its `commands.json`);
+/// non-idempotent ones among them are skipped this time.
+pub fn resume_steps(root: &Path, steps: &[Step], cfg: &Config, task: &str, tx: Uuid, succeeded: Vec<String>) -> Result<ApplySummary> {
+    apply_journaled(root, steps, false, cfg, task, Journal::new(root, tx, succeeded), |_, _| Ok(()))
+}
+
+fn apply_journaled(
+    root: &Path,
+    steps: &[Step],
+    dry_run: bool,
+    cfg: &Config,
+    task: &str,
+    mut journal: Journal,
+    mut after_step: impl FnMut(&Step, &[diffstat::FileChange]) -> Result<()>,
+) -> Result<ApplySummary> {
+    let tx = journal.backup.tx();
+    let applied = run_steps(root, steps, dry_run, cfg, task, &mut journal, &mut after_step);
+    // what a resume must not run again
+    let applied = match applied {
+        Err(e) if !dry_run && !journal.succeeded.is_empty() => {
+            crate::log::save_artifact("commands.json", &journal.succeeded, tx, cfg)?;
+            Err(e.context(format!("`vibe tx resume {}` re-runs the steps without repeating the commands that succeeded", tx)))
+        }
+        other => other,
+    };
+    match applied {
+        Err(e) if cfg.transactional_apply && !dry_run => match rollback(root, tx, &mut journal.backup, cfg) {
+            Ok(n) if journal.commands.is_empty() => Err(e.context(format!("transaction aborted, no changes: restored {} file(s) written before the failure", n))),
+            Ok(n) => Err(e.context(format!(
+                "transaction aborted: restored {} file(s) written before the failure, but these commands ran and anything else they changed is left as is: {}",
+                n,
+                journal.commands.iter().map(|c| format!("`{}`", c)).collect::<Vec<_>>().join(", ")
+            ))),
+            Err(r) => Err(e.context(format!("transaction aborted, and rolling back failed ({:#}); `vibe undo {}` can retry", r, tx))),
+        },
+        other => other,
+    }
+}
+
+/// What an apply has done so far, for rolling it back or resuming it.
+struct Journal {
+    /// Pre-apply copies of every touched file, for `vibe restore` and rollback.
+    backup: Backup,
+    /// Commands started; a rollback can't undo what they did.
+    commands: Vec<String>,
+    /// Commands that exited 0, including a resumed apply's earlier ones;
+    /// non-idempotent ones are not run again.
+    succeeded: Vec<String>,
+}
+
+impl Journal {
+    fn new(root: &Path, tx: Uuid, succeeded: Vec<String>) -> Self {
+        Self { backup: Backup::new(root, tx), commands: Vec::new(), succeeded }
+    }
+}
+
+/// Put every file `backup` recorded back as it was before the transaction
+/// (deleting the ones it created), byte for byte, newest first. Returns how
+/// many.
+fn rollback(root: &Path, tx: Uuid, backup: &mut Backup, cfg: &Config) -> Result<usize> {
+    let entries: Vec<String> = backup.entries().iter().rev().map(|e| e.path.clone()).collect();
+    for rel in &entries {
+        let original = crate::backup::original_bytes(root, tx, rel)?;
+        restore_file(root, rel, original.as_deref(), cfg)?;
+        // so `vibe undo` sees nothing left to revert
+        backup.written_bytes(rel, original.as_deref())?;
+    }
+    Ok(entries.len())
+}
+
+fn run_steps(
+    root: &Path,
+    steps: &[Step],
+    dry_run: bool,
+    cfg: &Config,
+    task: &str,
+    journal: &mut Journal,
+    after_step: &mut impl FnMut(&Step, &[diffstat::FileChange]) -> Result<()>,
+) -> Result<ApplySummary> {
+    let tx = journal.backup.tx();
+    let mut summary = ApplySummary { follow_ups: crate::followups::collect(root, steps), ..Default::default() };
+    let package_manager = crate::exec::policy::detect_package_manager(root);
+    // `after_step` runs for a step once the next begins (steps `continue`
+    // early), with the file changes it made
+    let mut done: Option<(&Step, usize)> = None;
 
     for step in steps {
+        if let Some((prev, from)) = done.take() {
+            after_step(prev, &summary.changes[from..])?;
+        }
+        done = Some((step, summary.changes.len()));
         match step {
             Step::Create {
+                id,
                 path,
                 content,
                 ..
             } => {
-                let abs = safe_join(root, path, &cfg.path_allowlist)
+                let abs = safe_join(root, path, &cfg.path_allowlist, &cfg.symlink_allowlist)
                     .with_context(|| format!("create path rejected: {}", path))?;
                 let data = content
                     .as_ref()
                     .ok_or_else(|| anyhow!("create step missing content for {}", path))?;
+                let headed;
+                let data = match &cfg.license_header {
+                    Some(t) => {
+                        headed = crate::license::insert(path, data, t);
+                        &headed
+                    }
+                    None => data,
+                };
+                let annotated;
+                let data = if cfg.annotate_created_files {
+                    annotated = crate::annotate::annotate(path, data, tx, id);
+                    &annotated
+                } else {
+                    data
+                };
+                let old = fs::read_to_string(&abs).ok();
+                summary.change(path, old.as_deref(), Some(data));
                 if dry_run {
                     summary.created += 1;
                     summary.bytes += data.as_bytes().len();
                     continue;
                 }
-                write_atomic(&abs, data)?;
+                journal.backup.record(&abs, path)?;
+                let written = write_atomic(&abs, data)?;
+                journal.backup.written(path, Some(&written))?;
                 summary.created += 1;
                 summary.bytes += data.as_bytes().len();
             }
@@ -73,15 +260,26 @@ pub fn apply_steps(
                 patch,
                 ..
             } => {
-                let abs = safe_join(root, path, &cfg.path_allowlist)
+                let abs = safe_join(root, path, &cfg.path_allowlist, &cfg.symlink_allowlist)
                     .with_context(|| format!("update path rejected: {}", path))?;
                 if content.is_none() && patch.is_none() {
-                    summary.skipped += 1;
+                    summary.skip(step, SkipReason::MissingContent);
                     continue;
                 }
 
                 if let Some(new_content) = content {
-                    if abs.exists() && abs.is_file() {
+                    if abs.exists() && abs.is_file() && cfg.is_generated(path) {
+                        // generated file: full overwrite, no merge
+                        let old = fs::read_to_string(&abs).ok();
+                        summary.change(path, old.as_deref(), Some(new_content));
+                        if !dry_run {
+                            journal.backup.record(&abs, path)?;
+                            let written = write_atomic(&abs, new_content)?;
+                            journal.backup.written(path, Some(&written))?;
+                        }
+                        summary.updated += 1;
+                        summary.bytes += new_content.as_bytes().len();
+                    } else if abs.exists() && abs.is_file() {
                         let old = fs::read_to_string(&abs).unwrap_or_default();
                         let mut final_content = new_content.clone();
 
@@ -96,53 +294,82 @@ pub fn apply_steps(
                             final_content = merged;
                         }
 
+                        summary.change(path, Some(&old), Some(&final_content));
                         if dry_run {
                             summary.updated += 1;
                             summary.bytes += final_content.as_bytes().len();
                         } else {
-                            write_atomic(&abs, &final_content)?;
+                            journal.backup.record(&abs, path)?;
+                            let written = write_atomic(&abs, &final_content)?;
+                            journal.backup.written(path, Some(&written))?;
                             summary.updated += 1;
                             summary.bytes += final_content.as_bytes().len();
                         }
                     } else {
                         // No old file; treat as create
+                        summary.change(path, None, Some(new_content));
                         if dry_run {
                             summary.created += 1;
                             summary.bytes += new_content.as_bytes().len();
                         } else {
-                            write_atomic(&abs, new_content)?;
+                            journal.backup.record(&abs, path)?;
+                            let written = write_atomic(&abs, new_content)?;
+                            journal.backup.written(path, Some(&written))?;
                             summary.created += 1;
                             summary.bytes += new_content.as_bytes().len();
                         }
                     }
                 } else if let Some(_patch) = patch {
                     // Patch-only path — conservative skip (your preview already showed details)
-                    summary.skipped += 1;
+                    summary.skip(step, SkipReason::PatchOnly);
                 }
             }
 
             Step::Delete { path, .. } => {
-                let abs = safe_join(root, path, &cfg.path_allowlist)
+                let abs = safe_join(root, path, &cfg.path_allowlist, &cfg.symlink_allowlist)
                     .with_context(|| format!("delete path rejected: {}", path))?;
+                if !cfg.trusted {
+                    // untrusted workspace: deletes are blocked
+                    summary.skip(step, SkipReason::Untrusted);
+                    continue;
+                }
+                if abs.is_file() {
+                    let old = fs::read_to_string(&abs).ok();
+                    summary.change(path, old.as_deref(), None);
+                }
                 if dry_run {
                     if abs.exists() {
                         summary.deleted += 1;
                     } else {
-                        summary.skipped += 1;
+                        summary.skip(step, SkipReason::DeleteTargetMissing);
                     }
                     continue;
                 }
                 if abs.exists() {
+                    journal.backup.record(&abs, path)?;
                     fs::remove_file(&abs).with_context(|| format!("failed to delete {}", path))?;
+                    journal.backup.written(path, None)?;
                     summary.deleted += 1;
                 } else {
-                    summary.skipped += 1;
+                    summary.skip(step, SkipReason::DeleteTargetMissing);
                 }
             }
 
             Step::Command { command, cwd, .. } => {
                 summary.commands += 1;
-                if dry_run {
+                let command = &crate::exec::policy::rewrite_install(
+                    command,
+                    package_manager,
+                    cfg.run_profile,
+                    cfg.offline_installs,
+                );
+                if !cfg.trusted {
+                    let mut placeholder = CmdResult::default();
+                    placeholder.command = format!("(skipped-untrusted) {}", command);
+                    placeholder.cwd = Some(cwd.clone().unwrap_or_else(|| ".".into()));
+                    summary.command_outputs.push(placeholder);
+                    summary.skip(step, SkipReason::Untrusted);
+                } else if dry_run {
                     let mut placeholder = CmdResult::default();
                     placeholder.command = command.clone();
                     placeholder.cwd = Some(cwd.clone().unwrap_or_else(|| ".".into()));
@@ -151,16 +378,44 @@ pub fn apply_steps(
                     placeholder.duration_ms = 0;
                     placeholder.via_shell_fallback = false;
                     summary.command_outputs.push(placeholder);
+                } else if journal.succeeded.iter().any(|c| c == command.trim()) && !cfg.command_is_idempotent(command) {
+                    let mut placeholder = CmdResult::default();
+                    placeholder.command = format!("(skipped-non-idempotent-rerun) {}", command);
+                    placeholder.cwd = Some(cwd.clone().unwrap_or_else(|| ".".into()));
+                    summary.command_outputs.push(placeholder);
+                    summary.skip(step, SkipReason::NonIdempotentRerun);
                 } else {
+                    // installs rewrite these; back them up so a rollback can put them back
+                    let manifests = dependency_manifests(root, cwd.as_deref(), package_manager, cfg);
+                    for (rel, abs) in &manifests {
+                        journal.backup.record(abs, rel)?;
+                    }
+                    journal.commands.push(command.clone());
                     let res = run_command_allowlisted(command, cfg, cwd.as_deref(), cfg.timeout_secs)
-                        .with_context(|| format!("command failed: {}", command))?;
+                        .with_context(|| format!("command failed: {}", command));
+                    for (rel, abs) in &manifests {
+                        journal.backup.written_bytes(rel, fs::read(abs).ok().as_deref())?;
+                    }
+                    let res = res?;
+                    if res.status_code == 0 {
+                        journal.succeeded.push(command.trim().to_string());
+                    } else if cfg.transactional_apply {
+                        let why = if res.timed_out { "timed out".to_string() } else { format!("exited with {}", res.status_code) };
+                        bail!("step {}: `{}` {}:\n{}", step.id(), command, why, res.stderr.trim());
+                    }
                     summary.command_outputs.push(res);
                 }
             }
 
             Step::Test { command, .. } => {
                 summary.tests += 1;
-                if dry_run {
+                if !cfg.trusted {
+                    let mut placeholder = CmdResult::default();
+                    placeholder.command = format!("(skipped-untrusted) {}", command);
+                    placeholder.cwd = Some(".".into());
+                    summary.command_outputs.push(placeholder);
+                    summary.skip(step, SkipReason::Untrusted);
+                } else if dry_run {
                     let mut placeholder = CmdResult::default();
                     placeholder.command = command.clone();
                     placeholder.cwd = Some(".".into());
@@ -170,7 +425,12 @@ pub fn apply_steps(
                     placeholder.via_shell_fallback = false;
                     summary.command_outputs.push(placeholder);
                 } else {
-                    if cfg.command_allowlist.iter().any(|c| c == command) {
+                    // added by the user from a test template (`add test <path>`)
+                    if cfg.user_test_commands.iter().any(|c| c == command.trim()) {
+                        let res = crate::exec::run_command(command, None, cfg.timeout_secs)
+                            .with_context(|| format!("test command failed: {}", command))?;
+                        summary.command_outputs.push(res);
+                    } else if cfg.command_allowlist.iter().any(|c| c == command) {
                         let res = run_command_allowlisted(command, cfg, None, cfg.timeout_secs)
                             .with_context(|| format!("test command failed: {}", command))?;
                         summary.command_outputs.push(res);
@@ -183,28 +443,41 @@ pub fn apply_steps(
                         placeholder.duration_ms = 0;
                         placeholder.via_shell_fallback = false;
                         summary.command_outputs.push(placeholder);
-                        summary.skipped += 1;
+                        summary.skip(step, SkipReason::NotAllowlisted);
                     }
                 }
             }
         }
     }
+    if let Some((prev, from)) = done {
+        after_step(prev, &summary.changes[from..])?;
+    }
 
     Ok(summary)
 }
 
+/// Put `rel` back to `content` (or delete it when `None`) through the same
+/// allowlist and atomic-write path as a normal apply.
+pub fn restore_file(root: &Path, rel: &str, content: Option<impl AsRef<[u8]>>, cfg: &Config) -> Result<()> {
+    let abs = safe_join(root, rel, &cfg.path_allowlist, &cfg.symlink_allowlist).with_context(|| format!("restore path rejected: {}", rel))?;
+    match content {
+        Some(c) => write_bytes_atomic(&abs, c.as_ref()),
+        None => {
+            if abs.exists() {
+                fs::remove_file(&abs).with_context(|| format!("failed to delete {}", rel))?;
+            }
+            Ok(())
+        }
+    }
+}
+
 /// Join `root` with a relative path `rel`, enforcing an allowlist and preventing escape.
 /// Works even when the target file doesn't exist yet (important for CREATE steps)
 /// and when `root` is a relative path (e.g., `..\my-app` on Windows).
-fn safe_join(root: &Path, rel: &str, allowlist: &[String]) -> Result<PathBuf> {
-    // quick allowlist prefix check (top-level segments)
-    let allowed = allowlist.iter().any(|p| {
-        if p == rel {
-            return true;
-        }
-        rel.starts_with(p.trim_end_matches('/').trim_end_matches('\\'))
-    });
-    if !allowed {
+/// Refuses to go through symlinks resolving outside the root unless in `link_allowlist`.
+fn safe_join(root: &Path, rel: &str, allowlist: &[String], link_allowlist: &[String]) -> Result<PathBuf> {
+    // allowlist prefix check (segment-aware, NFC-normalized)
+    if !crate::safety::rel_matches_allowlist(rel, allowlist) {
         return Err(anyhow!("path '{}' not allowed by allowlist", rel));
     }
 
@@ -249,16 +522,22 @@ fn safe_join(root: &Path, rel: &str, allowlist: &[String]) -> Result<PathBuf> {
         return Err(anyhow!("path escapes project root: {}", rel));
     }
 
+    // The lexical check can't see links: `src/lib -> /elsewhere` would write out of tree
+    let lexical_rel = out.strip_prefix(&root_abs).unwrap_or(&out).to_string_lossy().to_string();
+    if let Some(link) = crate::safety::escaping_symlink(&root_abs, &lexical_rel, link_allowlist) {
+        return Err(anyhow!(
+            "path '{}' goes through symlink '{}' that resolves outside the project root (add it to symlink_allowlist if intentional)",
+            rel,
+            link
+        ));
+    }
+
     Ok(out)
 }
 
 /// Atomic write with directory creation.
-fn write_atomic(path: &Path, contents: &str) -> Result<()> {
-    if let Some(dir) = path.parent() {
-        fs::create_dir_all(dir)
-            .with_context(|| format!("failed to create dir {}", dir.display()))?;
-    }
-
+/// Returns what was written: `contents` with a trailing newline ensured.
+fn write_atomic(path: &Path, contents: &str) -> Result<String> {
     // Ensure trailing newline per hygiene rule when writing text files
     let final_contents = if contents.ends_with('\n') {
         contents.to_string()
@@ -267,6 +546,16 @@ fn write_atomic(path: &Path, contents: &str) -> Result<()> {
         s.push('\n');
         s
     };
+    write_bytes_atomic(path, final_contents.as_bytes())?;
+    Ok(final_contents)
+}
+
+/// Write `contents` as is through a temp file and a rename.
+fn write_bytes_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create dir {}", dir.display()))?;
+    }
 
     // Write to a temp file then rename
     let tmp = path.with_extension(".__tmp__");
@@ -277,7 +566,7 @@ fn write_atomic(path: &Path, contents: &str) -> Result<()> {
             .write(true)
             .open(&tmp)
             .with_context(|| format!("open temp for write: {}", tmp.display()))?;
-        f.write_all(final_contents.as_bytes())
+        f.write_all(contents)
             .with_context(|| format!("write temp: {}", tmp.display()))?;
         f.flush()?;
     }
@@ -285,3 +574,26 @@ fn write_atomic(path: &Path, contents: &str) -> Result<()> {
         .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
     Ok(())
 }
+
+/// package.json and the lockfiles in a command's `cwd` (root when unset)
+/// that the allowlists let us restore, as (root-relative, absolute): the ones
+/// that exist, and `pm`'s lockfile, which an install may create.
+fn dependency_manifests(
+    root: &Path,
+    cwd: Option<&str>,
+    pm: crate::exec::policy::PackageManager,
+    cfg: &Config,
+) -> Vec<(String, PathBuf)> {
+    const NAMES: [&str; 7] =
+        ["package.json", "package-lock.json", "npm-shrinkwrap.json", "yarn.lock", "pnpm-lock.yaml", "bun.lock", "bun.lockb"];
+    let dir = cwd.map(crate::safety::normalize_rel).filter(|d| !d.is_empty() && d != ".");
+    NAMES
+        .iter()
+        .map(|name| match &dir {
+            Some(d) => format!("{}/{}", d, name),
+            None => name.to_string(),
+        })
+        .filter_map(|rel| safe_join(root, &rel, &cfg.path_allowlist, &cfg.symlink_allowlist).ok().map(|abs| (rel, abs)))
+        .filter(|(rel, abs)| abs.is_file() || rel.ends_with(pm.lockfile()))
+        .collect()
+}
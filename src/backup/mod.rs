@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::log;
+use crate::safety::normalize_rel;
+
+/// Pre-apply state of one file touched by a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub path: String,
+    /// False when the transaction created the file (restoring deletes it).
+    pub existed: bool,
+    /// What the transaction left; absent from backups made before this was
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<After>,
+}
+
+/// A file's state right after the transaction wrote it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum After {
+    Sha256(String),
+    Deleted,
+}
+
+impl After {
+    pub fn of(content: Option<&str>) -> Self {
+        match content {
+            Some(c) => After::Sha256(content_hash(c)),
+            None => After::Deleted,
+        }
+    }
+}
+
+/// Hex sha256 of file content, as recorded in `BackupEntry::after` and
+/// `FileBlob::hash`.
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(content.as_ref()))
+}
+
+/// Copies of files as they were before a transaction touched them, stored
+/// under `.vibe/tx/<id>/backup/` with a `manifest.json` index, and of what
+/// it wrote (`after/`), for `vibe diff`.
+pub struct Backup {
+    tx: Uuid,
+    dir: PathBuf,
+    entries: Vec<BackupEntry>,
+}
+
+fn backup_dir(root: &Path, tx: Uuid) -> PathBuf {
+    log::tx_dir(root, tx).join("backup")
+}
+
+impl Backup {
+    pub fn new(root: &Path, tx: Uuid) -> Self {
+        Self { tx, dir: backup_dir(root, tx), entries: Vec::new() }
+    }
+
+    /// The backup `tx` already saved, to note later writes to its files.
+    pub fn open(root: &Path, tx: Uuid) -> Result<Self> {
+        Ok(Self { tx, dir: backup_dir(root, tx), entries: load_manifest(root, tx)? })
+    }
+
+    /// Note what every recorded file holds now, after something besides
+    /// the apply (a `post_apply` formatter) rewrote them.
+    pub fn rerecord(&mut self, root: &Path) -> Result<()> {
+        let paths: Vec<String> = self.entries.iter().map(|e| e.path.clone()).collect();
+        for rel in paths {
+            self.written_bytes(&rel, fs::read(root.join(&rel)).ok().as_deref())?;
+        }
+        Ok(())
+    }
+
+    pub fn tx(&self) -> Uuid {
+        self.tx
+    }
+
+    /// Save `rel`'s current content (first touch only) before it is overwritten
+    /// or deleted.
+    pub fn record(&mut self, abs: &Path, rel: &str) -> Result<()> {
+        let rel = normalize_rel(rel);
+        if self.entries.iter().any(|e| e.path == rel) {
+            return Ok(());
+        }
+        let existed = abs.is_file();
+        if existed {
+            let dst = self.dir.join("files").join(&rel);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let data = fs::read(abs).with_context(|| format!("backing up {}", rel))?;
+            log::crypt::write(&dst, &data).with_context(|| format!("backing up {}", rel))?;
+        }
+        self.entries.push(BackupEntry { path: rel, existed, after: None });
+        self.save()
+    }
+
+    /// Note what `rel` holds after the write (`None`: deleted), so `vibe undo`
+    /// can tell later edits apart from the transaction's own, and keep a copy
+    /// for `vibe diff`.
+    pub fn written(&mut self, rel: &str, content: Option<&str>) -> Result<()> {
+        self.written_bytes(rel, content.map(str::as_bytes))
+    }
+
+    /// `written` for content that need not be UTF-8.
+    pub fn written_bytes(&mut self, rel: &str, content: Option<&[u8]>) -> Result<()> {
+        let rel = normalize_rel(rel);
+        if let Some(e) = self.entries.iter_mut().find(|e| e.path == rel) {
+            e.after = Some(content.map_or(After::Deleted, |c| After::Sha256(content_hash(c))));
+        }
+        // a deleted file's copy is stale; `after` says it's gone
+        if let Some(c) = content {
+            let dst = self.dir.join("after").join(&rel);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            log::crypt::write(&dst, c).with_context(|| format!("saving applied {}", rel))?;
+        }
+        self.save()
+    }
+
+    /// Files recorded so far, in the order they were first touched.
+    pub fn entries(&self) -> &[BackupEntry] {
+        &self.entries
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join("manifest.json"), serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+pub fn load_manifest(root: &Path, tx: Uuid) -> Result<Vec<BackupEntry>> {
+    let p = backup_dir(root, tx).join("manifest.json");
+    if !p.exists() {
+        anyhow::bail!("transaction {} has no backup", tx);
+    }
+    let s = fs::read_to_string(&p)?;
+    serde_json::from_str(&s).with_context(|| format!("parsing {}", p.display()))
+}
+
+/// Content of `rel` before transaction `tx` (`None` if the tx created it).
+pub fn original_content(root: &Path, tx: Uuid, rel: &str) -> Result<Option<String>> {
+    original_bytes(root, tx, rel)?.map(String::from_utf8).transpose().map_err(Into::into)
+}
+
+/// `original_content` as the bytes that were backed up, UTF-8 or not.
+pub fn original_bytes(root: &Path, tx: Uuid, rel: &str) -> Result<Option<Vec<u8>>> {
+    let rel = normalize_rel(rel);
+    let entry = load_manifest(root, tx)?
+        .into_iter()
+        .find(|e| e.path == rel)
+        .with_context(|| format!("{} was not touched by transaction {}", rel, tx))?;
+    if !entry.existed {
+        return Ok(None);
+    }
+    let p = backup_dir(root, tx).join("files").join(&rel);
+    let data = log::crypt::read(&p)?.with_context(|| format!("backup of {} is missing from {}", rel, tx))?;
+    Ok(Some(data))
+}
+
+/// Content of `rel` right after transaction `tx` (`None` if it deleted the
+/// file). Errors for backups made before applied copies were kept.
+pub fn applied_content(root: &Path, tx: Uuid, rel: &str) -> Result<Option<String>> {
+    let rel = normalize_rel(rel);
+    let entry = load_manifest(root, tx)?
+        .into_iter()
+        .find(|e| e.path == rel)
+        .with_context(|| format!("{} was not touched by transaction {}", rel, tx))?;
+    if entry.after == Some(After::Deleted) {
+        return Ok(None);
+    }
+    let p = backup_dir(root, tx).join("after").join(&rel);
+    let data = log::crypt::read(&p)?.with_context(|| format!("transaction {} kept no copy of {} as applied", tx, rel))?;
+    Ok(Some(String::from_utf8(data)?))
+}
+
+/// One transaction that touched a file, from its backup manifest.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub tx: Uuid,
+    /// When the backup manifest was last written (i.e. the apply time).
+    pub applied_at: DateTime<Utc>,
+    pub existed_before: bool,
+}
+
+/// Every transaction whose backup covers `rel`, oldest first.
+pub fn file_history(root: &Path, rel: &str) -> Result<Vec<HistoryEntry>> {
+    let rel = normalize_rel(rel);
+    let base = log::tx_root(root);
+    let mut out = Vec::new();
+    if !base.exists() {
+        return Ok(out);
+    }
+    for entry in fs::read_dir(&base)? {
+        let entry = entry?;
+        let Ok(tx) = Uuid::parse_str(&entry.file_name().to_string_lossy()) else { continue };
+        let manifest = backup_dir(root, tx).join("manifest.json");
+        if !manifest.exists() {
+            continue;
+        }
+        let Some(e) = load_manifest(root, tx)?.into_iter().find(|e| e.path == rel) else { continue };
+        let applied_at = fs::metadata(&manifest)?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        out.push(HistoryEntry { tx, applied_at, existed_before: e.existed });
+    }
+    out.sort_by_key(|h| h.applied_at);
+    Ok(out)
+}
+
+/// Whether `rel` is exactly what the last transaction touching it left
+/// there, i.e. its uncommitted changes are vibe's own and that tx's backup
+/// has what came before.
+pub fn as_last_applied(root: &Path, rel: &str) -> Result<bool> {
+    let Some(last) = file_history(root, rel)?.pop() else { return Ok(false) };
+    let rel = normalize_rel(rel);
+    let after = load_manifest(root, last.tx)?.into_iter().find(|e| e.path == rel).and_then(|e| e.after);
+    let now = fs::read(root.join(&rel)).ok();
+    Ok(after.is_some() && after == Some(now.map_or(After::Deleted, |c| After::Sha256(content_hash(c)))))
+}
+
+/// File versions along `history`: index 0 is the content before the first
+/// transaction, index k the content after the k-th (the last one is the file
+/// on disk now). `None` means the file did not exist.
+pub fn versions(root: &Path, rel: &str, history: &[HistoryEntry]) -> Result<Vec<Option<String>>> {
+    let mut out = Vec::new();
+    for h in history {
+        out.push(original_content(root, h.tx, rel)?);
+    }
+    if !history.is_empty() {
+        out.push(fs::read_to_string(root.join(normalize_rel(rel))).ok());
+    }
+    Ok(out)
+}
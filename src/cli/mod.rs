@@ -1,4 +1,4 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
 #[derive(ValueEnum, Clone, Debug, Serialize, Deserialize)]
@@ -6,10 +6,65 @@ use serde::{Deserialize, Serialize};
 pub enum ProviderKind {
     #[value(alias = "open-ai", alias = "openai")]
     OpenAI,
+    /// Any OpenAI chat API server at `--api-base` (key optional)
+    #[value(name = "openai-compatible")]
+    #[serde(rename = "openai-compatible")]
+    OpenAICompatible,
+    /// OpenRouter (OpenAI-compatible); models like `anthropic/claude-3.5-sonnet`
+    #[value(name = "openrouter", alias = "open-router")]
+    OpenRouter,
+    /// Groq (OpenAI-compatible, low latency); models like `llama-3.3-70b-versatile`
+    Groq,
+    /// Azure OpenAI deployment (see the `[azure]` config table)
+    #[value(alias = "azure-openai")]
+    Azure,
     #[value(alias = "anthropic")]
     Anthropic,
+    /// Anthropic models on AWS Bedrock (SigV4, AWS_* env credentials)
+    #[value(alias = "aws-bedrock")]
+    Bedrock,
+    /// Mistral La Plateforme (mistral-large-latest, codestral-latest)
+    Mistral,
     #[value(alias = "ollama")]
     Ollama,
+    /// Self-hosted vLLM server (`--api-base`, default localhost:8000); JSON
+    /// replies are constrained with guided decoding
+    Vllm,
+    /// Self-hosted Text Generation Inference (`--api-base`, default
+    /// localhost:8080); JSON replies are grammar-constrained
+    #[value(alias = "text-generation-inference")]
+    Tgi,
+    /// LM Studio's local server (`--api-base`, default localhost:1234); without
+    /// `--model`, the loaded models are listed to pick from
+    #[value(name = "lmstudio", alias = "lm-studio")]
+    #[serde(rename = "lmstudio", alias = "lm-studio")]
+    LmStudio,
+    /// Answer from a previous transaction's saved responses (`--replay-tx`); no API calls
+    Replay,
+}
+
+/// `--model` when none is given.
+pub const DEFAULT_MODEL: &str = "gpt-4.1-mini";
+
+/// What a run (no subcommand) does.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunMode {
+    /// PLAN, CODEGEN and apply for `--task`.
+    #[default]
+    Code,
+    /// Write a conventional-commit message for the staged changes to
+    /// .git/COMMIT_EDITMSG (`--task` is an optional hint).
+    Commitmsg,
+}
+
+/// How the OpenAI provider maps the request onto chat messages.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenAIMessages {
+    /// Whole LlmRequest serialized into a single user message.
+    Single,
+    /// instruction.system/developer as system/developer roles; task+context as user.
+    Roles,
 }
 
 #[derive(Parser, Debug)]
@@ -18,18 +73,35 @@ pub struct Args {
     #[arg(long, default_value = ".")]
     pub root: String,
 
+    /// Allow a root without package.json/Cargo.toml/.git
+    #[arg(long, default_value_t = false)]
+    pub force_root: bool,
+
     #[arg(long, default_value = "vibe-index/.vibe/out")]
     pub vibe_out: String,
 
     #[arg(long, value_enum, default_value_t = ProviderKind::OpenAI)]
     pub provider: ProviderKind,
 
-    #[arg(long, default_value = "gpt-4.1-mini")]
-    pub model: String,
+    /// Model name (default: gpt-4.1-mini; with `--provider lmstudio`, picked
+    /// from the server's model list)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = OpenAIMessages::Single)]
+    pub openai_messages: OpenAIMessages,
 
     #[arg(long)]
     pub task: Option<String>,
 
+    /// Run the oldest follow-up queued in .vibe/followups.txt as `--task`;
+    /// it leaves the queue once applied
+    #[arg(long, default_value_t = false, conflicts_with = "task")]
+    pub follow_up: bool,
+
+    #[arg(long, value_enum, default_value_t = RunMode::Code)]
+    pub mode: RunMode,
+
     #[arg(long, default_value_t = false)]
     pub dry_run: bool,
 
@@ -53,4 +125,243 @@ pub struct Args {
 
     #[arg(long)]
     pub config: Option<String>,
+
+    /// Reply format to request from the model (overrides config `wire_format`)
+    #[arg(long, value_enum)]
+    pub wire_format: Option<crate::wire::WireFormat>,
+
+    /// Send the PLAN request N times and keep the best-scoring plan
+    /// (overrides config `plan_samples`)
+    #[arg(long)]
+    pub plan_samples: Option<usize>,
+
+    /// Model for the PLAN phase and pipeline stages (default: --model)
+    #[arg(long)]
+    pub plan_model: Option<String>,
+
+    /// Model for the CODEGEN phase and its repairs (default: --model)
+    #[arg(long)]
+    pub codegen_model: Option<String>,
+
+    /// Transaction whose saved responses `--provider replay` answers with
+    #[arg(long)]
+    pub replay_tx: Option<String>,
+
+    /// Sampling temperature (overrides config `temperature`)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling cutoff (overrides config `top_p`)
+    #[arg(long)]
+    pub top_p: Option<f32>,
+
+    /// Cap on reply tokens (overrides config `max_output_tokens`); raise it
+    /// when large CODEGEN replies come back truncated
+    #[arg(long)]
+    pub max_output_tokens: Option<u32>,
+
+    /// Base URL for OpenAI-style providers (e.g. http://localhost:8080/v1);
+    /// overrides a provider profile's api_base
+    #[arg(long)]
+    pub api_base: Option<String>,
+
+    /// Ollama server URL (default: OLLAMA_HOST or http://localhost:11434)
+    #[arg(long)]
+    pub ollama_url: Option<String>,
+
+    /// Named provider profile from the config (provider, model, key, endpoint)
+    #[arg(long)]
+    pub profile_provider: Option<String>,
+
+    /// Run profile; overrides `run_profile` from the config file
+    #[arg(long, value_enum)]
+    pub run_profile: Option<crate::exec::policy::RunProfile>,
+
+    /// Race the PLAN request across these `<provider>:<model>` entries and
+    /// keep the first valid plan (overrides config `plan_race`)
+    #[arg(long, value_delimiter = ',')]
+    pub race_plan: Option<Vec<String>>,
+
+    /// When files the plan touches have uncommitted changes: apply anyway,
+    /// refuse, or stash them around the apply (overrides config `dirty_worktree`)
+    #[arg(long, value_enum)]
+    pub dirty_worktree: Option<crate::git::DirtyWorktree>,
+
+    /// Skip the startup cleanup of leftover temp files and stale locks
+    #[arg(long, default_value_t = false)]
+    pub no_gc: bool,
+
+    /// Apply on a new `vibe/<short-txid>` branch created from HEAD instead of
+    /// the current branch (overrides config `git_branch`)
+    #[arg(long, default_value_t = false)]
+    pub git_branch: bool,
+
+    /// Commit the files the transaction changed (and only those) after apply,
+    /// with a message from the plan (overrides config `git_commit`)
+    #[arg(long, default_value_t = false)]
+    pub git_commit: bool,
+
+    /// Commit after each file-affecting step with the step title as the
+    /// message (implies --git-commit; overrides config `git_commit_per_step`)
+    #[arg(long, default_value_t = false)]
+    pub commit_per_step: bool,
+
+    /// Copy the answer, or after apply the commit message for the changes,
+    /// to the system clipboard
+    #[arg(long, default_value_t = false)]
+    pub copy: bool,
+
+    /// Push the transaction branch and open a pull/merge request after apply
+    /// (implies --git-branch and --git-commit; overrides config `pr.open`)
+    #[arg(long, default_value_t = false)]
+    pub open_pr: bool,
+
+    /// How providers ask for JSON replies instead of picking from the model's
+    /// capabilities (overrides config `structured_output`)
+    #[arg(long, value_enum)]
+    pub structured_output: Option<crate::provider::capabilities::StructuredOutput>,
+
+    /// Send recent commits and the uncommitted diff of the context files to
+    /// the model (overrides config `git_context`)
+    #[arg(long, default_value_t = false)]
+    pub git_context: bool,
+
+    /// For a fix task, tell the model which regions of the files it names
+    /// recent commits changed and why (overrides config `blame_context`)
+    #[arg(long, default_value_t = false)]
+    pub blame_context: bool,
+
+    /// Fetch and warn about files the plan writes that changed on the
+    /// upstream branch (overrides config `upstream_check`)
+    #[arg(long, default_value_t = false)]
+    pub check_upstream: bool,
+
+    /// Print the plan's step dependency graph with the preview and save it
+    /// to the tx directory
+    #[arg(long, value_enum)]
+    pub graph: Option<crate::plan::graph::GraphFormat>,
+
+    /// Resolve confirmation prompts nobody answers within this long
+    /// (`120s`, `2m`) with --confirm-default (overrides config `confirm_timeout_secs`)
+    #[arg(long, value_parser = crate::ux::input::parse_duration)]
+    pub confirm_timeout: Option<std::time::Duration>,
+
+    /// What a timed-out confirmation does (overrides config `confirm_default`)
+    #[arg(long, value_enum)]
+    pub confirm_default: Option<crate::ux::input::ConfirmDefault>,
+
+    /// Apply steps touching paths CODEOWNERS assigns to other teams even in the ci profile
+    #[arg(long, default_value_t = false)]
+    pub override_owners: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export/import session state (turn history, applied tx ids, learned preferences)
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+    /// Start the dev server, request every route and print a health table
+    Smoke,
+    /// Inspect saved transactions
+    Tx {
+        #[command(subcommand)]
+        action: TxAction,
+    },
+    /// List the transactions that touched a file; `--diff A B` compares versions
+    History {
+        path: String,
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        diff: Option<Vec<usize>>,
+    },
+    /// Show which task, transaction and plan step created a file
+    Blame { path: String },
+    /// Keep the retrieval index (embeddings.jsonl) current by re-chunking files as they change
+    Watch,
+    /// Send one tiny request to each configured provider/model and report key,
+    /// reachability and latency
+    Doctor,
+    /// Store or remove a provider API key in the OS keychain (env vars still win)
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Run PLAN + CODEGEN for `--task` with two models on the same snapshot
+    /// (nothing is applied) and compare the files, tokens and cost
+    Compare {
+        /// Two models, `model` (on --provider) or `provider:model`, comma-separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        models: Vec<String>,
+    },
+    /// Re-print the latest run's plan, preview, apply dashboard and verification
+    /// from its saved artifacts
+    Last {
+        /// Open the transaction directory in the file manager
+        #[arg(long, default_value_t = false)]
+        open: bool,
+    },
+    /// Restore one file to its content before transaction `--tx`
+    Restore {
+        path: String,
+        #[arg(long)]
+        tx: uuid::Uuid,
+    },
+    /// Revert every file transaction `tx` changed (and nothing else); files
+    /// edited since are left alone unless --force
+    Undo {
+        tx: uuid::Uuid,
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Show the full diff of every file transaction `tx` changed
+    Diff { tx: uuid::Uuid },
+    /// Run the recurring tasks in .vibe/schedule.yaml that are due (for
+    /// cron/CI): `reindex`, `deps`, `doctor`, `prune`, each with an optional
+    /// `every: 1d`; writes .vibe/schedule.report.json and fails if a task did
+    Schedule {
+        /// Run only these tasks (by name)
+        #[arg(long)]
+        only: Vec<String>,
+        /// Run tasks even if they are not due
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthAction {
+    /// Read a key from stdin and save it for `provider`
+    Login {
+        #[arg(value_enum)]
+        provider: ProviderKind,
+    },
+    /// Remove the saved key for `provider`
+    Logout {
+        #[arg(value_enum)]
+        provider: ProviderKind,
+    },
+    /// Generate a VIBE_ARTIFACT_KEY for `encrypt_artifacts` and save it to
+    /// the OS keychain; it is printed once so it can be kept elsewhere too
+    ArtifactKey,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TxAction {
+    /// List a transaction's artifacts, or print one (decompressed) by name
+    Show { tx: uuid::Uuid, artifact: Option<String> },
+    /// Apply a failed transaction's plan again as a new transaction, skipping
+    /// the non-idempotent commands that already succeeded in it
+    Resume { tx: uuid::Uuid },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionAction {
+    /// Bundle the project's session state into a single JSON file
+    Export { file: String },
+    /// Restore a session bundle into this project
+    Import { file: String },
 }
@@ -0,0 +1,16 @@
+use anyhow::Result;
+
+/// Put `text` (color codes removed) on the system clipboard. On X11 the
+/// content is handed to the clipboard manager when the clipboard is dropped,
+/// so it outlives the process where one is running.
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    let mut cb = arboard::Clipboard::new().map_err(|e| anyhow::anyhow!("no clipboard available: {}", e))?;
+    cb.set_text(crate::ux::theme::strip(text))
+        .map_err(|e| anyhow::anyhow!("copying to the clipboard: {}", e))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    anyhow::bail!("built without the `clipboard` feature")
+}
@@ -0,0 +1,509 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::cli::{Args, AuthAction, Command, SessionAction, TxAction};
+use crate::config::Config;
+use crate::{annotate, apply, backup, compare, credentials, doctor, log, patch, schedule, session, smoke, trust, txdiff, undo, ux};
+#[cfg(feature = "watch")]
+use crate::watch;
+
+/// Dispatch `vibe <subcommand>`; the default (no subcommand) is the plan/codegen run.
+pub async fn run(cmd: &Command, args: &Args, cfg: &Config) -> Result<()> {
+    let root = Path::new(&cfg.root);
+    match cmd {
+        Command::Session { action } => match action {
+            SessionAction::Export { file } => {
+                let b = session::export(root, Path::new(file))?;
+                println!(
+                    "Exported {} turn(s), {} applied tx, {} preference entr(ies) to {}",
+                    b.turns.len(),
+                    b.applied_tx.len(),
+                    b.memory.decisions.len() + b.memory.notes.len(),
+                    file
+                );
+            }
+            SessionAction::Import { file } => {
+                let b = session::import(root, Path::new(file))?;
+                println!("Imported {} turn(s) from {} (exported {})", b.turns.len(), b.root, b.exported_at);
+            }
+        },
+        Command::Smoke => {
+            let mut cfg = cfg.clone();
+            cfg.trusted = trust::ensure_trusted(root)?;
+            let report = smoke::run(root, &cfg).await?;
+            ux::print_smoke_table(&report);
+            if !report.healthy {
+                anyhow::bail!("smoke check failed");
+            }
+        }
+        Command::Tx { action } => match action {
+            TxAction::Show { tx, artifact } => tx_show(root, *tx, artifact.as_deref())?,
+            TxAction::Resume { tx } => tx_resume(root, *tx, cfg)?,
+        },
+        Command::History { path, diff } => history(root, path, diff.as_deref())?,
+        Command::Last { open } => last(root, *open)?,
+        Command::Restore { path, tx } => restore(root, path, *tx, cfg)?,
+        Command::Undo { tx, force } => undo(root, *tx, *force, cfg)?,
+        Command::Diff { tx } => diff(root, *tx)?,
+        Command::Blame { path } => blame(root, path)?,
+        Command::Auth { action } => auth(action)?,
+        #[cfg(feature = "watch")]
+        Command::Watch => watch::run(root, Path::new(&args.vibe_out), cfg)?,
+        #[cfg(not(feature = "watch"))]
+        Command::Watch => anyhow::bail!("`vibe watch` needs the `watch` feature; rebuild with default features"),
+        Command::Compare { models } => {
+            let cmp = compare::run(models, args, cfg).await?;
+            ux::print_comparison(&cmp);
+        }
+        Command::Doctor => {
+            let report = doctor::run(args, cfg).await?;
+            ux::print_doctor_report(&report);
+            if report.iter().any(|h| !h.ok) {
+                anyhow::bail!("provider check failed");
+            }
+        }
+        Command::Schedule { only, force } => {
+            // unattended (cron/CI): no trust prompt, only a stored answer
+            let mut cfg = cfg.clone();
+            cfg.trusted = trust::is_trusted(root)?;
+            let report = schedule::run(args, &cfg, only, *force).await?;
+            ux::print_schedule_report(&report);
+            if report.failed() {
+                anyhow::bail!("scheduled task(s) failed; see {}", schedule::REPORT_FILE);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn auth(action: &AuthAction) -> Result<()> {
+    match action {
+        AuthAction::Login { provider } => {
+            let var = credentials::key_var(provider)?;
+            let key = ux::read_secret(&format!("API key for {:?}", provider));
+            if key.is_empty() {
+                anyhow::bail!("no key entered");
+            }
+            credentials::store(&var, &key)?;
+            println!("Saved {} to the OS keychain.", var);
+            if std::env::var(&var).is_ok_and(|v| !v.is_empty()) {
+                println!("Note: {} is also set in the environment and takes precedence.", var);
+            }
+        }
+        AuthAction::Logout { provider } => {
+            let var = credentials::key_var(provider)?;
+            if credentials::forget(&var)? {
+                println!("Removed {} from the OS keychain.", var);
+            } else {
+                println!("No stored key for {}.", var);
+            }
+        }
+        AuthAction::ArtifactKey => {
+            let var = log::crypt::KEY_VAR;
+            if credentials::api_key(var).is_some() && !ux::confirm(&format!("{} is already set; replace it (old artifacts become unreadable)?", var)) {
+                return Ok(());
+            }
+            let key = log::crypt::generate_key()?;
+            credentials::store(var, &key)?;
+            println!("Saved {} to the OS keychain. Keep a copy; without it encrypted artifacts can't be read:\n  {}", var, key);
+        }
+    }
+    Ok(())
+}
+
+fn restore(root: &Path, path: &str, tx: Uuid, cfg: &Config) -> Result<()> {
+    let original = backup::original_content(root, tx, path)?;
+    let current = std::fs::read_to_string(root.join(path)).ok();
+    if original == current {
+        println!("{} already matches its state before {}", path, tx);
+        return Ok(());
+    }
+    match &original {
+        Some(o) => println!("{}", patch::short_diff(current.as_deref().unwrap_or(""), o, 200)),
+        None => println!("{} did not exist before {}; restoring deletes it", path, tx),
+    }
+    if !cfg.auto_approve && !ux::confirm(&format!("Restore {} from transaction {}?", path, tx)) {
+        println!("Aborted by user.");
+        return Ok(());
+    }
+
+    // the restore is its own transaction so it can be reverted the same way
+    let restore_tx = Uuid::new_v4();
+    let mut b = backup::Backup::new(root, restore_tx);
+    b.record(&root.join(path), path)?;
+    apply::restore_file(root, path, original.as_deref(), cfg)?;
+    b.written(path, original.as_deref())?;
+    log::save_artifact(
+        "restore.json",
+        &serde_json::json!({ "path": path, "from_tx": tx }),
+        restore_tx,
+        cfg,
+    )?;
+    println!("Restored {} (undo with --tx {})", path, restore_tx);
+    Ok(())
+}
+
+/// `vibe undo <tx>`: put back the files `tx` changed. The undo is its own
+/// transaction, so `vibe undo` on it re-applies.
+fn undo(root: &Path, tx: Uuid, force: bool, cfg: &Config) -> Result<()> {
+    let files = undo::plan(root, tx)?;
+    ux::print_undo_plan(&files, force);
+    let todo: Vec<&undo::UndoFile> = files
+        .iter()
+        .filter(|f| match f.state {
+            undo::State::AsApplied | undo::State::Unknown => true,
+            undo::State::ChangedSince => force,
+            undo::State::Reverted => false,
+        })
+        .collect();
+    if todo.is_empty() {
+        println!("Nothing to undo for {}.", tx);
+        return Ok(());
+    }
+    if !cfg.auto_approve && !ux::confirm(&format!("Revert {} file(s) changed by {}?", todo.len(), tx)) {
+        println!("Aborted by user.");
+        return Ok(());
+    }
+
+    let undo_tx = Uuid::new_v4();
+    let mut b = backup::Backup::new(root, undo_tx);
+    for f in &todo {
+        b.record(&root.join(&f.path), &f.path)?;
+        apply::restore_file(root, &f.path, f.original.as_deref(), cfg)?;
+        b.written(&f.path, f.original.as_deref())?;
+    }
+    let kept: Vec<&str> = files.iter().filter(|f| f.state == undo::State::ChangedSince && !force).map(|f| f.path.as_str()).collect();
+    log::save_artifact(
+        "undo.json",
+        &serde_json::json!({
+            "from_tx": tx,
+            "reverted": todo.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+            "kept": kept,
+        }),
+        undo_tx,
+        cfg,
+    )?;
+    println!("Reverted {} file(s) (redo with `vibe undo {}`).", todo.len(), undo_tx);
+    Ok(())
+}
+
+/// `vibe diff <tx>`: what a past transaction changed, file by file.
+fn diff(root: &Path, tx: Uuid) -> Result<()> {
+    let previews = txdiff::previews(&txdiff::files(root, tx)?);
+    let task = tx_task(root, tx);
+    println!("Transaction {}{}", tx, if task.is_empty() { String::new() } else { format!(": {}", task) });
+    if previews.is_empty() {
+        println!("No file changes.");
+        return Ok(());
+    }
+    for p in &previews {
+        println!("\n{}", patch::colorize_preview(p));
+    }
+    Ok(())
+}
+
+fn tx_show(root: &Path, tx: Uuid, artifact: Option<&str>) -> Result<()> {
+    let dir = log::tx_dir(root, tx);
+    if !dir.is_dir() {
+        anyhow::bail!("no transaction {} under {}", tx, log::tx_root(root).display());
+    }
+    match artifact {
+        Some(name) => {
+            let s = log::read_artifact(&dir, name)?
+                .with_context(|| format!("transaction {} has no artifact {}", tx, name))?;
+            println!("{}", s);
+        }
+        None => {
+            for (name, p) in log::list_artifacts(&dir)? {
+                let size = std::fs::metadata(&p).map(|m| m.len()).unwrap_or(0);
+                let file = p.file_name().unwrap_or_default().to_string_lossy();
+                let compressed = if file.trim_end_matches(".enc").ends_with(".zst") { " (zstd)" } else { "" };
+                let encrypted = if file.ends_with(".enc") { " (encrypted)" } else { "" };
+                println!("  {:<36} {:>9}{}{}", name, humansize::format_size(size, humansize::DECIMAL), compressed, encrypted);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Newest transaction that is a run (has a PLAN request), by the request's
+/// `transaction.timestamp`; directory mtime, which any later artifact write
+/// bumps, only stands in for requests without one.
+fn latest_run(root: &Path) -> Result<Option<(Uuid, PathBuf)>> {
+    let tx_root = log::tx_root(root);
+    if !tx_root.is_dir() {
+        return Ok(None);
+    }
+    let mut best: Option<(chrono::DateTime<chrono::Utc>, Uuid, PathBuf)> = None;
+    for entry in std::fs::read_dir(&tx_root)? {
+        let dir = entry?.path();
+        let Some(tx) = dir.file_name().and_then(|n| Uuid::parse_str(&n.to_string_lossy()).ok()) else { continue };
+        let Some(req) = log::read_artifact(&dir, "plan.request.json")? else { continue };
+        let started = serde_json::from_str::<serde_json::Value>(&req)
+            .ok()
+            .and_then(|v| serde_json::from_value(v.pointer("/transaction/timestamp")?.clone()).ok());
+        let started = match started {
+            Some(t) => t,
+            None => std::fs::metadata(&dir)?.modified()?.into(),
+        };
+        if best.as_ref().map(|(t, _, _)| started > *t).unwrap_or(true) {
+            best = Some((started, tx, dir));
+        }
+    }
+    Ok(best.map(|(_, tx, dir)| (tx, dir)))
+}
+
+/// `vibe tx resume <tx>`: apply `tx`'s final plan again in a new transaction.
+/// Commands it ran successfully (`commands.json`) are only repeated when
+/// `command_classes` marks them idempotent.
+fn tx_resume(root: &Path, tx: Uuid, cfg: &Config) -> Result<()> {
+    let dir = log::tx_dir(root, tx);
+    let plan = read_json::<crate::wire::Plan>(&dir, "plan.json")?
+        .with_context(|| format!("transaction {} saved no plan.json to resume", tx))?;
+    if read_json::<apply::ApplySummary>(&dir, "apply.json")?.is_some() {
+        anyhow::bail!("transaction {} applied completely; nothing to resume", tx);
+    }
+    let succeeded: Vec<String> = read_json(&dir, "commands.json")?.unwrap_or_default();
+    let mut cfg = cfg.clone();
+    cfg.trusted = trust::ensure_trusted(root)?;
+
+    ux::show_plan(&plan);
+    let skipped: Vec<&String> = succeeded.iter().filter(|c| !cfg.command_is_idempotent(c)).collect();
+    if !skipped.is_empty() {
+        println!("Already ran in {}, not repeated:", tx);
+        for c in &skipped {
+            println!("  - {}", c);
+        }
+    }
+    if !cfg.auto_approve && !ux::confirm(&format!("Apply the {} step(s) of {} again?", plan.steps.len(), tx)) {
+        println!("Aborted by user.");
+        return Ok(());
+    }
+
+    let resume_tx = Uuid::new_v4();
+    let _lock = crate::gc::RunLock::acquire(root, resume_tx)?;
+    log::save_artifact("plan.json", &plan, resume_tx, &cfg)?;
+    log::save_artifact("resume.json", &serde_json::json!({ "from_tx": tx, "skipped_commands": skipped }), resume_tx, &cfg)?;
+    let summary = apply::resume_steps(root, &plan.steps, &cfg, &tx_task(root, tx), resume_tx, succeeded)?;
+    ux::print_apply_dashboard(&summary);
+    log::save_artifact("apply.json", &summary, resume_tx, &cfg)?;
+    println!("Resumed {} as {}.", tx, resume_tx);
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(dir: &Path, name: &str) -> Result<Option<T>> {
+    match log::read_artifact(dir, name)? {
+        Some(s) => Ok(Some(serde_json::from_str(&s).with_context(|| format!("parsing {}", name))?)),
+        None => Ok(None),
+    }
+}
+
+/// `vibe last`: the newest run as it was printed, from artifacts only.
+/// Runs from before plan.json existed fall back to the CODEGEN response.
+fn last(root: &Path, open: bool) -> Result<()> {
+    let Some((tx, dir)) = latest_run(root)? else {
+        anyhow::bail!("no runs recorded under {}", log::tx_root(root).display());
+    };
+    println!("Transaction {} ({})", tx, dir.display());
+    let task = tx_task(root, tx);
+    if !task.is_empty() {
+        println!("Task: {}", task);
+    }
+    let plan = match read_json::<crate::wire::Plan>(&dir, "plan.json")? {
+        Some(p) => Some(p),
+        None => read_json::<crate::wire::LlmResponse>(&dir, "codegen.response.json")?.and_then(|r| r.plan),
+    };
+    match plan {
+        Some(p) => ux::show_plan(&p),
+        None => println!("\n(no plan was produced)"),
+    }
+    if let Some(previews) = read_json::<Vec<patch::Preview>>(&dir, "preview.json")? {
+        ux::print_preview_dashboard(&previews);
+    }
+    match read_json::<apply::ApplySummary>(&dir, "apply.json")? {
+        Some(summary) => ux::print_apply_dashboard(&summary),
+        None => println!("\n(not applied: dry run, aborted or failed before apply)"),
+    }
+    if let Some(report) = read_json::<crate::verify::VerifyReport>(&dir, "verify.json")? {
+        ux::print_verify_matrix(&report);
+    }
+    if let Some(report) = read_json::<crate::smoke::SmokeReport>(&dir, "smoke.json")? {
+        ux::print_smoke_table(&report);
+    }
+    if open {
+        open_dir(&dir)?;
+    }
+    Ok(())
+}
+
+/// Open `dir` with the platform's file manager.
+fn open_dir(dir: &Path) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(dir)
+        .spawn()
+        .with_context(|| format!("running {} {}", opener, dir.display()))?;
+    Ok(())
+}
+
+/// The user task of a transaction, from its saved PLAN request.
+fn tx_task(root: &Path, tx: Uuid) -> String {
+    log::read_artifact(&log::tx_dir(root, tx), "plan.request.json")
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v.get("task").and_then(|t| t.as_str()).map(|t| t.to_string()))
+        .unwrap_or_default()
+}
+
+/// Title of step `id` in the transaction's CODEGEN response, if saved.
+fn step_title(root: &Path, tx: Uuid, id: &str) -> Option<String> {
+    let s = log::read_artifact(&log::tx_dir(root, tx), "codegen.response.json").ok()??;
+    let v: serde_json::Value = serde_json::from_str(&s).ok()?;
+    v.pointer("/plan/steps")?
+        .as_array()?
+        .iter()
+        .find(|st| st.get("id").and_then(|i| i.as_str()) == Some(id))
+        .and_then(|st| st.get("title").and_then(|t| t.as_str()).map(|t| t.to_string()))
+}
+
+fn blame(root: &Path, path: &str) -> Result<()> {
+    let content = std::fs::read_to_string(root.join(path)).with_context(|| format!("reading {}", path))?;
+    let hist = backup::file_history(root, path)?;
+    // annotation first; otherwise the transaction whose backup shows the file as new
+    let (tx, step) = match annotate::find(&content) {
+        Some((tx, step)) => (tx, Some(step)),
+        None => match hist.iter().find(|h| !h.existed_before) {
+            Some(h) => (h.tx, None),
+            None => {
+                println!("{}: no vibe annotation and no transaction created it", path);
+                return Ok(());
+            }
+        },
+    };
+
+    println!("{}", path);
+    println!("  created by  tx {}", tx);
+    if let Some(h) = hist.iter().find(|h| h.tx == tx) {
+        println!("  applied     {}", h.applied_at.format("%Y-%m-%d %H:%M:%S"));
+    }
+    let task = tx_task(root, tx);
+    if !task.is_empty() {
+        println!("  task        {}", task);
+    }
+    if let Some(step) = &step {
+        match step_title(root, tx, step) {
+            Some(title) => println!("  step        {} — {}", step, title),
+            None => println!("  step        {}", step),
+        }
+    }
+    let later = hist.iter().filter(|h| h.tx != tx && h.existed_before).count();
+    if later > 0 {
+        println!("  modified by {} later transaction(s); see `history {}`", later, path);
+    }
+    Ok(())
+}
+
+fn history(root: &Path, path: &str, diff: Option<&[usize]>) -> Result<()> {
+    let hist = backup::file_history(root, path)?;
+    if hist.is_empty() {
+        println!("No transaction touched {}", path);
+        return Ok(());
+    }
+    let versions = backup::versions(root, path, &hist)?;
+    if let Some([from, to]) = diff {
+        let get = |i: usize| {
+            versions
+                .get(i)
+                .with_context(|| format!("no version {} (0..={})", i, versions.len() - 1))
+        };
+        let (a, b) = (get(*from)?, get(*to)?);
+        println!("{} v{} -> v{}", path, from, to);
+        println!("{}", patch::short_diff(a.as_deref().unwrap_or(""), b.as_deref().unwrap_or(""), 500));
+        return Ok(());
+    }
+
+    println!("v0  before the first transaction{}", if versions[0].is_none() { " (absent)" } else { "" });
+    for (i, h) in hist.iter().enumerate() {
+        let task = tx_task(root, h.tx);
+        let state = if versions[i + 1].is_none() { " (deleted)" } else if !h.existed_before { " (created)" } else { "" };
+        println!(
+            "v{:<3} {}  {}{}  {}",
+            i + 1,
+            h.applied_at.format("%Y-%m-%d %H:%M:%S"),
+            h.tx,
+            state,
+            task
+        );
+    }
+    println!("\nCompare versions with: history {} --diff <FROM> <TO>", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backed-up transaction that changed `rel` from `before` to `after`.
+    fn applied(root: &Path, rel: &str, before: &str, after: &str) -> Uuid {
+        let tx = Uuid::new_v4();
+        let abs = root.join(rel);
+        std::fs::create_dir_all(abs.parent().unwrap()).unwrap();
+        std::fs::write(&abs, before).unwrap();
+        let mut b = backup::Backup::new(root, tx);
+        b.record(&abs, rel).unwrap();
+        std::fs::write(&abs, after).unwrap();
+        b.written(rel, Some(after)).unwrap();
+        tx
+    }
+
+    fn newest_other_tx(root: &Path, seen: &[Uuid]) -> Uuid {
+        std::fs::read_dir(log::tx_root(root))
+            .unwrap()
+            .filter_map(|e| Uuid::parse_str(&e.unwrap().file_name().to_string_lossy()).ok())
+            .find(|t| !seen.contains(t))
+            .unwrap()
+    }
+
+    #[test]
+    fn restore_then_undo_brings_back_the_applied_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let cfg = Config { root: root.to_string_lossy().into_owned(), auto_approve: true, ..Config::default() };
+        let rel = "src/page.tsx";
+        let tx = applied(root, rel, "v1", "v2");
+
+        restore(root, rel, tx, &cfg).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join(rel)).unwrap(), "v1");
+        let restore_tx = newest_other_tx(root, &[tx]);
+
+        // the restore knows what it wrote, so undo sees the file untouched
+        let files = undo::plan(root, restore_tx).unwrap();
+        assert!(files.iter().all(|f| f.state == undo::State::AsApplied));
+        undo(root, restore_tx, false, &cfg).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join(rel)).unwrap(), "v2");
+    }
+
+    #[test]
+    fn undo_of_a_restore_keeps_later_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let cfg = Config { root: root.to_string_lossy().into_owned(), auto_approve: true, ..Config::default() };
+        let rel = "src/page.tsx";
+        let tx = applied(root, rel, "v1", "v2");
+
+        restore(root, rel, tx, &cfg).unwrap();
+        let restore_tx = newest_other_tx(root, &[tx]);
+        std::fs::write(root.join(rel), "v3").unwrap();
+
+        undo(root, restore_tx, false, &cfg).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join(rel)).unwrap(), "v3");
+    }
+}
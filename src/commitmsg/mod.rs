@@ -0,0 +1,72 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use fs_err as fs;
+use serde_json::json;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::provider::jsonfix::strip_fences;
+use crate::provider::DynProvider;
+use crate::{context, git, log, prompt, wire};
+
+/// `--mode commitmsg`: ask `prov` for a conventional-commit message for the
+/// staged changes (`--task` is passed along as a hint) and, unless
+/// `--dry-run`, write it to `.git/COMMIT_EDITMSG` for `git commit -e`/`-F`.
+pub async fn run(prov: &DynProvider, root: &Path, txid: Uuid, args: &Args, cfg: &Config) -> Result<String> {
+    if !git::is_repo(root) {
+        bail!("{} is not inside a git repository", root.display());
+    }
+    let Some(staged) = context::history::staged(root, cfg.git_context_max_diff_bytes)? else {
+        bail!("nothing is staged; `git add` the changes to describe first");
+    };
+    if staged.diff_truncated {
+        eprintln!("warning: staged diff cut to {} bytes (git_context_max_diff_bytes)", cfg.git_context_max_diff_bytes);
+    }
+    let hint = args.task.as_deref().unwrap_or("");
+    let req = wire::LlmRequest {
+        schema_version: "v1".into(),
+        mode: wire::Mode::Plan,
+        transaction: wire::Tx { id: txid, timestamp: Utc::now(), dry_run: true },
+        limits: wire::Limits { max_actions: 0, max_patch_bytes: 0, allowed_commands: vec![] },
+        task: hint.to_string(),
+        context: wire::ContextSlice {
+            summary: json!({ "note": "commit message for the staged diff" }),
+            files_index: vec![],
+            routes: vec![],
+            symbols: json!({}),
+            diagnostics: vec![],
+            files_snapshot: vec![],
+            git: Some(staged),
+        },
+        capabilities: vec![],
+        safety: wire::Safety { path_allowlist: vec![], command_allowlist: vec![] },
+        instruction: wire::Instruction {
+            system: prompt::system_prompt_commitmsg(),
+            user: prompt::user_prompt_commitmsg(hint),
+            developer: None,
+        },
+        wire_format: wire::WireFormat::Json,
+        sampling: cfg.sampling(),
+        follow_up: None,
+    };
+    let resp = prov.send(&req, args.debug).await?;
+    let saved = log::save_stage("commitmsg", &req, &resp, txid, cfg, args.save_request, args.save_response)?;
+    if args.debug {
+        log::print_saved_paths("commitmsg", &saved);
+        log::print_json_debug("commitmsg", &req, &resp)?;
+    }
+    let message = resp
+        .answer
+        .map(|a| strip_fences(&a.content).trim().to_string())
+        .filter(|m| !m.is_empty())
+        .context("the model returned no commit message")?;
+
+    if !args.dry_run {
+        let path = git::git_path(root, "COMMIT_EDITMSG")?;
+        fs::write(&path, format!("{}\n", message))?;
+        println!("Wrote {} (commit with `git commit -eF {}`).", path.display(), path.display());
+    }
+    Ok(message)
+}
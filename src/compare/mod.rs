@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use difflib::sequencematcher::SequenceMatcher;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::cli::{Args, ProviderKind};
+use crate::config::Config;
+use crate::provider::{self, tokens, Endpoint, Provider};
+use crate::wire::{self, LlmRequest, Plan, Step};
+use crate::{context, log, prompt};
+
+/// Price per million tokens (`[model_prices.<model>]` in config).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// What one model produced for the task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outcome {
+    pub target: String,
+    pub plan_steps: usize,
+    /// path -> action of the CODEGEN file steps.
+    pub files: BTreeMap<String, String>,
+    pub commands: Vec<String>,
+    pub tokens_in: usize,
+    pub tokens_out: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    contents: BTreeMap<String, String>,
+}
+
+/// One path across the two models.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRow {
+    pub path: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+    /// Line similarity of the two contents (0-1), when both wrote the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub task: String,
+    pub a: Outcome,
+    pub b: Outcome,
+    pub files: Vec<FileRow>,
+}
+
+/// `openai:gpt-4.1` or a bare model name on the selected `--provider`.
+fn target(entry: &str, args: &Args) -> Result<(ProviderKind, String)> {
+    if entry.contains(':') {
+        if let Ok(t) = provider::parse_chain_entry(entry) {
+            return Ok(t);
+        }
+    }
+    Ok((args.provider.clone(), entry.trim().to_string()))
+}
+
+fn base_request(mode: wire::Mode, task: &str, files: Vec<wire::FileBlob>, instruction: wire::Instruction, cfg: &Config) -> LlmRequest {
+    LlmRequest {
+        schema_version: "v1".into(),
+        mode,
+        transaction: wire::Tx { id: Uuid::new_v4(), timestamp: Utc::now(), dry_run: true },
+        limits: wire::Limits {
+            max_actions: cfg.max_actions,
+            max_patch_bytes: cfg.max_patch_bytes,
+            allowed_commands: cfg.command_allowlist.clone(),
+        },
+        task: task.to_string(),
+        context: wire::ContextSlice {
+            summary: json!({ "router": "App", "typescript": true, "note": "compare run" }),
+            files_index: vec![],
+            routes: vec![],
+            symbols: json!({}),
+            diagnostics: vec![],
+            files_snapshot: files,
+            git: None,
+        },
+        capabilities: vec!["fs.apply_patch".into(), "tests.run".into(), "cmd.run".into()],
+        safety: wire::Safety { path_allowlist: cfg.path_allowlist.clone(), command_allowlist: cfg.command_allowlist.clone() },
+        instruction,
+        wire_format: wire::WireFormat::Json,
+        sampling: cfg.sampling(),
+        follow_up: None,
+    }
+}
+
+/// PLAN then CODEGEN on one provider, same snapshot for every model.
+async fn run_one(
+    prov: &dyn Provider,
+    task: &str,
+    ctx_files: &[String],
+    plan_file_bytes: usize,
+    root: &Path,
+    cfg: &Config,
+    debug: bool,
+) -> Result<(Plan, Plan, usize, usize)> {
+    let plan_req = base_request(
+        wire::Mode::Plan,
+        task,
+        context::snapshot_files(ctx_files, root, plan_file_bytes, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist),
+        wire::Instruction {
+            system: prompt::system_prompt_plan(),
+            user: prompt::user_prompt_plan(task, ctx_files),
+            developer: Some("Output exactly one JSON object; PLAN must not include file contents.".to_string()),
+        },
+        cfg,
+    );
+    let plan_resp = prov.send(&plan_req, debug).await?;
+    let plan = plan_resp.plan.clone().filter(|p| !p.steps.is_empty()).ok_or_else(|| anyhow!("no usable plan"))?;
+    let codegen_req = base_request(
+        wire::Mode::Codegen,
+        task,
+        context::snapshot_files(ctx_files, root, 300_000, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist),
+        wire::Instruction {
+            system: prompt::system_prompt_codegen(),
+            user: prompt::user_prompt_codegen(
+                task,
+                &plan,
+                ctx_files,
+                &plan_req.instruction.system,
+                &plan_req.instruction.user,
+                plan_req.instruction.developer.as_deref(),
+            ),
+            developer: Some("Return full file contents in 'content' for created/updated files; prefer 'content' over 'patch'.".to_string()),
+        },
+        cfg,
+    );
+    let codegen_resp = prov.send(&codegen_req, debug).await?;
+    let tokens_in = tokens::request_tokens(&plan_req) + tokens::request_tokens(&codegen_req);
+    let tokens_out = [&plan_resp, &codegen_resp]
+        .iter()
+        .map(|r| tokens::estimate(&serde_json::to_string(r).unwrap_or_default()))
+        .sum();
+    let code = codegen_resp.plan.ok_or_else(|| anyhow!("no code changes returned"))?;
+    Ok((plan, code, tokens_in, tokens_out))
+}
+
+fn outcome(target: String, result: Result<(Plan, Plan, usize, usize)>, model: &str, latency_ms: u128, cfg: &Config) -> Outcome {
+    let mut o = Outcome {
+        target,
+        plan_steps: 0,
+        files: BTreeMap::new(),
+        commands: vec![],
+        tokens_in: 0,
+        tokens_out: 0,
+        cost_usd: None,
+        latency_ms,
+        error: None,
+        contents: BTreeMap::new(),
+    };
+    let (plan, code, tokens_in, tokens_out) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            o.error = Some(format!("{:#}", e).lines().next().unwrap_or("").to_string());
+            return o;
+        }
+    };
+    o.plan_steps = plan.steps.len();
+    o.tokens_in = tokens_in;
+    o.tokens_out = tokens_out;
+    o.cost_usd = cfg
+        .model_prices
+        .get(model)
+        .map(|p| (tokens_in as f64 * p.input + tokens_out as f64 * p.output) / 1_000_000.0);
+    for s in &code.steps {
+        match s {
+            Step::Create { path, content, .. } | Step::Update { path, content, .. } => {
+                o.files.insert(path.clone(), if matches!(s, Step::Create { .. }) { "create" } else { "update" }.into());
+                if let Some(c) = content {
+                    o.contents.insert(path.clone(), c.clone());
+                }
+            }
+            Step::Delete { path, .. } => {
+                o.files.insert(path.clone(), "delete".into());
+            }
+            Step::Command { command, .. } | Step::Test { command, .. } => o.commands.push(command.clone()),
+        }
+    }
+    o
+}
+
+/// Run one `--models` entry; a failing model is reported, not fatal.
+async fn measure(entry: &str, task: &str, ctx_files: &[String], plan_file_bytes: usize, endpoint: &Endpoint, args: &Args, cfg: &Config) -> Result<Outcome> {
+    let (kind, model) = target(entry, args)?;
+    let label = format!("{:?}:{}", kind, model).to_lowercase();
+    println!("Running {} ...", label);
+    let started = Instant::now();
+    let result = match provider::make_provider(kind, model.clone(), args.timeout_secs, args.openai_messages, endpoint.clone(), cfg) {
+        Ok(prov) => run_one(prov.as_ref(), task, ctx_files, plan_file_bytes, Path::new(&cfg.root), cfg, args.debug).await,
+        Err(e) => Err(e),
+    };
+    Ok(outcome(label, result, &model, started.elapsed().as_millis(), cfg))
+}
+
+fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    SequenceMatcher::new(&a, &b).ratio()
+}
+
+/// `vibe compare --models A,B --task ...`: run PLAN + CODEGEN with both
+/// models against the same snapshot (nothing is applied) and line up the
+/// files each would write. Saved as `compare.json` in a fresh tx directory.
+pub async fn run(models: &[String], args: &Args, cfg: &Config) -> Result<Comparison> {
+    let [a, b] = models else {
+        anyhow::bail!("--models takes exactly two models, e.g. --models gpt-4.1,anthropic:claude-sonnet-4-5");
+    };
+    let task = args.task.clone().ok_or_else(|| anyhow!("vibe compare needs --task"))?;
+    let root = Path::new(&cfg.root);
+    let breadth = cfg.adaptive_top_k.then(|| context::breadth::estimate(&task, root, cfg));
+    let top_k = breadth.as_ref().map(|b| b.top_k).unwrap_or(12);
+    let plan_file_bytes = breadth.as_ref().map(|b| b.plan_file_bytes).unwrap_or(8_192);
+    let (mut ctx_files, _) = context::select_relevant_files(&task, root, Path::new(&args.vibe_out), top_k);
+    for p in breadth.iter().flat_map(|b| &b.mentioned) {
+        if !ctx_files.contains(p) {
+            ctx_files.push(p.clone());
+        }
+    }
+    let endpoint = Endpoint { api_key: None, api_base: args.api_base.clone() };
+
+    let a = measure(a, &task, &ctx_files, plan_file_bytes, &endpoint, args, cfg).await?;
+    let b = measure(b, &task, &ctx_files, plan_file_bytes, &endpoint, args, cfg).await?;
+
+    let paths: BTreeSet<&String> = a.files.keys().chain(b.files.keys()).collect();
+    let files = paths
+        .into_iter()
+        .map(|p| FileRow {
+            path: p.clone(),
+            a: a.files.get(p).cloned(),
+            b: b.files.get(p).cloned(),
+            similarity: match (a.contents.get(p), b.contents.get(p)) {
+                (Some(x), Some(y)) => Some(similarity(x, y)),
+                _ => None,
+            },
+        })
+        .collect();
+    let cmp = Comparison { task, a, b, files };
+    log::save_artifact("compare.json", &cmp, Uuid::new_v4(), cfg)?;
+    Ok(cmp)
+}
@@ -1,15 +1,29 @@
+use anyhow::{Context, Result};
+use fs_err as fs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::ProviderKind;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub root: String,
     pub vibe_out: String,
-    pub provider: crate::cli::ProviderKind,
+    pub provider: ProviderKind,
     pub model: String,
     pub task: String,
     pub dry_run: bool,
     pub auto_approve: bool,
     pub timeout_secs: u64,
+
+    // Provider HTTP: give up connecting after `connect_timeout_secs`, and
+    // (when set) after `read_timeout_secs` without a byte of the reply; the
+    // whole request is still capped by `--timeout-secs`. Leave the read
+    // timeout off for slow non-streaming replies, which send nothing until done
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: Option<u64>,
     pub save_request: bool,
     pub save_response: bool,
     pub debug: bool,
@@ -17,6 +31,320 @@ pub struct Config {
     // Safety allowlists used by exec and request-building
     pub path_allowlist: Vec<String>,
     pub command_allowlist: Vec<String>,
+
+    // Approval rules (`allow update src/app/**`, `deny delete **`,
+    // `require-confirm command *`), relative to the root; see
+    // `safety::policy`. No file means every apply is confirmed
+    pub policy_file: String,
+
+    // Limits sent with every request: most steps per plan and largest
+    // patch/content the model should produce
+    pub max_actions: usize,
+    pub max_patch_bytes: usize,
+
+    // Files larger than this are listed in the snapshot by path and size
+    // only; generated file content over `max_generated_file_bytes` is
+    // rejected with a warning
+    pub max_snapshot_file_bytes: usize,
+    pub max_generated_file_bytes: usize,
+
+    // Symlinks (relative paths) that may resolve outside the root, e.g. pnpm
+    // workspace packages; any other such link is neither read nor written through
+    pub symlink_allowlist: Vec<String>,
+
+    // Globs (relative to root) for generated files: steps touching them are
+    // written as full overwrites, skipping the additive/strict merge path.
+    pub generated_globs: Vec<String>,
+
+    // Append the learned-preferences block from .vibe/preferences.json to PLAN prompts
+    pub learned_preferences: bool,
+
+    // When PLAN/CODEGEN says a project file was not provided, add it to the
+    // snapshot and retry that phase once
+    pub auto_widen_context: bool,
+
+    // Warn when at least this fraction of embedding chunks is stale; with
+    // `reindex_command` set (and allowlisted) it is run instead, e.g. an
+    // incremental `vibe index`
+    pub index_stale_ratio: f32,
+    pub reindex_command: Option<String>,
+
+    // `vibe watch`: quiet period before a burst of saves is reindexed
+    pub watch_debounce_ms: u64,
+
+    // Post-apply verification matrix (typecheck, lint, tests, build...)
+    pub verify: Vec<crate::verify::VerifyEntry>,
+    pub verify_parallel: bool,
+
+    // Extra pipeline stages run between PLAN and CODEGEN, in order
+    pub stages: Vec<crate::stages::StageConfig>,
+
+    // Idempotency classification of commands (longest matching prefix wins);
+    // non-idempotent commands are not re-executed on retry once they succeeded,
+    // within an apply or by `vibe tx resume`
+    pub command_classes: Vec<CommandClass>,
+
+    // CODEOWNERS owners that count as "us" (e.g. "@acme/web"); steps touching
+    // paths owned by anyone else are flagged, and blocked in the ci profile
+    // unless --override-owners is given
+    pub owners: Vec<String>,
+
+    // Run profile: `ci` rewrites install commands to frozen-lockfile variants
+    // for the detected package manager; `offline_installs` adds `--offline`
+    pub run_profile: crate::exec::policy::RunProfile,
+    pub offline_installs: bool,
+
+    // `vibe smoke`: dev server command (default `<pm> run dev`), port and
+    // startup timeout; `smoke_after_apply` runs it as a post-apply check
+    pub smoke_command: Option<String>,
+    pub smoke_port: u16,
+    pub smoke_startup_secs: u64,
+    pub smoke_after_apply: bool,
+
+    // Per-provider key names, e.g. { openai = "WORK_OPENAI_KEY" }; the name is
+    // looked up as an env var, in ~/.config/vibe/credentials.toml and in the
+    // OS keychain, in that order
+    pub credential_names: BTreeMap<String, String>,
+
+    // USD per million input/output tokens by model name, e.g.
+    // `[model_prices."gpt-4.1"] input = 2.0, output = 8.0`; used for the cost
+    // column of `vibe compare`
+    pub model_prices: BTreeMap<String, crate::compare::Price>,
+
+    // Named provider profiles (`--profile-provider <name>`); merged with the
+    // ones from the user config, project entries win on name clashes
+    pub provider_profiles: BTreeMap<String, ProviderProfile>,
+
+    // Pages over this many lines fail linting; `split_strategy` decides whether
+    // components are extracted deterministically first or the model is asked.
+    // Pages still over the limit after the repairs stop the run
+    pub max_file_lines: Option<usize>,
+    pub split_strategy: SplitStrategy,
+
+    // Ollama server (`--ollama-url`); falls back to OLLAMA_HOST, then localhost
+    pub ollama_url: Option<String>,
+
+    // Azure OpenAI endpoint/deployment/api-version (env vars as fallback)
+    pub azure: crate::provider::azure::AzureConfig,
+
+    // Retries with exponential backoff on 429/5xx, timeouts and resets
+    pub retry: crate::provider::retry::RetryPolicy,
+
+    // Client-side requests/tokens per minute for each provider; over the limit
+    // requests wait instead of being sent
+    pub rate_limit: crate::provider::ratelimit::RateLimit,
+
+    // Fallback chain, e.g. ["openai:gpt-4.1", "anthropic:claude-3-5-sonnet-latest"]:
+    // replaces --provider/--model; on an error (after retries) or unparseable
+    // reply the same request goes to the next entry. `@name` uses a provider
+    // profile; entries on the run's own provider share its key/--api-base
+    pub provider_chain: Vec<String>,
+
+    // PLAN racing, e.g. ["openai:gpt-4.1", "anthropic:claude-sonnet-4-0"]
+    // (`--race-plan`): the PLAN request goes to all of them at once and the
+    // first valid plan wins; the others are cancelled. Empty = off
+    pub plan_race: Vec<String>,
+
+    // Per-phase models on the selected provider, e.g. a cheap one for PLAN
+    // and a strong one for CODEGEN (`--plan-model`/`--codegen-model` win);
+    // unset uses `--model`. Ignored when `provider_chain` is set
+    pub plan_model: Option<String>,
+    pub codegen_model: Option<String>,
+
+    // Self-consistency: PLAN is sampled this many times (extra samples at
+    // `temperature`, or 0.7 when that is unset/0) and the plan scoring best on
+    // allowlists, consistency with the files on disk and size is kept
+    pub plan_samples: usize,
+
+    // Lint repair round-trips after CODEGEN; the temperature steps down to 0
+    // over the attempts
+    pub repair_attempts: usize,
+
+    // `--provider replay`: transaction (uuid under .vibe/tx) whose saved
+    // responses are replayed instead of calling a model
+    pub replay_tx: Option<String>,
+
+    // Sampling sent to every provider (`--temperature`, `--top-p`,
+    // `--max-output-tokens` win); unset keeps each adapter's default
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+
+    // Reply format requested from the model (`--wire-format`); `yaml` drops
+    // the providers' JSON modes and parses YAML instead
+    pub wire_format: crate::wire::WireFormat,
+
+    // Model context window in tokens (default: looked up from the model
+    // name); requests over it (or rejected as too long) get files_snapshot
+    // compacted and are retried, or are refused when `trim_snapshot_to_fit`
+    // is off
+    pub context_window: Option<usize>,
+    pub trim_snapshot_to_fit: bool,
+
+    // Tools every apply needs (e.g. "node>=18", "git"); checked before apply
+    // together with the plan's `requires` and the programs its commands run
+    pub requirements: Vec<String>,
+
+    // OpenAI/Azure: send the wire schema as a strict `json_schema` response
+    // format; turn off for models without structured outputs (falls back to
+    // `json_object`)
+    pub openai_json_schema: bool,
+
+    // How providers ask for JSON replies: `json-schema`, `json-object` or
+    // `prompt`. Unset picks the most reliable one the model supports (see
+    // `model_capabilities`); an endpoint that rejects it is retried with the
+    // next and the limit remembered in .vibe/capabilities.json
+    pub structured_output: Option<crate::provider::capabilities::StructuredOutput>,
+
+    // Per-model capabilities over the built-in table and what was learned,
+    // keyed by `provider:model` or a bare model (`*` wildcards):
+    // [model_capabilities."openai-compatible:qwen*"] json_object = false
+    pub model_capabilities: BTreeMap<String, crate::provider::capabilities::Capabilities>,
+
+    // Anthropic: return plans through a forced `emit_plan` tool call instead
+    // of printed JSON
+    pub anthropic_tool_use: bool,
+
+    // Send the strict PLAN re-ask as a follow-up turn on the first PLAN
+    // conversation (chat providers) so its unchanged prefix hits the prompt
+    // cache; off re-sends a standalone strict request
+    pub strict_follow_up: bool,
+
+    // vLLM/TGI: constrain JSON replies to the wire schema (vLLM
+    // `structured_outputs`, TGI `/generate` grammars); turn off for servers
+    // built without guided decoding
+    pub guided_decoding: bool,
+
+    // Anthropic/Bedrock: mark the system prompt and the request turn as
+    // prompt-cache breakpoints so repeat runs and follow-ups pay cached-input
+    // prices (OpenAI caches long prefixes on its own)
+    pub prompt_cache: bool,
+
+    // Step templates offered by the plan editor (`add <name> <args...>`);
+    // test steps added from a test template run without being in
+    // `command_allowlist`
+    pub step_templates: Vec<crate::templates::StepTemplate>,
+
+    // Output theme (`default`, `colorblind`, `mono`; VIBE_THEME wins), per-role
+    // color overrides (`add`, `remove`, `change`, `ok`, `error`, `warn`, ...)
+    // and whether +/-/~ style symbols prefix labels
+    pub theme: Option<String>,
+    pub theme_palette: BTreeMap<String, String>,
+    pub theme_symbols: bool,
+
+    // Store tx artifacts as `*.json.zst`; turn off to keep plain JSON that
+    // can be opened directly
+    pub compress_artifacts: bool,
+
+    // Append a `vibe: tx <id> step <id>` comment to created files so
+    // `vibe blame` can trace them back to their task
+    pub annotate_created_files: bool,
+
+    // After apply, offer to queue the TODO/FIXME markers the generated code
+    // introduced as tasks in .vibe/followups.txt (run them with --follow-up)
+    pub offer_follow_up_queue: bool,
+
+    // Tell CODEGEN the tsconfig `paths` alias convention and rewrite
+    // generated import specifiers to it (`../../components/X` -> `@/components/X`)
+    pub normalize_import_aliases: bool,
+
+    // Confirmation prompts left unanswered this many seconds resolve to
+    // `confirm_default` (abort or apply); the decision is saved to the tx's
+    // approval.json. Unset waits forever
+    pub confirm_timeout_secs: Option<u64>,
+    pub confirm_default: crate::ux::input::ConfirmDefault,
+
+    // License/copyright header prepended to created source files, one
+    // comment line per template line (`{year}` is filled in); full-content
+    // updates that drop an existing header get it back
+    pub license_header: Option<String>,
+
+    // Scale context retrieval with the task's breadth: "fix a typo in
+    // NavBar" gets `top_k_min` files with up to `plan_file_bytes_max` of each
+    // in the PLAN snapshot, "transform the whole app" up to `top_k_max` files
+    // at `plan_file_bytes_min`. Off: 12 files, 8 KiB each
+    pub adaptive_top_k: bool,
+    pub top_k_min: usize,
+    pub top_k_max: usize,
+    pub plan_file_bytes_min: usize,
+    pub plan_file_bytes_max: usize,
+
+    // Commands run (allowlisted, trusted workspaces only) in the project
+    // root right before apply and right after it, before any commit, e.g.
+    // pre_apply = "npm run lint", post_apply = "npm run format". A failing
+    // hook aborts the run (`hook_failure = "abort"`) or only warns ("warn")
+    pub pre_apply: Option<String>,
+    pub post_apply: Option<String>,
+    pub hook_failure: crate::exec::hooks::HookFailure,
+
+    // All or nothing: when a step fails (a rejected path, a write error, a
+    // command exiting non-zero) apply puts back every file it had written
+    // and, with `--commit-per-step`, drops the step commits. Off, the steps
+    // before the failure stay applied and failing commands are only reported
+    pub transactional_apply: bool,
+
+    // In a git repository, what to do when files the plan touches have
+    // uncommitted changes: `allow`, `refuse` (default) or `stash` them and
+    // pop the stash after apply. Files still as an earlier run left them
+    // don't count
+    pub dirty_worktree: crate::git::DirtyWorktree,
+
+    // Create `vibe/<short-txid>` from HEAD and apply there, leaving the
+    // working branch untouched (`--git-branch`)
+    pub git_branch: bool,
+
+    // After apply, stage only the files the transaction wrote or deleted and
+    // commit them with a message built from the plan (`--git-commit`); the
+    // SHA is saved to the tx's git.json
+    pub git_commit: bool,
+
+    // Commit after each file-affecting step instead, with the step title as
+    // the message, so reviewers can step through the change
+    // (`--commit-per-step`, implies git_commit)
+    pub git_commit_per_step: bool,
+
+    // Push the tx branch and open a pull/merge request with `gh`/`glab`
+    // after apply (`--open-pr`, implies --git-branch and --git-commit):
+    // [pr] open, host (auto|github|gitlab), remote, base, draft, labels,
+    // reviewers, max_diff_lines
+    pub pr: crate::git::pr::PrConfig,
+
+    // Send the last 10 commits (`git log --oneline`) and the uncommitted
+    // diff of the context files along with them (`--git-context`); the diff
+    // is cut at `git_context_max_diff_bytes`
+    pub git_context: bool,
+    pub git_context_max_diff_bytes: usize,
+
+    // For a fix task (fix, bug, crash...) that names files, add to the
+    // context summary which regions of those files the last 5 commits
+    // changed, with their messages (`--blame-context`)
+    pub blame_context: bool,
+
+    // Before the apply prompt, `git fetch` and warn about files the plan
+    // writes that changed on the branch's upstream since HEAD, i.e. that
+    // will conflict on push (`--check-upstream`)
+    pub upstream_check: bool,
+
+    // Encrypt new .vibe/tx artifacts and backups (AES-256-GCM, `*.enc`) with
+    // the key in VIBE_ARTIFACT_KEY (env, credentials.toml or the keychain:
+    // `vibe auth artifact-key`); encrypted ones are read back transparently
+    pub encrypt_artifacts: bool,
+
+    // On startup, delete `*.__tmp__` files a crashed apply left that are at
+    // least `gc_min_age_secs` old, and a `.vibe/lock` whose run is gone
+    // (`--no-gc` skips this)
+    pub gc_on_startup: bool,
+    pub gc_min_age_secs: u64,
+
+    // Set at startup from the workspace trust store; untrusted projects
+    // run with commands disabled and deletes blocked.
+    #[serde(skip)]
+    pub trusted: bool,
+
+    // Set after plan editing: the test commands the user added from test
+    // templates this run, which run without being in `command_allowlist`.
+    #[serde(skip)]
+    pub user_test_commands: Vec<String>,
 }
 
 impl Default for Config {
@@ -24,18 +352,218 @@ impl Default for Config {
         Self {
             root: ".".to_string(),
             vibe_out: ".vibe/out".to_string(),
-            provider: crate::cli::ProviderKind::OpenAI,
+            provider: ProviderKind::OpenAI,
             model: "gpt-4o-mini".to_string(),
             task: String::new(),
             dry_run: false,
             auto_approve: false,
             timeout_secs: 2400,
+            connect_timeout_secs: 10,
+            read_timeout_secs: None,
             save_request: true,
             save_response: true,
             debug: false,
             path_allowlist: default_path_allowlist(),
             command_allowlist: default_command_allowlist(),
+            policy_file: ".vibe/policy.yaml".to_string(),
+            max_actions: 40,
+            max_patch_bytes: 300_000,
+            max_snapshot_file_bytes: 1_000_000,
+            max_generated_file_bytes: 512_000,
+            symlink_allowlist: vec![],
+            generated_globs: vec![],
+            learned_preferences: false,
+            auto_widen_context: true,
+            index_stale_ratio: 0.2,
+            reindex_command: None,
+            watch_debounce_ms: 500,
+            verify: vec![],
+            verify_parallel: false,
+            stages: vec![],
+            command_classes: default_command_classes(),
+            owners: vec![],
+            run_profile: crate::exec::policy::RunProfile::Dev,
+            offline_installs: false,
+            smoke_command: None,
+            smoke_port: 3000,
+            smoke_startup_secs: 120,
+            smoke_after_apply: false,
+            credential_names: BTreeMap::new(),
+            model_prices: BTreeMap::new(),
+            provider_profiles: BTreeMap::new(),
+            max_file_lines: None,
+            split_strategy: SplitStrategy::Extract,
+            ollama_url: None,
+            azure: Default::default(),
+            retry: Default::default(),
+            rate_limit: Default::default(),
+            provider_chain: vec![],
+            plan_race: vec![],
+            plan_model: None,
+            codegen_model: None,
+            plan_samples: 1,
+            repair_attempts: 2,
+            replay_tx: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            wire_format: Default::default(),
+            context_window: None,
+            trim_snapshot_to_fit: true,
+            requirements: vec![],
+            openai_json_schema: true,
+            structured_output: None,
+            model_capabilities: BTreeMap::new(),
+            anthropic_tool_use: true,
+            guided_decoding: true,
+            strict_follow_up: true,
+            prompt_cache: true,
+            step_templates: crate::templates::default_step_templates(),
+            theme: None,
+            theme_palette: BTreeMap::new(),
+            theme_symbols: true,
+            compress_artifacts: true,
+            annotate_created_files: true,
+            offer_follow_up_queue: true,
+            normalize_import_aliases: true,
+            confirm_timeout_secs: None,
+            confirm_default: crate::ux::input::ConfirmDefault::Abort,
+            license_header: None,
+            adaptive_top_k: true,
+            top_k_min: 6,
+            top_k_max: 40,
+            plan_file_bytes_min: 4_096,
+            plan_file_bytes_max: 16_384,
+            pre_apply: None,
+            post_apply: None,
+            hook_failure: crate::exec::hooks::HookFailure::Abort,
+            transactional_apply: true,
+            dirty_worktree: crate::git::DirtyWorktree::Refuse,
+            git_branch: false,
+            git_commit: false,
+            git_commit_per_step: false,
+            pr: Default::default(),
+            git_context: false,
+            git_context_max_diff_bytes: 20_000,
+            blame_context: false,
+            upstream_check: false,
+            encrypt_artifacts: false,
+            gc_on_startup: true,
+            gc_min_age_secs: 600,
+            trusted: false,
+            user_test_commands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitStrategy {
+    /// Move self-contained components out deterministically, ask the model for the rest.
+    Extract,
+    /// Always ask the model (via the lint repair round-trip).
+    Model,
+}
+
+/// Provider settings bundled under a name (e.g. `work-azure`, `local`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderProfile {
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Literal key; prefer `api_key_env` to keep secrets out of config files.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    #[serde(default)]
+    pub api_base: Option<String>,
+}
+
+impl ProviderProfile {
+    pub fn resolve_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .or_else(|| self.api_key_env.as_ref().and_then(|v| std::env::var(v).ok()))
+    }
+}
+
+/// Subset of `~/.config/vibe/config.toml` read on every run.
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    provider_profiles: BTreeMap<String, ProviderProfile>,
+}
+
+/// `~/.config/vibe` (HOME on *nix, USERPROFILE on Windows).
+pub fn user_config_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".config").join("vibe"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandClass {
+    pub prefix: String,
+    pub idempotent: bool,
+}
+
+impl Config {
+    /// Load a TOML config file; missing keys fall back to defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = fs::read_to_string(path)?;
+        toml::from_str(&s).with_context(|| format!("parsing config {}", path.display()))
+    }
+
+    /// Whether `cmd` is safe to re-run. Unclassified commands are treated as
+    /// non-idempotent so retries never repeat them blindly.
+    pub fn command_is_idempotent(&self, cmd: &str) -> bool {
+        let cmd = cmd.trim();
+        self.command_classes
+            .iter()
+            .filter(|c| cmd == c.prefix || cmd.starts_with(&format!("{} ", c.prefix)))
+            .max_by_key(|c| c.prefix.len())
+            .map(|c| c.idempotent)
+            .unwrap_or(false)
+    }
+
+    /// Add provider profiles from the user config that the project config
+    /// doesn't define itself.
+    pub fn merge_user_profiles(&mut self) -> Result<()> {
+        let Some(path) = user_config_dir().map(|d| d.join("config.toml")) else { return Ok(()) };
+        if !path.exists() {
+            return Ok(());
+        }
+        let s = fs::read_to_string(&path)?;
+        let user: UserConfig = toml::from_str(&s).with_context(|| format!("parsing user config {}", path.display()))?;
+        for (name, p) in user.provider_profiles {
+            self.provider_profiles.entry(name).or_insert(p);
         }
+        Ok(())
+    }
+
+    pub fn provider_profile(&self, name: &str) -> Result<&ProviderProfile> {
+        self.provider_profiles.get(name).with_context(|| {
+            let known: Vec<&str> = self.provider_profiles.keys().map(|k| k.as_str()).collect();
+            format!("unknown provider profile '{}' (available: {})", name, known.join(", "))
+        })
+    }
+
+    pub fn sampling(&self) -> crate::wire::Sampling {
+        crate::wire::Sampling {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_output_tokens: self.max_output_tokens,
+        }
+    }
+
+    /// True if `rel` (project-relative) matches one of `generated_globs`.
+    pub fn is_generated(&self, rel: &str) -> bool {
+        let rel = crate::safety::normalize_rel(rel);
+        self.generated_globs.iter().any(|g| {
+            glob::Pattern::new(g)
+                .map(|p| p.matches(&rel))
+                .unwrap_or(false)
+        })
     }
 }
 
@@ -82,5 +610,42 @@ pub fn default_command_allowlist() -> Vec<String> {
         "yarn dev".to_string(),
         "yarn install".to_string(),
         "yarn add".to_string(), // new
+
+        // bun
+        "bun install".to_string(),
+        "bun i".to_string(),
+        "bun add".to_string(),
+        "bun run build".to_string(),
+        "bun run dev".to_string(),
+    ]
+}
+
+pub fn default_command_classes() -> Vec<CommandClass> {
+    let class = |prefix: &str, idempotent: bool| CommandClass { prefix: prefix.to_string(), idempotent };
+    vec![
+        // installs/builds converge to the same state when re-run
+        class("npm ci", true),
+        class("npm install", true),
+        class("npm i", true),
+        class("npm run build", true),
+        class("pnpm i", true),
+        class("pnpm install", true),
+        class("pnpm add", true),
+        class("pnpm build", true),
+        class("yarn", true),
+        class("yarn install", true),
+        class("yarn add", true),
+        class("yarn build", true),
+        class("bun install", true),
+        class("bun i", true),
+        class("bun add", true),
+        class("bun run build", true),
+        // long-running or state-changing
+        class("npm run dev", false),
+        class("pnpm dev", false),
+        class("yarn dev", false),
+        class("bun run dev", false),
+        class("npx prisma migrate", false),
+        class("prisma migrate", false),
     ]
 }
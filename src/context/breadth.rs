@@ -0,0 +1,132 @@
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config::Config;
+
+/// Phrases that mean the task reaches across the app.
+const BROAD: &[&str] = &[
+    "whole app", "entire", "every page", "all pages", "all components", "everywhere", "across", "throughout",
+    "site-wide", "sitewide", "app-wide", "full application", "from scratch", "redesign", "rebrand", "overhaul",
+    "restructure", "transform", "migrate", "refactor", "rename", "dark mode", "theme", "i18n", "translate",
+    "scaffold", "augment",
+];
+/// Phrases that mean a small, local change.
+const NARROW: &[&str] = &[
+    "typo", "fix a", "small", "tweak", "just", "only", "single", "one line", "wording", "label", "padding",
+    "margin", "spacing", "color of", "button text",
+];
+const SKIP_DIRS: &[&str] = &["node_modules", ".next", ".git", ".vibe", "dist", "build", "out", "coverage"];
+const SOURCE_EXTS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "css", "json", "md", "mdx"];
+
+/// How much of the project a task is likely to touch, and the retrieval
+/// budget that follows from it.
+#[derive(Debug, Clone)]
+pub struct Breadth {
+    /// 0.0 (one spot) to 1.0 (the whole app).
+    pub score: f32,
+    /// Files to retrieve from the index.
+    pub top_k: usize,
+    /// Per-file byte budget of the PLAN snapshot.
+    pub plan_file_bytes: usize,
+    /// Project files the task names (by path or component name); always in context.
+    pub mentioned: Vec<String>,
+    /// What moved the score, for the one-line report.
+    pub reasons: Vec<String>,
+}
+
+fn path_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[\w@./-]*[\w-]\.(?:tsx?|jsx?|mjs|css|json|mdx?)\b|[\w@.-]+(?:/[\w@.\[\]()-]+)+").unwrap())
+}
+
+fn has_word(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(at, _)| {
+        let before = text[..at].chars().next_back().map(|c| !c.is_alphanumeric()).unwrap_or(true);
+        let after = text[at + phrase.len()..].chars().next().map(|c| !c.is_alphanumeric()).unwrap_or(true);
+        before && after
+    })
+}
+
+/// Files under `root` the task names: explicit paths that exist, and source
+/// files whose stem matches a capitalized word (`NavBar` -> `.../NavBar.tsx`).
+pub fn mentioned_files(task: &str, root: &Path) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for m in path_re().find_iter(task) {
+        let rel = crate::safety::normalize_rel(m.as_str().trim_end_matches('.'));
+        if root.join(&rel).is_file() && !out.contains(&rel) {
+            out.push(rel);
+        }
+    }
+    let names: Vec<&str> = task
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 3 && w.starts_with(|c: char| c.is_ascii_uppercase()))
+        .collect();
+    if names.is_empty() {
+        return out;
+    }
+    // gitignore-aware, so a component in build output isn't taken for the source
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|e| !(e.file_type().is_some_and(|t| t.is_dir()) && SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref())))
+        .build();
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_some_and(|t| t.is_file())) {
+        let p = entry.path();
+        let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        if !SOURCE_EXTS.contains(&ext) || !names.contains(&stem) {
+            continue;
+        }
+        let Ok(rel) = p.strip_prefix(root) else { continue };
+        let rel = crate::safety::normalize_rel(&rel.to_string_lossy());
+        if !out.contains(&rel) {
+            out.push(rel);
+        }
+    }
+    out
+}
+
+/// Estimate `task`'s breadth from its wording, the files it names and the
+/// operation-mode hints it gives, and scale the retrieval budget between the
+/// configured bounds: broad tasks get more files with less of each, narrow
+/// ones fewer files read further.
+pub fn estimate(task: &str, root: &Path, cfg: &Config) -> Breadth {
+    let text = task.to_lowercase();
+    let mentioned = mentioned_files(task, root);
+    let mut score: f32 = 0.35;
+    let mut reasons = Vec::new();
+
+    let broad: Vec<&str> = BROAD.iter().copied().filter(|p| has_word(&text, p)).collect();
+    let narrow: Vec<&str> = NARROW.iter().copied().filter(|p| has_word(&text, p)).collect();
+    score += 0.2 * broad.len() as f32 - 0.15 * narrow.len() as f32;
+    if !broad.is_empty() {
+        reasons.push(format!("broad: {}", broad.join(", ")));
+    }
+    if !narrow.is_empty() {
+        reasons.push(format!("narrow: {}", narrow.join(", ")));
+    }
+    // naming a file or two pins the task down; naming many spreads it out
+    match mentioned.len() {
+        0 => {}
+        n @ 1..=2 => {
+            score -= 0.15 * n as f32;
+            reasons.push(format!("names {}", mentioned.join(", ")));
+        }
+        n => {
+            score += 0.1;
+            reasons.push(format!("names {} files", n));
+        }
+    }
+    if text.split_whitespace().count() > 60 {
+        score += 0.1;
+        reasons.push("long task".into());
+    }
+    let score = score.clamp(0.0, 1.0);
+
+    let (k_lo, k_hi) = (cfg.top_k_min.min(cfg.top_k_max), cfg.top_k_max.max(cfg.top_k_min));
+    let (b_lo, b_hi) = (cfg.plan_file_bytes_min.min(cfg.plan_file_bytes_max), cfg.plan_file_bytes_max.max(cfg.plan_file_bytes_min));
+    let top_k = k_lo + ((k_hi - k_lo) as f32 * score).round() as usize;
+    let plan_file_bytes = b_hi - ((b_hi - b_lo) as f32 * score).round() as usize;
+    Breadth { score, top_k, plan_file_bytes, mentioned, reasons }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use fs_err as fs;
+#[cfg(feature = "sqlite")]
 use rusqlite::{Connection, OpenFlags};
 use serde::Deserialize;
 use serde_json::Value;
@@ -31,6 +32,25 @@ pub struct EmbeddingChunk {
     pub sha1: Option<String>,
 }
 
+/// How much of the index still matches the working tree.
+#[derive(Debug, Clone, Default)]
+pub struct Freshness {
+    pub chunks: usize,
+    pub stale: usize,
+    /// Files with at least one stale chunk (deleted or edited since indexing).
+    pub stale_files: Vec<String>,
+}
+
+impl Freshness {
+    pub fn stale_ratio(&self) -> f32 {
+        if self.chunks == 0 {
+            0.0
+        } else {
+            self.stale as f32 / self.chunks as f32
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct EmbeddingIndex {
     pub manifest: Option<EmbeddingManifest>,
@@ -112,6 +132,7 @@ impl EmbeddingIndex {
     }
 
     /// Try opening the sqlite to ensure it's readable (optional).
+    #[cfg(feature = "sqlite")]
     pub fn ping_sqlite(&self) -> Result<bool> {
         if let Some(p) = &self.vectors_db {
             let _conn = Connection::open_with_flags(
@@ -124,6 +145,55 @@ impl EmbeddingIndex {
         }
     }
 
+    /// Without the `sqlite` feature the database can't be opened; its
+    /// presence is taken as readable.
+    #[cfg(not(feature = "sqlite"))]
+    pub fn ping_sqlite(&self) -> Result<bool> {
+        Ok(self.vectors_db.is_some())
+    }
+
+    /// Compare indexed chunks against the files under `root`. Files not
+    /// modified since the manifest's `generatedAt` count as fresh without being
+    /// read; others are fresh only if each chunk's text still appears verbatim.
+    pub fn freshness(&self, root: &Path) -> Freshness {
+        let generated_at = self
+            .manifest
+            .as_ref()
+            .and_then(|m| m.generatedAt.as_deref())
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(std::time::SystemTime::from);
+
+        let mut by_path: HashMap<&str, Vec<&EmbeddingChunk>> = HashMap::new();
+        for ch in &self.chunks {
+            by_path.entry(ch.path.as_str()).or_default().push(ch);
+        }
+
+        let mut out = Freshness { chunks: self.chunks.len(), ..Default::default() };
+        for (path, chunks) in by_path {
+            let abs = root.join(path);
+            let Ok(meta) = fs::metadata(&abs) else {
+                out.stale += chunks.len();
+                out.stale_files.push(path.to_string());
+                continue;
+            };
+            let unchanged = match (generated_at, meta.modified()) {
+                (Some(g), Ok(m)) => m <= g,
+                _ => false,
+            };
+            if unchanged {
+                continue;
+            }
+            let current = fs::read_to_string(&abs).unwrap_or_default();
+            let stale = chunks.iter().filter(|c| !current.contains(c.text.trim())).count();
+            if stale > 0 {
+                out.stale += stale;
+                out.stale_files.push(path.to_string());
+            }
+        }
+        out.stale_files.sort();
+        out
+    }
+
     /// Rank file paths by lexical similarity of chunk text to the query.
     /// Returns unique file paths (normalized, POSIX-ish) ordered by score.
     pub fn top_paths_for_query(&self, query: &str, limit: usize) -> Vec<String> {
@@ -155,6 +225,60 @@ impl EmbeddingIndex {
     }
 }
 
+/// Lines per chunk written by `reindex_files`.
+#[cfg(feature = "watch")]
+const CHUNK_LINES: usize = 60;
+
+/// Replace the chunks of `paths` (root-relative) in `embeddings.jsonl` with
+/// fresh ones cut from the files on disk; deleted files just lose theirs.
+/// Other lines are kept as they are. Returns the number of chunks written.
+#[cfg(feature = "watch")]
+pub fn reindex_files(vibe_out: &Path, root: &Path, paths: &[String]) -> Result<usize> {
+    let jsonl_path = vibe_out.join("embeddings.jsonl");
+    let changed: std::collections::HashSet<String> = paths.iter().map(|p| normalize_path(p)).collect();
+    let existing = if jsonl_path.exists() { fs::read_to_string(&jsonl_path)? } else { String::new() };
+
+    let mut out: Vec<String> = existing
+        .lines()
+        .filter(|line| {
+            let path = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|v| v.get("path").and_then(|p| p.as_str()).map(normalize_path));
+            !path.is_some_and(|p| changed.contains(&p))
+        })
+        .map(|l| l.to_string())
+        .collect();
+
+    let mut written = 0;
+    for rel in &changed {
+        let Ok(text) = fs::read_to_string(root.join(rel)) else { continue };
+        let lines: Vec<&str> = text.lines().collect();
+        let lang = Path::new(rel).extension().and_then(|e| e.to_str()).map(|e| e.to_string());
+        for (i, window) in lines.chunks(CHUNK_LINES).enumerate() {
+            let start = i * CHUNK_LINES + 1;
+            let body = window.join("\n");
+            if body.trim().is_empty() {
+                continue;
+            }
+            out.push(serde_json::to_string(&serde_json::json!({
+                "id": format!("{}#{}", rel, start),
+                "path": rel,
+                "start": start,
+                "end": start + window.len() - 1,
+                "text": body,
+                "lang": lang,
+            }))?);
+            written += 1;
+        }
+    }
+
+    fs::create_dir_all(vibe_out)?;
+    let tmp = vibe_out.join("embeddings.jsonl.tmp");
+    fs::write(&tmp, out.join("\n") + "\n")?;
+    fs::rename(&tmp, &jsonl_path)?;
+    Ok(written)
+}
+
 /// Extract expected fields from a JSON value. The embeddings.jsonl lines can vary,
 /// but the example shows keys: id, path, start, end, text, lang, sha1
 fn extract_fields(v: &Value) -> Option<(String, String, Option<usize>, Option<usize>, String, Option<String>, Option<String>)> {
@@ -170,7 +294,7 @@ fn extract_fields(v: &Value) -> Option<(String, String, Option<usize>, Option<us
 
 fn tokenize(s: &str) -> Vec<String> {
     s.chars()
-        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .flat_map(|c| if c.is_alphanumeric() { c.to_lowercase().collect::<Vec<_>>() } else { vec![' '] })
         .collect::<String>()
         .split_whitespace()
         .filter(|t| !t.is_empty())
@@ -207,7 +331,7 @@ fn score_text(text: &str, qtokens: &[String]) -> f32 {
     (hits as f32) / len_penalty
 }
 
-/// Normalize backslashes into forward slashes for consistency.
+/// Normalize backslashes into forward slashes (and NFC) for consistency.
 fn normalize_path(p: &str) -> String {
-    p.replace('\\', "/")
+    crate::safety::normalize_rel(p)
 }
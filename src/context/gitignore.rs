@@ -0,0 +1,65 @@
+use ignore::gitignore::Gitignore;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Git's ignore rules for a project: every `.gitignore` from the root down,
+/// `.git/info/exclude` and the global excludes file. Applied whether or not
+/// the project is a git repository, so build output and env files never
+/// reach a provider.
+pub struct Ignores {
+    root: PathBuf,
+    /// Matcher of each directory's `.gitignore`, loaded on first use.
+    dirs: HashMap<PathBuf, Gitignore>,
+    exclude: Gitignore,
+    global: Gitignore,
+}
+
+impl Ignores {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dirs: HashMap::new(),
+            exclude: Gitignore::new(root.join(".git").join("info").join("exclude")).0,
+            global: Gitignore::global().0,
+        }
+    }
+
+    fn dir(&mut self, rel_dir: &Path) -> &Gitignore {
+        let root = &self.root;
+        self.dirs.entry(rel_dir.to_path_buf()).or_insert_with(|| Gitignore::new(root.join(rel_dir).join(".gitignore")).0)
+    }
+
+    /// Whether root-relative `rel` (a file) or a directory above it is ignored.
+    /// A deeper `.gitignore` wins over a shallower one, as in git.
+    pub fn is_ignored(&mut self, rel: &str) -> bool {
+        let rel = Path::new(rel);
+        // deepest first; `ancestors` ends with the empty path (the root)
+        for dir in rel.ancestors().skip(1) {
+            let under = rel.strip_prefix(dir).unwrap_or(rel);
+            let m = self.dir(dir).matched_path_or_any_parents(under, false);
+            if !m.is_none() {
+                return m.is_ignore();
+            }
+        }
+        for gi in [&self.exclude, &self.global] {
+            let m = gi.matched_path_or_any_parents(rel, false);
+            if !m.is_none() {
+                return m.is_ignore();
+            }
+        }
+        false
+    }
+
+    /// Drop ignored entries from `paths`; returns the dropped ones.
+    pub fn retain_visible(&mut self, paths: &mut Vec<String>) -> Vec<String> {
+        let mut dropped = Vec::new();
+        paths.retain(|p| {
+            let ignored = self.is_ignored(p);
+            if ignored {
+                dropped.push(p.clone());
+            }
+            !ignored
+        });
+        dropped
+    }
+}
@@ -0,0 +1,89 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::git::{self, blame};
+use crate::wire::GitContext;
+
+/// Commits of history sent with the context.
+const LOG_LINES: usize = 10;
+/// Word stems that make a task a fix, for `blame_context`.
+const FIX_STEMS: &[&str] = &["fix", "bug", "broke", "crash", "error", "regress", "fail"];
+/// Commits per file whose regions the blame summary lists.
+const BLAME_COMMITS: usize = 5;
+/// Files blamed per task.
+const BLAME_FILES: usize = 3;
+
+/// Regions of a file recent commits changed, with their subjects.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileBlame {
+    pub path: String,
+    pub regions: Vec<blame::Region>,
+}
+
+fn is_fix(task: &str) -> bool {
+    task.to_lowercase().split(|c: char| !c.is_alphanumeric()).any(|w| FIX_STEMS.iter().any(|s| w.starts_with(s)))
+}
+
+/// When `task` is a fix, a `git blame` summary of the `files` it names:
+/// which regions the most recent commits changed and their messages.
+pub fn blame(root: &Path, task: &str, files: &[String]) -> Vec<FileBlame> {
+    if files.is_empty() || !is_fix(task) || !git::is_repo(root) {
+        return vec![];
+    }
+    files
+        .iter()
+        .take(BLAME_FILES)
+        .filter_map(|f| match blame::recent_regions(root, f, BLAME_COMMITS) {
+            Ok(regions) if !regions.is_empty() => Some(FileBlame { path: f.clone(), regions }),
+            Ok(_) => None,
+            // untracked files have no history
+            Err(_) => None,
+        })
+        .collect()
+}
+
+/// Recent history and the uncommitted diff of `files`, so the model sees
+/// what the user is in the middle of. `None` outside a git repository or
+/// when git has nothing to say.
+pub fn collect(root: &Path, files: &[String], max_diff_bytes: usize) -> Option<GitContext> {
+    if !git::is_repo(root) {
+        return None;
+    }
+    let log = git::recent_log(root, LOG_LINES).unwrap_or_else(|e| {
+        eprintln!("warning: git log for context failed: {}", e);
+        vec![]
+    });
+    let mut diff = git::diff_head(root, files).unwrap_or_else(|e| {
+        eprintln!("warning: git diff for context failed: {}", e);
+        String::new()
+    });
+    let diff_truncated = truncate(&mut diff, max_diff_bytes);
+    if log.is_empty() && diff.is_empty() {
+        return None;
+    }
+    Some(GitContext { log, diff, diff_truncated })
+}
+
+/// Recent history and the staged diff, for `--mode commitmsg`. `None` when
+/// nothing is staged.
+pub fn staged(root: &Path, max_diff_bytes: usize) -> anyhow::Result<Option<GitContext>> {
+    let mut diff = git::diff_staged(root)?;
+    if diff.trim().is_empty() {
+        return Ok(None);
+    }
+    let diff_truncated = truncate(&mut diff, max_diff_bytes);
+    Ok(Some(GitContext { log: git::recent_log(root, LOG_LINES)?, diff, diff_truncated }))
+}
+
+/// Cut `diff` to `max_bytes` on a char boundary; whether it was cut.
+fn truncate(diff: &mut String, max_bytes: usize) -> bool {
+    if diff.len() <= max_bytes {
+        return false;
+    }
+    let mut cut = max_bytes;
+    while !diff.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    diff.truncate(cut);
+    true
+}
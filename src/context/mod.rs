@@ -2,22 +2,46 @@ use crate::wire::FileBlob;
 use fs_err as fs;
 use std::path::{Path, PathBuf};
 
+pub mod breadth;
 pub mod embeddings; // NEW: semantic-ish retrieval support
+pub mod gitignore;
+pub mod history;
+pub mod routes;
+pub mod widen;
 
 /// Read the first `max_bytes` of each given file (relative to `root`) and
-/// produce FileBlob entries for the LLM request.
-pub fn snapshot_files(paths: &[String], root: &Path, max_bytes: usize) -> Vec<FileBlob> {
+/// produce FileBlob entries for the LLM request. Files over `skip_over` bytes
+/// get an index-only entry (path and size, no content). Files reached through
+/// a symlink leading out of the project are skipped unless the link is allowlisted,
+/// and files git ignores are never read. `hash` is over the whole file, so
+/// apply can tell whether it changed since.
+pub fn snapshot_files(paths: &[String], root: &Path, max_bytes: usize, skip_over: usize, link_allowlist: &[String]) -> Vec<FileBlob> {
     let mut out = Vec::new();
+    let mut ignores = gitignore::Ignores::new(root);
     for rel in paths {
         let abs = root.join(rel);
         if !abs.exists() || !abs.is_file() {
             continue;
         }
+        if ignores.is_ignored(rel) {
+            eprintln!("warning: not snapshotting {}: ignored by .gitignore", rel);
+            continue;
+        }
+        if let Some(link) = crate::safety::escaping_symlink(root, rel, link_allowlist) {
+            eprintln!("warning: not snapshotting {}: symlink {} points outside the project", rel, link);
+            continue;
+        }
+        let size = fs::metadata(&abs).map(|m| m.len() as usize).unwrap_or(0);
+        if size > skip_over {
+            eprintln!("warning: {} is {} bytes (max_snapshot_file_bytes {}); sending its path only", rel, size, skip_over);
+            out.push(FileBlob { path: rel.clone(), bytes: size, hash: None, truncated: true, content: String::new() });
+            continue;
+        }
         match read_prefix(&abs, max_bytes) {
-            Ok((content, bytes, truncated)) => out.push(FileBlob {
+            Ok((content, bytes, truncated, hash)) => out.push(FileBlob {
                 path: rel.clone(),
                 bytes,
-                hash: None,
+                hash: Some(hash),
                 truncated,
                 content,
             }),
@@ -30,13 +54,46 @@ pub fn snapshot_files(paths: &[String], root: &Path, max_bytes: usize) -> Vec<Fi
     out
 }
 
-fn read_prefix(path: &Path, max_bytes: usize) -> anyhow::Result<(String, usize, bool)> {
+fn read_prefix(path: &Path, max_bytes: usize) -> anyhow::Result<(String, usize, bool, String)> {
     let data = fs::read(path)?;
+    let hash = crate::backup::content_hash(&data);
     let bytes = data.len();
     let truncated = bytes > max_bytes;
-    let slice = if truncated { &data[..max_bytes] } else { &data[..] };
+    let slice = if truncated { &data[..utf8_floor(&data, max_bytes)] } else { &data[..] };
     let content = String::from_utf8_lossy(slice).into_owned();
-    Ok((content, bytes, truncated))
+    Ok((content, bytes, truncated, hash))
+}
+
+/// Largest index <= `max` that doesn't split a UTF-8 sequence, so truncated
+/// snapshots of non-ASCII files don't end in a replacement character.
+fn utf8_floor(data: &[u8], max: usize) -> usize {
+    let mut i = max.min(data.len());
+    // continuation bytes are 0b10xxxxxx
+    while i > 0 && i < data.len() && (data[i] & 0xC0) == 0x80 {
+        i -= 1;
+    }
+    i
+}
+
+/// Where the context file selection came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetrievalMode {
+    /// Chunks ranked from a full index (manifest + vectors.sqlite).
+    Embeddings,
+    /// embeddings.jsonl chunks only, no vector store.
+    Lexical,
+    /// No index: the hard-coded baseline files.
+    BaselineOnly,
+}
+
+impl RetrievalMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RetrievalMode::Embeddings => "embeddings",
+            RetrievalMode::Lexical => "lexical",
+            RetrievalMode::BaselineOnly => "baseline-only",
+        }
+    }
 }
 
 /// Select relevant Next.js files for the current task, mixing:
@@ -45,8 +102,8 @@ fn read_prefix(path: &Path, max_bytes: usize) -> anyhow::Result<(String, usize,
 /// - top-k semantic-ish hits from embeddings.jsonl (if present)
 ///
 /// `vibe_out` points to the `.vibe/out` directory. On any error/missing files,
-/// we gracefully fall back to the baseline set.
-pub fn select_relevant_files(task: &str, root: &Path, vibe_out: &Path, top_k: usize) -> Vec<String> {
+/// we gracefully fall back to the baseline set; the mode says which happened.
+pub fn select_relevant_files(task: &str, root: &Path, vibe_out: &Path, top_k: usize) -> (Vec<String>, RetrievalMode) {
     // Baseline set (kept for backward compatibility)
     let mut set = vec![
         "src/app/page.tsx".to_string(),
@@ -56,24 +113,47 @@ pub fn select_relevant_files(task: &str, root: &Path, vibe_out: &Path, top_k: us
     ];
 
     // Try to load the embedding index
+    let mut mode = RetrievalMode::BaselineOnly;
     match embeddings::EmbeddingIndex::load(vibe_out) {
-        Ok(index) => {
-            // Optional: ping sqlite so we can surface a debug later if needed (ignore result here)
-            let _ = index.ping_sqlite();
+        Ok(index) if !index.chunks.is_empty() => {
+            mode = match (&index.manifest, index.ping_sqlite()) {
+                (Some(_), Ok(true)) => RetrievalMode::Embeddings,
+                _ => RetrievalMode::Lexical,
+            };
 
-            let mut top = index.top_paths_for_query(task, top_k);
-            // Filter to repo files that exist, normalize and dedupe
+            // rank everything, so gitignored hits (build output, env files) don't cost slots
+            let mut top = index.top_paths_for_query(task, usize::MAX);
+            // Filter to repo files that exist and git doesn't ignore, normalize and dedupe
             top.retain(|p| root.join(p).exists());
+            gitignore::Ignores::new(root).retain_visible(&mut top);
+            top.truncate(top_k);
             for p in top {
                 if !set.iter().any(|x| *x == p) {
                     set.push(p);
                 }
             }
         }
-        Err(_) => {
+        _ => {
             // No embeddings; keep baseline
         }
     }
 
-    set
+    (set, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn utf8_floor_lands_on_a_char_boundary(s in "[a-zé日👍]{0,40}", max in 0usize..200) {
+            let i = utf8_floor(s.as_bytes(), max);
+            prop_assert!(i <= max.min(s.len()));
+            prop_assert!(s.is_char_boundary(i));
+            // backs off at most to the start of the char `max` cut into
+            prop_assert!(max.min(s.len()) - i < 4);
+        }
+    }
 }
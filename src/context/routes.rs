@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// One App Router page discovered on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteEntry {
+    /// URL path, e.g. `/settings` (route groups stripped).
+    pub route: String,
+    /// Project-relative page file.
+    pub file: String,
+    /// Contains `[param]` segments, so it can't be requested without values.
+    pub dynamic: bool,
+}
+
+/// Map a path relative to the app dir (e.g. `(marketing)/about/page.tsx`) to
+/// its URL; `None` for non-page files and private/parallel segments.
+pub fn route_for_page(rel: &str) -> Option<(String, bool)> {
+    let rel = rel.replace('\\', "/");
+    let (dirs, file) = rel.rsplit_once('/').unwrap_or(("", rel.as_str()));
+    if !["page.tsx", "page.ts", "page.jsx", "page.js"].contains(&file) {
+        return None;
+    }
+    let mut segs = Vec::new();
+    let mut dynamic = false;
+    for seg in dirs.split('/').filter(|s| !s.is_empty()) {
+        if seg.starts_with('_') || seg.starts_with('@') {
+            return None;
+        }
+        if seg.starts_with('(') && seg.ends_with(')') {
+            continue;
+        }
+        if seg.starts_with('[') {
+            dynamic = true;
+        }
+        segs.push(seg);
+    }
+    Some((format!("/{}", segs.join("/")), dynamic))
+}
+
+/// Index every page under `src/app` or `app`, sorted by route.
+pub fn discover(root: &Path) -> Vec<RouteEntry> {
+    let mut out = Vec::new();
+    for app in ["src/app", "app"] {
+        let base = root.join(app);
+        if !base.is_dir() {
+            continue;
+        }
+        for entry in WalkDir::new(&base).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(rel) = entry.path().strip_prefix(&base) else { continue };
+            if let Some((route, dynamic)) = route_for_page(&rel.to_string_lossy()) {
+                out.push(RouteEntry {
+                    route,
+                    file: format!("{}/{}", app, rel.to_string_lossy().replace('\\', "/")),
+                    dynamic,
+                });
+            }
+        }
+    }
+    out.sort_by(|a, b| a.route.cmp(&b.route));
+    out.dedup_by(|a, b| a.route == b.route);
+    out
+}
@@ -0,0 +1,83 @@
+use regex::Regex;
+use std::path::{Component, Path};
+use std::sync::OnceLock;
+
+use crate::wire::{LlmRequest, LlmResponse};
+
+/// Cap on files added by one widening round.
+const MAX_WIDEN_FILES: usize = 8;
+
+fn complaint_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)(not|n't|never) (been )?(provided|included|shown|given|available|visible|in the snapshot)|missing (file|context|contents?)|(cannot|can't|unable to) see|without (seeing|access to|the contents)|need(s)? (to see|the contents)",
+        )
+        .unwrap()
+    })
+}
+
+fn path_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"[\w@.\-/\\\[\]()]+\.(tsx|ts|jsx|js|mjs|cjs|json|css|scss|md|mdx)\b").unwrap()
+    })
+}
+
+/// Plan summary, step titles and answer text: where models say what they lack.
+pub fn response_text(resp: &LlmResponse) -> String {
+    let mut out = String::new();
+    if let Some(plan) = &resp.plan {
+        out.push_str(&plan.summary);
+        for s in &plan.steps {
+            out.push('\n');
+            out.push_str(s.title());
+        }
+    }
+    if let Some(ans) = &resp.answer {
+        out.push('\n');
+        out.push_str(&ans.title);
+        out.push('\n');
+        out.push_str(&ans.content);
+    }
+    out
+}
+
+/// Project files the model says it was not given ("file X not provided").
+/// Only lines with a complaint are searched; paths must exist under `root`
+/// and not already be in `have`.
+pub fn missing_context_paths(text: &str, root: &Path, have: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for line in text.lines().filter(|l| complaint_re().is_match(l)) {
+        for m in path_re().find_iter(line) {
+            let Some(rel) = resolve(m.as_str(), root) else { continue };
+            if !have.contains(&rel) && !out.contains(&rel) {
+                out.push(rel);
+            }
+        }
+    }
+    out.truncate(MAX_WIDEN_FILES);
+    out
+}
+
+/// Normalize a mentioned path and find it under `root` (as given, or under
+/// `src/` for paths the model wrote relative to it).
+fn resolve(mention: &str, root: &Path) -> Option<String> {
+    let rel = mention.replace('\\', "/");
+    let rel = rel.trim_start_matches("./");
+    if Path::new(rel).components().any(|c| !matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    [rel.to_string(), format!("src/{}", rel)]
+        .into_iter()
+        .find(|p| root.join(p).is_file())
+}
+
+/// Snapshot `added` into the request and tell the model they are there now.
+pub fn widen_request(req: &mut LlmRequest, root: &Path, added: &[String], max_bytes: usize, skip_over: usize, link_allowlist: &[String]) {
+    req.context.files_snapshot.extend(super::snapshot_files(added, root, max_bytes, skip_over, link_allowlist));
+    req.instruction.user.push_str(&format!(
+        "\n\nFiles you reported as missing are now included in context.files_snapshot: {}. Redo the response using them.",
+        added.join(", ")
+    ));
+}
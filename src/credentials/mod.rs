@@ -0,0 +1,136 @@
+use anyhow::{anyhow, bail, Result};
+#[cfg(feature = "keychain")]
+use anyhow::Context;
+use fs_err as fs;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::cli::ProviderKind;
+
+/// Keychain service name; entries are keyed by the key name (env var) they
+/// stand in for.
+#[cfg(feature = "keychain")]
+const SERVICE: &str = "vibe_codegen";
+
+/// Default key name -> configured one (`credential_names`), set once at startup.
+static RENAMES: OnceLock<HashMap<&'static str, String>> = OnceLock::new();
+
+/// Apply `credential_names` from config (`openai = "WORK_OPENAI_KEY"`): that
+/// provider's key is then looked up under the given name everywhere.
+pub fn init(names: &BTreeMap<String, String>) -> Result<()> {
+    let mut renames = HashMap::new();
+    for (provider, name) in names {
+        let kind = <ProviderKind as clap::ValueEnum>::from_str(provider, true)
+            .map_err(|_| anyhow!("credential_names: unknown provider '{}'", provider))?;
+        renames.insert(default_key_var(&kind)?, name.clone());
+    }
+    let _ = RENAMES.set(renames);
+    Ok(())
+}
+
+fn default_key_var(kind: &ProviderKind) -> Result<&'static str> {
+    Ok(match kind {
+        ProviderKind::OpenAI | ProviderKind::OpenAICompatible => "OPENAI_API_KEY",
+        ProviderKind::OpenRouter => "OPENROUTER_API_KEY",
+        ProviderKind::Groq => "GROQ_API_KEY",
+        ProviderKind::Azure => "AZURE_OPENAI_API_KEY",
+        ProviderKind::Anthropic => "ANTHROPIC_API_KEY",
+        ProviderKind::Mistral => "MISTRAL_API_KEY",
+        ProviderKind::Vllm => "VLLM_API_KEY",
+        ProviderKind::Tgi => "HF_TOKEN",
+        ProviderKind::LmStudio => "LM_STUDIO_API_KEY",
+        ProviderKind::Bedrock => bail!("bedrock uses the AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY credentials"),
+        ProviderKind::Ollama | ProviderKind::Replay => bail!("{:?} needs no API key", kind),
+    })
+}
+
+/// Name `var` is looked up under after `credential_names` renames.
+fn resolve_name(var: &str) -> String {
+    RENAMES.get().and_then(|m| m.get(var)).cloned().unwrap_or_else(|| var.to_string())
+}
+
+/// Name under which `kind`'s API key is looked up (env var, credentials.toml
+/// entry and keychain account).
+pub fn key_var(kind: &ProviderKind) -> Result<String> {
+    Ok(resolve_name(default_key_var(kind)?))
+}
+
+#[cfg(feature = "keychain")]
+fn entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| anyhow!("OS keychain unavailable: {}", e))
+}
+
+/// `~/.config/vibe/credentials.toml`: a flat table of key name = key.
+fn from_file(name: &str) -> Option<String> {
+    let path = crate::config::user_config_dir()?.join("credentials.toml");
+    let text = fs::read_to_string(&path).ok()?;
+    let keys: BTreeMap<String, String> = match toml::from_str(&text) {
+        Ok(k) => k,
+        Err(e) => {
+            eprintln!("warning: ignoring {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    keys.get(name).filter(|k| !k.is_empty()).cloned()
+}
+
+/// API key for `var` (renamed per `credential_names`): a non-empty env var
+/// wins, then `~/.config/vibe/credentials.toml`, then the keychain entry
+/// saved by `vibe auth login`.
+pub fn api_key(var: &str) -> Option<String> {
+    let name = resolve_name(var);
+    if let Some(v) = std::env::var(&name).ok().filter(|v| !v.is_empty()) {
+        return Some(v);
+    }
+    if let Some(v) = from_file(&name) {
+        return Some(v);
+    }
+    from_keychain(&name)
+}
+
+#[cfg(feature = "keychain")]
+fn from_keychain(name: &str) -> Option<String> {
+    entry(name).ok()?.get_password().ok()
+}
+
+#[cfg(not(feature = "keychain"))]
+fn from_keychain(_name: &str) -> Option<String> {
+    None
+}
+
+/// Same as `api_key`, as an error naming the places that were looked at.
+pub fn require(var: &str) -> Result<String> {
+    api_key(var).ok_or_else(|| missing(var))
+}
+
+pub fn missing(var: &str) -> anyhow::Error {
+    anyhow!(
+        "{} is not set in the environment, credentials.toml or the OS keychain (vibe auth login)",
+        resolve_name(var)
+    )
+}
+
+#[cfg(feature = "keychain")]
+pub fn store(name: &str, key: &str) -> Result<()> {
+    entry(name)?.set_password(key).with_context(|| format!("saving {} to the OS keychain", name))
+}
+
+/// Remove the stored key; false if there was none.
+#[cfg(feature = "keychain")]
+pub fn forget(name: &str) -> Result<bool> {
+    match entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow!("removing {} from the OS keychain: {}", name, e)),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store(name: &str, _key: &str) -> Result<()> {
+    bail!("can't save {}: built without the `keychain` feature; set it in the environment or credentials.toml", name)
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn forget(name: &str) -> Result<bool> {
+    bail!("can't remove {}: built without the `keychain` feature", name)
+}
@@ -0,0 +1,242 @@
+use fs_err as fs;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+use crate::wire::{Plan, Step};
+
+const MANIFEST: &str = "package.json";
+const DEP_TABLES: &[&str] = &["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+const SOURCE_EXTS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts"];
+const SKIP_DIRS: &[&str] = &["node_modules", ".next", ".git", ".vibe", "dist", "build", "out", "coverage"];
+
+/// One package.json dependency as the scheduled `deps` check sees it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DepStatus {
+    pub package: String,
+    /// Range in package.json.
+    pub declared: String,
+    /// Version in node_modules; `None` when it isn't installed.
+    pub installed: Option<String>,
+    pub dev: bool,
+    /// Imported by some source file.
+    pub imported: bool,
+}
+
+/// A source file that still imports a package the plan removes.
+#[derive(Debug, Clone)]
+pub struct Straggler {
+    pub package: String,
+    pub path: String,
+    pub line: usize,
+}
+
+fn dep_names(manifest: &Value) -> BTreeSet<String> {
+    DEP_TABLES
+        .iter()
+        .filter_map(|t| manifest.get(t).and_then(Value::as_object))
+        .flat_map(|o| o.keys().cloned())
+        .collect()
+}
+
+fn is_manifest(path: &str) -> bool {
+    crate::safety::normalize_rel(path) == MANIFEST
+}
+
+/// Packages named by `npm uninstall|rm|remove`, `pnpm remove|rm|un` or
+/// `yarn|bun remove`, with the index of the first name in the words.
+fn uninstall_args(command: &str) -> Option<(usize, Vec<&str>)> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let verbs: &[&str] = match *words.first()? {
+        "npm" => &["uninstall", "un", "rm", "remove", "r", "unlink"],
+        "pnpm" => &["remove", "rm", "uninstall", "un"],
+        "yarn" | "bun" => &["remove", "rm"],
+        _ => return None,
+    };
+    let at = words.iter().position(|w| verbs.contains(w))?;
+    let names = words[at + 1..].iter().filter(|w| !w.starts_with('-')).copied().collect();
+    Some((at + 1, names))
+}
+
+/// Packages the plan takes out: dependency keys its package.json update
+/// drops, plus the targets of uninstall commands.
+pub fn removed_packages(root: &Path, plan: &Plan) -> Vec<String> {
+    let mut out = BTreeSet::new();
+    let before = fs::read_to_string(root.join(MANIFEST)).ok().and_then(|s| serde_json::from_str::<Value>(&s).ok());
+    for s in &plan.steps {
+        match s {
+            Step::Update { path, content: Some(c), .. } if is_manifest(path) => {
+                if let (Some(old), Ok(new)) = (&before, serde_json::from_str::<Value>(c)) {
+                    out.extend(dep_names(old).difference(&dep_names(&new)).cloned());
+                }
+            }
+            Step::Command { command, .. } => {
+                if let Some((_, names)) = uninstall_args(command) {
+                    out.extend(names.into_iter().map(str::to_string));
+                }
+            }
+            _ => {}
+        }
+    }
+    out.into_iter().collect()
+}
+
+fn import_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(?:from\s+|import\s*\(?\s*|require\(\s*)['"]([^'"]+)['"]"#).unwrap())
+}
+
+/// `lucide-react/icons/x` -> `lucide-react`, `@scope/pkg/sub` -> `@scope/pkg`.
+fn package_of(spec: &str) -> Option<String> {
+    if spec.starts_with('.') || spec.starts_with('/') || spec.starts_with("@/") || spec.starts_with("~/") {
+        return None;
+    }
+    let n = if spec.starts_with('@') { 2 } else { 1 };
+    Some(spec.split('/').take(n).collect::<Vec<_>>().join("/"))
+}
+
+/// Every dependency and devDependency: whether it is installed and whether
+/// any source file imports it.
+pub fn vet(root: &Path) -> anyhow::Result<Vec<DepStatus>> {
+    let text = fs::read_to_string(root.join(MANIFEST))?;
+    let manifest: Value = serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("parsing {}: {}", MANIFEST, e))?;
+    let mut imported = BTreeSet::new();
+    let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
+        !(e.file_type().is_dir() && SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let ext = entry.path().extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !SOURCE_EXTS.contains(&ext) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(entry.path()) else { continue };
+        imported.extend(import_re().captures_iter(&content).filter_map(|c| package_of(&c[1])));
+    }
+    let mut out = Vec::new();
+    for (table, dev) in [("dependencies", false), ("devDependencies", true)] {
+        let Some(deps) = manifest.get(table).and_then(Value::as_object) else { continue };
+        for (package, declared) in deps {
+            let installed = fs::read_to_string(root.join("node_modules").join(package).join(MANIFEST))
+                .ok()
+                .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+                .and_then(|v| v.get("version").and_then(Value::as_str).map(str::to_string));
+            out.push(DepStatus {
+                package: package.clone(),
+                declared: declared.as_str().unwrap_or_default().to_string(),
+                installed,
+                dev,
+                imported: imported.contains(package),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Imports of `packages` left in the tree as it will be after `plan` is
+/// applied: planned contents replace files on disk, deleted files drop out.
+pub fn stragglers(root: &Path, plan: &Plan, packages: &[String]) -> Vec<Straggler> {
+    if packages.is_empty() {
+        return vec![];
+    }
+    let mut files: BTreeMap<String, Option<String>> = BTreeMap::new();
+    for s in &plan.steps {
+        match s {
+            Step::Create { path, content: Some(c), .. } | Step::Update { path, content: Some(c), .. } => {
+                files.insert(crate::safety::normalize_rel(path), Some(c.clone()));
+            }
+            Step::Delete { path, .. } => {
+                files.insert(crate::safety::normalize_rel(path), None);
+            }
+            _ => {}
+        }
+    }
+    let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
+        !(e.file_type().is_dir() && SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let Ok(rel) = entry.path().strip_prefix(root) else { continue };
+        let rel = crate::safety::normalize_rel(&rel.to_string_lossy());
+        files.entry(rel).or_insert_with(|| fs::read_to_string(entry.path()).ok());
+    }
+
+    let mut out = Vec::new();
+    for (path, content) in &files {
+        let ext = path.rsplit('.').next().unwrap_or("");
+        let Some(content) = content.as_deref().filter(|_| SOURCE_EXTS.contains(&ext)) else { continue };
+        for (i, line) in content.lines().enumerate() {
+            for c in import_re().captures_iter(line) {
+                if let Some(pkg) = package_of(&c[1]).filter(|p| packages.contains(p)) {
+                    out.push(Straggler { package: pkg, path: path.clone(), line: i + 1 });
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Put `packages` back into the planned package.json and take them out of
+/// uninstall commands (dropping commands left with nothing to remove), so
+/// the removal waits until nothing imports them. Returns what was changed.
+pub fn hold_back(root: &Path, plan: &mut Plan, packages: &[String]) -> Vec<String> {
+    let mut notes = Vec::new();
+    let old = fs::read_to_string(root.join(MANIFEST)).unwrap_or_default();
+    for s in plan.steps.iter_mut() {
+        match s {
+            Step::Update { path, content: Some(c), .. } if is_manifest(path) => {
+                for pkg in packages {
+                    if let Some(restored) = restore_dep(&old, c, pkg) {
+                        *c = restored;
+                        notes.push(format!("kept {} in package.json", pkg));
+                    }
+                }
+            }
+            Step::Command { command, .. } => {
+                let Some((first, names)) = uninstall_args(command) else { continue };
+                let keep: Vec<&str> = names.iter().copied().filter(|n| !packages.iter().any(|p| p == n)).collect();
+                if keep.len() == names.len() {
+                    continue;
+                }
+                let words: Vec<&str> = command.split_whitespace().collect();
+                let flags = words[first..].iter().filter(|w| w.starts_with('-'));
+                let rewritten = words[..first].iter().chain(flags).chain(keep.iter()).copied().collect::<Vec<_>>().join(" ");
+                notes.push(format!("`{}` -> {}", command, if keep.is_empty() { "dropped".to_string() } else { format!("`{}`", rewritten) }));
+                *command = if keep.is_empty() { String::new() } else { rewritten };
+            }
+            _ => {}
+        }
+    }
+    plan.steps.retain(|s| !matches!(s, Step::Command { command, .. } if command.is_empty()));
+    notes
+}
+
+/// Re-insert `pkg`'s entry from `old` package.json text into the same table
+/// of `new`, textually so the file's formatting and key order survive.
+fn restore_dep(old: &str, new: &str, pkg: &str) -> Option<String> {
+    let old_v: Value = serde_json::from_str(old).ok()?;
+    let new_v: Value = serde_json::from_str(new).ok()?;
+    let table = DEP_TABLES.iter().find(|t| old_v.get(**t).and_then(|o| o.get(pkg)).is_some())?;
+    if new_v.get(*table).and_then(|o| o.get(pkg)).is_some() {
+        return None;
+    }
+    let version = old_v[*table][pkg].as_str()?;
+    let entry = format!("{}: {}", serde_json::to_string(pkg).ok()?, serde_json::to_string(version).ok()?);
+    let key = format!("\"{}\"", table);
+    let out = match new.find(&key) {
+        Some(at) => {
+            let open = at + new[at..].find('{')? + 1;
+            let empty = new[open..].trim_start().starts_with('}');
+            let indent = new[open..].lines().nth(1).map(|l| &l[..l.len() - l.trim_start().len()]).unwrap_or("    ");
+            let sep = if empty { "" } else { "," };
+            format!("{}\n{}{}{}{}", &new[..open], indent, entry, sep, &new[open..])
+        }
+        // the whole table went: add it back after the opening brace
+        None => {
+            let open = new.find('{')? + 1;
+            format!("{}\n  {}: {{ {} }},{}", &new[..open], key, entry, &new[open..])
+        }
+    };
+    serde_json::from_str::<Value>(&out).ok().map(|_| out)
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::cli::{Args, ProviderKind};
+use crate::config::Config;
+use crate::credentials;
+use crate::errors::VibeError;
+use crate::provider::{self, Endpoint};
+use crate::wire;
+
+/// Health of one provider/model pair as seen by `vibe doctor`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    pub target: String,
+    /// `set`, `missing`, or `n/a` for providers without an API key.
+    pub key: String,
+    pub ok: bool,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// How JSON replies are requested, after any rejection the ping hit.
+    #[serde(default)]
+    pub structured: String,
+}
+
+/// Providers a run would use: every `provider_chain` entry, else the
+/// selected provider with each distinct phase model.
+fn targets(args: &Args, cfg: &Config) -> Result<Vec<(ProviderKind, String, Endpoint)>> {
+    let profile = match &args.profile_provider {
+        Some(name) => Some(cfg.provider_profile(name)?.clone()),
+        None => None,
+    };
+    let kind = profile.as_ref().map(|p| p.provider.clone()).unwrap_or_else(|| args.provider.clone());
+    let model = profile.as_ref().and_then(|p| p.model.clone()).or_else(|| args.model.clone()).unwrap_or_else(|| crate::cli::DEFAULT_MODEL.to_string());
+    let endpoint = Endpoint {
+        api_key: profile.as_ref().and_then(|p| p.resolve_api_key()),
+        api_base: args.api_base.clone().or_else(|| profile.as_ref().and_then(|p| p.api_base.clone())),
+    };
+    if !cfg.provider_chain.is_empty() {
+        return cfg.provider_chain.iter().map(|e| provider::resolve_chain_entry(e, (&kind, &endpoint), cfg)).collect();
+    }
+    let mut models = vec![model.clone()];
+    for m in [&cfg.plan_model, &cfg.codegen_model].into_iter().flatten() {
+        if !models.contains(m) {
+            models.push(m.clone());
+        }
+    }
+    Ok(models.into_iter().map(|m| (kind.clone(), m, endpoint.clone())).collect())
+}
+
+/// Smallest request that still exercises key, endpoint and model.
+fn ping_request(cfg: &Config) -> wire::LlmRequest {
+    wire::LlmRequest {
+        schema_version: "v1".into(),
+        mode: wire::Mode::Plan,
+        transaction: wire::Tx { id: Uuid::new_v4(), timestamp: Utc::now(), dry_run: true },
+        limits: wire::Limits { max_actions: 1, max_patch_bytes: 0, allowed_commands: vec![] },
+        task: "health check".into(),
+        context: wire::ContextSlice {
+            summary: json!({}),
+            files_index: vec![],
+            routes: vec![],
+            symbols: json!({}),
+            diagnostics: vec![],
+            files_snapshot: vec![],
+            git: None,
+        },
+        capabilities: vec![],
+        safety: wire::Safety { path_allowlist: vec![], command_allowlist: vec![] },
+        instruction: wire::Instruction {
+            system: "Health check. Reply with exactly this JSON object and nothing else: \
+                     {\"schema_version\":\"v1\",\"kind\":\"answer\",\"answer\":{\"title\":\"ok\",\"content\":\"ok\"}}"
+                .into(),
+            user: "ping".into(),
+            developer: None,
+        },
+        wire_format: wire::WireFormat::Json,
+        sampling: wire::Sampling { max_output_tokens: Some(64), ..cfg.sampling() },
+        follow_up: None,
+    }
+}
+
+/// Short reason for a failed ping: rejected key, unknown model, unreachable host...
+fn diagnose(e: &anyhow::Error) -> String {
+    for cause in e.chain() {
+        if let Some(VibeError::Http { status, .. }) = cause.downcast_ref::<VibeError>() {
+            return match status.as_u16() {
+                401 | 403 => format!("key rejected ({})", status),
+                404 => format!("model or endpoint not found ({})", status),
+                429 => format!("rate limited ({})", status),
+                _ => format!("HTTP {}", status),
+            };
+        }
+        if let Some(r) = cause.downcast_ref::<reqwest::Error>() {
+            if r.is_connect() {
+                return "endpoint unreachable".into();
+            }
+            if r.is_timeout() {
+                return "timed out".into();
+            }
+        }
+    }
+    e.to_string().lines().next().unwrap_or("").to_string()
+}
+
+/// Check every provider the run would use with one tiny request each. A
+/// reply that fails to parse still proves the key, endpoint and model work.
+pub async fn run(args: &Args, cfg: &Config) -> Result<Vec<ProviderHealth>> {
+    let mut cfg = cfg.clone();
+    cfg.provider_chain.clear();
+    cfg.retry.max_attempts = 1;
+    let mut out = Vec::new();
+    for (kind, model, endpoint) in targets(args, &cfg)? {
+        let target = format!("{:?}:{}", kind, model).to_lowercase();
+        let key = match credentials::key_var(&kind) {
+            Err(_) => "n/a".to_string(),
+            Ok(_) if endpoint.api_key.is_some() => "set".to_string(),
+            Ok(var) if credentials::api_key(&var).is_some() => "set".to_string(),
+            Ok(_) => "missing".to_string(),
+        };
+        let started = Instant::now();
+        let caps_for = (kind.clone(), model.clone());
+        let result = match provider::make_provider(kind, model, args.timeout_secs.min(60), args.openai_messages, endpoint, &cfg) {
+            Ok(prov) => prov.send(&ping_request(&cfg), args.debug).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        let latency_ms = started.elapsed().as_millis();
+        let (ok, note) = match result {
+            Ok(()) => (true, None),
+            Err(e) if format!("{:#}", e).contains("did not return a valid") => {
+                (true, Some("reachable; reply was not valid JSON".to_string()))
+            }
+            Err(e) => (false, Some(diagnose(&e))),
+        };
+        let caps = provider::capabilities::resolve(&caps_for.0, &caps_for.1, &cfg);
+        let structured = provider::capabilities::strategy(&caps, &cfg).as_str().to_string();
+        out.push(ProviderHealth { target, key, ok, latency_ms, note, structured });
+    }
+    Ok(out)
+}
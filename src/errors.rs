@@ -6,4 +6,7 @@ pub enum VibeError {
     #[error("schema error: {0}")] Schema(String),
     #[error("safety violation: {0}")] Safety(String),
     #[error("apply failed: {0}")] Apply(String),
+    /// Non-2xx reply from a model API; the status drives retry decisions.
+    #[error("{provider} API error ({status}): {body}")]
+    Http { provider: &'static str, status: reqwest::StatusCode, body: String },
 }
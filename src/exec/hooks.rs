@@ -0,0 +1,50 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::CmdResult;
+use crate::config::Config;
+use crate::ux::theme::{self, Role};
+
+/// What a failing hook does to the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailure {
+    /// Stop: nothing is applied (pre) or committed (post).
+    #[default]
+    Abort,
+    /// Print a warning and carry on.
+    Warn,
+}
+
+/// Run hook `name` (`pre_apply`/`post_apply`) through the allowlisted exec
+/// path in the project root. A failure, a disallowed command or an
+/// untrusted workspace is an error under `hook_failure = "abort"`, a
+/// warning otherwise. Returns the command's result when it ran.
+pub fn run(name: &str, cmd: &str, cfg: &Config) -> Result<Option<CmdResult>> {
+    println!("Running {} hook `{}`...", name, cmd);
+    let outcome = if cfg.trusted {
+        super::run_command_allowlisted(cmd, cfg, Some(&cfg.root), cfg.timeout_secs)
+    } else {
+        Err(anyhow::anyhow!("commands are disabled in untrusted workspaces; {}", crate::trust::HOW_TO_TRUST))
+    };
+    let (result, problem) = match outcome {
+        Ok(r) if r.status == 0 && !r.timed_out => {
+            println!("{}{} hook passed ({} ms)", theme::mark(Role::Ok), name, r.duration_ms);
+            return Ok(Some(r));
+        }
+        Ok(r) => {
+            let why = if r.timed_out { "timed out".to_string() } else { format!("exited with {}", r.status) };
+            let tail: Vec<&str> = r.stderr.lines().chain(r.stdout.lines()).rev().take(10).collect();
+            let tail: Vec<&str> = tail.into_iter().rev().collect();
+            (Some(r.clone()), format!("{} hook `{}` {}\n{}", name, cmd, why, tail.join("\n")))
+        }
+        Err(e) => (None, format!("{} hook `{}` failed: {:#}", name, cmd, e)),
+    };
+    match cfg.hook_failure {
+        HookFailure::Abort => bail!("{}", problem.trim_end()),
+        HookFailure::Warn => {
+            eprintln!("{}", theme::paint(Role::Warn, &format!("warning: {}", problem.trim_end())));
+            Ok(result)
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+
+/// A long-running child (e.g. a dev server) whose stdout/stderr are collected
+/// into a shared buffer. The whole process tree is stopped on `stop`/drop.
+pub struct ManagedProcess {
+    child: Child,
+    output: Arc<Mutex<String>>,
+}
+
+impl ManagedProcess {
+    pub fn spawn(cmd: &str, cwd: &str) -> Result<Self> {
+        let parts = shlex::split(cmd).with_context(|| format!("cannot parse command: {}", cmd))?;
+        let (prog, args) = parts.split_first().with_context(|| "empty command")?;
+        let prog = which::which(prog).unwrap_or_else(|_| prog.into());
+
+        let mut c = Command::new(prog);
+        c.args(args)
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            // own process group so `npm run dev` and its node child stop together
+            use std::os::unix::process::CommandExt;
+            c.process_group(0);
+        }
+        let mut child = c.spawn().with_context(|| format!("failed to start: {}", cmd))?;
+
+        let output = Arc::new(Mutex::new(String::new()));
+        if let Some(p) = child.stdout.take() {
+            collect(BufReader::new(p), output.clone());
+        }
+        if let Some(p) = child.stderr.take() {
+            collect(BufReader::new(p), output.clone());
+        }
+        Ok(Self { child, output })
+    }
+
+    /// Byte offset of the collected output so far (see `output_since`).
+    pub fn output_len(&self) -> usize {
+        self.output.lock().len()
+    }
+
+    pub fn output_since(&self, offset: usize) -> String {
+        let out = self.output.lock();
+        out.get(offset..).unwrap_or("").to_string()
+    }
+
+    /// True while the process has not exited.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn stop(&mut self) {
+        if !self.is_running() {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            let _ = Command::new("kill")
+                .args(["-TERM", &format!("-{}", self.child.id())])
+                .status();
+        }
+        #[cfg(windows)]
+        {
+            let _ = Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &self.child.id().to_string()])
+                .status();
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for ManagedProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn collect<R: BufRead + Send + 'static>(reader: R, out: Arc<Mutex<String>>) {
+    thread::spawn(move || {
+        for line in reader.lines().map_while(|l| l.ok()) {
+            let mut o = out.lock();
+            o.push_str(&line);
+            o.push('\n');
+        }
+    });
+}
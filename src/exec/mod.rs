@@ -1,19 +1,94 @@
 use anyhow::{bail, Context, Result};
-use std::io;
-use std::process::{Command, Stdio};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::config::Config;
 
-#[derive(Debug, Clone)]
+pub mod hooks;
+pub mod managed;
+pub mod policy;
+pub mod preflight;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CmdResult {
     pub command: String,
     pub cwd: Option<String>,
     pub status: i32,
+    /// Canonical status code (same as `status`; -1 when killed by timeout).
+    pub status_code: i32,
     pub stdout: String,
     pub stderr: String,
+    /// Wall-clock duration in milliseconds.
+    pub duration_ms: u128,
+    pub timed_out: bool,
     pub via_shell_fallback: bool,
 }
 
+/// Run `c` capturing stdout/stderr; kill it after `timeout_secs` (0 = no limit).
+/// Returns the output and whether the process was killed by the timeout.
+fn output_with_timeout(mut c: Command, timeout_secs: u64) -> io::Result<(Output, bool)> {
+    c.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = c.spawn()?;
+
+    // drain pipes on threads so a chatty child can't block on a full pipe
+    let mut out_pipe = child.stdout.take();
+    let mut err_pipe = child.stderr.take();
+    let out_t = thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(p) = out_pipe.as_mut() {
+            let _ = p.read_to_end(&mut buf);
+        }
+        buf
+    });
+    let err_t = thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(p) = err_pipe.as_mut() {
+            let _ = p.read_to_end(&mut buf);
+        }
+        buf
+    });
+
+    let started = Instant::now();
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(st) = child.try_wait()? {
+            break st;
+        }
+        if timeout_secs > 0 && started.elapsed() >= Duration::from_secs(timeout_secs) {
+            let _ = child.kill();
+            timed_out = true;
+            break child.wait()?;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let stdout = out_t.join().unwrap_or_default();
+    let stderr = err_t.join().unwrap_or_default();
+    Ok((Output { status, stdout, stderr }, timed_out))
+}
+
+fn to_result(cmd: &str, cwd: Option<&str>, out: Output, timed_out: bool, started: Instant, via_shell_fallback: bool) -> CmdResult {
+    let code = if timed_out { -1 } else { out.status.code().unwrap_or_default() };
+    let mut stderr = String::from_utf8_lossy(&out.stderr).to_string();
+    if timed_out {
+        stderr.push_str("\n(killed: timeout exceeded)");
+    }
+    CmdResult {
+        command: cmd.to_string(),
+        cwd: cwd.map(|s| s.to_string()),
+        status: code,
+        status_code: code,
+        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+        stderr,
+        duration_ms: started.elapsed().as_millis(),
+        timed_out,
+        via_shell_fallback,
+    }
+}
+
 pub fn run_command_allowlisted(
     cmd: &str,
     cfg: &Config,
@@ -27,7 +102,11 @@ pub fn run_command_allowlisted(
             cfg.command_allowlist
         );
     }
+    run_command(cmd, cwd, timeout_secs)
+}
 
+/// Run `cmd` whatever the allowlist says; callers vet it first.
+pub fn run_command(cmd: &str, cwd: Option<&str>, timeout_secs: u64) -> Result<CmdResult> {
     // Try direct spawn first
     match run_direct(cmd, cwd, timeout_secs) {
         Ok(r) => return Ok(r),
@@ -44,7 +123,7 @@ pub fn run_command_allowlisted(
     }
 }
 
-fn run_direct(cmd: &str, cwd: Option<&str>, _timeout_secs: u64) -> Result<CmdResult> {
+fn run_direct(cmd: &str, cwd: Option<&str>, timeout_secs: u64) -> Result<CmdResult> {
     // Split command into program + args (simple split by whitespace)
     let mut parts = shlex::Shlex::new(cmd);
     let mut tokens: Vec<String> = parts.by_ref().collect();
@@ -58,56 +137,36 @@ fn run_direct(cmd: &str, cwd: Option<&str>, _timeout_secs: u64) -> Result<CmdRes
         c.current_dir(dir);
     }
     c.args(tokens);
-    c.stdout(Stdio::piped()).stderr(Stdio::piped());
 
-    let out = c.output().with_context(|| format!("failed to spawn command {}", cmd))?;
+    let started = Instant::now();
+    let (out, timed_out) = output_with_timeout(c, timeout_secs)
+        .with_context(|| format!("failed to spawn command {}", cmd))?;
 
-    Ok(CmdResult {
-        command: cmd.to_string(),
-        cwd: cwd.map(|s| s.to_string()),
-        status: out.status.code().unwrap_or_default(),
-        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
-        via_shell_fallback: false,
-    })
+    Ok(to_result(cmd, cwd, out, timed_out, started, false))
 }
 
 #[cfg(target_os = "windows")]
-fn shell_fallback(cmd: &str, cwd: Option<&str>, _timeout_secs: u64) -> Result<CmdResult> {
+fn shell_fallback(cmd: &str, cwd: Option<&str>, timeout_secs: u64) -> Result<CmdResult> {
     let mut c = Command::new("cmd");
     c.arg("/C").arg(cmd);
     if let Some(dir) = cwd {
         c.current_dir(dir);
     }
-    c.stdout(Stdio::piped()).stderr(Stdio::piped());
-    let out = c.output()?;
+    let started = Instant::now();
+    let (out, timed_out) = output_with_timeout(c, timeout_secs)?;
 
-    Ok(CmdResult {
-        command: cmd.to_string(),
-        cwd: cwd.map(|s| s.to_string()),
-        status: out.status.code().unwrap_or_default(),
-        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
-        via_shell_fallback: true,
-    })
+    Ok(to_result(cmd, cwd, out, timed_out, started, true))
 }
 
 #[cfg(not(target_os = "windows"))]
-fn shell_fallback(cmd: &str, cwd: Option<&str>, _timeout_secs: u64) -> Result<CmdResult> {
+fn shell_fallback(cmd: &str, cwd: Option<&str>, timeout_secs: u64) -> Result<CmdResult> {
     let mut c = Command::new("sh");
     c.arg("-lc").arg(cmd);
     if let Some(dir) = cwd {
         c.current_dir(dir);
     }
-    c.stdout(Stdio::piped()).stderr(Stdio::piped());
-    let out = c.output()?;
+    let started = Instant::now();
+    let (out, timed_out) = output_with_timeout(c, timeout_secs)?;
 
-    Ok(CmdResult {
-        command: cmd.to_string(),
-        cwd: cwd.map(|s| s.to_string()),
-        status: out.status.code().unwrap_or_default(),
-        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
-        via_shell_fallback: true,
-    })
+    Ok(to_result(cmd, cwd, out, timed_out, started, true))
 }
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Run profile: `ci` enforces lockfile-frozen installs, `dev` keeps normal installs.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunProfile {
+    Dev,
+    Ci,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+    /// Yarn 2+ (`.yarnrc.yml`): `--immutable` instead of `--frozen-lockfile`.
+    YarnBerry,
+    Bun,
+}
+
+impl PackageManager {
+    fn name(self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn | PackageManager::YarnBerry => "yarn",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    /// The lockfile an install writes when there is none yet.
+    pub fn lockfile(self) -> &'static str {
+        match self {
+            PackageManager::Npm => "package-lock.json",
+            PackageManager::Pnpm => "pnpm-lock.yaml",
+            PackageManager::Yarn | PackageManager::YarnBerry => "yarn.lock",
+            PackageManager::Bun => "bun.lock",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "npm" => Some(PackageManager::Npm),
+            "pnpm" => Some(PackageManager::Pnpm),
+            "yarn" => Some(PackageManager::Yarn),
+            "bun" => Some(PackageManager::Bun),
+            _ => None,
+        }
+    }
+}
+
+/// Detect the project's package manager from its lockfile (npm if none).
+pub fn detect_package_manager(root: &Path) -> PackageManager {
+    if root.join("pnpm-lock.yaml").exists() {
+        PackageManager::Pnpm
+    } else if root.join("yarn.lock").exists() {
+        if root.join(".yarnrc.yml").exists() {
+            PackageManager::YarnBerry
+        } else {
+            PackageManager::Yarn
+        }
+    } else if root.join("bun.lockb").exists() || root.join("bun.lock").exists() {
+        PackageManager::Bun
+    } else {
+        PackageManager::Npm
+    }
+}
+
+/// Install intent parsed from a model-produced command string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Install {
+    /// Reconcile dependencies from package.json/lockfile.
+    All,
+    /// Add packages (`dev` = devDependencies).
+    Add { packages: Vec<String>, dev: bool },
+}
+
+fn parse_install(cmd: &str) -> Option<Install> {
+    let tokens: Vec<String> = shlex::Shlex::new(cmd).collect();
+    let (first, rest) = tokens.split_first()?;
+    let pm = PackageManager::parse(first)?;
+    let (verb, args): (&str, &[String]) = match rest.split_first() {
+        Some((v, a)) => (v.as_str(), a),
+        // bare `yarn` installs
+        None if pm == PackageManager::Yarn => ("install", &[]),
+        None => return None,
+    };
+    let is_install_verb = match pm {
+        PackageManager::Npm => matches!(verb, "install" | "i" | "ci" | "add"),
+        _ => matches!(verb, "install" | "i" | "add"),
+    };
+    if !is_install_verb {
+        return None;
+    }
+    // any other flag (`-g`, `-E`, `--registry x`...) changes what the
+    // command does; leave it as the model wrote it
+    let is_dev_flag = |a: &String| a == "-D" || a == "--save-dev" || a == "--dev";
+    if args.iter().any(|a| a.starts_with('-') && !is_dev_flag(a)) {
+        return None;
+    }
+    let dev = args.iter().any(is_dev_flag);
+    let packages: Vec<String> = args.iter().filter(|a| !a.starts_with('-')).cloned().collect();
+    if packages.is_empty() {
+        Some(Install::All)
+    } else {
+        Some(Install::Add { packages, dev })
+    }
+}
+
+fn render(install: &Install, pm: PackageManager, profile: RunProfile, offline: bool) -> String {
+    let mut parts: Vec<String> = vec![pm.name().to_string()];
+    match install {
+        Install::All => match (pm, profile) {
+            (PackageManager::Npm, RunProfile::Ci) => parts.push("ci".into()),
+            (PackageManager::Npm, RunProfile::Dev) => parts.push("install".into()),
+            (PackageManager::YarnBerry, RunProfile::Ci) => {
+                parts.push("install".into());
+                parts.push("--immutable".into());
+            }
+            (_, RunProfile::Ci) => {
+                parts.push("install".into());
+                parts.push("--frozen-lockfile".into());
+            }
+            (_, RunProfile::Dev) => parts.push("install".into()),
+        },
+        Install::Add { packages, dev } => {
+            parts.push(if pm == PackageManager::Npm { "install".into() } else { "add".into() });
+            if *dev {
+                parts.push("-D".into());
+            }
+            parts.extend(packages.iter().cloned());
+        }
+    }
+    // yarn berry has no `--offline`; its offline mirror is configured in .yarnrc.yml
+    if offline && !matches!(pm, PackageManager::Bun | PackageManager::YarnBerry) {
+        parts.push("--offline".into());
+    }
+    parts.join(" ")
+}
+
+/// Rewrite an install command for the project's package manager and run
+/// profile (e.g. `npm install` -> `pnpm install --frozen-lockfile` in CI on
+/// a pnpm project). Non-install commands, and installs with flags other
+/// than the dev-dependency ones, are returned unchanged.
+pub fn rewrite_install(cmd: &str, pm: PackageManager, profile: RunProfile, offline: bool) -> String {
+    match parse_install(cmd) {
+        Some(install) => render(&install, pm, profile, offline),
+        None => cmd.to_string(),
+    }
+}
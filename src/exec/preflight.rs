@@ -0,0 +1,125 @@
+use std::process::Command;
+
+use crate::config::Config;
+use crate::wire::{Plan, Step};
+
+/// One requirement such as `node>=18`, `git` or `docker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub tool: String,
+    pub op: Option<String>,
+    pub version: Option<Vec<u64>>,
+}
+
+/// An unmet requirement and why.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub requirement: String,
+    pub message: String,
+}
+
+impl Requirement {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let at = s.find(['>', '<', '=']);
+        let (tool, rest) = match at {
+            Some(i) => (s[..i].trim(), Some(s[i..].trim())),
+            None => (s, None),
+        };
+        if tool.is_empty() {
+            return None;
+        }
+        let (op, version) = match rest {
+            Some(r) => {
+                let op_len = r.chars().take_while(|c| matches!(c, '>' | '<' | '=')).count();
+                let v = parse_version(&r[op_len..])?;
+                (Some(r[..op_len].to_string()), Some(v))
+            }
+            None => (None, None),
+        };
+        Some(Self { tool: tool.to_string(), op, version })
+    }
+
+    fn satisfied_by(&self, found: &[u64]) -> bool {
+        let (Some(op), Some(want)) = (&self.op, &self.version) else { return true };
+        // compare only as many components as the requirement gives (`node>=18`)
+        let have: Vec<u64> = (0..want.len()).map(|i| found.get(i).copied().unwrap_or(0)).collect();
+        match op.as_str() {
+            ">=" => have >= *want,
+            ">" => have > *want,
+            "<=" => have <= *want,
+            "<" => have < *want,
+            _ => have == *want,
+        }
+    }
+}
+
+/// First dotted number in `s` (`v18.19.0`, `git version 2.43.0`).
+fn parse_version(s: &str) -> Option<Vec<u64>> {
+    let start = s.find(|c: char| c.is_ascii_digit())?;
+    let digits: String = s[start..].chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    let v: Vec<u64> = digits.split('.').filter(|p| !p.is_empty()).filter_map(|p| p.parse().ok()).collect();
+    (!v.is_empty()).then_some(v)
+}
+
+/// Requirements for applying `plan`: config `requirements`, the plan's own
+/// `requires` plus `extra` (e.g. from the approved PLAN), and the program of
+/// every command/test step as apply will run it (when commands run at all).
+pub fn requirements(plan: &Plan, extra: &[String], cfg: &Config) -> Vec<String> {
+    let mut out: Vec<String> = cfg.requirements.iter().chain(&plan.requires).chain(extra).cloned().collect();
+    if cfg.trusted {
+        let pm = super::policy::detect_package_manager(std::path::Path::new(&cfg.root));
+        for step in &plan.steps {
+            if let Step::Command { command, .. } | Step::Test { command, .. } = step {
+                let command = super::policy::rewrite_install(command, pm, cfg.run_profile, cfg.offline_installs);
+                if let Some(program) = shlex::Shlex::new(&command).next() {
+                    out.push(program);
+                }
+            }
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    out.retain(|r| seen.insert(r.trim().to_string()));
+    out
+}
+
+/// Check every requirement; empty means all are met.
+pub fn check(requirements: &[String]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for raw in requirements {
+        let Some(req) = Requirement::parse(raw) else {
+            problems.push(Problem { requirement: raw.clone(), message: "unrecognized requirement".into() });
+            continue;
+        };
+        let Ok(path) = which::which(&req.tool) else {
+            problems.push(Problem { requirement: raw.clone(), message: format!("{} not found on PATH", req.tool) });
+            continue;
+        };
+        if req.version.is_none() {
+            continue;
+        }
+        let found = Command::new(&path)
+            .arg("--version")
+            .output()
+            .ok()
+            .and_then(|o| parse_version(&format!("{}{}", String::from_utf8_lossy(&o.stdout), String::from_utf8_lossy(&o.stderr))));
+        match found {
+            Some(v) if req.satisfied_by(&v) => {}
+            Some(v) => problems.push(Problem {
+                requirement: raw.clone(),
+                message: format!(
+                    "{} {} found, need {}{}",
+                    req.tool,
+                    v.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("."),
+                    req.op.as_deref().unwrap_or(""),
+                    req.version.as_ref().map(|w| w.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(".")).unwrap_or_default()
+                ),
+            }),
+            None => problems.push(Problem {
+                requirement: raw.clone(),
+                message: format!("could not read `{} --version`", req.tool),
+            }),
+        }
+    }
+    problems
+}
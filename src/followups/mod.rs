@@ -0,0 +1,108 @@
+use anyhow::Result;
+use fs_err as fs;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::wire::Step;
+
+/// Where accepted follow-ups are queued, one task per line.
+pub const QUEUE_FILE: &str = ".vibe/followups.txt";
+
+/// A TODO/FIXME the model left in generated code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowUp {
+    pub path: String,
+    pub line: usize,
+    pub marker: String,
+    pub text: String,
+}
+
+fn marker_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?://|/\*|\*|#|<!--|\{/\*)\s*(TODO|FIXME|XXX|HACK)\b[:(\s-]*(.*)").unwrap())
+}
+
+/// Markers in `content`, minus those already present in `old` (an update
+/// that keeps an existing TODO isn't a new follow-up).
+pub fn scan(path: &str, content: &str, old: Option<&str>) -> Vec<FollowUp> {
+    let re = marker_re();
+    let known: Vec<String> = old
+        .map(|o| re.captures_iter(o).map(|c| c[2].trim().to_string()).collect())
+        .unwrap_or_default();
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, l)| {
+            let c = re.captures(l)?;
+            let raw = c[2].trim().to_string();
+            if known.contains(&raw) {
+                return None;
+            }
+            // drop the comment's closing delimiter
+            let text = ["*/}", "*/", "-->"].iter().fold(raw.as_str(), |t, close| t.trim_end_matches(close)).trim().to_string();
+            Some(FollowUp { path: path.to_string(), line: i + 1, marker: c[1].to_string(), text })
+        })
+        .collect()
+}
+
+/// New markers across the plan's full-content create/update steps; call
+/// before applying, while the old files are still on disk.
+pub fn collect(root: &Path, steps: &[Step]) -> Vec<FollowUp> {
+    steps
+        .iter()
+        .flat_map(|s| match s {
+            Step::Create { path, content: Some(c), .. } => scan(path, c, None),
+            Step::Update { path, content: Some(c), .. } => scan(path, c, fs::read_to_string(root.join(path)).ok().as_deref()),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// The follow-up phrased as a task for a later run.
+pub fn as_task(f: &FollowUp) -> String {
+    let what = if f.text.is_empty() { "resolve the marker".to_string() } else { f.text.clone() };
+    format!("{} ({} at {}:{})", what, f.marker, f.path, f.line)
+}
+
+/// The oldest queued task, if any.
+pub fn next(root: &Path) -> Result<Option<String>> {
+    let path = root.join(QUEUE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(fs::read_to_string(&path)?.lines().map(str::trim).find(|l| !l.is_empty()).map(str::to_string))
+}
+
+/// Drop the first queued line equal to `task`; the file goes once empty.
+pub fn dequeue(root: &Path, task: &str) -> Result<()> {
+    let path = root.join(QUEUE_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut lines: Vec<String> = fs::read_to_string(&path)?.lines().map(str::to_string).collect();
+    if let Some(i) = lines.iter().position(|l| l.trim() == task) {
+        lines.remove(i);
+    }
+    if lines.iter().all(|l| l.trim().is_empty()) {
+        fs::remove_file(&path)?;
+    } else {
+        fs::write(&path, lines.join("\n") + "\n")?;
+    }
+    Ok(())
+}
+
+/// Append the follow-ups to `QUEUE_FILE`; returns its path.
+pub fn enqueue(root: &Path, items: &[FollowUp]) -> Result<PathBuf> {
+    let path = root.join(QUEUE_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut f = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    for item in items {
+        writeln!(f, "{}", as_task(item))?;
+    }
+    Ok(path)
+}
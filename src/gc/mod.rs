@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// Suffix of `apply::write_atomic`'s temp files (`page.tsx` -> `page..__tmp__`).
+const TMP_SUFFIX: &str = ".__tmp__";
+const SKIP_DIRS: &[&str] = &["node_modules", ".next", ".git", "dist", "build", "out", "coverage"];
+/// A lock whose owner can't be checked is considered stale after this long.
+const LOCK_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+fn lock_path(root: &Path) -> PathBuf {
+    root.join(".vibe").join("lock")
+}
+
+/// Contents of `.vibe/lock`: who is running in this project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub tx: Uuid,
+    pub started: DateTime<Utc>,
+}
+
+/// What a startup sweep removed.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub temp_files: Vec<String>,
+    /// Lock left by a run that is gone.
+    pub stale_lock: Option<LockInfo>,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.temp_files.is_empty() && self.stale_lock.is_none()
+    }
+}
+
+/// Whether process `pid` is still running; `None` where that can't be asked.
+fn alive(pid: u32) -> Option<bool> {
+    if cfg!(unix) {
+        let status = std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .ok()?;
+        Some(status.success())
+    } else {
+        None
+    }
+}
+
+fn read_lock(root: &Path) -> Option<LockInfo> {
+    serde_json::from_str(&fs::read_to_string(lock_path(root)).ok()?).ok()
+}
+
+fn is_stale(lock: &LockInfo) -> bool {
+    match alive(lock.pid) {
+        Some(up) => !up,
+        None => (Utc::now() - lock.started).to_std().map(|age| age > LOCK_MAX_AGE).unwrap_or(false),
+    }
+}
+
+/// Remove temp files older than `min_age` that a crashed apply left behind,
+/// and `.vibe/lock` if the run that took it is gone.
+pub fn collect(root: &Path, min_age: Duration) -> Result<Report> {
+    let mut report = Report::default();
+    let now = SystemTime::now();
+    let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
+        !(e.file_type().is_dir() && SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        if !entry.file_name().to_string_lossy().ends_with(TMP_SUFFIX) {
+            continue;
+        }
+        let old = entry
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| now.duration_since(t).ok())
+            .map(|age| age >= min_age)
+            .unwrap_or(false);
+        if !old {
+            continue;
+        }
+        fs::remove_file(entry.path())?;
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        report.temp_files.push(crate::safety::normalize_rel(&rel.to_string_lossy()));
+    }
+
+    let lock = lock_path(root);
+    if lock.exists() {
+        match read_lock(root) {
+            Some(info) if is_stale(&info) => {
+                fs::remove_file(&lock)?;
+                report.stale_lock = Some(info);
+            }
+            Some(_) => {}
+            // unreadable: a run died mid-write, or one is still writing it
+            None if is_old(&lock) => {
+                fs::remove_file(&lock)?;
+            }
+            None => {}
+        }
+    }
+    Ok(report)
+}
+
+/// `.vibe/lock` held for the length of a run, so two runs don't apply into
+/// the same project at once. Released on drop.
+pub struct RunLock {
+    root: PathBuf,
+    tx: Uuid,
+}
+
+impl RunLock {
+    /// Create the lock file exclusively; a stale one (its run is gone) is
+    /// removed and creation retried, so of two racing runs only one wins.
+    pub fn acquire(root: &Path, tx: Uuid) -> Result<Self> {
+        let path = lock_path(root);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let info = LockInfo { pid: std::process::id(), tx, started: Utc::now() };
+        for _ in 0..3 {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    std::io::Write::write_all(&mut f, serde_json::to_string_pretty(&info)?.as_bytes())?;
+                    return Ok(Self { root: root.to_path_buf(), tx });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(anyhow::Error::new(e).context(format!("creating {}", path.display()))),
+            }
+            match read_lock(root) {
+                Some(other) if !is_stale(&other) => bail!(
+                    "another run (pid {}, tx {}, started {}) holds {}; wait for it or delete the file if it is gone",
+                    other.pid,
+                    other.tx,
+                    other.started.format("%Y-%m-%d %H:%M:%S UTC"),
+                    path.display()
+                ),
+                Some(_) => {}
+                // unreadable: its owner may still be writing it, unless it is old
+                None if !is_old(&path) => bail!("another run is taking {}; try again", path.display()),
+                None => {}
+            }
+            // stale; a racing run may have removed it already
+            let _ = std::fs::remove_file(&path);
+        }
+        bail!("could not take {}; another run keeps replacing it", path.display())
+    }
+}
+
+impl Drop for RunLock {
+    /// Only removes the lock while it is still ours, not one another run
+    /// took after ours was judged stale.
+    fn drop(&mut self) {
+        if read_lock(&self.root).is_some_and(|l| l.pid == std::process::id() && l.tx == self.tx) {
+            let _ = std::fs::remove_file(lock_path(&self.root));
+        }
+    }
+}
+
+/// Whether `path` was last written over a minute ago.
+fn is_old(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_some_and(|age| age > Duration::from_secs(60))
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Consecutive lines of a file last changed by the same commit.
+#[derive(Debug, Clone, Serialize)]
+pub struct Region {
+    /// 1-based, inclusive.
+    pub start: usize,
+    pub end: usize,
+    pub commit: String,
+    pub author: String,
+    pub date: String,
+    /// The commit's subject line.
+    pub summary: String,
+}
+
+struct CommitInfo {
+    author: String,
+    time: i64,
+    summary: String,
+}
+
+/// The regions of root-relative `path` that its `max_commits` most recent
+/// commits last changed, in file order. Uncommitted lines are left out.
+pub fn recent_regions(root: &Path, path: &str, max_commits: usize) -> Result<Vec<Region>> {
+    let out = super::git_raw(root, &["blame", "--porcelain", "--", path])?;
+    let mut info: HashMap<String, CommitInfo> = HashMap::new();
+    // (line, sha) for each line of the file
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut current = String::new();
+    for l in out.lines() {
+        if l.starts_with('\t') {
+            continue;
+        }
+        let mut words = l.split(' ');
+        let first = words.next().unwrap_or("");
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            current = first.to_string();
+            let final_line = words.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            lines.push((final_line, current.clone()));
+            info.entry(current.clone()).or_insert(CommitInfo { author: String::new(), time: 0, summary: String::new() });
+            continue;
+        }
+        let Some(c) = info.get_mut(&current) else { continue };
+        let rest = l.split_once(' ').map(|(_, r)| r).unwrap_or("");
+        match first {
+            "author" => c.author = rest.to_string(),
+            "author-time" => c.time = rest.parse().unwrap_or(0),
+            "summary" => c.summary = rest.to_string(),
+            _ => {}
+        }
+    }
+
+    let uncommitted = |sha: &str| sha.bytes().all(|b| b == b'0');
+    let mut recent: Vec<(&String, &CommitInfo)> = info.iter().filter(|(sha, _)| !uncommitted(sha)).collect();
+    recent.sort_by_key(|(_, c)| std::cmp::Reverse(c.time));
+    recent.truncate(max_commits);
+
+    lines.sort_by_key(|(n, _)| *n);
+    let mut regions: Vec<Region> = Vec::new();
+    for (n, sha) in lines {
+        let Some((_, c)) = recent.iter().find(|(s, _)| **s == sha) else { continue };
+        let short = &sha[..12];
+        if let Some(r) = regions.last_mut().filter(|r| r.commit == short && r.end + 1 == n) {
+            r.end = n;
+            continue;
+        }
+        regions.push(Region {
+            start: n,
+            end: n,
+            commit: short.to_string(),
+            author: c.author.clone(),
+            date: DateTime::<Utc>::from_timestamp(c.time, 0).map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            summary: c.summary.clone(),
+        });
+    }
+    Ok(regions)
+}
@@ -1,4 +1,369 @@
-pub fn is_repo(_root: &std::path::Path) -> bool { false }
-pub fn commit_all(_root:&std::path::Path, _message:&str) -> anyhow::Result<String> { Ok(String::new()) }
-pub fn tag(_root:&std::path::Path, _name:&str, _commit:&str) -> anyhow::Result<()> { Ok(()) }
-pub fn rollback_last(_root:&std::path::Path) -> anyhow::Result<()> { Ok(()) }
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use fs_err as fs;
+use uuid::Uuid;
+
+use crate::wire::{Plan, Step};
+
+pub mod blame;
+pub mod pr;
+
+/// What to do when files the plan touches have uncommitted changes.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyWorktree {
+    /// Apply over them (the tx backup still has the old content).
+    Allow,
+    /// Refuse to apply until they are committed or stashed.
+    #[default]
+    Refuse,
+    /// `git stash` them before apply and pop the stash afterwards.
+    Stash,
+}
+
+/// What a transaction did in git; saved as `git.json` in the tx directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TxGit {
+    /// Branch checked out before the run (`None` on a detached HEAD).
+    #[serde(default)]
+    pub base_branch: Option<String>,
+    /// HEAD before any change was applied.
+    #[serde(default)]
+    pub base_commit: String,
+    /// `vibe/<short-txid>` when `--git-branch` created one.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// The commit `--git-commit` made of the applied files (with
+    /// `--commit-per-step`, the last one).
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// `--commit-per-step`: one commit per file-affecting step, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<String>,
+    /// URL of the pull/merge request `--open-pr` opened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr: Option<String>,
+}
+
+/// `git -C <root> <args>`, stdout trimmed; a non-zero exit is an error
+/// carrying git's stderr.
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    git_raw(root, args).map(|s| s.trim().to_string())
+}
+
+fn git_raw(root: &Path, args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .context("running git (is it installed?)")?;
+    if !out.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+pub fn is_repo(root: &Path) -> bool {
+    git(root, &["rev-parse", "--is-inside-work-tree"]).map(|s| s == "true").unwrap_or(false)
+}
+
+pub fn head_commit(root: &Path) -> Result<String> {
+    git(root, &["rev-parse", "HEAD"])
+}
+
+/// Current branch, `None` when HEAD is detached.
+pub fn current_branch(root: &Path) -> Option<String> {
+    git(root, &["symbolic-ref", "--quiet", "--short", "HEAD"]).ok().filter(|b| !b.is_empty())
+}
+
+/// `vibe/<first 8 hex digits of the txid>`.
+pub fn tx_branch_name(txid: Uuid) -> String {
+    format!("vibe/{}", &txid.simple().to_string()[..8])
+}
+
+impl TxGit {
+    /// Where the repository at `root` stands before anything is applied.
+    pub fn start(root: &Path) -> Result<Self> {
+        if !is_repo(root) {
+            bail!("{} is not inside a git repository (needed by --git-branch/--git-commit)", root.display());
+        }
+        let base_commit = head_commit(root).context("the repository needs at least one commit")?;
+        Ok(Self { base_branch: current_branch(root), base_commit, ..Default::default() })
+    }
+
+    /// Create the transaction's branch from HEAD and switch to it, so applied
+    /// changes never land on the working branch. Uncommitted work is carried
+    /// over as `git switch -c` does.
+    pub fn create_branch(&mut self, root: &Path, txid: Uuid) -> Result<()> {
+        let branch = tx_branch_name(txid);
+        git(root, &["switch", "-c", &branch])?;
+        self.branch = Some(branch);
+        Ok(())
+    }
+}
+
+/// Which of the root-relative `paths` have uncommitted changes (staged,
+/// unstaged or untracked).
+pub fn dirty(root: &Path, paths: &[String]) -> Result<Vec<String>> {
+    if paths.is_empty() {
+        return Ok(vec![]);
+    }
+    // porcelain paths are relative to the repository top, not to `root`
+    let prefix = git(root, &["rev-parse", "--show-prefix"])?;
+    let mut args = vec!["status", "--porcelain=v1", "-z", "--untracked-files=all", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let out = git_raw(root, &args)?;
+    let mut dirty = Vec::new();
+    let mut entries = out.split('\0');
+    while let Some(e) = entries.next() {
+        if e.len() < 4 {
+            continue;
+        }
+        let (status, path) = (&e[..2], &e[3..]);
+        if status.starts_with(['R', 'C']) {
+            entries.next(); // rename/copy source
+        }
+        dirty.push(path.strip_prefix(prefix.as_str()).unwrap_or(path).to_string());
+    }
+    Ok(dirty)
+}
+
+/// Stash the uncommitted changes to `paths` (untracked files included).
+pub fn stash_paths(root: &Path, paths: &[String], message: &str) -> Result<()> {
+    let mut args = vec!["stash", "push", "--include-untracked", "-m", message, "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git(root, &args).map(|_| ())
+}
+
+/// What `unstash_onto` couldn't merge back; the stash is kept when either
+/// is non-empty.
+#[derive(Debug, Default)]
+pub struct Unstashed {
+    /// Left with conflict markers.
+    pub conflicts: Vec<String>,
+    /// Deleted by the plan; not recreated from the stash.
+    pub deleted: Vec<String>,
+}
+
+/// Merge the newest stash's version of `paths` into the files as they are
+/// now (three-way, with the stash's base commit as the ancestor), then drop
+/// the stash. `git stash pop` would refuse because apply rewrote the same
+/// files. Paths in `deleted` (the plan removed them) are left alone.
+pub fn unstash_onto(root: &Path, paths: &[String], deleted: &[String]) -> Result<Unstashed> {
+    let mut out = Unstashed::default();
+    for path in paths {
+        if deleted.contains(path) && !root.join(path).exists() {
+            out.deleted.push(path.clone());
+            continue;
+        }
+        // untracked files live in the stash's third parent
+        let theirs = show_file(root, "stash@{0}", path).or_else(|| show_file(root, "stash@{0}^3", path));
+        let Some(theirs) = theirs else { continue };
+        let base = show_file(root, "stash@{0}^1", path).unwrap_or_default();
+        let abs = root.join(path);
+        let ours = fs::read_to_string(&abs).unwrap_or_default();
+        let (merged, clean) = merge_file(&ours, &base, &theirs, ["applied", "base", "yours"])
+            .with_context(|| format!("merging {}", path))?;
+        if !clean {
+            out.conflicts.push(path.clone());
+        }
+        if let Some(dir) = abs.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&abs, merged)?;
+    }
+    if out.conflicts.is_empty() && out.deleted.is_empty() {
+        git(root, &["stash", "drop", "--quiet"])?;
+    }
+    Ok(out)
+}
+
+/// Three-way merge of `ours` and `theirs` from their common `base` with
+/// `git merge-file`; `labels` name the three in conflict markers. Returns
+/// the result and whether it merged without conflicts.
+pub fn merge_file(ours: &str, base: &str, theirs: &str, labels: [&str; 3]) -> Result<(String, bool)> {
+    let tmp = tempfile::tempdir()?;
+    let files = [("ours", ours), ("base", base), ("theirs", theirs)].map(|(name, text)| {
+        let p = tmp.path().join(name);
+        fs::write(&p, text).map(|_| p)
+    });
+    let [ours_p, base_p, theirs_p] = files;
+    let out = Command::new("git")
+        .arg("merge-file")
+        .arg("-p")
+        .args(["-L", labels[0], "-L", labels[1], "-L", labels[2]])
+        .arg(ours_p?)
+        .arg(base_p?)
+        .arg(theirs_p?)
+        .output()
+        .context("running git merge-file")?;
+    // exit status is the number of conflicts; negative on error
+    match out.status.code() {
+        Some(n) if n >= 0 => Ok((String::from_utf8_lossy(&out.stdout).into_owned(), n == 0)),
+        _ => bail!("git merge-file failed: {}", String::from_utf8_lossy(&out.stderr).trim()),
+    }
+}
+
+/// The last `n` commits as `git log --oneline` lines; empty before the
+/// first commit.
+pub fn recent_log(root: &Path, n: usize) -> Result<Vec<String>> {
+    if head_commit(root).is_err() {
+        return Ok(vec![]);
+    }
+    let out = git(root, &["log", "--oneline", "--no-decorate", "-n", &n.to_string()])?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Uncommitted changes (staged and unstaged) to the root-relative `paths`,
+/// as a unified diff against HEAD.
+pub fn diff_head(root: &Path, paths: &[String]) -> Result<String> {
+    if paths.is_empty() || head_commit(root).is_err() {
+        return Ok(String::new());
+    }
+    let mut args = vec!["diff", "HEAD", "--no-color", "--no-ext-diff", "--relative", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_raw(root, &args)
+}
+
+/// Upstream of the current branch (`origin/main`); `None` without one.
+pub fn upstream(root: &Path) -> Option<String> {
+    git(root, &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"]).ok().filter(|u| !u.is_empty())
+}
+
+/// `git fetch` of the current branch's remote.
+pub fn fetch(root: &Path) -> Result<()> {
+    git(root, &["fetch", "--quiet"]).map(|_| ())
+}
+
+/// A file changed on the upstream branch by commits HEAD doesn't have.
+#[derive(Debug, Clone)]
+pub struct Diverged {
+    pub path: String,
+    /// `git log --oneline` lines, newest first.
+    pub commits: Vec<String>,
+}
+
+/// Which of the root-relative `paths` commits on `upstream` (and not in
+/// HEAD) touch.
+pub fn diverged(root: &Path, upstream: &str, paths: &[String]) -> Result<Vec<Diverged>> {
+    let range = format!("HEAD..{}", upstream);
+    let mut out = Vec::new();
+    for path in paths {
+        let log = git(root, &["log", "--oneline", "--no-decorate", &range, "--", path])?;
+        if !log.is_empty() {
+            out.push(Diverged { path: path.clone(), commits: log.lines().map(str::to_string).collect() });
+        }
+    }
+    Ok(out)
+}
+
+/// Everything staged for the next commit (the whole repository), as a
+/// unified diff against HEAD.
+pub fn diff_staged(root: &Path) -> Result<String> {
+    git_raw(root, &["diff", "--cached", "--no-color", "--no-ext-diff"])
+}
+
+/// `git rev-parse --git-path`: where `name` lives in the git directory
+/// (worktree-aware).
+pub fn git_path(root: &Path, name: &str) -> Result<std::path::PathBuf> {
+    Ok(root.join(git(root, &["rev-parse", "--git-path", name])?))
+}
+
+/// Files `commit` changed under `root`, relative to it.
+pub fn files_in_commit(root: &Path, commit: &str) -> Result<Vec<String>> {
+    let out = git(root, &["diff-tree", "--no-commit-id", "--name-only", "-r", "--root", "--relative", commit])?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Root-relative `path` as of `rev` (`None` when it doesn't exist there).
+pub fn show_file(root: &Path, rev: &str, path: &str) -> Option<String> {
+    git_raw(root, &["show", &format!("{}:./{}", rev, path)]).ok()
+}
+
+/// Stage `paths` (deletions included) and commit only them, leaving anything
+/// else already staged out of the commit. `None` when they hold no changes.
+pub fn commit_paths(root: &Path, paths: &[String], message: &str) -> Result<Option<String>> {
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let mut add = vec!["add", "-A", "--"];
+    add.extend(paths.iter().map(String::as_str));
+    git(root, &add)?;
+    let mut staged = vec!["diff", "--cached", "--name-only", "--"];
+    staged.extend(paths.iter().map(String::as_str));
+    if git(root, &staged)?.is_empty() {
+        return Ok(None);
+    }
+    let mut commit = vec!["commit", "--quiet", "-m", message, "--only", "--"];
+    commit.extend(paths.iter().map(String::as_str));
+    git(root, &commit)?;
+    head_commit(root).map(Some)
+}
+
+/// Move HEAD back to `to`, dropping the commits after it, and unstage what
+/// they committed. Files on disk and anything else staged are left as is.
+pub fn uncommit(root: &Path, to: &str) -> Result<()> {
+    let changed = git(root, &["diff", "--name-only", "--relative", to, "HEAD"])?;
+    git(root, &["reset", "--quiet", "--soft", to])?;
+    if !changed.is_empty() {
+        let mut args = vec!["reset", "--quiet", to, "--"];
+        args.extend(changed.lines());
+        git(root, &args)?;
+    }
+    Ok(())
+}
+
+/// `s` cut to 72 characters.
+fn subject_line(s: &str) -> String {
+    if s.chars().count() > 72 {
+        format!("{}...", s.chars().take(69).collect::<String>().trim_end())
+    } else {
+        s.to_string()
+    }
+}
+
+/// Commit message for one step under `--commit-per-step`: its title, what
+/// it did to which file, and the `Vibe-Tx` trailer.
+pub fn step_commit_message(step: &Step, txid: Uuid) -> String {
+    let title = step.title().trim();
+    let verb = match step {
+        Step::Create { .. } => "create",
+        Step::Delete { .. } => "delete",
+        _ => "update",
+    };
+    let subject = subject_line(if title.is_empty() { step.target() } else { title });
+    format!("{}\n\n{} {} (step {})\n\nVibe-Tx: {}", subject, verb, step.target(), step.id(), txid)
+}
+
+/// Commit message for an applied plan: the summary (minus its `mode=`
+/// prefix) as the subject, one line per file-affecting step, and a
+/// `Vibe-Tx` trailer.
+pub fn commit_message(plan: &Plan, txid: Uuid) -> String {
+    let summary = plan.summary.lines().next().unwrap_or("").trim();
+    let summary = match summary.strip_prefix("mode=") {
+        Some(rest) => rest.split_once([' ', ':', ';', ',']).map(|(_, r)| r).unwrap_or("").trim_start_matches([' ', ':', ';', ',', '-', '—']).trim(),
+        None => summary,
+    };
+    let subject = subject_line(if summary.is_empty() { "Apply vibe changes" } else { summary });
+    let steps: Vec<String> = plan
+        .steps
+        .iter()
+        .filter_map(|s| match s {
+            Step::Create { path, title, .. } => Some(format!("- create {}: {}", path, title)),
+            Step::Update { path, title, .. } => Some(format!("- update {}: {}", path, title)),
+            Step::Delete { path, title, .. } => Some(format!("- delete {}: {}", path, title)),
+            _ => None,
+        })
+        .collect();
+    let mut msg = subject;
+    if !steps.is_empty() {
+        msg.push_str("\n\n");
+        msg.push_str(&steps.join("\n"));
+    }
+    msg.push_str(&format!("\n\nVibe-Tx: {}", txid));
+    msg
+}
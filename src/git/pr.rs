@@ -0,0 +1,145 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use uuid::Uuid;
+
+use super::TxGit;
+use crate::patch::Preview;
+use crate::wire::Plan;
+
+/// Where the remote is hosted, i.e. which CLI opens the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrHost {
+    /// From the remote's URL.
+    #[default]
+    Auto,
+    /// `gh pr create`
+    Github,
+    /// `glab mr create`
+    Gitlab,
+}
+
+/// `[pr]` config table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrConfig {
+    /// Push the tx branch and open a pull/merge request after apply (`--open-pr`).
+    pub open: bool,
+    pub host: PrHost,
+    pub remote: String,
+    /// Target branch; the branch the run started on when unset.
+    pub base: Option<String>,
+    pub draft: bool,
+    pub labels: Vec<String>,
+    pub reviewers: Vec<String>,
+    /// Preview diff lines in the description, over all files.
+    pub max_diff_lines: usize,
+}
+
+impl Default for PrConfig {
+    fn default() -> Self {
+        Self {
+            open: false,
+            host: PrHost::Auto,
+            remote: "origin".to_string(),
+            base: None,
+            draft: false,
+            labels: vec![],
+            reviewers: vec![],
+            max_diff_lines: 400,
+        }
+    }
+}
+
+fn host_of(root: &Path, cfg: &PrConfig) -> Result<PrHost> {
+    if cfg.host != PrHost::Auto {
+        return Ok(cfg.host);
+    }
+    let url = super::git(root, &["remote", "get-url", &cfg.remote])?;
+    if url.contains("github") {
+        Ok(PrHost::Github)
+    } else if url.contains("gitlab") {
+        Ok(PrHost::Gitlab)
+    } else {
+        bail!("can't tell whether remote '{}' ({}) is GitHub or GitLab; set `host` in [pr]", cfg.remote, url)
+    }
+}
+
+/// Request description: the plan summary, its steps and the preview diffs.
+pub fn description(plan: &Plan, previews: &[Preview], txid: Uuid, max_diff_lines: usize) -> String {
+    let mut out = String::new();
+    out.push_str(plan.summary.trim());
+    out.push_str("\n\n");
+    let commit = super::commit_message(plan, txid);
+    if let Some((_, steps)) = commit.split_once("\n\n").filter(|(_, rest)| rest.starts_with("- ")) {
+        out.push_str("### Steps\n\n");
+        out.push_str(steps.split("\n\nVibe-Tx:").next().unwrap_or(steps));
+        out.push_str("\n\n");
+    }
+    let mut budget = max_diff_lines;
+    let diffs: Vec<(String, String)> = previews
+        .iter()
+        .filter_map(|p| Some((p.path.as_ref()?.display().to_string(), p.diff_snippet.as_ref()?)))
+        .map(|(path, d)| (path, crate::ux::theme::strip(d)))
+        .collect();
+    if !diffs.is_empty() {
+        out.push_str("### Preview diffs\n\n");
+        for (path, diff) in diffs {
+            if budget == 0 {
+                out.push_str("_(more diffs omitted)_\n\n");
+                break;
+            }
+            let lines: Vec<&str> = diff.lines().take(budget).collect();
+            budget -= lines.len();
+            out.push_str(&format!("<details><summary><code>{}</code></summary>\n\n```diff\n{}\n```\n</details>\n\n", path, lines.join("\n")));
+        }
+    }
+    out.push_str(&format!("Vibe-Tx: `{}`\n", txid));
+    out
+}
+
+/// Push the transaction's branch and open a pull (GitHub, `gh`) or merge
+/// (GitLab, `glab`) request from it. Returns the request's URL.
+pub fn open(root: &Path, cfg: &PrConfig, tx: &TxGit, title: &str, body: &str) -> Result<String> {
+    let Some(branch) = tx.branch.as_deref() else {
+        bail!("no transaction branch to open a request from (needs --git-branch)");
+    };
+    let Some(base) = cfg.base.as_deref().or(tx.base_branch.as_deref()) else {
+        bail!("the run started on a detached HEAD; set `base` in [pr]");
+    };
+    let host = host_of(root, cfg)?;
+    super::git(root, &["push", "--quiet", "-u", &cfg.remote, branch])?;
+
+    let (tool, mut args): (&str, Vec<String>) = match host {
+        PrHost::Github | PrHost::Auto => {
+            let args = ["pr", "create", "--head", branch, "--base", base, "--title", title, "--body", body];
+            ("gh", args.map(String::from).to_vec())
+        }
+        PrHost::Gitlab => {
+            let args = ["mr", "create", "--source-branch", branch, "--target-branch", base, "--title", title, "--description", body, "--yes"];
+            ("glab", args.map(String::from).to_vec())
+        }
+    };
+    if cfg.draft {
+        args.push("--draft".into());
+    }
+    match host {
+        PrHost::Gitlab if !cfg.labels.is_empty() => args.extend(["--label".into(), cfg.labels.join(",")]),
+        _ => args.extend(cfg.labels.iter().flat_map(|l| ["--label".to_string(), l.clone()])),
+    }
+    args.extend(cfg.reviewers.iter().flat_map(|r| ["--reviewer".to_string(), r.clone()]));
+
+    let out = Command::new(tool)
+        .args(&args)
+        .current_dir(root)
+        .output()
+        .with_context(|| format!("running {} (is it installed and logged in?)", tool))?;
+    if !out.status.success() {
+        bail!("{} {} failed: {}", tool, args[..2].join(" "), String::from_utf8_lossy(&out.stderr).trim());
+    }
+    // both print the new request's URL last
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    Ok(stdout.lines().rev().find(|l| l.trim().starts_with("http")).unwrap_or(stdout.trim()).trim().to_string())
+}
@@ -0,0 +1,96 @@
+use chrono::Datelike;
+use fs_err as fs;
+use regex::Regex;
+use std::path::Path;
+
+use crate::annotate;
+use crate::wire::{Plan, Step};
+
+/// How far into a file a header is looked for.
+const SCAN_LINES: usize = 40;
+
+/// `license_header` rendered for `path`: `{year}` filled in, one comment line
+/// per template line. `None` for files without comment syntax (JSON).
+pub fn render(path: &str, template: &str) -> Option<String> {
+    let year = chrono::Local::now().year().to_string();
+    let lines: Option<Vec<String>> = template
+        .trim_end()
+        .lines()
+        .map(|l| annotate::comment(path, l.replace("{year}", &year).trim_end()))
+        .collect();
+    Some(lines?.join("\n"))
+}
+
+/// Matches the first non-blank template line; `{year}` accepts any year or
+/// `2019-2024` style range, so headers from earlier years still count.
+fn first_line_re(template: &str) -> Option<Regex> {
+    let first = template.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let pat = regex::escape(first).replace(r"\{year\}", r"\d{4}(?:\s*-\s*\d{4})?");
+    Regex::new(&pat).ok()
+}
+
+/// The header block (as many lines as the template) found near the top of
+/// `content`, verbatim.
+pub fn find(content: &str, template: &str) -> Option<String> {
+    let re = first_line_re(template)?;
+    let lines: Vec<&str> = content.lines().take(SCAN_LINES).collect();
+    let start = lines.iter().position(|l| re.is_match(l))?;
+    let n = template.trim_end().lines().count().max(1);
+    Some(lines[start..(start + n).min(lines.len())].join("\n"))
+}
+
+/// A `'use client'` / `'use server'` directive or `#!` line, which has to stay
+/// first in the file.
+fn must_lead(line: &str) -> bool {
+    let l = line.trim_start_matches('\u{feff}').trim().trim_end_matches(';');
+    l.starts_with("#!") || matches!(l, "'use client'" | "\"use client\"" | "'use server'" | "\"use server\"")
+}
+
+/// `content` with `header` placed at the top, after any leading directive.
+fn insert_block(content: &str, header: &str) -> String {
+    let mut lines = content.lines().peekable();
+    let mut out = String::new();
+    while let Some(l) = lines.next_if(|l| l.trim().is_empty() || must_lead(l)) {
+        if !l.trim().is_empty() {
+            out.push_str(l);
+            out.push_str("\n\n");
+        }
+    }
+    out.push_str(header);
+    out.push_str("\n\n");
+    let rest: Vec<&str> = lines.collect();
+    out.push_str(&rest.join("\n"));
+    if content.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// `content` of a created file with the license header prepended, unless it
+/// already carries one or the file type has no comments.
+pub fn insert(path: &str, content: &str, template: &str) -> String {
+    if find(content, template).is_some() {
+        return content.to_string();
+    }
+    match render(path, template) {
+        Some(header) => insert_block(content, &header),
+        None => content.to_string(),
+    }
+}
+
+/// Restore headers that full-content updates dropped: when the file on disk
+/// has a header and the new content doesn't, the old header is put back.
+/// Patch updates are left alone. Returns a warning per restored file.
+pub fn keep_headers(plan: &mut Plan, root: &Path, template: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for s in plan.steps.iter_mut() {
+        let Step::Update { path, content: Some(c), .. } = s else { continue };
+        let Ok(old) = fs::read_to_string(root.join(&*path)) else { continue };
+        let Some(header) = find(&old, template) else { continue };
+        if find(c, template).is_none() {
+            *c = insert_block(c, &header);
+            warnings.push(format!("update of {} dropped the license header; restored it", path));
+        }
+    }
+    warnings
+}
@@ -0,0 +1,216 @@
+use crate::aliases::PathAliases;
+use crate::config::{Config, SplitStrategy};
+use crate::merge::has_use_client_top;
+use crate::split;
+use crate::wire::{Plan, Step};
+
+/// A machine-checkable convention violation found in CODEGEN output.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub path: String,
+    pub rule: &'static str,
+    pub message: String,
+    /// True when the linter already rewrote the content to fix it.
+    pub fixed: bool,
+}
+
+/// One lint repair round-trip as recorded in the tx's `repair.json`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RepairAttempt {
+    pub stage: String,
+    pub sampling: crate::wire::Sampling,
+    /// Violations still unfixed after this attempt's reply was linted.
+    pub unfixed: usize,
+}
+
+const CLIENT_HOOKS: &[&str] = &[
+    "useState(", "useEffect(", "useLayoutEffect(", "useReducer(", "useRef(",
+    "useContext(", "useTheme(", "useRouter(", "usePathname(", "useSearchParams(",
+];
+
+fn is_layout(path: &str) -> bool {
+    let p = path.replace('\\', "/");
+    p.ends_with("app/layout.tsx") || p.ends_with("app/layout.jsx") || p.ends_with("app/layout.js")
+}
+
+fn is_source(path: &str) -> bool {
+    [".tsx", ".ts", ".jsx", ".js"].iter().any(|e| path.ends_with(e))
+}
+
+fn is_pages_router(path: &str) -> bool {
+    let p = path.replace('\\', "/");
+    p.starts_with("pages/") || p.starts_with("src/pages/")
+}
+
+fn is_default_lucide_import(line: &str) -> bool {
+    let l = line.trim();
+    if !l.starts_with("import ") || !l.contains("lucide-react") {
+        return false;
+    }
+    let spec = l.trim_start_matches("import ").trim_start();
+    // `import { X } from`, `import type { X } from` and `import * as X from` are fine
+    !(spec.starts_with('{') || spec.starts_with("type ") || spec.starts_with('*'))
+}
+
+fn is_global_css_import(line: &str) -> bool {
+    let l = line.trim();
+    // side-effect CSS imports only; CSS modules (`import s from "./x.module.css"`) are scoped
+    (l.starts_with("import \"") || l.starts_with("import '"))
+        && l.contains(".css")
+        && !l.contains(".module.css")
+}
+
+/// Lint a single file's content. Trivial violations are fixed in place.
+fn lint_content(path: &str, content: &mut String) -> Vec<Violation> {
+    let mut out = Vec::new();
+    if !is_source(path) {
+        return out;
+    }
+
+    for line in content.lines() {
+        if is_default_lucide_import(line) {
+            out.push(Violation {
+                path: path.to_string(),
+                rule: "lucide-default-import",
+                message: format!("default import from lucide-react: `{}`", line.trim()),
+                fixed: false,
+            });
+        }
+    }
+
+    if !is_layout(path) && content.lines().any(is_global_css_import) {
+        let kept: Vec<&str> = content.lines().filter(|l| !is_global_css_import(l)).collect();
+        *content = kept.join("\n");
+        out.push(Violation {
+            path: path.to_string(),
+            rule: "global-css-outside-layout",
+            message: "global CSS imported outside src/app/layout.tsx (import removed)".into(),
+            fixed: true,
+        });
+    }
+
+    let uses_hooks = CLIENT_HOOKS.iter().any(|h| content.contains(h));
+    if uses_hooks && !has_use_client_top(content) && !content.contains("'use server'") && !content.contains("\"use server\"") {
+        let mut s = String::from("'use client'\n\n");
+        s.push_str(content.trim_start_matches('\u{feff}'));
+        *content = s;
+        out.push(Violation {
+            path: path.to_string(),
+            rule: "client-hooks-without-use-client",
+            message: "client hooks used without 'use client' (directive added)".into(),
+            fixed: true,
+        });
+    }
+
+    out
+}
+
+fn is_page(path: &str) -> bool {
+    ["page.tsx", "page.jsx", "page.js"].iter().any(|f| path.replace('\\', "/").ends_with(&format!("/{}", f)))
+}
+
+/// Enforce `max_file_lines` on pages: with `SplitStrategy::Extract`, self-contained
+/// components move to colocated `_components/` files as extra create steps;
+/// anything still over the limit is left unfixed for the model to split.
+fn split_oversized_pages(plan: &mut Plan, cfg: &Config, out: &mut Vec<Violation>) {
+    let Some(max) = cfg.max_file_lines else { return };
+    let mut inserts: Vec<(usize, Vec<Step>)> = Vec::new();
+    for (i, s) in plan.steps.iter_mut().enumerate() {
+        let (id, group, path, content) = match s {
+            Step::Create { id, group, path, content: Some(c), .. } | Step::Update { id, group, path, content: Some(c), .. } => {
+                (id.clone(), group.clone(), path.clone(), c)
+            }
+            _ => continue,
+        };
+        let before = content.lines().count();
+        if !is_page(&path) || before <= max {
+            continue;
+        }
+        let mut created = Vec::new();
+        if cfg.split_strategy == SplitStrategy::Extract {
+            if let Some((page, parts)) = split::split_page(&path, content) {
+                *content = page;
+                created = parts
+                    .into_iter()
+                    .map(|e| Step::Create {
+                        id: format!("{}-{}", id, e.name.to_lowercase()),
+                        title: format!("Extract {} from {}", e.name, path),
+                        group: group.clone(),
+                        path: e.path,
+                        language: Some("tsx".into()),
+                        content: Some(e.content),
+                    })
+                    .collect();
+            }
+        }
+        let after = content.lines().count();
+        out.push(Violation {
+            path: path.clone(),
+            rule: "max-file-lines",
+            message: if after <= max {
+                format!("page had {} lines (max {}); split into {} colocated component(s)", before, max, created.len())
+            } else {
+                format!(
+                    "page has {} lines (max {}); split its sections into colocated components under `_components/` as additional create steps",
+                    after, max
+                )
+            },
+            fixed: after <= max,
+        });
+        if !created.is_empty() {
+            inserts.push((i + 1, created));
+        }
+    }
+    for (at, steps) in inserts.into_iter().rev() {
+        plan.steps.splice(at..at, steps);
+    }
+}
+
+/// Fail when a page is still over `max_file_lines` once the repairs are
+/// done: unlike the other rules, the limit is not left to the reviewer.
+pub fn enforce_max_file_lines(violations: &[Violation]) -> anyhow::Result<()> {
+    let over: Vec<&str> = violations.iter().filter(|v| v.rule == "max-file-lines" && !v.fixed).map(|v| v.path.as_str()).collect();
+    if !over.is_empty() {
+        anyhow::bail!("{} page(s) still over max_file_lines after repair: {}", over.len(), over.join(", "));
+    }
+    Ok(())
+}
+
+/// Lint CODEGEN output against the conventions in the prompts.
+/// Trivial violations are auto-fixed in `plan`; the rest are returned unfixed
+/// so the caller can round-trip a targeted repair request.
+pub fn lint_plan(plan: &mut Plan, cfg: &Config) -> Vec<Violation> {
+    let mut out = Vec::new();
+    split_oversized_pages(plan, cfg, &mut out);
+    let aliases = if cfg.normalize_import_aliases { PathAliases::load(std::path::Path::new(&cfg.root)) } else { None };
+    for s in plan.steps.iter_mut() {
+        match s {
+            Step::Create { path, content, .. } | Step::Update { path, content, .. } => {
+                if is_pages_router(path) {
+                    out.push(Violation {
+                        path: path.clone(),
+                        rule: "pages-router-path",
+                        message: "file targets the legacy Pages Router; use src/app/<route>/page.tsx".into(),
+                        fixed: false,
+                    });
+                }
+                if let Some(c) = content.as_mut() {
+                    out.extend(lint_content(path, c));
+                    if let Some(a) = aliases.as_ref().filter(|_| is_source(path)) {
+                        if let Some((fixed, changed)) = a.normalize(path, c) {
+                            *c = fixed;
+                            out.push(Violation {
+                                path: path.clone(),
+                                rule: "import-alias-style",
+                                message: format!("imports rewritten to the project's alias style: {}", changed.join(", ")),
+                                fixed: true,
+                            });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
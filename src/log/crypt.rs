@@ -0,0 +1,116 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use fs_err as fs;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Credential holding the 32-byte artifact key (base64 or hex), looked up
+/// like an API key: environment, credentials.toml, then the OS keychain.
+pub const KEY_VAR: &str = "VIBE_ARTIFACT_KEY";
+/// Appended to the name of every encrypted file (`plan.request.json.zst.enc`).
+pub const SUFFIX: &str = "enc";
+const MAGIC: &[u8] = b"VIBEENC1";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+static KEY: OnceLock<Result<[u8; 32], String>> = OnceLock::new();
+
+/// Turn encryption of newly written artifacts on (config `encrypt_artifacts`).
+/// Reading encrypted artifacts works either way.
+pub fn init(on: bool) -> Result<()> {
+    let _ = ENABLED.set(on);
+    if on {
+        key()?;
+    }
+    Ok(())
+}
+
+fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+fn parse_key(s: &str) -> Result<[u8; 32], String> {
+    let s = s.trim();
+    let bytes = if s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..32).map(|i| u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).unwrap_or(0)).collect()
+    } else {
+        base64::engine::general_purpose::STANDARD.decode(s).map_err(|e| format!("{} is neither hex nor base64: {}", KEY_VAR, e))?
+    };
+    bytes.try_into().map_err(|b: Vec<u8>| format!("{} must be 32 bytes, got {}", KEY_VAR, b.len()))
+}
+
+fn key() -> Result<LessSafeKey> {
+    let raw = KEY.get_or_init(|| match crate::credentials::api_key(KEY_VAR) {
+        Some(s) => parse_key(&s),
+        None => Err(format!(
+            "{} is not set (environment, credentials.toml or keychain); create one with `vibe auth artifact-key`",
+            KEY_VAR
+        )),
+    });
+    let raw = raw.as_ref().map_err(|e| anyhow!("artifact encryption: {}", e))?;
+    Ok(LessSafeKey::new(UnboundKey::new(&AES_256_GCM, raw).map_err(|_| anyhow!("invalid artifact key"))?))
+}
+
+/// A fresh random key, base64.
+pub fn generate_key() -> Result<String> {
+    let mut raw = [0u8; 32];
+    SystemRandom::new().fill(&mut raw).map_err(|_| anyhow!("no randomness for the key"))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(raw))
+}
+
+/// AES-256-GCM: magic, random nonce, then ciphertext and tag.
+fn seal(plain: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| anyhow!("no randomness for the artifact nonce"))?;
+    let mut buf = plain.to_vec();
+    key()?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buf)
+        .map_err(|_| anyhow!("encrypting artifact"))?;
+    Ok([MAGIC, &nonce[..], &buf].concat())
+}
+
+fn open(path: &Path, data: &[u8]) -> Result<Vec<u8>> {
+    let Some(rest) = data.strip_prefix(MAGIC).filter(|r| r.len() >= NONCE_LEN) else {
+        bail!("{} is not an encrypted artifact", path.display());
+    };
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("bad nonce in {}", path.display()))?;
+    let mut buf = sealed.to_vec();
+    let plain = key()?
+        .open_in_place(nonce, Aad::empty(), &mut buf)
+        .map_err(|_| anyhow!("decrypting {} failed (wrong {}?)", path.display(), KEY_VAR))?;
+    Ok(plain.to_vec())
+}
+
+fn sealed_path(path: &Path) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!(".{}", SUFFIX));
+    PathBuf::from(s)
+}
+
+/// Write `bytes` to `path`, or encrypted to `<path>.enc` when encryption is
+/// on. Returns the path written.
+pub fn write(path: &Path, bytes: &[u8]) -> Result<PathBuf> {
+    if enabled() {
+        let p = sealed_path(path);
+        fs::write(&p, seal(bytes)?)?;
+        Ok(p)
+    } else {
+        fs::write(path, bytes)?;
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Content of `path` or, decrypted, of `<path>.enc`; `None` if neither exists.
+pub fn read(path: &Path) -> Result<Option<Vec<u8>>> {
+    if path.exists() {
+        return Ok(Some(fs::read(path)?));
+    }
+    let p = sealed_path(path);
+    if p.exists() {
+        let data = fs::read(&p)?;
+        return open(&p, &data).map(Some).with_context(|| format!("reading {}", path.display()));
+    }
+    Ok(None)
+}
@@ -6,14 +6,111 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+pub mod crypt;
+
 pub struct SavedPaths {
     pub dir: PathBuf,
     pub request: Option<PathBuf>,
     pub response: Option<PathBuf>,
 }
 
-fn tx_dir(root: &Path, tx: Uuid) -> PathBuf {
-    root.join(".vibe").join("tx").join(tx.to_string())
+/// `.vibe/tx` under the project root; one subdirectory per transaction.
+pub fn tx_root(root: &Path) -> PathBuf {
+    root.join(".vibe").join("tx")
+}
+
+pub fn tx_dir(root: &Path, tx: Uuid) -> PathBuf {
+    tx_root(root).join(tx.to_string())
+}
+
+/// Delete transaction directories (artifacts and backups) older than
+/// `older_than`, always keeping the `keep_last` newest. Returns the removed
+/// ids, oldest first.
+pub fn prune_tx(root: &Path, keep_last: usize, older_than: std::time::Duration) -> anyhow::Result<Vec<String>> {
+    let dir = tx_root(root);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut txs: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    txs.sort_by_key(|t| std::cmp::Reverse(t.0));
+    let now = std::time::SystemTime::now();
+    let mut removed = Vec::new();
+    for (modified, path) in txs.into_iter().skip(keep_last).rev() {
+        if now.duration_since(modified).map(|age| age < older_than).unwrap_or(true) {
+            continue;
+        }
+        fs::remove_dir_all(&path)?;
+        removed.push(path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default());
+    }
+    Ok(removed)
+}
+
+#[cfg(feature = "compression")]
+fn zstd_encode(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(zstd::encode_all(bytes, 3)?)
+}
+
+#[cfg(feature = "compression")]
+fn zstd_decode(_path: &Path, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(zstd::decode_all(bytes)?)
+}
+
+// Builds without `compression` write plain JSON (see `write_json`) and can't
+// read artifacts compressed by a full build.
+#[cfg(not(feature = "compression"))]
+fn zstd_encode(_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("built without the `compression` feature")
+}
+
+#[cfg(not(feature = "compression"))]
+fn zstd_decode(path: &Path, _bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("{} is zstd-compressed; this build lacks the `compression` feature", path.display())
+}
+
+/// Write a JSON artifact as `<name>` or, when `compress`, `<name>.zst`
+/// (either with `.enc` appended under `encrypt_artifacts`).
+fn write_json<T: serde::Serialize + ?Sized>(dir: &Path, name: &str, value: &T, compress: bool) -> anyhow::Result<PathBuf> {
+    let json = to_string_pretty(value)?;
+    if compress && cfg!(feature = "compression") {
+        crypt::write(&dir.join(format!("{name}.zst")), &zstd_encode(json.as_bytes())?)
+    } else {
+        crypt::write(&dir.join(name), json.as_bytes())
+    }
+}
+
+/// Read artifact `name` (e.g. `plan.request.json`) from a tx directory,
+/// transparently decrypting `.enc` and decompressing `<name>.zst`. `None` if
+/// no form of it exists.
+pub fn read_artifact(dir: &Path, name: &str) -> anyhow::Result<Option<String>> {
+    if let Some(bytes) = crypt::read(&dir.join(name))? {
+        return Ok(Some(String::from_utf8(bytes)?));
+    }
+    let zst = dir.join(format!("{name}.zst"));
+    if let Some(bytes) = crypt::read(&zst)? {
+        return Ok(Some(String::from_utf8(zstd_decode(&zst, &bytes)?)?));
+    }
+    Ok(None)
+}
+
+/// JSON artifacts in a tx directory as (logical name, stored path), sorted.
+pub fn list_artifacts(dir: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let p = entry?.path();
+        let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let name = name.strip_suffix(".enc").map(str::to_string).unwrap_or(name);
+        if let Some(logical) = name.strip_suffix(".zst").filter(|n| n.ends_with(".json")) {
+            out.push((logical.to_string(), p));
+        } else if name.ends_with(".json") {
+            out.push((name, p));
+        }
+    }
+    out.sort();
+    Ok(out)
 }
 
 pub fn save_stage(
@@ -32,20 +129,52 @@ pub fn save_stage(
     let mut response_path = None;
 
     if save_request {
-        let p = dir.join(format!("{stage}.request.json"));
-        fs::write(&p, to_string_pretty(req)?)?;
-        request_path = Some(p);
+        request_path = Some(write_json(&dir, &format!("{stage}.request.json"), req, cfg.compress_artifacts)?);
     }
 
     if save_response {
-        let p = dir.join(format!("{stage}.response.json"));
-        fs::write(&p, to_string_pretty(resp)?)?;
-        response_path = Some(p);
+        response_path = Some(write_json(&dir, &format!("{stage}.response.json"), resp, cfg.compress_artifacts)?);
     }
 
+    crate::ux::print_phase_usage(stage, &resp.usage);
+    record_usage(&dir, stage, &resp.usage, cfg.compress_artifacts)?;
+
     Ok(SavedPaths { dir, request: request_path, response: response_path })
 }
 
+/// One `metrics.json` entry: a phase and what its request cost.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PhaseUsage {
+    stage: String,
+    #[serde(flatten)]
+    usage: crate::wire::Usage,
+}
+
+/// Append `stage`'s usage to the tx's `metrics.json`.
+fn record_usage(dir: &Path, stage: &str, usage: &crate::wire::Usage, compress: bool) -> anyhow::Result<()> {
+    let mut all: Vec<PhaseUsage> = match read_artifact(dir, "metrics.json")? {
+        Some(text) => serde_json::from_str(&text).unwrap_or_default(),
+        None => Vec::new(),
+    };
+    all.push(PhaseUsage { stage: stage.to_string(), usage: usage.clone() });
+    write_json(dir, "metrics.json", &all, compress)?;
+    Ok(())
+}
+
+/// Save an extra JSON artifact (e.g. `verify.json`) into the tx directory.
+pub fn save_artifact<T: serde::Serialize>(name: &str, value: &T, tx: Uuid, cfg: &Config) -> anyhow::Result<PathBuf> {
+    let dir = tx_dir(Path::new(&cfg.root), tx);
+    fs::create_dir_all(&dir)?;
+    write_json(&dir, name, value, cfg.compress_artifacts)
+}
+
+/// Save a plain-text artifact (e.g. `plan.mmd`) into the tx directory, never compressed.
+pub fn save_text(name: &str, text: &str, tx: Uuid, cfg: &Config) -> anyhow::Result<PathBuf> {
+    let dir = tx_dir(Path::new(&cfg.root), tx);
+    fs::create_dir_all(&dir)?;
+    crypt::write(&dir.join(name), text.as_bytes())
+}
+
 pub fn print_planned_paths(root: &Path, tx: Uuid) {
     let dir = tx_dir(root, tx);
     println!("debug: planned artifacts directory: {}", dir.display());
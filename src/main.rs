@@ -20,6 +20,35 @@ mod errors;
 mod prompt;
 mod ux;
 mod merge;
+mod trust;
+mod lint;
+mod prefs;
+mod verify;
+mod session;
+mod commands;
+mod stages;
+mod metrics;
+mod smoke;
+mod backup;
+mod split;
+mod templates;
+mod annotate;
+mod credentials;
+mod doctor;
+#[cfg(feature = "watch")]
+mod watch;
+mod owners;
+mod license;
+mod compare;
+mod followups;
+mod aliases;
+mod deps;
+mod undo;
+mod gc;
+mod clipboard;
+mod schedule;
+mod commitmsg;
+mod txdiff;
 
 fn is_code_action(task: &str) -> bool {
     let t = task.to_lowercase();
@@ -35,39 +64,265 @@ fn is_code_action(task: &str) -> bool {
     file_hints.iter().any(|h| t.contains(h))
 }
 
+/// `--copy`: a failure is only a warning, the run itself succeeded.
+fn copy_to_clipboard(what: &str, text: &str) {
+    match clipboard::copy(text) {
+        Ok(()) => println!("Copied {} to the clipboard.", what),
+        Err(e) => eprintln!("warning: could not copy {}: {:#}", what, e),
+    }
+}
+
+/// Keep a record in the tx of prompts `--confirm-timeout` answered.
+fn save_timed_out_prompts(txid: Uuid, cfg: &config::Config) -> anyhow::Result<()> {
+    let decisions = ux::input::timed_out();
+    if !decisions.is_empty() {
+        log::save_artifact("approval.json", &decisions, txid, cfg)?;
+    }
+    Ok(())
+}
+
+/// Stale embeddings quietly skew file selection: warn, or re-index when a
+/// `reindex_command` is configured and the workspace is trusted.
+fn check_index_freshness(root: &Path, vibe_out: &Path, cfg: &config::Config) {
+    let Ok(index) = context::embeddings::EmbeddingIndex::load(vibe_out) else { return };
+    let fresh = index.freshness(root);
+    if fresh.chunks == 0 || fresh.stale_ratio() < cfg.index_stale_ratio {
+        return;
+    }
+    let pct = (fresh.stale_ratio() * 100.0).round();
+    match &cfg.reindex_command {
+        Some(cmd) if cfg.trusted => {
+            println!("Embedding index is {}% stale ({} file(s)); running `{}`.", pct, fresh.stale_files.len(), cmd);
+            if let Err(e) = exec::run_command_allowlisted(cmd, cfg, Some(&cfg.root), cfg.timeout_secs) {
+                eprintln!("Re-index failed: {:#}", e);
+            }
+        }
+        _ => eprintln!(
+            "warning: embedding index is {}% stale ({} file(s) changed since indexing, e.g. {}); re-run the indexer for better context selection.",
+            pct,
+            fresh.stale_files.len(),
+            fresh.stale_files.iter().take(3).cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args = cli::Args::parse();
+    let mut args = cli::Args::parse();
 
-    let mut cfg = config::Config::default();
+    let mut cfg = match &args.config {
+        Some(p) => config::Config::load(Path::new(p))?,
+        None => config::Config::default(),
+    };
     cfg.root = args.root.clone();
+    cfg.merge_user_profiles()?;
+    credentials::init(&cfg.credential_names)?;
+    log::crypt::init(cfg.encrypt_artifacts)?;
+    ux::theme::init(cfg.theme.as_deref(), &cfg.theme_palette, cfg.theme_symbols);
+    if let Some(u) = &args.ollama_url {
+        cfg.ollama_url = Some(u.clone());
+    }
+    if let Some(p) = args.run_profile {
+        cfg.run_profile = p;
+    }
+    if let Some(f) = args.wire_format {
+        cfg.wire_format = f;
+    }
+    if let Some(tx) = &args.replay_tx {
+        cfg.replay_tx = Some(tx.clone());
+    }
+    if let Some(n) = args.plan_samples {
+        cfg.plan_samples = n;
+    }
+    if let Some(m) = &args.plan_model {
+        cfg.plan_model = Some(m.clone());
+    }
+    if let Some(m) = &args.codegen_model {
+        cfg.codegen_model = Some(m.clone());
+    }
+    if let Some(t) = args.temperature {
+        cfg.temperature = Some(t);
+    }
+    if let Some(p) = args.top_p {
+        cfg.top_p = Some(p);
+    }
+    if let Some(n) = args.max_output_tokens {
+        cfg.max_output_tokens = Some(n);
+    }
+
+    if let Some(t) = args.confirm_timeout {
+        cfg.confirm_timeout_secs = Some(t.as_secs());
+    }
+    if let Some(d) = args.dirty_worktree {
+        cfg.dirty_worktree = d;
+    }
+    if args.git_branch {
+        cfg.git_branch = true;
+    }
+    if args.git_commit {
+        cfg.git_commit = true;
+    }
+    if args.git_context {
+        cfg.git_context = true;
+    }
+    if args.blame_context {
+        cfg.blame_context = true;
+    }
+    if args.check_upstream {
+        cfg.upstream_check = true;
+    }
+    if args.open_pr {
+        cfg.pr.open = true;
+    }
+    if args.commit_per_step {
+        cfg.git_commit_per_step = true;
+    }
+    if cfg.pr.open {
+        cfg.git_branch = true;
+        cfg.git_commit = true;
+    }
+    if cfg.git_commit_per_step {
+        cfg.git_commit = true;
+    }
+    if let Some(s) = args.structured_output {
+        cfg.structured_output = Some(s);
+    }
+    if let Some(r) = &args.race_plan {
+        cfg.plan_race = r.clone();
+    }
+    if let Some(d) = args.confirm_default {
+        cfg.confirm_default = d;
+    }
+    ux::input::init(cfg.confirm_timeout_secs.map(std::time::Duration::from_secs), cfg.confirm_default);
+
+    // before any scanning/apply: refuse `/`, $HOME and other non-project dirs
+    safety::ensure_project_root(Path::new(&cfg.root), args.force_root)?;
+
+    // leftovers of crashed runs: temp files and a lock nobody holds
+    if cfg.gc_on_startup && !args.no_gc {
+        match gc::collect(Path::new(&cfg.root), std::time::Duration::from_secs(cfg.gc_min_age_secs)) {
+            Ok(report) if !report.is_empty() => ux::print_gc_report(&report),
+            Ok(_) => {}
+            Err(e) => eprintln!("warning: startup cleanup failed: {:#}", e),
+        }
+    }
+
+    if let Some(cmd) = &args.command {
+        return commands::run(cmd, &args, &cfg).await;
+    }
+
+    if args.follow_up {
+        match followups::next(Path::new(&cfg.root))? {
+            Some(task) => {
+                println!("Follow-up: {}", task);
+                args.task = Some(task);
+            }
+            None => anyhow::bail!("no follow-ups queued in {}", followups::QUEUE_FILE),
+        }
+    }
 
     let txid = Uuid::new_v4();
+    let _lock = gc::RunLock::acquire(Path::new(&cfg.root), txid)?;
     if args.debug {
         println!("debug: flag enabled");
         log::print_planned_paths(Path::new(&cfg.root), txid);
     }
 
+    // First run in a project asks for trust; untrusted runs can't exec or delete.
+    cfg.trusted = trust::ensure_trusted(Path::new(&cfg.root))?;
+
     let root = Path::new(&cfg.root);
     let vibe_out = Path::new(&args.vibe_out);
 
+    // a provider profile overrides --provider/--model and supplies key/endpoint
+    let profile = match &args.profile_provider {
+        Some(name) => Some(cfg.provider_profile(name)?.clone()),
+        None => None,
+    };
+    let kind = profile.as_ref().map(|p| p.provider.clone()).unwrap_or_else(|| args.provider.clone());
+    let endpoint = provider::Endpoint {
+        api_key: profile.as_ref().and_then(|p| p.resolve_api_key()),
+        api_base: args.api_base.clone().or_else(|| profile.as_ref().and_then(|p| p.api_base.clone())),
+    };
+    let model = match profile.as_ref().and_then(|p| p.model.clone()).or_else(|| args.model.clone()) {
+        Some(m) => m,
+        // no --model for LM Studio: ask the server what it has
+        None if matches!(kind, cli::ProviderKind::LmStudio) => {
+            let base = provider::lmstudio::api_base(endpoint.api_base.clone());
+            provider::lmstudio::pick_model(&base, provider::Timeouts::new(args.timeout_secs, &cfg)).await?
+        }
+        None => cli::DEFAULT_MODEL.to_string(),
+    };
+    // PLAN (and stages) and CODEGEN may run on different models of the same provider
+    let make = |phase_model: &Option<String>| {
+        provider::make_provider(
+            kind.clone(),
+            phase_model.clone().unwrap_or_else(|| model.clone()),
+            args.timeout_secs,
+            args.openai_messages,
+            endpoint.clone(),
+            &cfg,
+        )
+    };
+    let plan_prov = if cfg.plan_race.is_empty() {
+        make(&cfg.plan_model)?
+    } else {
+        provider::make_race((&kind, &endpoint), args.timeout_secs, args.openai_messages, &cfg)?
+    };
+    let codegen_prov = make(&cfg.codegen_model)?;
+
+    if args.mode == cli::RunMode::Commitmsg {
+        let message = commitmsg::run(&codegen_prov, root, txid, &args, &cfg).await?;
+        println!("\n=== COMMIT MESSAGE ===\n{}\n", message);
+        if args.copy {
+            copy_to_clipboard("the commit message", &message);
+        }
+        return Ok(());
+    }
+
+    check_index_freshness(root, vibe_out, &cfg);
+
+    // how much to retrieve depends on how much of the app the task touches
+    let breadth = cfg.adaptive_top_k.then(|| context::breadth::estimate(args.task.as_deref().unwrap_or(""), root, &cfg));
+    let plan_file_bytes = breadth.as_ref().map(|b| b.plan_file_bytes).unwrap_or(8_192);
+
     // embeddings-aware selection + baseline (always includes package.json)
-    let ctx_files = context::select_relevant_files(
+    let (mut ctx_files, retrieval) = context::select_relevant_files(
         args.task.as_deref().unwrap_or(""),
         root,
         vibe_out,
-        12,
+        breadth.as_ref().map(|b| b.top_k).unwrap_or(12),
     );
+    if let Some(b) = &breadth {
+        for p in &b.mentioned {
+            if !ctx_files.contains(p) {
+                ctx_files.push(p.clone());
+            }
+        }
+    }
 
-    let prov = provider::make_provider(
-        args.provider.clone(),
-        args.model.clone(),
-        args.timeout_secs,
-        cfg.ollama_url.clone(),
-    )?;
+    ux::print_retrieval_mode(retrieval, vibe_out, cfg.reindex_command.as_deref());
+    if let Some(b) = &breadth {
+        ux::print_task_breadth(b);
+    }
+    let git_ctx = if cfg.git_context { context::history::collect(root, &ctx_files, cfg.git_context_max_diff_bytes) } else { None };
+    if let Some(g) = &git_ctx {
+        ux::print_git_context(g);
+    }
+    // a fix in files the task names: what recent commits changed there, and why
+    let recent_changes = if cfg.blame_context {
+        let task = args.task.as_deref().unwrap_or("");
+        let named = breadth.as_ref().map(|b| b.mentioned.clone()).unwrap_or_else(|| context::breadth::mentioned_files(task, root));
+        context::history::blame(root, task, &named)
+    } else {
+        vec![]
+    };
+    if !recent_changes.is_empty() {
+        ux::print_blame_context(&recent_changes);
+    }
 
     // ===== PHASE 1: PLAN =====
-    let plan_files_snapshot = context::snapshot_files(&ctx_files, root, 8_192);
+    let plan_files_snapshot = context::snapshot_files(&ctx_files, root, plan_file_bytes, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist);
     let mut plan_req = wire::LlmRequest {
         schema_version: "v1".into(),
         mode: wire::Mode::Plan,
@@ -79,12 +334,13 @@ async fn main() -> anyhow::Result<()> {
         },
         task: args.task.clone().unwrap_or_default(),
         context: wire::ContextSlice {
-            summary: json!({ "router":"App", "typescript": true, "note": "PLAN phase request" }),
+            summary: json!({ "router":"App", "typescript": true, "note": "PLAN phase request", "retrieval": retrieval.as_str() }),
             files_index: vec![],
             routes: vec![],
             symbols: json!({}),
             diagnostics: vec![],
             files_snapshot: plan_files_snapshot,
+            git: git_ctx.clone(),
         },
         capabilities: vec!["fs.apply_patch".into(),"tests.run".into(),"cmd.run".into()],
         safety: wire::Safety { path_allowlist: cfg.path_allowlist.clone(), command_allowlist: cfg.command_allowlist.clone() },
@@ -93,38 +349,118 @@ async fn main() -> anyhow::Result<()> {
             user: prompt::user_prompt_plan(args.task.as_deref().unwrap_or(""), &ctx_files),
             developer: Some("Output exactly one JSON object; PLAN must not include file contents. If libraries are added/removed, include UPDATE package.json (content:null) and a COMMAND step to run installer.".to_string()),
         },
+        wire_format: wire::WireFormat::Json,
+        sampling: cfg.sampling(),
+        follow_up: None,
     };
+    if !recent_changes.is_empty() {
+        plan_req.context.summary["recent_changes"] = json!(recent_changes);
+    }
 
-    let mut plan_resp = prov.send(&plan_req, args.debug).await?;
+    // Learned per-project preferences (habitually skipped steps + hand-written notes)
+    let mut prefs = prefs::Preferences::load(root).unwrap_or_default();
+    if cfg.learned_preferences {
+        if let Some(block) = prefs.prompt_block() {
+            plan_req.instruction.user.push_str("\n\n");
+            plan_req.instruction.user.push_str(&block);
+        }
+    }
+
+    let mut plan_resp = plan_prov.send(&plan_req, args.debug).await?;
     let saved_plan = log::save_stage("plan", &plan_req, &plan_resp, txid, &cfg, args.save_request, args.save_response)?;
     if args.debug {
         log::print_saved_paths("plan", &saved_plan);
         log::print_json_debug("plan", &plan_req, &plan_resp)?;
     }
 
+    // The model named files it wasn't shown: add them and retry PLAN once
+    let missing = context::widen::missing_context_paths(&context::widen::response_text(&plan_resp), root, &ctx_files);
+    if cfg.auto_widen_context && !missing.is_empty() {
+        println!("Model reported missing context; adding {} and retrying PLAN.", missing.join(", "));
+        context::widen::widen_request(&mut plan_req, root, &missing, plan_file_bytes, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist);
+        ctx_files.extend(missing);
+        plan_resp = plan_prov.send(&plan_req, args.debug).await?;
+        let saved_widen = log::save_stage("plan.widen", &plan_req, &plan_resp, txid, &cfg, args.save_request, args.save_response)?;
+        if args.debug {
+            log::print_saved_paths("plan.widen", &saved_widen);
+            log::print_json_debug("plan.widen", &plan_req, &plan_resp)?;
+        }
+    }
+
+    // Self-consistency: sample more plans at a higher temperature, keep the best
+    if cfg.plan_samples > 1 {
+        let mut candidates = vec![plan_resp.clone()];
+        let mut sample_req = plan_req.clone();
+        sample_req.sampling.temperature =
+            Some(cfg.temperature.filter(|t| *t > 0.0).unwrap_or(plan::samples::SAMPLE_TEMPERATURE));
+        for i in 2..=cfg.plan_samples {
+            let label = format!("plan.sample.{}", i);
+            let resp = match plan_prov.send(&sample_req, args.debug).await {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("warning: {} failed: {:#}", label, e);
+                    continue;
+                }
+            };
+            let saved = log::save_stage(&label, &sample_req, &resp, txid, &cfg, args.save_request, args.save_response)?;
+            if args.debug {
+                log::print_saved_paths(&label, &saved);
+                log::print_json_debug(&label, &sample_req, &resp)?;
+            }
+            candidates.push(resp);
+        }
+        let (best, scores) = plan::samples::pick(&candidates, root, &cfg);
+        ux::print_plan_samples(&scores, best);
+        if let Some(b) = best {
+            plan_resp = candidates.swap_remove(b);
+        }
+    }
+
     let is_code = is_code_action(args.task.as_deref().unwrap_or(""));
     let answer_present = plan_resp.answer.is_some();
-    let need_strict = (matches!(plan_resp.kind, wire::Kind::Answer)
-        || plan_resp.plan.as_ref().map(|p| p.steps.is_empty()).unwrap_or(true)
-        || (answer_present && is_code));
+    // an explanation alongside real steps is a hybrid reply, not a failure
+    let need_strict = !plan_resp.is_hybrid()
+        && (matches!(plan_resp.kind, wire::Kind::Answer)
+            || plan_resp.plan.as_ref().map(|p| p.steps.is_empty()).unwrap_or(true)
+            || (answer_present && is_code));
 
     if need_strict {
+        let problem = if matches!(plan_resp.kind, wire::Kind::Answer) || answer_present {
+            "it was an answer, not a plan"
+        } else {
+            "the plan had no steps"
+        };
         let mut strict_req = plan_req.clone();
         strict_req.instruction.system = prompt::system_prompt_plan_strict();
+        // the strict system prompt repeats the policy blocks: don't pay for them twice
+        strict_req.instruction.user = prompt::user_prompt_plan_strict(&plan_req.instruction.user, problem);
         strict_req.instruction.developer = Some("STRICT MODE: This is a code-change task. Return kind:\"plan\" ONLY. Do not include code, content or patches in PLAN. Do not include an 'answer' field. If dependencies are implicated, include UPDATE package.json (content:null) and a COMMAND step to run installer.".to_string());
-        let strict_resp = prov.send(&strict_req, args.debug).await?;
+        // chat providers continue the PLAN conversation instead, so the snapshot
+        // prefix is a prompt-cache hit rather than a second full-price send
+        if cfg.strict_follow_up {
+            strict_req.follow_up = Some(wire::FollowUp {
+                previous: Box::new(plan_req.clone()),
+                reply: serde_json::to_string(&plan_resp)?,
+                text: prompt::follow_up_plan_strict(problem, strict_req.instruction.developer.as_deref().unwrap_or_default()),
+            });
+        }
+        let strict_resp = plan_prov.send(&strict_req, args.debug).await?;
         let saved_plan_strict = log::save_stage("plan.strict", &strict_req, &strict_resp, txid, &cfg, args.save_request, args.save_response)?;
         if args.debug {
             log::print_saved_paths("plan.strict", &saved_plan_strict);
             log::print_json_debug("plan.strict", &strict_req, &strict_resp)?;
         }
         plan_req = strict_req;
+        plan_req.follow_up = None;
         plan_resp = strict_resp;
     }
 
-    if matches!(plan_resp.kind, wire::Kind::Answer) {
+    if matches!(plan_resp.kind, wire::Kind::Answer) && !plan_resp.is_hybrid() {
         if let Some(ans) = plan_resp.answer {
             println!("\n=== ANSWER ===\n{}\n\n{}\n", ans.title, ans.content);
+            if args.copy {
+                copy_to_clipboard("the answer", &ans.content);
+            }
         } else {
             println!("\n=== ANSWER ===\n(model returned no answer payload)\n");
         }
@@ -140,23 +476,40 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // Show plan & ask for confirmation (user may edit once)
+    plan::infer_groups(&mut approved_plan);
+    let proposed_plan = approved_plan.clone();
+    if let Some(ans) = &plan_resp.answer {
+        ux::print_plan_note(ans);
+    }
     ux::show_plan(&approved_plan);
-    let mut proceed = ux::confirm("Apply this plan? (enter 'n' to edit)");
-    if !proceed {
-        approved_plan = ux::edit_plan(approved_plan);
+    let codeowners = owners::Codeowners::load(root);
+    if let Some(co) = &codeowners {
+        ux::print_foreign_owners(&co.foreign_steps(&approved_plan, &cfg.owners));
+    }
+    approved_plan = ux::review_groups(approved_plan);
+    let decision = ux::confirm_plan("Apply this plan? (enter 'n' to edit)", &approved_plan, root);
+    let mut proceed = decision.approved;
+    // a timed-out answer means nobody is there to edit the plan
+    if !proceed && !decision.by_timeout {
+        let (edited, user_tests) = ux::edit_plan(approved_plan, &cfg.step_templates, root);
+        approved_plan = edited;
+        cfg.user_test_commands = user_tests;
         ux::show_plan(&approved_plan);
         proceed = ux::confirm("Apply this edited plan?");
     }
     if !proceed {
         println!("Aborted by user.");
+        save_timed_out_prompts(txid, &cfg)?;
         return Ok(());
     }
+    prefs.record_review(&proposed_plan, &approved_plan);
+    let _ = prefs.save(root);
 
     // ===== PHASE 2: CODEGEN =====
-    let codegen_files_snapshot = context::snapshot_files(&ctx_files, root, 300_000);
+    let codegen_files_snapshot = context::snapshot_files(&ctx_files, root, 300_000, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist);
 
     // NEW: pass original task + prior PLAN prompts to CODEGEN user prompt (for rich continuity)
-    let codegen_user = prompt::user_prompt_codegen(
+    let mut codegen_user = prompt::user_prompt_codegen(
         args.task.as_deref().unwrap_or(""),
         &approved_plan,
         &ctx_files,
@@ -165,7 +518,28 @@ async fn main() -> anyhow::Result<()> {
         plan_req.instruction.developer.as_deref(),
     );
 
-    let codegen_req = wire::LlmRequest {
+    // Custom pipeline stages (config `stages`) between PLAN and CODEGEN
+    let mut stage_outputs: Vec<stages::StageOutput> = Vec::new();
+    for stage in &cfg.stages {
+        let stage_req = stages::build_request(stage, &plan_req, args.task.as_deref().unwrap_or(""), &approved_plan, &stage_outputs);
+        let stage_resp = plan_prov.send(&stage_req, args.debug).await?;
+        let label = format!("stage.{}", stage.name);
+        let saved_stage = log::save_stage(&label, &stage_req, &stage_resp, txid, &cfg, args.save_request, args.save_response)?;
+        if args.debug {
+            log::print_saved_paths(&label, &saved_stage);
+            log::print_json_debug(&label, &stage_req, &stage_resp)?;
+        }
+        stage_outputs.push(stages::StageOutput { name: stage.name.clone(), content: stages::output_text(&stage_resp) });
+    }
+    codegen_user.push_str(&stages::context_block(&stage_outputs));
+    if cfg.normalize_import_aliases {
+        if let Some(a) = aliases::PathAliases::load(root) {
+            codegen_user.push_str("\n\n");
+            codegen_user.push_str(&a.prompt_block());
+        }
+    }
+
+    let mut codegen_req = wire::LlmRequest {
         schema_version: "v1".into(),
         mode: wire::Mode::Codegen,
         transaction: wire::Tx { id: txid, timestamp: Utc::now(), dry_run: args.dry_run },
@@ -176,12 +550,13 @@ async fn main() -> anyhow::Result<()> {
         },
         task: args.task.clone().unwrap_or_default(),
         context: wire::ContextSlice {
-            summary: json!({ "router":"App", "typescript": true, "note": "CODEGEN phase request" }),
+            summary: json!({ "router":"App", "typescript": true, "note": "CODEGEN phase request", "retrieval": retrieval.as_str() }),
             files_index: vec![],
             routes: vec![],
             symbols: json!({}),
             diagnostics: vec![],
             files_snapshot: codegen_files_snapshot,
+            git: git_ctx.clone(),
         },
         capabilities: vec!["fs.apply_patch".into(),"tests.run".into(),"cmd.run".into()],
         safety: wire::Safety { path_allowlist: cfg.path_allowlist.clone(), command_allowlist: cfg.command_allowlist.clone() },
@@ -190,43 +565,409 @@ async fn main() -> anyhow::Result<()> {
             user: codegen_user,
             developer: Some("Return full file contents in 'content' for created/updated files; prefer 'content' over 'patch'. Never remove top-of-file directives like 'use client' unless explicitly asked. If libraries are added/removed, also UPDATE package.json (full JSON) and add a COMMAND step to run 'npm install'. Use context.files_snapshot as the source of truth for existing files.".to_string()),
         },
+        wire_format: wire::WireFormat::Json,
+        sampling: cfg.sampling(),
+        follow_up: None,
     };
+    if !recent_changes.is_empty() {
+        codegen_req.context.summary["recent_changes"] = json!(recent_changes);
+    }
 
-    let codegen_resp = prov.send(&codegen_req, args.debug).await?;
+    let mut codegen_resp = codegen_prov.send(&codegen_req, args.debug).await?;
     let saved_codegen = log::save_stage("codegen", &codegen_req, &codegen_resp, txid, &cfg, args.save_request, args.save_response)?;
     if args.debug {
         log::print_saved_paths("codegen", &saved_codegen);
         log::print_json_debug("codegen", &codegen_req, &codegen_resp)?;
     }
 
-    let raw_plan = match codegen_resp.plan {
+    let missing = context::widen::missing_context_paths(&context::widen::response_text(&codegen_resp), root, &ctx_files);
+    if cfg.auto_widen_context && !missing.is_empty() {
+        println!("Model reported missing context; adding {} and retrying CODEGEN.", missing.join(", "));
+        context::widen::widen_request(&mut codegen_req, root, &missing, 300_000, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist);
+        codegen_resp = codegen_prov.send(&codegen_req, args.debug).await?;
+        let saved_widen = log::save_stage("codegen.widen", &codegen_req, &codegen_resp, txid, &cfg, args.save_request, args.save_response)?;
+        if args.debug {
+            log::print_saved_paths("codegen.widen", &saved_widen);
+            log::print_json_debug("codegen.widen", &codegen_req, &codegen_resp)?;
+        }
+    }
+
+    let mut raw_plan = match codegen_resp.plan {
         Some(p) => p,
         None => { println!("\n(no code changes returned by model)\n"); return Ok(()); }
     };
 
-    let (plan_filtered, warnings) = plan::sanitize(raw_plan);
+    // Lint against prompt conventions: trivial fixes in place, up to
+    // `repair_attempts` round-trips for the rest, each less random than the last
+    let mut violations = lint::lint_plan(&mut raw_plan, &cfg);
+    let mut repairs: Vec<lint::RepairAttempt> = Vec::new();
+    for attempt in 0..cfg.repair_attempts {
+        if !violations.iter().any(|v| !v.fixed) {
+            break;
+        }
+        let label = if attempt == 0 { "codegen.repair".to_string() } else { format!("codegen.repair.{}", attempt + 1) };
+        let mut repair_req = codegen_req.clone();
+        repair_req.instruction.user = prompt::user_prompt_repair(&violations, &raw_plan);
+        repair_req.sampling = codegen_req.sampling.for_attempt(attempt, cfg.repair_attempts);
+        let repair_resp = codegen_prov.send(&repair_req, args.debug).await?;
+        let saved_repair = log::save_stage(&label, &repair_req, &repair_resp, txid, &cfg, args.save_request, args.save_response)?;
+        if args.debug {
+            log::print_saved_paths(&label, &saved_repair);
+            log::print_json_debug(&label, &repair_req, &repair_resp)?;
+        }
+        if let Some(p) = repair_resp.plan {
+            raw_plan = p;
+            violations = lint::lint_plan(&mut raw_plan, &cfg);
+        }
+        repairs.push(lint::RepairAttempt {
+            stage: label,
+            sampling: repair_req.sampling,
+            unfixed: violations.iter().filter(|v| !v.fixed).count(),
+        });
+    }
+    if !repairs.is_empty() {
+        log::save_artifact("repair.json", &repairs, txid, &cfg)?;
+    }
+
+    // Removing a dependency: nothing may still import it. Offer one pass to
+    // migrate the stragglers, otherwise hold the removal back.
+    let removed = deps::removed_packages(root, &raw_plan);
+    let mut stragglers = deps::stragglers(root, &raw_plan, &removed);
+    if !stragglers.is_empty() {
+        ux::print_dependency_stragglers(&stragglers);
+        if ux::confirm(&format!("Run a repair pass to migrate {} import(s) off the removed package(s)?", stragglers.len())) {
+            let mut migrate_req = codegen_req.clone();
+            let shown: Vec<String> = migrate_req.context.files_snapshot.iter().map(|f| f.path.clone()).collect();
+            let mut extra: Vec<String> = stragglers.iter().map(|s| s.path.clone()).filter(|p| !shown.contains(p)).collect();
+            extra.dedup();
+            migrate_req.context.files_snapshot.extend(context::snapshot_files(&extra, root, 300_000, cfg.max_snapshot_file_bytes, &cfg.symlink_allowlist));
+            migrate_req.instruction.user = prompt::user_prompt_dependency_migration(&stragglers, &raw_plan);
+            let migrate_resp = codegen_prov.send(&migrate_req, args.debug).await?;
+            let saved_migrate = log::save_stage("codegen.deps", &migrate_req, &migrate_resp, txid, &cfg, args.save_request, args.save_response)?;
+            if args.debug {
+                log::print_saved_paths("codegen.deps", &saved_migrate);
+                log::print_json_debug("codegen.deps", &migrate_req, &migrate_resp)?;
+            }
+            if let Some(p) = migrate_resp.plan {
+                raw_plan = p;
+                violations = lint::lint_plan(&mut raw_plan, &cfg);
+                stragglers = deps::stragglers(root, &raw_plan, &removed);
+            }
+        }
+        if !stragglers.is_empty() {
+            let mut blocked: Vec<String> = stragglers.iter().map(|s| s.package.clone()).collect();
+            blocked.sort();
+            blocked.dedup();
+            ux::print_held_back_removals(&blocked, &deps::hold_back(root, &mut raw_plan, &blocked));
+        }
+    }
+    ux::print_lint_report(&violations);
+    lint::enforce_max_file_lines(&violations)?;
+
+    let (mut plan_filtered, mut warnings) = plan::sanitize(raw_plan);
+    warnings.extend(plan::drop_oversized(&mut plan_filtered, cfg.max_generated_file_bytes));
+    if let Some(t) = &cfg.license_header {
+        warnings.extend(license::keep_headers(&mut plan_filtered, root, t));
+    }
     if !warnings.is_empty() {
         println!("\nSanitizer warnings:");
         for w in warnings { println!(" - {}", w); }
     }
 
-    safety::validate(&plan_filtered, &cfg)?;
-    let previews = patch::preview(root, &plan_filtered, args.task.as_deref().unwrap_or(""))?;
+    let review = safety::validate(&plan_filtered, &cfg)?;
+    let previews = patch::preview(root, &plan_filtered, args.task.as_deref().unwrap_or(""), &cfg)?;
     ux::print_preview_dashboard(&previews);
+    // what `vibe last` re-prints: the final plan and its preview
+    log::save_artifact("plan.json", &plan_filtered, txid, &cfg)?;
+    log::save_artifact("preview.json", &previews, txid, &cfg)?;
+    if let Some(format) = args.graph {
+        let graph = plan::graph::render(&plan_filtered, format);
+        let saved = log::save_text(format.file_name(), &graph, txid, &cfg)?;
+        println!("\nStep graph ({}):\n{}", saved.display(), graph);
+    }
 
-    if !ux::confirm("Proceed to apply these changes?") {
-        println!("Aborted by user.");
-        return Ok(());
+    // in CI, steps on other teams' paths need an explicit --override-owners
+    if let Some(co) = &codeowners {
+        let foreign = co.foreign_steps(&plan_filtered, &cfg.owners);
+        if !foreign.is_empty()
+            && !cfg.owners.is_empty()
+            && cfg.run_profile == exec::policy::RunProfile::Ci
+            && !args.override_owners
+        {
+            ux::print_foreign_owners(&foreign);
+            anyhow::bail!(
+                "{} step(s) touch paths owned by other teams; pass --override-owners to apply them in the ci profile",
+                foreign.len()
+            );
+        }
+    }
+
+    // preflight: missing tools fail here, not halfway through the steps
+    let required = exec::preflight::requirements(&plan_filtered, &approved_plan.requires, &cfg);
+    let problems = exec::preflight::check(&required);
+    if !problems.is_empty() {
+        ux::print_preflight_problems(&problems);
+        if !args.dry_run {
+            anyhow::bail!("preflight failed: {} requirement(s) not met", problems.len());
+        }
+    }
+
+    let targets: Vec<String> = plan_filtered
+        .steps
+        .iter()
+        .filter_map(|s| match s {
+            wire::Step::Create { path, .. } | wire::Step::Update { path, .. } | wire::Step::Delete { path, .. } => {
+                Some(safety::normalize_rel(path))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // files someone else changed upstream: the push will conflict
+    if cfg.upstream_check && git::is_repo(root) {
+        match git::upstream(root) {
+            Some(upstream) => {
+                if let Err(e) = git::fetch(root) {
+                    eprintln!("warning: fetch failed, checking against the last fetched {}: {:#}", upstream, e);
+                }
+                match git::diverged(root, &upstream, &targets) {
+                    Ok(d) if !d.is_empty() => ux::print_upstream_divergence(&upstream, &d),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("warning: upstream check failed: {:#}", e),
+                }
+            }
+            None => eprintln!("warning: upstream_check: the current branch has no upstream"),
+        }
+    }
+
+    // the policy file ships with the project, so an untrusted one can't skip the prompt
+    if review.auto_approved() && cfg.trusted {
+        println!("All steps are allowed by {}; applying without confirmation.", cfg.policy_file);
+    } else {
+        ux::print_policy_review(&review);
+        if !ux::confirm_apply("Proceed to apply these changes?", &previews, &git::commit_message(&plan_filtered, txid)) {
+            println!("Aborted by user.");
+            save_timed_out_prompts(txid, &cfg)?;
+            return Ok(());
+        }
+    }
+
+    // files edited while the model worked or the plan was reviewed
+    let drifted = apply::drift::changed_since(root, &plan_filtered.steps, &codegen_req.context.files_snapshot);
+    if !drifted.is_empty() && !args.dry_run {
+        match ux::confirm_drift(&drifted) {
+            apply::drift::Resolution::Remerge => {
+                let failed = apply::drift::remerge(root, &mut plan_filtered.steps, &drifted)?;
+                if !failed.is_empty() {
+                    for (path, why) in &failed {
+                        eprintln!("  - {}: {}", path, why);
+                    }
+                    anyhow::bail!("{} file(s) can't be re-merged; nothing was written. Re-run the task against the current files", failed.len());
+                }
+                println!("Merged your edits to {} file(s) into the generated changes.", drifted.len());
+            }
+            apply::drift::Resolution::Overwrite => {}
+            apply::drift::Resolution::Abort => {
+                println!("Aborted: files changed since the snapshot.");
+                save_timed_out_prompts(txid, &cfg)?;
+                return Ok(());
+            }
+        }
     }
 
-    let summary = apply::apply_steps(
+    let mut hook_results = Vec::new();
+    if let (Some(cmd), false) = (&cfg.pre_apply, args.dry_run) {
+        hook_results.extend(exec::hooks::run("pre_apply", cmd, &cfg)?);
+    }
+
+    // uncommitted edits to the files about to be written: refuse or stash them
+    let mut stashed: Option<Vec<String>> = None;
+    if cfg.dirty_worktree != git::DirtyWorktree::Allow && !args.dry_run && git::is_repo(root) {
+        // left by an earlier run and not edited since: its backup covers them
+        let mut dirty = git::dirty(root, &targets)?;
+        dirty.retain(|p| !backup::as_last_applied(root, p).unwrap_or(false));
+        if !dirty.is_empty() {
+            ux::print_dirty_targets(&dirty, cfg.dirty_worktree);
+            if cfg.dirty_worktree == git::DirtyWorktree::Refuse {
+                anyhow::bail!("{} file(s) the plan writes have uncommitted changes; commit or stash them, or pass --dirty-worktree stash|allow", dirty.len());
+            }
+            git::stash_paths(root, &dirty, &format!("vibe: before tx {}", txid))?;
+            stashed = Some(dirty);
+        }
+    }
+
+    let mut tx_git = None;
+    if (cfg.git_branch || cfg.git_commit) && !args.dry_run {
+        let mut g = git::TxGit::start(root)?;
+        if cfg.git_branch {
+            g.create_branch(root, txid)?;
+            println!(
+                "Applying on branch {} (from {}).",
+                g.branch.as_deref().unwrap_or_default(),
+                g.base_branch.as_deref().unwrap_or(&g.base_commit)
+            );
+        }
+        log::save_artifact("git.json", &g, txid, &cfg)?;
+        tx_git = Some(g);
+    }
+
+    let per_step = cfg.git_commit_per_step && tx_git.is_some();
+    let mut step_commits = Vec::new();
+    let applied = apply::apply_steps(
         root,
         &plan_filtered.steps,
         args.dry_run,
         &cfg,
         args.task.as_deref().unwrap_or(""),
-    )?;
+        txid,
+        |step, changes| {
+            if !per_step || changes.is_empty() {
+                return Ok(());
+            }
+            let mut paths: Vec<String> = changes.iter().map(|c| c.path.clone()).collect();
+            paths.dedup();
+            if let Some(sha) = git::commit_paths(root, &paths, &git::step_commit_message(step, txid))? {
+                step_commits.push(sha);
+            }
+            Ok(())
+        },
+    );
+    // the rollback put the files back; the step commits go too
+    if let (Some(g), true) = (tx_git.as_ref(), applied.is_err() && cfg.transactional_apply && !step_commits.is_empty()) {
+        match git::uncommit(root, &g.base_commit) {
+            Ok(()) => {
+                println!("Dropped {} step commit(s).", step_commits.len());
+                step_commits.clear();
+            }
+            Err(e) => eprintln!("warning: could not drop the step commits: {:#}", e),
+        }
+    }
+    if let (Some(g), false) = (tx_git.as_mut(), step_commits.is_empty()) {
+        println!("Committed {} step(s), {}..{}.", step_commits.len(), &step_commits[0][..12], &step_commits[step_commits.len() - 1][..12]);
+        g.commit = step_commits.last().cloned();
+        g.commits = step_commits;
+        log::save_artifact("git.json", g, txid, &cfg)?;
+    }
+    if applied.is_err() && stashed.is_some() {
+        eprintln!("note: your uncommitted changes are still stashed (`git stash list`, \"vibe: before tx {}\")", txid);
+    }
+    let summary = applied?;
     ux::print_apply_dashboard(&summary);
+    if args.copy {
+        copy_to_clipboard("the commit message", &git::commit_message(&plan_filtered, txid));
+    }
+    if !args.dry_run {
+        log::save_artifact("apply.json", &summary, txid, &cfg)?;
+        // after apply, before anything is committed, so a formatter's changes are included
+        if let Some(cmd) = &cfg.post_apply {
+            let ran = exec::hooks::run("post_apply", cmd, &cfg);
+            if ran.is_err() && stashed.is_some() {
+                eprintln!("note: your uncommitted changes are still stashed (`git stash list`, \"vibe: before tx {}\")", txid);
+            }
+            hook_results.extend(ran?);
+            // so `vibe undo`/`diff` see the hook's rewrite as the tx's own
+            if !summary.changes.is_empty() {
+                backup::Backup::open(root, txid)?.rerecord(root)?;
+            }
+            if let (Some(g), true) = (tx_git.as_mut(), per_step) {
+                let mut paths: Vec<String> = summary.changes.iter().map(|c| c.path.clone()).collect();
+                paths.sort();
+                paths.dedup();
+                if let Some(sha) = git::commit_paths(root, &paths, &format!("Run post_apply hook\n\n{}\n\nVibe-Tx: {}", cmd, txid))? {
+                    g.commits.push(sha.clone());
+                    g.commit = Some(sha);
+                    log::save_artifact("git.json", g, txid, &cfg)?;
+                }
+            }
+        }
+        if !hook_results.is_empty() {
+            log::save_artifact("hooks.json", &hook_results, txid, &cfg)?;
+        }
+        if let (Some(g), true) = (tx_git.as_mut(), cfg.git_commit && !per_step) {
+            let mut paths: Vec<String> = summary.changes.iter().map(|c| c.path.clone()).collect();
+            paths.sort();
+            paths.dedup();
+            match git::commit_paths(root, &paths, &git::commit_message(&plan_filtered, txid))? {
+                Some(sha) => {
+                    println!("Committed {} file(s) as {}.", paths.len(), &sha[..sha.len().min(12)]);
+                    g.commit = Some(sha);
+                    log::save_artifact("git.json", g, txid, &cfg)?;
+                }
+                None => println!("Nothing to commit: the applied files are unchanged."),
+            }
+        }
+        if let (Some(g), true) = (tx_git.as_mut().filter(|g| g.commit.is_some()), cfg.pr.open) {
+            let message = git::commit_message(&plan_filtered, txid);
+            let title = message.lines().next().unwrap_or_default();
+            let body = git::pr::description(&plan_filtered, &previews, txid, cfg.pr.max_diff_lines);
+            match git::pr::open(root, &cfg.pr, g, title, &body) {
+                Ok(url) => {
+                    println!("Opened {}", url);
+                    g.pr = Some(url);
+                    log::save_artifact("git.json", g, txid, &cfg)?;
+                }
+                Err(e) => eprintln!("warning: could not open the pull request: {:#}", e),
+            }
+        }
+        if let Some(paths) = &stashed {
+            let deleted: Vec<String> = plan_filtered
+                .steps
+                .iter()
+                .filter_map(|s| match s {
+                    wire::Step::Delete { path, .. } => Some(safety::normalize_rel(path)),
+                    _ => None,
+                })
+                .collect();
+            match git::unstash_onto(root, paths, &deleted) {
+                Ok(u) if u.conflicts.is_empty() && u.deleted.is_empty() => println!("Merged your stashed changes back in."),
+                Ok(u) => {
+                    if !u.conflicts.is_empty() {
+                        println!(
+                            "{}Your stashed changes conflict with the applied ones in {}; resolve the markers.",
+                            ux::theme::mark(ux::theme::Role::Warn),
+                            u.conflicts.join(", ")
+                        );
+                    }
+                    if !u.deleted.is_empty() {
+                        println!(
+                            "{}The plan deleted {}, which had stashed changes; they were not brought back.",
+                            ux::theme::mark(ux::theme::Role::Warn),
+                            u.deleted.join(", ")
+                        );
+                    }
+                    println!("The stash is kept (\"vibe: before tx {}\").", txid);
+                }
+                Err(e) => eprintln!("warning: {:#}\nYour changes stay in `git stash list` (\"vibe: before tx {}\").", e, txid),
+            }
+        }
+        if let (true, Some(task)) = (args.follow_up, &args.task) {
+            followups::dequeue(root, task)?;
+        }
+        if cfg.offer_follow_up_queue
+            && !summary.follow_ups.is_empty()
+            && ux::confirm(&format!("Queue {} follow-up(s) as tasks in {}?", summary.follow_ups.len(), followups::QUEUE_FILE))
+        {
+            let queued = followups::enqueue(root, &summary.follow_ups)?;
+            println!("Queued in {}.", queued.display());
+        }
+    }
+    save_timed_out_prompts(txid, &cfg)?;
+
+    if !args.dry_run && !cfg.verify.is_empty() {
+        let report = verify::run_matrix(&cfg);
+        ux::print_verify_matrix(&report);
+        log::save_artifact("verify.json", &report, txid, &cfg)?;
+    }
+
+    if !args.dry_run && cfg.smoke_after_apply {
+        match smoke::run(root, &cfg).await {
+            Ok(report) => {
+                ux::print_smoke_table(&report);
+                log::save_artifact("smoke.json", &report, txid, &cfg)?;
+            }
+            Err(e) => eprintln!("Smoke check failed to run: {:#}", e),
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,85 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::merge::has_use_client_top;
+
+/// Simple read-only metrics over a generated source file, shown in the preview
+/// so reviewers can spot monolithic pages before approving them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileMetrics {
+    /// Non-blank, non-comment lines.
+    pub loc: usize,
+    /// Capitalized function/arrow declarations (React components).
+    pub components: usize,
+    pub functions: usize,
+    pub client: bool,
+    /// Highest decision-point count of any top-level function (+1).
+    pub max_complexity: usize,
+}
+
+/// Files above this many lines get flagged in the preview.
+pub const LARGE_FILE_LOC: usize = 300;
+/// Functions above this complexity get flagged in the preview.
+pub const HIGH_COMPLEXITY: usize = 15;
+
+fn is_source(path: &str) -> bool {
+    [".tsx", ".ts", ".jsx", ".js"].iter().any(|e| path.ends_with(e))
+}
+
+fn decision_points(line: &str) -> usize {
+    let words = ["if ", "if(", "for ", "for(", "while ", "while(", "case ", "catch ", "catch("];
+    let ops = ["&&", "||", "??", " ? "];
+    words.iter().map(|w| line.matches(w).count()).sum::<usize>()
+        + ops.iter().map(|o| line.matches(o).count()).sum::<usize>()
+}
+
+pub fn analyze(path: &str, content: &str) -> Option<FileMetrics> {
+    if !is_source(path) {
+        return None;
+    }
+    let fn_re = Regex::new(r"^\s*(export\s+)?(default\s+)?(async\s+)?function\s+([A-Za-z_$][\w$]*)").ok()?;
+    let arrow_re = Regex::new(r"^\s*(export\s+)?const\s+([A-Za-z_$][\w$]*)\s*(:[^=]+)?=\s*(async\s*)?(\([^)]*\)|[A-Za-z_$][\w$]*)\s*(:[^=]+)?=>").ok()?;
+
+    let mut m = FileMetrics { client: has_use_client_top(content), ..Default::default() };
+    let mut depth: i64 = 0;
+    let mut current: Option<usize> = None;
+
+    for raw in content.lines() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with("//") || line.starts_with("/*") || line.starts_with('*') {
+            continue;
+        }
+        m.loc += 1;
+
+        let name = fn_re
+            .captures(line)
+            .and_then(|c| c.get(4))
+            .or_else(|| arrow_re.captures(line).and_then(|c| c.get(2)))
+            .map(|n| n.as_str().to_string());
+        if let Some(n) = name {
+            m.functions += 1;
+            if n.chars().next().map(|c| c.is_ascii_uppercase()).unwrap_or(false) {
+                m.components += 1;
+            }
+            if depth == 0 {
+                current = Some(1);
+            }
+        }
+
+        if let Some(c) = current.as_mut() {
+            *c += decision_points(line);
+        }
+
+        depth += line.matches('{').count() as i64 - line.matches('}').count() as i64;
+        if depth <= 0 {
+            depth = 0;
+            if let Some(c) = current.take() {
+                m.max_complexity = m.max_complexity.max(c);
+            }
+        }
+    }
+    if let Some(c) = current {
+        m.max_complexity = m.max_complexity.max(c);
+    }
+    Some(m)
+}
@@ -0,0 +1,84 @@
+use fs_err as fs;
+use std::path::Path;
+
+use crate::wire::{Plan, Step};
+
+/// Where GitHub/GitLab look for the file, in their order of precedence.
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+struct Rule {
+    patterns: Vec<glob::Pattern>,
+    owners: Vec<String>,
+}
+
+/// Parsed CODEOWNERS; the last matching rule wins, as on GitHub.
+pub struct Codeowners {
+    rules: Vec<Rule>,
+}
+
+/// A plan step touching a path owned by a team not listed in `owners`.
+#[derive(Debug, Clone)]
+pub struct ForeignStep {
+    pub id: String,
+    pub path: String,
+    pub owners: Vec<String>,
+}
+
+/// gitignore-style CODEOWNERS pattern as globs over root-relative paths: a
+/// leading `/` anchors at the root, a pattern without a slash matches at
+/// any depth, and a match on a directory covers everything below it.
+fn compile(pattern: &str) -> Vec<glob::Pattern> {
+    let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+    let p = pattern.trim_start_matches('/').trim_end_matches('/');
+    let base = if anchored || p.starts_with("**") { p.to_string() } else { format!("**/{}", p) };
+    [base.clone(), format!("{}/**", base)]
+        .iter()
+        .filter_map(|g| glob::Pattern::new(g).ok())
+        .collect()
+}
+
+impl Codeowners {
+    /// The first CODEOWNERS found under `root`, if any.
+    pub fn load(root: &Path) -> Option<Self> {
+        let text = LOCATIONS.iter().find_map(|l| fs::read_to_string(root.join(l)).ok())?;
+        let rules = text
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('['))
+            .filter_map(|l| {
+                let mut parts = l.split_whitespace();
+                let pattern = parts.next()?;
+                Some(Rule { patterns: compile(pattern), owners: parts.map(|o| o.to_string()).collect() })
+            })
+            .collect();
+        Some(Self { rules })
+    }
+
+    /// Owners of `rel`; empty when no rule matches or the last match has none.
+    pub fn owners_of(&self, rel: &str) -> &[String] {
+        let rel = crate::safety::normalize_rel(rel);
+        let opts = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| r.patterns.iter().any(|p| p.matches_with(&rel, opts)))
+            .map(|r| r.owners.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// File steps whose path is owned, but not by any of `mine` (`owners` in
+    /// config). With `mine` empty every owned path is reported.
+    pub fn foreign_steps(&self, plan: &Plan, mine: &[String]) -> Vec<ForeignStep> {
+        plan.steps
+            .iter()
+            .filter_map(|s| {
+                let (Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. }) = s else {
+                    return None;
+                };
+                let owners = self.owners_of(path);
+                let foreign = !owners.is_empty() && !owners.iter().any(|o| mine.iter().any(|m| m.eq_ignore_ascii_case(o)));
+                foreign.then(|| ForeignStep { id: s.id().to_string(), path: path.clone(), owners: owners.to_vec() })
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,19 @@
 use anyhow::Result;
 use colored::Colorize;
 use fs_err as fs;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use crate::config::Config;
+use crate::ux::theme::{self, Role};
 use crate::merge::{additive_merge, preserve_use_client, is_additive_task};
+use crate::metrics::{self, FileMetrics};
 use crate::wire::{Plan, Step};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChangeKind { Create, Update, Delete, Command, Test }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Preview {
     pub kind: ChangeKind,
     pub path: Option<PathBuf>,
@@ -17,6 +21,8 @@ pub struct Preview {
     pub bytes_after: Option<u64>,
     pub diff_snippet: Option<String>,
     pub command: Option<String>,
+    pub group: Option<String>,
+    pub metrics: Option<FileMetrics>,
 }
 
 fn read_to_string_if_exists(path: &Path) -> Result<Option<String>> {
@@ -27,7 +33,7 @@ fn read_to_string_if_exists(path: &Path) -> Result<Option<String>> {
     }
 }
 
-fn short_diff(old: &str, new: &str, max_lines: usize) -> String {
+pub fn short_diff(old: &str, new: &str, max_lines: usize) -> String {
     let old_lines: Vec<&str> = old.lines().collect();
     let new_lines: Vec<&str> = new.lines().collect();
     let mut out: Vec<String> = Vec::new();
@@ -41,11 +47,11 @@ fn short_diff(old: &str, new: &str, max_lines: usize) -> String {
             continue;
         }
         if i < old_lines.len() {
-            out.push(format!("{}", format!("- {}", old_lines[i]).red()));
+            out.push(theme::paint(Role::Remove, &format!("- {}", old_lines[i])).to_string());
             i += 1;
         }
         if j < new_lines.len() {
-            out.push(format!("{}", format!("+ {}", new_lines[j]).green()));
+            out.push(theme::paint(Role::Add, &format!("+ {}", new_lines[j])).to_string());
             j += 1;
         }
     }
@@ -56,7 +62,7 @@ fn short_diff(old: &str, new: &str, max_lines: usize) -> String {
     out.join("\n")
 }
 
-pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>> {
+pub fn preview(root: &Path, plan: &Plan, user_task: &str, cfg: &Config) -> Result<Vec<Preview>> {
     let mut previews = Vec::new();
     let additive = is_additive_task(user_task);
 
@@ -80,12 +86,18 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
                     bytes_after: after,
                     diff_snippet: diff,
                     command: None,
+                    group: s.group().map(|g| g.to_string()),
+                    metrics: content.as_ref().and_then(|c| metrics::analyze(path, c)),
                 });
             }
             Step::Update { path, content, .. } => {
                 let abs = root.join(path);
                 let before = if abs.exists() { Some(abs.metadata()?.len()) } else { None };
                 let (after, diff) = match (read_to_string_if_exists(&abs)?, content) {
+                    (Some(old), Some(new_model)) if cfg.is_generated(path) => {
+                        let after = new_model.as_bytes().len() as u64;
+                        (Some(after), Some(short_diff(&old, new_model, 120)))
+                    }
                     (Some(old), Some(new_model)) => {
                         let merged_base = if additive { additive_merge(&old, new_model) } else { new_model.clone() };
                         let merged = preserve_use_client(Some(&old), &merged_base, user_task);
@@ -102,6 +114,8 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
                     bytes_after: after,
                     diff_snippet: diff,
                     command: None,
+                    group: s.group().map(|g| g.to_string()),
+                    metrics: content.as_ref().and_then(|c| metrics::analyze(path, c)),
                 });
             }
             Step::Delete { path, .. } => {
@@ -114,6 +128,8 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
                     bytes_after: Some(0),
                     diff_snippet: None,
                     command: None,
+                    group: s.group().map(|g| g.to_string()),
+                    metrics: None,
                 });
             }
             Step::Command { command, .. } => {
@@ -124,6 +140,8 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
                     bytes_after: None,
                     diff_snippet: None,
                     command: Some(command.clone()),
+                    group: s.group().map(|g| g.to_string()),
+                    metrics: None,
                 });
             }
             Step::Test { command, .. } => {
@@ -134,6 +152,8 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
                     bytes_after: None,
                     diff_snippet: None,
                     command: Some(command.clone()),
+                    group: s.group().map(|g| g.to_string()),
+                    metrics: None,
                 });
             }
         }
@@ -141,42 +161,65 @@ pub fn preview(root: &Path, plan: &Plan, user_task: &str) -> Result<Vec<Preview>
     Ok(previews)
 }
 
+fn metrics_line(m: &FileMetrics) -> String {
+    let loc = if m.loc > metrics::LARGE_FILE_LOC {
+        format!("{} {}", Role::Warn.symbol(), theme::paint(Role::Warn, &format!("{} LOC (large)", m.loc)).bold())
+    } else {
+        format!("{} LOC", m.loc)
+    };
+    let cx = if m.max_complexity > metrics::HIGH_COMPLEXITY {
+        format!("{} {}", Role::Warn.symbol(), theme::paint(Role::Warn, &format!("max complexity {} (high)", m.max_complexity)).bold())
+    } else {
+        format!("max complexity {}", m.max_complexity)
+    };
+    format!(
+        "  metrics: {} · {} component(s) · {} function(s) · {} · {}\n",
+        loc,
+        m.components,
+        m.functions,
+        if m.client { "client" } else { "server" },
+        cx
+    )
+}
+
 pub fn colorize_preview(p: &Preview) -> String {
     match p.kind {
         ChangeKind::Create => {
             format!(
-                "{} {}  ({} -> {})\n{}",
-                "[CREATE]".green().bold(),
+                "{} {}  ({} -> {})\n{}{}",
+                theme::label(Role::Add, "[CREATE]"),
                 p.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
                 p.bytes_before.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into()),
                 p.bytes_after.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into()),
+                p.metrics.as_ref().map(metrics_line).unwrap_or_default(),
                 p.diff_snippet.clone().unwrap_or_default()
             )
         }
         ChangeKind::Update => {
             format!(
-                "{} {}  ({} -> {})\n{}",
-                "[UPDATE]".yellow().bold(),
+                "{} {}  ({} -> {})\n{}{}",
+                theme::label(Role::Change, "[UPDATE]"),
                 p.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
                 p.bytes_before.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into()),
                 p.bytes_after.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into()),
+                p.metrics.as_ref().map(metrics_line).unwrap_or_default(),
                 p.diff_snippet.clone().unwrap_or_default()
             )
         }
         ChangeKind::Delete => {
             format!(
                 "{} {}  ({} -> {})",
-                "[DELETE]".red().bold(),
+                theme::label(Role::Remove, "[DELETE]"),
                 p.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
                 p.bytes_before.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into()),
                 p.bytes_after.map(|b| format!("{b}B")).unwrap_or_else(|| "-".into())
             )
         }
         ChangeKind::Command => {
-            format!("{} {}", "[COMMAND]".cyan().bold(), p.command.clone().unwrap_or_default())
+            format!("{} {}", theme::label(Role::Command, "[COMMAND]"), p.command.clone().unwrap_or_default())
         }
         ChangeKind::Test => {
-            format!("{} {}", "[TEST]".magenta().bold(), p.command.clone().unwrap_or_default())
+            format!("{} {}", theme::label(Role::Test, "[TEST]"), p.command.clone().unwrap_or_default())
         }
     }
 }
@@ -0,0 +1,173 @@
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use crate::wire::{Plan, Step};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Mermaid,
+    Dot,
+}
+
+impl GraphFormat {
+    /// File name the graph is saved under in the tx directory.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            GraphFormat::Mermaid => "plan.mmd",
+            GraphFormat::Dot => "plan.dot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Edge {
+    /// The target's content imports a file the source writes.
+    Imports,
+    /// Steps run in order: a command runs after the file steps before it,
+    /// and file steps after a command depend on it (e.g. an install).
+    After,
+}
+
+fn node_label(s: &Step) -> String {
+    let what = match s {
+        Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. } => path.clone(),
+        Step::Command { command, .. } | Step::Test { command, .. } => format!("$ {}", command),
+    };
+    format!("{} {}", super::action_name(s), what)
+}
+
+fn is_command(s: &Step) -> bool {
+    matches!(s, Step::Command { .. } | Step::Test { .. })
+}
+
+/// `path` without extension or trailing `/index`, the form import
+/// specifiers resolve to.
+fn module_key(path: &str) -> String {
+    let p = crate::safety::normalize_rel(path);
+    let p = match p.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => stem.to_string(),
+        _ => p,
+    };
+    p.strip_suffix("/index").map(str::to_string).unwrap_or(p)
+}
+
+/// Candidate module keys for an import specifier in the file at `from`:
+/// relative specifiers against its directory, `@/x` against the root and `src/`.
+fn resolve(from: &str, spec: &str) -> Vec<String> {
+    if let Some(rest) = spec.strip_prefix("@/") {
+        return vec![module_key(rest), module_key(&format!("src/{}", rest))];
+    }
+    if !spec.starts_with('.') {
+        return vec![];
+    }
+    let mut parts: Vec<&str> = from.split('/').collect();
+    parts.pop();
+    for seg in spec.split('/') {
+        match seg {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            s => parts.push(s),
+        }
+    }
+    vec![module_key(&parts.join("/"))]
+}
+
+fn imports(content: &str) -> Vec<&str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r#"(?:from\s+|import\s*\(?\s*|require\(\s*)['"]([^'"]+)['"]"#).unwrap());
+    re.captures_iter(content).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect()
+}
+
+/// Dependencies between steps as (from, to, kind) index pairs.
+fn edges(plan: &Plan) -> BTreeSet<(usize, usize, Edge)> {
+    let mut out = BTreeSet::new();
+    let written: Vec<(usize, String)> = plan
+        .steps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| match s {
+            Step::Create { path, .. } | Step::Update { path, .. } => Some((i, module_key(path))),
+            _ => None,
+        })
+        .collect();
+    for (i, s) in plan.steps.iter().enumerate() {
+        let (Step::Create { path, content: Some(c), .. } | Step::Update { path, content: Some(c), .. }) = s else {
+            continue;
+        };
+        for spec in imports(c) {
+            for key in resolve(path, spec) {
+                for (j, _) in written.iter().filter(|(j, k)| *j != i && *k == key) {
+                    out.insert((*j, i, Edge::Imports));
+                }
+            }
+        }
+    }
+    let mut last_command: Option<usize> = None;
+    let mut since: Vec<usize> = Vec::new();
+    for (i, s) in plan.steps.iter().enumerate() {
+        if is_command(s) {
+            match (since.is_empty(), last_command) {
+                (true, Some(c)) => {
+                    out.insert((c, i, Edge::After));
+                }
+                _ => out.extend(since.drain(..).map(|j| (j, i, Edge::After))),
+            }
+            last_command = Some(i);
+        } else {
+            if let Some(c) = last_command {
+                out.insert((c, i, Edge::After));
+            }
+            since.push(i);
+        }
+    }
+    out
+}
+
+fn mermaid(plan: &Plan) -> String {
+    let mut out = String::from("flowchart TD\n");
+    for (gi, (group, idxs)) in super::group_steps(&plan.steps).iter().enumerate() {
+        out.push_str(&format!("  subgraph g{}[\"{}\"]\n", gi, group.replace('"', "#quot;")));
+        for &i in idxs {
+            out.push_str(&format!("    s{}[\"{}\"]\n", i, node_label(&plan.steps[i]).replace('"', "#quot;")));
+        }
+        out.push_str("  end\n");
+    }
+    for (a, b, kind) in edges(plan) {
+        match kind {
+            Edge::Imports => out.push_str(&format!("  s{} -. imports .-> s{}\n", a, b)),
+            Edge::After => out.push_str(&format!("  s{} --> s{}\n", a, b)),
+        }
+    }
+    out
+}
+
+fn dot(plan: &Plan) -> String {
+    let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph plan {\n  rankdir=TB;\n  node [shape=box];\n");
+    for (gi, (group, idxs)) in super::group_steps(&plan.steps).iter().enumerate() {
+        out.push_str(&format!("  subgraph cluster_{} {{\n    label=\"{}\";\n", gi, esc(group)));
+        for &i in idxs {
+            out.push_str(&format!("    s{} [label=\"{}\"];\n", i, esc(&node_label(&plan.steps[i]))));
+        }
+        out.push_str("  }\n");
+    }
+    for (a, b, kind) in edges(plan) {
+        match kind {
+            Edge::Imports => out.push_str(&format!("  s{} -> s{} [style=dashed, label=\"imports\"];\n", a, b)),
+            Edge::After => out.push_str(&format!("  s{} -> s{};\n", a, b)),
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The plan's steps (clustered by group) and the dependencies between them.
+pub fn render(plan: &Plan, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Mermaid => mermaid(plan),
+        GraphFormat::Dot => dot(plan),
+    }
+}
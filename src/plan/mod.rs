@@ -1,6 +1,9 @@
 use crate::wire::{Plan, Step};
 use std::collections::HashMap;
 
+pub mod graph;
+pub mod samples;
+
 pub fn validate_and_extract(p: Option<&Plan>) -> anyhow::Result<Plan> {
     match p {
         Some(x) => Ok(x.clone()),
@@ -12,13 +15,67 @@ pub fn coerce(p: Option<&Plan>) -> anyhow::Result<Plan> {
     validate_and_extract(p)
 }
 
+/// Infer a feature/phase group from a step's path, e.g.
+/// `src/app/settings/page.tsx` -> "settings", `package.json` -> "config".
+fn group_for_path(path: &str) -> String {
+    let p = path.replace('\\', "/");
+    let p = p.trim_start_matches("./");
+    let p = p.strip_prefix("src/").unwrap_or(p);
+    let segs: Vec<&str> = p.split('/').filter(|s| !s.is_empty()).collect();
+    match segs.as_slice() {
+        [] | [_] => "config".to_string(),
+        ["app", rest @ ..] => {
+            // skip route groups like `(marketing)`; a bare file under app/ is the shell/home
+            let dirs = &rest[..rest.len() - 1];
+            match dirs.iter().find(|s| !(s.starts_with('(') && s.ends_with(')'))) {
+                Some(seg) => seg.to_string(),
+                None if rest.last().map(|f| f.starts_with("page.")).unwrap_or(false) => "home".to_string(),
+                None => "app shell".to_string(),
+            }
+        }
+        [first, ..] => first.to_string(),
+    }
+}
+
+/// Fill in `group` for steps the model left ungrouped.
+pub fn infer_groups(plan: &mut Plan) {
+    for s in plan.steps.iter_mut() {
+        if s.group().is_some() {
+            continue;
+        }
+        let g = match s {
+            Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. } => group_for_path(path),
+            Step::Command { .. } => "setup".to_string(),
+            Step::Test { .. } => "tests".to_string(),
+        };
+        s.set_group(g);
+    }
+}
+
+/// Bucket step indices by group, in order of first appearance.
+pub fn group_steps(steps: &[Step]) -> Vec<(String, Vec<usize>)> {
+    let mut out: Vec<(String, Vec<usize>)> = Vec::new();
+    for (i, s) in steps.iter().enumerate() {
+        let g = s.group().unwrap_or("ungrouped");
+        match out.iter_mut().find(|(name, _)| name == g) {
+            Some((_, idxs)) => idxs.push(i),
+            None => out.push((g.to_string(), vec![i])),
+        }
+    }
+    out
+}
+
 /// Sanitize/dedupe plan steps to avoid conflicting/wrong changes.
 /// - Deduplicate multiple UPDATEs to the same path (prefer the one with `content`)
 /// - Drop UPDATEs that have neither `content` nor `patch`
 /// - Keep only one step per (action,path) when applicable
-pub fn sanitize(plan: Plan) -> (Plan, Vec<String>) {
+/// - Resolve cross-step conflicts (see `resolve_conflicts`)
+/// - Infer a `group` for ungrouped steps
+pub fn sanitize(mut plan: Plan) -> (Plan, Vec<String>) {
+    infer_groups(&mut plan);
     let mut warnings = Vec::new();
     let original_summary = plan.summary.clone();
+    let requires = std::mem::take(&mut plan.requires);
 
     // First pass: collect best UPDATE per path
     let mut best_update: HashMap<String, usize> = HashMap::new();
@@ -90,11 +147,107 @@ pub fn sanitize(plan: Plan) -> (Plan, Vec<String>) {
         }
     }
 
+    let out = resolve_conflicts(out, &mut warnings);
+
     (
         Plan {
             summary: original_summary,
             steps: out,
+            requires,
         },
         warnings,
     )
 }
+
+/// Drop create/update steps whose content is over `max_bytes`
+/// (`max_generated_file_bytes`); returns a warning for each.
+pub fn drop_oversized(plan: &mut Plan, max_bytes: usize) -> Vec<String> {
+    let mut warnings = Vec::new();
+    plan.steps.retain(|s| match s {
+        Step::Create { path, content: Some(c), .. } | Step::Update { path, content: Some(c), .. } if c.len() > max_bytes => {
+            warnings.push(format!(
+                "dropped {} of {}: generated content is {} bytes (max_generated_file_bytes {})",
+                action_name(s), path, c.len(), max_bytes
+            ));
+            false
+        }
+        _ => true,
+    });
+    warnings
+}
+
+/// File steps that write the path (create/update) as opposed to deleting it.
+fn written_path(s: &Step) -> Option<&str> {
+    match s {
+        Step::Create { path, .. } | Step::Update { path, .. } => Some(path),
+        _ => None,
+    }
+}
+
+/// Cross-step analysis over the deduped plan:
+/// - delete + create/update of one path: the delete is dropped (the less destructive reading)
+/// - create + update of one path: the later step carrying content wins
+/// - a file written at `a/b` while another step writes `a/b/...`: warned, not resolvable
+/// - two pages resolving to the same URL (route groups): warned, not resolvable
+fn resolve_conflicts(steps: Vec<Step>, warnings: &mut Vec<String>) -> Vec<Step> {
+    let norm = |p: &str| crate::safety::normalize_rel(p);
+    let written: Vec<String> = steps.iter().filter_map(written_path).map(norm).collect();
+
+    // create + update on the same path: the last step with content, else the first
+    let mut keep_write: HashMap<String, usize> = HashMap::new();
+    for (i, s) in steps.iter().enumerate() {
+        let Some(path) = written_path(s) else { continue };
+        let has_content = matches!(s, Step::Create { content: Some(_), .. } | Step::Update { content: Some(_), .. });
+        let path = norm(path);
+        if has_content || !keep_write.contains_key(&path) {
+            keep_write.insert(path, i);
+        }
+    }
+
+    let mut out = Vec::new();
+    for (i, s) in steps.into_iter().enumerate() {
+        match &s {
+            Step::Delete { path, .. } if written.contains(&norm(path)) => {
+                warnings.push(format!("dropped delete of {}: the plan also writes it", path));
+                continue;
+            }
+            Step::Create { path, .. } | Step::Update { path, .. } if keep_write.get(&norm(path)) != Some(&i) => {
+                warnings.push(format!("dropped {} for {}: another step in the plan writes the same file", action_name(&s), path));
+                continue;
+            }
+            _ => {}
+        }
+        out.push(s);
+    }
+
+    // file vs directory: `a/b` written as a file while `a/b/x` is also written
+    let mut files: Vec<String> = out.iter().filter_map(written_path).map(norm).collect();
+    files.sort();
+    for f in &files {
+        let prefix = format!("{}/", f);
+        if let Some(child) = files.iter().find(|o| o.starts_with(&prefix)) {
+            warnings.push(format!("conflict: {} is written as a file but {} needs it to be a directory", f, child));
+        }
+    }
+
+    // route collisions among pages the plan writes
+    let mut routes: HashMap<String, String> = HashMap::new();
+    for f in &files {
+        let Some(rel) = f.strip_prefix("src/app/").or_else(|| f.strip_prefix("app/")) else { continue };
+        let Some((route, _)) = crate::context::routes::route_for_page(rel) else { continue };
+        if let Some(other) = routes.insert(route.clone(), f.clone()) {
+            warnings.push(format!("conflict: {} and {} both resolve to route {}", other, f, route));
+        }
+    }
+    out
+}
+
+fn action_name(s: &Step) -> &'static str {
+    match s {
+        Step::Create { .. } => "create",
+        Step::Update { .. } => "update",
+        Step::Delete { .. } => "delete",
+        Step::Command { .. } => "command",
+        Step::Test { .. } => "test",
+    }
+}
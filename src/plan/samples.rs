@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use crate::config::Config;
+use crate::safety;
+use crate::wire::{LlmResponse, Plan, Step};
+
+/// Default sampling temperature for `--plan-samples` when none is configured.
+pub const SAMPLE_TEMPERATURE: f32 = 0.7;
+
+/// Score of one candidate plan; higher is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub total: i64,
+    /// Steps outside the path/command allowlists.
+    pub disallowed: usize,
+    /// Steps that contradict the files on disk (update/delete of a missing
+    /// file, create of an existing one).
+    pub inconsistent: usize,
+}
+
+/// Rank a plan by allowlist conformance and consistency with the project on
+/// disk, then by size: empty or over-`max_actions` plans lose, and among the
+/// rest fewer steps win.
+pub fn score(plan: &Plan, root: &Path, cfg: &Config) -> Score {
+    let mut disallowed = 0;
+    let mut inconsistent = 0;
+    for s in &plan.steps {
+        match s {
+            Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. } => {
+                if !safety::rel_matches_allowlist(path, &cfg.path_allowlist) {
+                    disallowed += 1;
+                }
+                let exists = root.join(path).is_file();
+                if matches!(s, Step::Create { .. }) == exists {
+                    inconsistent += 1;
+                }
+            }
+            Step::Command { command, .. } | Step::Test { command, .. } => {
+                if !safety::command_is_allowed(command, &cfg.command_allowlist) {
+                    disallowed += 1;
+                }
+            }
+        }
+    }
+    let n = plan.steps.len();
+    let size_penalty = if n == 0 || n > cfg.max_actions { 1_000 } else { n as i64 };
+    Score { total: -(disallowed as i64 * 100) - (inconsistent as i64 * 20) - size_penalty, disallowed, inconsistent }
+}
+
+/// Index of the best-scoring response carrying a plan, with every score (None
+/// for responses without a plan).
+pub fn pick(responses: &[LlmResponse], root: &Path, cfg: &Config) -> (Option<usize>, Vec<Option<Score>>) {
+    let scores: Vec<Option<Score>> = responses.iter().map(|r| r.plan.as_ref().map(|p| score(p, root, cfg))).collect();
+    let best = scores
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| s.map(|s| (i, s.total)))
+        // ties go to the earliest sample
+        .max_by_key(|&(i, total)| (total, std::cmp::Reverse(i)))
+        .map(|(i, _)| i);
+    (best, scores)
+}
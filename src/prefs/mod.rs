@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::wire::{Plan, Step};
+
+/// How often the user kept or dropped a kind of step during plan review.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecisionCounts {
+    pub approved: u32,
+    pub skipped: u32,
+}
+
+/// Per-project learned preferences, stored in `.vibe/preferences.json`.
+/// The file is meant to be hand-editable: `notes` are always included in
+/// the block, and `decisions` entries can be deleted to forget a habit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Preferences {
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub decisions: BTreeMap<String, DecisionCounts>,
+}
+
+/// A habit needs this many skips, at this ratio, before it's surfaced.
+const MIN_SKIPS: u32 = 2;
+const MIN_SKIP_RATIO: f32 = 0.6;
+
+pub fn prefs_path(root: &Path) -> PathBuf {
+    root.join(".vibe").join("preferences.json")
+}
+
+/// Key describing what kind of step this is, e.g. `update package.json`,
+/// `command npm install`.
+fn step_key(s: &Step) -> String {
+    match s {
+        Step::Create { path, .. } => format!("create {}", path),
+        Step::Update { path, .. } => format!("update {}", path),
+        Step::Delete { path, .. } => format!("delete {}", path),
+        Step::Command { command, .. } => {
+            let head: Vec<&str> = command.split_whitespace().take(2).collect();
+            format!("command {}", head.join(" "))
+        }
+        Step::Test { command, .. } => format!("test {}", command),
+    }
+}
+
+fn step_id(s: &Step) -> &str {
+    match s {
+        Step::Create { id, .. }
+        | Step::Update { id, .. }
+        | Step::Delete { id, .. }
+        | Step::Command { id, .. }
+        | Step::Test { id, .. } => id,
+    }
+}
+
+impl Preferences {
+    pub fn load(root: &Path) -> Result<Self> {
+        let p = prefs_path(root);
+        if !p.exists() {
+            return Ok(Self::default());
+        }
+        let s = fs::read_to_string(&p)?;
+        serde_json::from_str(&s).with_context(|| format!("parsing {}", p.display()))
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let p = prefs_path(root);
+        if let Some(dir) = p.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&p, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn record(&mut self, key: String, approved: bool) {
+        let e = self.decisions.entry(key).or_default();
+        if approved {
+            e.approved += 1;
+        } else {
+            e.skipped += 1;
+        }
+    }
+
+    /// Record review decisions: steps of `before` missing from `after` (by id)
+    /// were skipped, the rest approved. Group-level decisions are tracked too.
+    pub fn record_review(&mut self, before: &Plan, after: &Plan) {
+        for s in &before.steps {
+            let kept = after.steps.iter().any(|a| step_id(a) == step_id(s));
+            self.record(step_key(s), kept);
+        }
+        for (g, idxs) in crate::plan::group_steps(&before.steps) {
+            let kept = idxs.iter().any(|i| {
+                let id = step_id(&before.steps[*i]);
+                after.steps.iter().any(|a| step_id(a) == id)
+            });
+            self.record(format!("group {}", g), kept);
+        }
+    }
+
+    /// Prompt block for PLAN requests, or None when nothing was learned.
+    pub fn prompt_block(&self) -> Option<String> {
+        let mut habits = Vec::new();
+        for (key, c) in &self.decisions {
+            let total = c.approved + c.skipped;
+            if c.skipped >= MIN_SKIPS && (c.skipped as f32) / (total as f32) >= MIN_SKIP_RATIO {
+                habits.push(format!(" - usually rejects: {} (skipped {}/{})", key, c.skipped, total));
+            }
+        }
+        if habits.is_empty() && self.notes.is_empty() {
+            return None;
+        }
+        let mut s = String::from("Learned user preferences (avoid planning steps the user habitually rejects unless the task explicitly asks for them):\n");
+        for h in habits {
+            s.push_str(&h);
+            s.push('\n');
+        }
+        for n in &self.notes {
+            s.push_str(&format!(" - {}\n", n));
+        }
+        Some(s)
+    }
+}
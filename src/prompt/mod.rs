@@ -1,3 +1,4 @@
+use crate::lint::Violation;
 use crate::wire::Plan;
 use serde_json;
 
@@ -129,7 +130,8 @@ Return EXACTLY ONE JSON object (no markdown, no prose, no code fences) that conf
       {{ "id": string, "title": string, "action": "delete",  "path": string }},
       {{ "id": string, "title": string, "action": "command", "command": string, "cwd": string|null }},
       {{ "id": string, "title": string, "action": "test",    "command": string }}
-    ]
+    ],
+    "requires": [string]   // optional: tools the steps need, e.g. "node>=18", "git", "docker"
   }},
   "answer": {{ "title": string, "content": string }}
 }}
@@ -137,11 +139,14 @@ Return EXACTLY ONE JSON object (no markdown, no prose, no code fences) that conf
 Classification:
 - If the task is informational (pure Q&A), set kind:"answer" and fill "answer"; do not include a plan.
 - If the task is a code change (imperatives like add/update/fix/create/remove/rename/refactor/implement/migrate/configure, or mentions files/paths/extensions), you MUST set kind:"plan". Do NOT return "answer" for code-change tasks.
+- When a short explanation helps (e.g. the feature mostly exists already and only a small tweak is needed), keep kind:"plan" with its steps and ALSO fill "answer" with the explanation.
 
 Context Awareness:
 - You are given the current project state via JSON. The array `context.files_snapshot` contains:
   {{ "path": string, "bytes": number, "truncated": boolean, "content": string }}.
 - Use these snapshots to understand what exists today. DO NOT invent structure that contradicts the snapshot set.
+- When present, `context.git` holds `log` (recent `git log --oneline`) and `diff` (uncommitted changes to those files). Treat that diff as the user's work in progress: build on it, never revert it.
+- When present, `context.summary.recent_changes` lists, per file the task names, the line ranges recent commits changed with each commit's date, author and subject. A bug is often in or next to a recent change; read those regions first.
 
 {architecture_policy}
 
@@ -154,6 +159,7 @@ PLAN Rules:
 - When the intent implies a domain transformation, update the landing page `/` to a domain-specific multi-section layout and align navigation accordingly (Home, <All the business related menu>, Settings, theme toggle (next-themes), and user area (name/avatar; Sign-in/Sign up when unauthenticated)).
 - Prefer `src/app/*` paths; never use legacy Pages Router.
 - Keep steps ≤ max_actions and within allowlists.
+- For large plans (15+ steps), you MAY add an optional "group" string to each step naming its feature/phase (e.g., "settings", "navigation", "setup").
 - Preserve existing functionality; avoid duplicates (providers, imports, nav items, routes). Summarize copy where helpful.
 
 Dependencies & package.json (MANDATORY IN PLAN):
@@ -179,16 +185,7 @@ Richer Page Planning (MANDATORY IN PLAN):
 pub fn system_prompt_plan_strict() -> String {
     format!(r#"STRICT MODE — THIS IS A CODE-CHANGE TASK.
 
-Return EXACTLY ONE JSON object (no markdown, no prose, no code fences) with:
-- "schema_version": "v1"
-- "kind": "plan"   (MUST be "plan"; do NOT return "answer")
-- "plan": {{ "summary": string, "steps": [ create|update|delete|command|test items ] }}
-
-Additional STRICT requirements:
-- Begin "summary" with `mode=scaffold|augment|modify` and a one-line rationale based on `context.files_snapshot`.
-- All create/update items MUST have "content": null and "patch": null in PLAN phase.
-- Do not list files outside src/app except configuration or package.json when necessary.
-- Do not include code. Do not include file contents. Do not include diffs. Only list the planned steps.
+{strict_requirements}
 
 {architecture_policy}
 
@@ -205,11 +202,46 @@ Landing Page & Navigation (STRICT):
 
 Richer Page Planning (STRICT):
 - For any new route, specify the main sections/components (forms/tables/cards), field lists, and flows (validate, submit, success/error) in the plan summary or step titles. Still no code."#,
+        strict_requirements = strict_requirements(),
         architecture_policy = architecture_policy(),
         provider_requirements = provider_requirements()
     )
 }
 
+fn strict_requirements() -> &'static str {
+r#"Return EXACTLY ONE JSON object (no markdown, no prose, no code fences) with:
+- "schema_version": "v1"
+- "kind": "plan"   (MUST be "plan"; do NOT return "answer")
+- "plan": { "summary": string, "steps": [ create|update|delete|command|test items ] }
+
+Additional STRICT requirements:
+- Begin "summary" with `mode=scaffold|augment|modify` and a one-line rationale based on `context.files_snapshot`.
+- All create/update items MUST have "content": null and "patch": null in PLAN phase.
+- Do not list files outside src/app except configuration or package.json when necessary.
+- Do not include code. Do not include file contents. Do not include diffs. Only list the planned steps."#
+}
+
+fn plan_guidance() -> &'static str {
+r#"Create a minimal coherent plan to implement the intent.
+- First, infer OPERATION MODE from the current snapshot and state it in the summary: mode=scaffold|augment|modify + one-line reason.
+- Apply the Architecture & Scope Policy to decide whether to create new routes, integrate into navigation, or only modify existing files.
+- The landing page (`/`) must become domain-aware (sports/cars/sales/etc.) with multi-section content (Hero, Feature Cards, How It Works, Domain Highlights, Testimonials, FAQ, CTA, Footer) and lucide icons; group sections into Cards; keep layout clean and modern.
+- The NavBar must expose brand/logo, Home, <All the business related menu>, Settings, Register, a working theme toggle (next-themes) from the outset, username/avatar; include mobile/responsive behavior.
+- **Ensure Tailwind dark mode is class-based and the theme wiring uses `theme-provider.tsx`, `suppressHydrationWarning`, and either base body classes or CSS variables with `.dark` overrides.**
+- Do NOT include code or file contents.
+- When libraries are added/removed (e.g., lucide-react, next-themes), include an UPDATE step for package.json (content:null) and a COMMAND step to run the installer.
+- Preserve existing working functionality. Avoid duplicates; summarize long copy where helpful.
+
+Richer Page Planning:
+- When planning new pages, outline the key UI blocks:
+  • Settings: Profile form (name/email/avatar), Preferences (language, notifications, theme), Security (password change). Save/Cancel flows; zod validation; server action; inline errors + success message.
+  • Auth/Signup: Form with name/email/password/confirm password + terms checkbox; password guidance; zod validation; server action; on success, redirect or show confirmation; on error, show field errors.
+  • Domain List/Index pages: Card or table grid with mock rows, sortable headers, search/filter input, empty state, and pagination placeholders.
+  • Details pages: Summary header with icon, key stats, a few fields, and a related items section.
+
+(Plan only; still no code.)"#
+}
+
 pub fn user_prompt_plan(intent: &str, ctx_files: &[String]) -> String {
     let list = if ctx_files.is_empty() {
         "No preselected files were provided.".to_string()
@@ -234,31 +266,39 @@ Files of interest:
 
 {conventions}
 
-Create a minimal coherent plan to implement the intent.
-- First, infer OPERATION MODE from the current snapshot and state it in the summary: mode=scaffold|augment|modify + one-line reason.
-- Apply the Architecture & Scope Policy to decide whether to create new routes, integrate into navigation, or only modify existing files.
-- The landing page (`/`) must become domain-aware (sports/cars/sales/etc.) with multi-section content (Hero, Feature Cards, How It Works, Domain Highlights, Testimonials, FAQ, CTA, Footer) and lucide icons; group sections into Cards; keep layout clean and modern.
-- The NavBar must expose brand/logo, Home, <All the business related menu>, Settings, Register, a working theme toggle (next-themes) from the outset, username/avatar; include mobile/responsive behavior.
-- **Ensure Tailwind dark mode is class-based and the theme wiring uses `theme-provider.tsx`, `suppressHydrationWarning`, and either base body classes or CSS variables with `.dark` overrides.**
-- Do NOT include code or file contents.
-- When libraries are added/removed (e.g., lucide-react, next-themes), include an UPDATE step for package.json (content:null) and a COMMAND step to run the installer.
-- Preserve existing working functionality. Avoid duplicates; summarize long copy where helpful.
-
-Richer Page Planning:
-- When planning new pages, outline the key UI blocks:
-  • Settings: Profile form (name/email/avatar), Preferences (language, notifications, theme), Security (password change). Save/Cancel flows; zod validation; server action; inline errors + success message.
-  • Auth/Signup: Form with name/email/password/confirm password + terms checkbox; password guidance; zod validation; server action; on success, redirect or show confirmation; on error, show field errors.
-  • Domain List/Index pages: Card or table grid with mock rows, sortable headers, search/filter input, empty state, and pagination placeholders.
-  • Details pages: Summary header with icon, key stats, a few fields, and a related items section.
-
-(Plan only; still no code.)",
+{guidance}",
 architecture_policy = architecture_policy(),
 provider_requirements = provider_requirements(),
 conventions = conventions(),
+guidance = plan_guidance(),
 intent = intent,
 list = list)
 }
 
+/// User prompt for the strict PLAN retry: the first PLAN user prompt minus
+/// the blocks the strict system prompt already carries (architecture policy,
+/// provider requirements, page-planning guidance), plus a note on what was
+/// wrong with the first reply.
+pub fn user_prompt_plan_strict(plan_user: &str, problem: &str) -> String {
+    let trimmed = plan_user
+        .replace(architecture_policy(), "")
+        .replace(provider_requirements(), "")
+        .replace(plan_guidance(), "Follow the STRICT requirements from the system prompt. (Plan only; still no code.)");
+    format!("{}\n\nYour previous reply was rejected: {}. Return kind:\"plan\" with concrete steps.", trimmed.trim_end(), problem)
+}
+
+/// The strict re-ask as a follow-up turn after the rejected reply: only
+/// what is new relative to the PLAN request (the strict requirements and
+/// developer note), since the conversation already carries the rest.
+pub fn follow_up_plan_strict(problem: &str, developer: &str) -> String {
+    format!(
+        "Your previous reply was rejected: {}. STRICT MODE — THIS IS A CODE-CHANGE TASK.\n\n{}\n\nDeveloper notes:\n{}",
+        problem,
+        strict_requirements(),
+        developer
+    )
+}
+
 pub fn system_prompt_codegen() -> String {
     format!(r#"You are a precise code generator for a Next.js (App Router, TypeScript) project used by Vibe Coding.
 
@@ -275,7 +315,8 @@ Return EXACTLY ONE JSON object (no markdown, no prose, no code fences) that conf
       {{ "id": string, "title": string, "action": "delete",  "path": string }},
       {{ "id": string, "title": string, "action": "command", "command": string, "cwd": string|null }},
       {{ "id": string, "title": string, "action": "test",    "command": string }}
-    ]
+    ],
+    "requires": [string]   // optional: tools the steps need, e.g. "node>=18", "git", "docker"
   }}
 }}
 
@@ -289,6 +330,8 @@ Context Awareness (MANDATORY):
   4) Return the full, final file in the step's `content` field.
 - Do NOT fabricate a new file from scratch when a snapshot exists. Preserve directives like 'use client', imports, component names, JSX, Providers, and metadata.
 - If a snapshot for a requested path is missing or `truncated: true`, limit changes and prefer a minimal `patch` or note the limitation in 'summary'.
+- When present, `context.git.diff` shows uncommitted changes already in those snapshots; keep them.
+- When present, `context.summary.recent_changes` points at the regions recent commits changed (and why); fix the cause there rather than working around it elsewhere.
 
 Operation Mode Enforcement (from approved plan summary):
 - If `mode=scaffold`: create `src/app/layout.tsx` (if missing) plus `src/app/components/NavBar.tsx` and the requested feature routes (/settings, /auth/signup and so on). Insert nav items for each new top-level route. **Integrate ThemeProvider from `next-themes` via `src/app/theme-provider.tsx` (client) and wire it in `layout.tsx` with `suppressHydrationWarning` and base body colors.** Ensure Tailwind dark mode is class-based.
@@ -456,3 +499,78 @@ conventions = conventions(),
 plan_json = plan_json
 )
 }
+
+/// Targeted repair prompt after CODEGEN output failed the convention lint.
+pub fn user_prompt_repair(violations: &[Violation], previous: &Plan) -> String {
+    let mut list = String::new();
+    for v in violations.iter().filter(|v| !v.fixed) {
+        list.push_str(&format!(" - {} [{}]: {}\n", v.path, v.rule, v.message));
+    }
+    let plan_json = serde_json::to_string_pretty(previous)
+        .unwrap_or_else(|_| "<plan-json-unavailable>".to_string());
+
+    format!(
+"Your previous CODEGEN output violates project conventions:
+{list}
+Return the same JSON (kind:\"plan\") with these violations fixed and nothing else changed.
+- lucide-react: use named imports only (e.g., `import {{ BookOpen }} from \"lucide-react\"`).
+- Never target the Pages Router; routes live in src/app/<route>/page.tsx.
+- Keep full file contents in 'content' for every created/updated file.
+
+Previous output:
+{plan_json}",
+list = list,
+plan_json = plan_json
+)
+}
+
+/// Output contract appended to user-defined pipeline stage system prompts.
+pub fn user_prompt_dependency_migration(stragglers: &[crate::deps::Straggler], previous: &Plan) -> String {
+    let mut list = String::new();
+    for s in stragglers {
+        list.push_str(&format!(" - {}:{} imports {}\n", s.path, s.line, s.package));
+    }
+    let plan_json = serde_json::to_string_pretty(previous)
+        .unwrap_or_else(|_| "<plan-json-unavailable>".to_string());
+
+    format!(
+"Your previous CODEGEN output removes package(s) that these files still import:
+{list}
+Return the same JSON (kind:\"plan\") plus UPDATE steps (full 'content') that migrate every listed file off the removed package(s), using what the plan replaces them with or plain code. The files are in context.files_snapshot. Keep every other step unchanged.
+
+Previous output:
+{plan_json}",
+list = list,
+plan_json = plan_json
+)
+}
+
+/// `--mode commitmsg`: a commit message for the staged diff in `context.git`.
+pub fn system_prompt_commitmsg() -> String {
+r#"You write git commit messages in the Conventional Commits format.
+
+Return EXACTLY ONE JSON object (no markdown, no code fences):
+{ "schema_version": "v1", "kind": "answer", "answer": { "title": string, "content": string } }
+
+- "answer.title": the subject line alone.
+- "answer.content": the full message: `type(scope): subject`, a blank line, then a short body.
+- type is one of feat, fix, refactor, perf, docs, test, build, ci, chore, style; scope is optional and names the area touched.
+- Subject: imperative mood, lower case, no trailing period, at most 72 characters.
+- Body: what changed and why, wrapped at 72 columns; leave it out for trivial changes. No lists of file names.
+- Mark breaking changes with `!` after the type and a `BREAKING CHANGE:` footer.
+- context.git.diff is the staged diff (cut short when context.git.diff_truncated); context.git.log shows the repository's recent subjects. Describe only what the diff does."#.to_string()
+}
+
+pub fn user_prompt_commitmsg(hint: &str) -> String {
+    let mut s = String::from("Write the commit message for the staged changes in context.git.diff.");
+    if !hint.trim().is_empty() {
+        s.push_str(&format!("\n\nThe author's note on the change: {}", hint.trim()));
+    }
+    s
+}
+
+pub fn stage_wire_contract() -> &'static str {
+r#"Return EXACTLY ONE JSON object (no markdown, no code fences):
+{ "schema_version": "v1", "kind": "answer", "answer": { "title": string, "content": string } }
+Put your full stage output (review notes, decisions, constraints for code generation) in "answer.content". Do not include file contents."#
+}
@@ -1,33 +1,102 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use serde_json::{json, Value};
 
-use crate::wire::{Instruction, LlmRequest, LlmResponse};
-use super::Provider;
+use crate::errors::VibeError;
+use crate::wire::{Instruction, Kind, LlmRequest, LlmResponse, Mode, WireFormat};
+use super::{Provider, Timeouts};
+
+pub const DEFAULT_API_BASE: &str = "https://api.anthropic.com";
+pub const DEFAULT_API_VERSION: &str = "2023-06-01";
 
 pub struct Anthropic {
     pub model: String,
     pub api_key: String,
-    pub timeout: Duration,
+    pub timeouts: Timeouts,
     pub api_base: String,
     pub api_version: String,
+    /// Return plans through the `emit_plan` tool instead of printed JSON.
+    pub tool_use: bool,
+    /// Mark the system prompt (and the tools before it) as a cache breakpoint.
+    pub prompt_cache: bool,
+}
+
+impl Anthropic {
+    /// Key from `api_key` or `ANTHROPIC_API_KEY` (env or keychain); base/version from
+    /// `api_base`, `ANTHROPIC_BASE_URL` / `ANTHROPIC_VERSION` or the defaults.
+    pub fn from_env(model: String, timeouts: Timeouts, api_key: Option<String>, api_base: Option<String>) -> Result<Self> {
+        let api_key = match api_key {
+            Some(k) => k,
+            None => crate::credentials::require("ANTHROPIC_API_KEY")?,
+        };
+        Ok(Self {
+            model,
+            api_key,
+            timeouts,
+            api_base: api_base
+                .or_else(|| std::env::var("ANTHROPIC_BASE_URL").ok())
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string()),
+            api_version: std::env::var("ANTHROPIC_VERSION").unwrap_or_else(|_| DEFAULT_API_VERSION.to_string()),
+            tool_use: false,
+            prompt_cache: false,
+        })
+    }
+
+    pub fn with_tool_use(mut self, on: bool) -> Self {
+        self.tool_use = on;
+        self
+    }
+
+    pub fn with_prompt_cache(mut self, on: bool) -> Self {
+        self.prompt_cache = on;
+        self
+    }
+}
+
+const PLAN_TOOL: &str = "emit_plan";
+const ANSWER_TOOL: &str = "emit_answer";
+
+/// `emit_plan` (input = `wire::Plan`) and, in PLAN mode, `emit_answer` for
+/// informational tasks or, called alongside `emit_plan`, a short explanation
+/// of the plan. Tool choice forces at least one; CODEGEN only gets `emit_plan`.
+fn tools(mode: &Mode) -> (Value, Value) {
+    let plan_tool = json!({
+        "name": PLAN_TOOL,
+        "description": "Emit the plan (summary + steps) as the response. Use this for every code-change task.",
+        "input_schema": crate::wire::plan_json_schema(),
+    });
+    match mode {
+        Mode::Plan => (
+            json!([
+                plan_tool,
+                {
+                    "name": ANSWER_TOOL,
+                    "description": "Answer a purely informational question (no code changes), or call it together with emit_plan to explain a small plan.",
+                    "input_schema": crate::wire::answer_json_schema(),
+                }
+            ]),
+            json!({ "type": "any" }),
+        ),
+        Mode::Codegen => (json!([plan_tool]), json!({ "type": "tool", "name": PLAN_TOOL })),
+    }
 }
 
 #[derive(Serialize)]
 struct MsgRequest<'a> {
     model: &'a str,
     max_tokens: u32,
-    messages: Vec<Msg<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<&'a str>,
-}
-
-#[derive(Serialize)]
-struct Msg<'a> {
-    role: &'a str,
-    content: &'a str,
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    messages: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
 }
 
 #[derive(Deserialize)]
@@ -35,15 +104,72 @@ struct MsgResponse {
     content: Vec<Block>,
 }
 
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Usage {
+    input_tokens: u64,
+    cache_creation_input_tokens: u64,
+    cache_read_input_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct UsageOnly {
+    usage: Option<Usage>,
+}
+
 #[derive(Deserialize)]
 struct Block {
     #[serde(default)]
     text: String,
     #[serde(default)]
     r#type: String,
+    /// `tool_use` blocks: tool name and its JSON input.
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+/// `system` for the Messages API: with `cache`, a single text block carrying
+/// an ephemeral `cache_control` breakpoint, so the tools and the (large,
+/// call-invariant) system prompt are served from the prompt cache on repeat
+/// calls. Prompts under the model's minimum cacheable size are just not cached.
+pub(super) fn system_value(system: &str, cache: bool) -> Value {
+    if cache {
+        json!([{ "type": "text", "text": system, "cache_control": { "type": "ephemeral" } }])
+    } else {
+        Value::String(system.to_string())
+    }
 }
 
-fn split_instruction<'a>(ins: &'a Instruction) -> (String, String) {
+/// `turns` as Messages API messages. With `cache`, the first user turn (the
+/// request with its context) gets a breakpoint too, so a follow-up, which
+/// re-sends it ahead of the reply and the new text, reads it from the cache.
+pub(super) fn messages(turns: &[(&'static str, String)], cache: bool) -> Vec<Value> {
+    turns
+        .iter()
+        .enumerate()
+        .map(|(i, (role, content))| {
+            let content = if cache && i == 0 {
+                json!([{ "type": "text", "text": content, "cache_control": { "type": "ephemeral" } }])
+            } else {
+                Value::String(content.clone())
+            };
+            json!({ "role": role, "content": content })
+        })
+        .collect()
+}
+
+/// Debug line with the prompt-cache hit/write counts from a Messages API body.
+pub(super) fn cache_report(text: &str) -> Option<String> {
+    let u = serde_json::from_str::<UsageOnly>(text).ok()?.usage?;
+    Some(format!(
+        "input {} tokens, cache read {}, cache write {}",
+        u.input_tokens, u.cache_read_input_tokens, u.cache_creation_input_tokens
+    ))
+}
+
+pub(super) fn split_instruction(ins: &Instruction) -> (String, String) {
     let mut system = ins.system.clone();
     if let Some(dev) = &ins.developer {
         system.push_str("\n\nDeveloper notes:\n");
@@ -52,17 +178,50 @@ fn split_instruction<'a>(ins: &'a Instruction) -> (String, String) {
     (system, ins.user.clone())
 }
 
+/// User turn: the user instruction plus the request (task, context, limits)
+/// minus the instruction itself, which travels as `system`.
+pub(super) fn user_message(req: &LlmRequest, user: &str) -> Result<String> {
+    let mut payload = serde_json::to_value(req)?;
+    if let Some(obj) = payload.as_object_mut() {
+        obj.remove("instruction");
+    }
+    Ok(format!("{}\n\nRequest:\n{}", user, serde_json::to_string(&payload)?))
+}
+
+/// System prompt and the (role, content) turns for `req`: one user turn, or
+/// for a follow-up the previous request's turns, its reply and the new text.
+pub(super) fn turns(req: &LlmRequest) -> Result<(String, Vec<(&'static str, String)>)> {
+    if let Some(f) = &req.follow_up {
+        let (system, mut turns) = turns(&f.previous)?;
+        turns.push(("assistant", f.reply.clone()));
+        turns.push(("user", f.text.clone()));
+        return Ok((system, turns));
+    }
+    let (system, user) = split_instruction(&req.instruction);
+    Ok((system, vec![("user", user_message(req, &user)?)]))
+}
+
 #[async_trait]
 impl Provider for Anthropic {
     async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
         let url = format!("{}/v1/messages", self.api_base.trim_end_matches('/'));
-        let client = Client::builder().timeout(self.timeout).build()?;
-        let (system, user) = split_instruction(&req.instruction);
+        let client = self.timeouts.client()?;
+        let (system, turns) = turns(req)?;
+        let (tools, tool_choice) = if self.tool_use && req.wire_format == WireFormat::Json {
+            let (t, c) = tools(&req.mode);
+            (Some(t), Some(c))
+        } else {
+            (None, None)
+        };
         let body = MsgRequest {
             model: &self.model,
-            max_tokens: 4096,
-            messages: vec![Msg { role: "user", content: &user }],
-            system: Some(Box::leak(system.into_boxed_str())), // quick stable ref
+            max_tokens: req.sampling.max_output_tokens.unwrap_or(8192),
+            temperature: req.sampling.temperature,
+            top_p: req.sampling.top_p,
+            messages: messages(&turns, self.prompt_cache),
+            system: Some(system_value(&system, self.prompt_cache)),
+            tools,
+            tool_choice,
         };
 
         if debug {
@@ -78,25 +237,63 @@ impl Provider for Anthropic {
             .await
             .context("anthropic request failed")?;
 
+        let status = resp.status();
         let text = resp.text().await.context("anthropic read body failed")?;
         if debug {
             eprintln!("debug/anthropic: raw body:\n{}\n", text);
+            if let Some(r) = cache_report(&text) {
+                eprintln!("debug/anthropic: {}", r);
+            }
+        }
+        if !status.is_success() {
+            return Err(VibeError::Http { provider: "Anthropic", status, body: text }.into());
         }
+        super::usage::record_body(&text);
+        parse_messages_response(&text, req.wire_format)
+    }
+}
 
-        // Try to parse standard response
-        let parsed: MsgResponse = serde_json::from_str(&text)
-            .map_err(|e| anyhow!("anthropic response parse error: {}", e))?;
+/// Parse a Messages API body (Anthropic or Anthropic-on-Bedrock) into an LlmResponse.
+pub(super) fn parse_messages_response(text: &str, format: WireFormat) -> Result<LlmResponse> {
+    // Try to parse standard response
+    let parsed: MsgResponse = serde_json::from_str(text)
+        .map_err(|e| anyhow!("anthropic response parse error: {}", e))?;
 
-        let content = parsed
-            .content
-            .into_iter()
-            .find(|b| b.r#type == "text" || !b.text.is_empty())
-            .map(|b| b.text)
-            .ok_or_else(|| anyhow!("anthropic: empty content"))?;
+    // tool-use mode: the plan/answer arrives as the tool input, already
+    // structured; both tools in one reply make an answer+plan hybrid
+    let calls: Vec<&Block> = parsed.content.iter().filter(|b| b.r#type == "tool_use").collect();
+    if !calls.is_empty() {
+        let mut resp = LlmResponse { schema_version: "v1".into(), kind: Kind::Answer, plan: None, answer: None, usage: Default::default() };
+        for call in calls {
+            let input = call.input.clone().unwrap_or(Value::Null);
+            match call.name.as_str() {
+                PLAN_TOOL => {
+                    resp.kind = Kind::Plan;
+                    resp.plan = Some(serde_json::from_value(input).map_err(|e| anyhow!("{} input does not match Plan: {}", PLAN_TOOL, e))?);
+                }
+                ANSWER_TOOL => {
+                    resp.answer = Some(serde_json::from_value(input).map_err(|e| anyhow!("{} input does not match Answer: {}", ANSWER_TOOL, e))?);
+                }
+                other => return Err(anyhow!("anthropic: unexpected tool call '{}'", other)),
+            }
+        }
+        return Ok(resp);
+    }
 
-        let llm_resp: LlmResponse = serde_json::from_str(&content)
-            .map_err(|e| anyhow!("failed to parse LLM JSON: {}.\nContent was:\n{}", e, content))?;
+    let content = parsed
+        .content
+        .into_iter()
+        .filter(|b| b.r#type == "text")
+        .map(|b| b.text)
+        .collect::<Vec<_>>()
+        .join("");
+    if content.trim().is_empty() {
+        return Err(anyhow!("anthropic: empty content"));
+    }
 
-        Ok(llm_resp)
+    if format == WireFormat::Yaml {
+        return super::yaml::parse(&content);
     }
+
+    super::jsonfix::parse_response(&content)
 }
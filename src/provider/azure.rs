@@ -0,0 +1,55 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_API_VERSION: &str = "2024-06-01";
+
+/// `[azure]` config table; unset fields fall back to `AZURE_OPENAI_ENDPOINT`,
+/// `AZURE_OPENAI_DEPLOYMENT` and `AZURE_OPENAI_API_VERSION`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AzureConfig {
+    pub endpoint: Option<String>,
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+}
+
+/// A resolved Azure OpenAI chat deployment.
+#[derive(Debug, Clone)]
+pub struct AzureTarget {
+    pub endpoint: String,
+    pub deployment: String,
+    pub api_version: String,
+}
+
+impl AzureTarget {
+    /// Resolve from config, then env. `api_base` (provider profile) wins for
+    /// the endpoint; the deployment defaults to the model name.
+    pub fn resolve(cfg: &AzureConfig, api_base: Option<String>, model: &str) -> Result<Self> {
+        let env = |k: &str| std::env::var(k).ok().filter(|v| !v.is_empty());
+        let endpoint = api_base
+            .or_else(|| cfg.endpoint.clone())
+            .or_else(|| env("AZURE_OPENAI_ENDPOINT"))
+            .ok_or_else(|| anyhow!("Azure endpoint not set (azure.endpoint or AZURE_OPENAI_ENDPOINT)"))?;
+        Ok(Self {
+            endpoint,
+            deployment: cfg
+                .deployment
+                .clone()
+                .or_else(|| env("AZURE_OPENAI_DEPLOYMENT"))
+                .unwrap_or_else(|| model.to_string()),
+            api_version: cfg
+                .api_version
+                .clone()
+                .or_else(|| env("AZURE_OPENAI_API_VERSION"))
+                .unwrap_or_else(|| DEFAULT_API_VERSION.to_string()),
+        })
+    }
+
+    pub fn chat_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint.trim_end_matches('/'),
+            self.deployment,
+            self.api_version
+        )
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+
+use crate::errors::VibeError;
+use crate::wire::{LlmRequest, LlmResponse};
+use super::anthropic::{cache_report, messages, parse_messages_response, system_value, turns};
+use super::sigv4::{self, AwsCredentials};
+use super::{Provider, Timeouts};
+
+pub const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+
+/// Anthropic models on AWS Bedrock (`InvokeModel`), SigV4-signed with static
+/// credentials from the environment.
+pub struct Bedrock {
+    pub model: String,
+    pub region: String,
+    pub creds: AwsCredentials,
+    pub timeouts: Timeouts,
+    /// Override for the runtime host, e.g. a VPC endpoint.
+    pub endpoint: Option<String>,
+    /// Cache breakpoint on the system prompt (Claude models that support it).
+    pub prompt_cache: bool,
+}
+
+impl Bedrock {
+    /// Region from AWS_REGION / AWS_DEFAULT_REGION (default us-east-1).
+    pub fn from_env(model: String, timeouts: Timeouts, endpoint: Option<String>) -> Result<Self> {
+        let creds = AwsCredentials::from_env()
+            .ok_or_else(|| anyhow!("AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY env vars are not set"))?;
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        Ok(Self { model, region, creds, timeouts, endpoint, prompt_cache: false })
+    }
+
+    pub fn with_prompt_cache(mut self, on: bool) -> Self {
+        self.prompt_cache = on;
+        self
+    }
+
+    fn host(&self) -> String {
+        match &self.endpoint {
+            Some(e) => e.trim_start_matches("https://").trim_end_matches('/').to_string(),
+            None => format!("bedrock-runtime.{}.amazonaws.com", self.region),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Bedrock {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let (system, turns) = turns(req)?;
+        let messages = messages(&turns, self.prompt_cache);
+        let mut body = json!({
+            "anthropic_version": BEDROCK_ANTHROPIC_VERSION,
+            "max_tokens": req.sampling.max_output_tokens.unwrap_or(8192),
+            "system": system_value(&system, self.prompt_cache),
+            "messages": messages,
+        });
+        if let Some(t) = req.sampling.temperature {
+            body["temperature"] = json!(t);
+        }
+        if let Some(p) = req.sampling.top_p {
+            body["top_p"] = json!(p);
+        }
+        let body = serde_json::to_vec(&body)?;
+
+        let host = self.host();
+        // model ids contain ':' (e.g. `...-v1:0`), which must be encoded in the path
+        let path = format!("/model/{}/invoke", sigv4::encode_segment(&self.model));
+        let url = format!("https://{}{}", host, path);
+        if debug {
+            eprintln!("debug/bedrock: POST {}", url);
+        }
+
+        let client = self.timeouts.client()?;
+        let mut builder = client
+            .post(&url)
+            .header("content-type", "application/json")
+            .header("accept", "application/json");
+        for (k, v) in sigv4::sign_post(&self.creds, &self.region, "bedrock", &host, &path, &body, Utc::now()) {
+            builder = builder.header(k, v);
+        }
+        let resp = builder.body(body).send().await.context("bedrock request failed")?;
+
+        let status = resp.status();
+        let text = resp.text().await.context("bedrock read body failed")?;
+        if debug {
+            eprintln!("debug/bedrock: raw body:\n{}\n", text);
+            if let Some(r) = cache_report(&text) {
+                eprintln!("debug/bedrock: {}", r);
+            }
+        }
+        if !status.is_success() {
+            return Err(VibeError::Http { provider: "Bedrock", status, body: text }.into());
+        }
+        super::usage::record_body(&text);
+        parse_messages_response(&text, req.wire_format)
+    }
+}
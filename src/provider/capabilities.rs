@@ -0,0 +1,255 @@
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::ProviderKind;
+use crate::config::Config;
+
+/// Where limits found at runtime are remembered, under the project root.
+const LEARNED_FILE: &str = ".vibe/capabilities.json";
+
+/// How a provider asks for a JSON reply, most reliable first.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StructuredOutput {
+    /// Constrained to `wire::response_json_schema()` (structured outputs).
+    JsonSchema,
+    /// Any valid JSON object (`response_format: json_object`, Ollama `format: json`).
+    JsonObject,
+    /// Nothing on the request; the prompt asks for JSON and `jsonfix` digs it out.
+    Prompt,
+}
+
+impl StructuredOutput {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StructuredOutput::JsonSchema => "json-schema",
+            StructuredOutput::JsonObject => "json-object",
+            StructuredOutput::Prompt => "prompt",
+        }
+    }
+
+    /// Next strategy to try when the endpoint rejects this one.
+    pub fn weaker(self) -> Option<Self> {
+        match self {
+            StructuredOutput::JsonSchema => Some(StructuredOutput::JsonObject),
+            StructuredOutput::JsonObject => Some(StructuredOutput::Prompt),
+            StructuredOutput::Prompt => None,
+        }
+    }
+}
+
+/// What a model/endpoint supports. `None` is unknown; layers are merged
+/// field by field (built-in table, then learned, then config).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_object: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<bool>,
+}
+
+impl Capabilities {
+    const fn new(json_schema: Option<bool>, json_object: Option<bool>, tools: Option<bool>, vision: Option<bool>) -> Self {
+        Self { json_schema, json_object, tools, vision, streaming: Some(true) }
+    }
+
+    /// `other`'s known fields win.
+    fn overlay(self, other: &Capabilities) -> Self {
+        Self {
+            json_schema: other.json_schema.or(self.json_schema),
+            json_object: other.json_object.or(self.json_object),
+            tools: other.tools.or(self.tools),
+            vision: other.vision.or(self.vision),
+            streaming: other.streaming.or(self.streaming),
+        }
+    }
+
+    /// The most reliable strategy not known to fail. A json_schema of
+    /// unknown support isn't tried; json_object is, since a rejection is
+    /// caught and learned (see `Learn`).
+    pub fn structured_output(&self) -> StructuredOutput {
+        if self.json_schema == Some(true) {
+            StructuredOutput::JsonSchema
+        } else if self.json_object != Some(false) {
+            StructuredOutput::JsonObject
+        } else {
+            StructuredOutput::Prompt
+        }
+    }
+}
+
+const Y: Option<bool> = Some(true);
+const N: Option<bool> = Some(false);
+const U: Option<bool> = None;
+
+/// Known models, first match wins: (provider, model pattern, capabilities).
+/// Patterns take `*` as a wildcard.
+const BUILTIN: &[(&str, &str, Capabilities)] = &[
+    // the o1 previews reject response_format and tools altogether
+    ("openai", "o1-preview*", Capabilities::new(N, N, N, N)),
+    ("openai", "o1-mini*", Capabilities::new(N, N, N, N)),
+    ("openai", "gpt-4o*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "gpt-4.1*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "gpt-5*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "o1*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "o3*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "o4*", Capabilities::new(Y, Y, Y, Y)),
+    ("openai", "gpt-4-turbo*", Capabilities::new(N, Y, Y, Y)),
+    ("openai", "gpt-3.5-turbo*", Capabilities::new(N, Y, Y, N)),
+    ("openai", "gpt-4*", Capabilities::new(N, N, Y, N)),
+    // LM Studio rejects `json_object`; it takes (and enforces) a json_schema
+    ("lmstudio", "*", Capabilities::new(Y, N, U, U)),
+    ("groq", "*", Capabilities::new(N, Y, Y, U)),
+    ("mistral", "pixtral*", Capabilities::new(N, Y, Y, Y)),
+    ("mistral", "*", Capabilities::new(N, Y, Y, N)),
+    ("ollama", "*", Capabilities::new(U, Y, U, U)),
+    ("anthropic", "*", Capabilities::new(N, N, Y, Y)),
+    ("bedrock", "*", Capabilities::new(N, N, Y, Y)),
+    ("vllm", "*", Capabilities::new(Y, Y, U, U)),
+    ("tgi", "*", Capabilities::new(Y, Y, U, U)),
+];
+
+/// `*` matches any run of characters; case-insensitive.
+fn glob_match(pattern: &str, s: &str) -> bool {
+    let (pattern, s) = (pattern.to_lowercase(), s.to_lowercase());
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == s;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !s.starts_with(first) || s.len() < first.len() + last.len() || !s.ends_with(last) {
+        return false;
+    }
+    let mut rest = &s[first.len()..s.len() - last.len()];
+    for mid in &parts[1..parts.len() - 1] {
+        match rest.find(mid) {
+            Some(i) => rest = &rest[i + mid.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Provider name as in the table and in `provider:model` keys.
+fn label(kind: &ProviderKind) -> String {
+    match kind {
+        ProviderKind::OpenAI => "openai".to_string(),
+        other => super::provider_label(other),
+    }
+}
+
+/// `provider:model`, the key of learned and configured entries.
+pub fn key(kind: &ProviderKind, model: &str) -> String {
+    format!("{}:{}", label(kind), model)
+}
+
+fn builtin(provider: &str, model: &str) -> Capabilities {
+    // OpenAI model names mean the same on Azure and OpenRouter (`openai/gpt-4o`)
+    let (family, model) = match (provider, model.split_once('/')) {
+        ("azure", _) => ("openai", model),
+        ("openrouter", Some(("openai", m))) => ("openai", m),
+        _ => (provider, model),
+    };
+    BUILTIN
+        .iter()
+        .find(|(p, m, _)| *p == family && glob_match(m, model))
+        .map(|(_, _, c)| *c)
+        .unwrap_or_default()
+}
+
+fn learned_path(root: &Path) -> PathBuf {
+    root.join(LEARNED_FILE)
+}
+
+fn load_learned(root: &Path) -> BTreeMap<String, Capabilities> {
+    fs::read_to_string(learned_path(root)).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+/// Capabilities of `model` on `kind`: the built-in table, overlaid with what
+/// earlier runs learned, overlaid with config `model_capabilities` (keys are
+/// `provider:model` or a bare model, `*` allowed; more specific keys last).
+pub fn resolve(kind: &ProviderKind, model: &str, cfg: &Config) -> Capabilities {
+    let key = key(kind, model);
+    let mut caps = builtin(&label(kind), model);
+    if matches!(kind, ProviderKind::OpenAI | ProviderKind::Azure) {
+        // `openai_json_schema` off rules structured outputs out; on, it's
+        // assumed for models (and Azure deployments) the table doesn't know
+        if !cfg.openai_json_schema {
+            caps.json_schema = Some(false);
+        } else {
+            caps.json_schema.get_or_insert(true);
+        }
+    }
+    if let Some(l) = load_learned(Path::new(&cfg.root)).get(&key) {
+        caps = caps.overlay(l);
+    }
+    let mut configured: Vec<(&String, &Capabilities)> = cfg
+        .model_capabilities
+        .iter()
+        .filter(|(pat, _)| if pat.contains(':') { glob_match(pat, &key) } else { glob_match(pat, model) })
+        .collect();
+    configured.sort_by_key(|(pat, _)| (pat.contains(':'), pat.len()));
+    for (_, c) in configured {
+        caps = caps.overlay(c);
+    }
+    caps
+}
+
+/// The structured-output strategy for a provider: config
+/// `structured_output` when set, else the best one `caps` allows.
+pub fn strategy(caps: &Capabilities, cfg: &Config) -> StructuredOutput {
+    cfg.structured_output.unwrap_or_else(|| caps.structured_output())
+}
+
+/// Records, for the next runs, that an endpoint rejected a strategy.
+#[derive(Debug, Clone)]
+pub struct Learn {
+    root: PathBuf,
+    key: String,
+}
+
+impl Learn {
+    pub fn new(root: &str, kind: &ProviderKind, model: &str) -> Self {
+        Self { root: PathBuf::from(root), key: key(kind, model) }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn rejected(&self, s: StructuredOutput) {
+        let mut all = load_learned(&self.root);
+        let entry = all.entry(self.key.clone()).or_default();
+        match s {
+            StructuredOutput::JsonSchema => entry.json_schema = Some(false),
+            StructuredOutput::JsonObject => entry.json_object = Some(false),
+            StructuredOutput::Prompt => return,
+        }
+        let path = learned_path(&self.root);
+        let written = path
+            .parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| fs::write(&path, serde_json::to_string_pretty(&all).unwrap_or_default()));
+        if let Err(e) = written {
+            eprintln!("warning: could not record capabilities in {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Whether an error reply says the request's `response_format` is unsupported.
+pub fn rejects_response_format(status: reqwest::StatusCode, body: &str) -> bool {
+    if !(status == reqwest::StatusCode::BAD_REQUEST || status == reqwest::StatusCode::UNPROCESSABLE_ENTITY) {
+        return false;
+    }
+    let body = body.to_lowercase();
+    ["response_format", "json_schema", "json_object", "structured output", "json mode"].iter().any(|p| body.contains(p))
+}
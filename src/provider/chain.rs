@@ -0,0 +1,36 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::wire::{LlmRequest, LlmResponse};
+use super::{DynProvider, Provider};
+
+/// `provider_chain`: send to each provider in order until one returns a
+/// parsed response. Entries are already wrapped in their own retry layer.
+pub struct Chain {
+    links: Vec<(String, DynProvider)>,
+}
+
+impl Chain {
+    pub fn new(links: Vec<(String, DynProvider)>) -> Self {
+        Self { links }
+    }
+}
+
+#[async_trait]
+impl Provider for Chain {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let mut last_err = None;
+        for (i, (label, prov)) in self.links.iter().enumerate() {
+            match prov.send(req, debug).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) => {
+                    if let Some((next, _)) = self.links.get(i + 1) {
+                        crate::ux::print_fallback_status(label, next, &e);
+                    }
+                    last_err = Some(e.context(format!("provider {} failed", label)));
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow!("provider_chain is empty")))
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+
+use crate::wire::LlmResponse;
+
+/// Body of the first ``` fenced block (any language tag), or `s` unchanged.
+pub fn strip_fences(s: &str) -> &str {
+    let Some(open) = s.find("```") else { return s };
+    let after = &s[open + 3..];
+    // skip the info string (`json`, `JSON5`...) up to the end of the line
+    let body = after.find('\n').map(|n| &after[n + 1..]).unwrap_or(after);
+    match body.find("```") {
+        Some(close) => &body[..close],
+        None => body,
+    }
+}
+
+/// First balanced top-level `{...}` in `s`. Braces inside strings (either
+/// quote style) don't count.
+pub fn extract_first_json_object(s: &str) -> Option<String> {
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' if start.is_some() => quote = Some(c),
+            '{' => {
+                start.get_or_insert(i);
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return start.map(|st| s[st..=i].to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Fix the usual near-JSON slips: single-quoted strings become double-quoted
+/// (inner `"` escaped, `\'` unescaped) and trailing commas before `}`/`]`
+/// are dropped. Text inside double-quoted strings is left alone.
+pub fn repair(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    out.push(c);
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\'' => {
+                out.push('"');
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if chars.peek() == Some(&'\'') => {
+                            out.push('\'');
+                            chars.next();
+                        }
+                        '\\' => {
+                            out.push(c);
+                            if let Some(n) = chars.next() {
+                                out.push(n);
+                            }
+                        }
+                        '"' => out.push_str("\\\""),
+                        '\'' => break,
+                        _ => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            ',' => {
+                let rest = chars.clone().find(|c| !c.is_whitespace());
+                if !matches!(rest, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse a model's reply into an LlmResponse, as leniently as it takes:
+/// as is, without code fences, the first balanced object, then each of
+/// those repaired. Used by every adapter for text (non-tool) replies.
+pub fn parse_response(content: &str) -> Result<LlmResponse> {
+    let unfenced = strip_fences(content);
+    let mut candidates: Vec<String> = vec![content.to_string(), unfenced.to_string()];
+    candidates.extend(extract_first_json_object(unfenced));
+    candidates.extend(extract_first_json_object(content));
+    let repaired: Vec<String> = candidates.iter().map(|c| repair(c)).collect();
+    candidates
+        .iter()
+        .chain(repaired.iter())
+        .find_map(|c| serde_json::from_str::<LlmResponse>(c.trim()).ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "Model did not return a valid JSON response body.\n--- content start ---\n{}\n--- content end ---",
+                content
+            )
+        })
+}
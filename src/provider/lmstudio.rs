@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use super::Timeouts;
+
+/// LM Studio's local server (Developer tab -> Start Server).
+pub const DEFAULT_API_BASE: &str = "http://localhost:1234/v1";
+
+/// `--api-base`/a profile, then `LM_STUDIO_BASE_URL`, then the default port.
+pub fn api_base(api_base: Option<String>) -> String {
+    api_base
+        .or_else(|| std::env::var("LM_STUDIO_BASE_URL").ok())
+        .unwrap_or_else(|| DEFAULT_API_BASE.to_string())
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Model ids the server offers (`GET /models`), embedding models left out.
+pub async fn list_models(api_base: &str, timeouts: Timeouts) -> Result<Vec<String>> {
+    let url = format!("{}/models", api_base.trim_end_matches('/'));
+    let resp = timeouts
+        .client()?
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("LM Studio not reachable at {} (is the local server running?)", api_base))?;
+    let status = resp.status();
+    let text = resp.text().await?;
+    if !status.is_success() {
+        return Err(anyhow!("LM Studio {} returned {}: {}", url, status, text));
+    }
+    let list: ModelList = serde_json::from_str(&text).with_context(|| format!("parsing {}", url))?;
+    Ok(list.data.into_iter().map(|m| m.id).filter(|id| !id.contains("embed")).collect())
+}
+
+/// The model to use when `--model` is omitted: the only one available, or
+/// the user's pick from the list.
+pub async fn pick_model(api_base: &str, timeouts: Timeouts) -> Result<String> {
+    let models = list_models(api_base, timeouts).await?;
+    match models.len() {
+        0 => Err(anyhow!("LM Studio at {} has no models; download one in LM Studio or pass --model", api_base)),
+        1 => {
+            println!("Using LM Studio model {}", models[0]);
+            Ok(models[0].clone())
+        }
+        _ => crate::ux::choose("LM Studio model", &models)
+            .map(|i| models[i].clone())
+            .ok_or_else(|| anyhow!("no model selected; pass --model")),
+    }
+}
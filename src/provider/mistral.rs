@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::VibeError;
+use crate::wire::{LlmRequest, LlmResponse, WireFormat};
+use super::anthropic::turns;
+use super::{Provider, Timeouts};
+
+pub const DEFAULT_API_BASE: &str = "https://api.mistral.ai/v1";
+
+/// Mistral La Plateforme chat completions (mistral-large, codestral, ...).
+/// Codestral keys issued for `codestral.mistral.ai` work via `api_base`.
+pub struct Mistral {
+    pub model: String,
+    pub api_key: String,
+    pub timeouts: Timeouts,
+    pub api_base: String,
+    /// Send `response_format: json_object` for JSON replies.
+    pub json_mode: bool,
+}
+
+impl Mistral {
+    /// Key from `api_key` or `MISTRAL_API_KEY` (env or keychain); base from `api_base`,
+    /// `MISTRAL_BASE_URL` or the default.
+    pub fn from_env(model: String, timeouts: Timeouts, api_key: Option<String>, api_base: Option<String>) -> Result<Self> {
+        let api_key = match api_key {
+            Some(k) => k,
+            None => crate::credentials::require("MISTRAL_API_KEY")?,
+        };
+        Ok(Self {
+            model,
+            api_key,
+            timeouts,
+            api_base: api_base
+                .or_else(|| std::env::var("MISTRAL_BASE_URL").ok())
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string()),
+            json_mode: true,
+        })
+    }
+
+    pub fn with_json_mode(mut self, on: bool) -> Self {
+        self.json_mode = on;
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Msg<'a>>,
+    temperature: f32,
+    top_p: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    r#type: &'static str,
+}
+
+#[derive(Serialize)]
+struct Msg<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Content,
+}
+
+/// `content` is a plain string for most models, a list of typed chunks for
+/// reasoning models (magistral).
+#[derive(Deserialize, Default)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Chunks(Vec<Chunk>),
+    #[default]
+    Empty,
+}
+
+#[derive(Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    r#type: String,
+    #[serde(default)]
+    text: String,
+}
+
+impl Content {
+    fn into_text(self) -> String {
+        match self {
+            Content::Text(s) => s,
+            Content::Chunks(chunks) => chunks
+                .into_iter()
+                .filter(|c| c.r#type == "text")
+                .map(|c| c.text)
+                .collect::<Vec<_>>()
+                .join(""),
+            Content::Empty => String::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Mistral {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let url = format!("{}/chat/completions", self.api_base.trim_end_matches('/'));
+        let client = self.timeouts.client()?;
+        // no developer role on Mistral: developer notes are folded into system
+        let (system, turns) = turns(req)?;
+        let body = ChatRequest {
+            model: &self.model,
+            messages: std::iter::once(Msg { role: "system", content: &system })
+                .chain(turns.iter().map(|(role, content)| Msg { role, content }))
+                .collect(),
+            temperature: req.sampling.temperature.unwrap_or(0.0),
+            top_p: req.sampling.top_p.unwrap_or(1.0),
+            max_tokens: req.sampling.max_output_tokens,
+            response_format: (req.wire_format == WireFormat::Json && self.json_mode).then_some(ResponseFormat { r#type: "json_object" }),
+        };
+
+        if debug {
+            eprintln!("debug/mistral: POST {}", url);
+        }
+
+        let resp = client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("mistral request failed")?;
+
+        let status = resp.status();
+        let text = resp.text().await.context("mistral read body failed")?;
+        if debug {
+            eprintln!("debug/mistral: raw body:\n{}\n", text);
+        }
+        if !status.is_success() {
+            return Err(VibeError::Http { provider: "Mistral", status, body: text }.into());
+        }
+        super::usage::record_body(&text);
+
+        let parsed: ChatResponse =
+            serde_json::from_str(&text).map_err(|e| anyhow!("mistral response parse error: {}", e))?;
+        let content = parsed
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content.into_text())
+            .unwrap_or_default();
+        if content.trim().is_empty() {
+            return Err(anyhow!("mistral: empty content"));
+        }
+
+        if req.wire_format == WireFormat::Yaml {
+            return super::yaml::parse(&content);
+        }
+
+        // json_object mode can still wrap output in a code fence on some models
+        super::jsonfix::parse_response(&content)
+    }
+}
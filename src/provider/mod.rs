@@ -1,12 +1,30 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use std::time::Duration;
 
-use crate::cli::ProviderKind;
-use crate::wire::{LlmRequest, LlmResponse};
+use crate::cli::{OpenAIMessages, ProviderKind};
+use crate::config::Config;
+use crate::wire::{LlmRequest, LlmResponse, WireFormat};
 
 pub mod openai;
 pub mod anthropic;
 pub mod ollama;
+pub mod azure;
+pub mod bedrock;
+pub mod mistral;
+pub mod retry;
+pub mod chain;
+pub mod yaml;
+pub mod tokens;
+pub mod replay;
+pub mod ratelimit;
+pub mod usage;
+pub mod jsonfix;
+pub mod selfhosted;
+pub mod race;
+pub mod lmstudio;
+pub mod capabilities;
+mod sigv4;
 
 #[async_trait]
 pub trait Provider: Send + Sync {
@@ -15,21 +33,254 @@ pub trait Provider: Send + Sync {
 
 pub type DynProvider = Box<dyn Provider + Send + Sync>;
 
+/// Key/base URL overrides (from `--api-base` or a provider profile); adapters
+/// fall back to their own env vars and default hosts.
+#[derive(Debug, Clone, Default)]
+pub struct Endpoint {
+    pub api_key: Option<String>,
+    pub api_base: Option<String>,
+}
+
+/// HTTP timeouts for provider clients: connecting fails fast so a dead
+/// endpoint is reported in seconds, an optional limit on a stalled read, and
+/// the overall cap on one request (`--timeout-secs`).
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub read: Option<Duration>,
+    pub overall: Duration,
+}
+
+impl Timeouts {
+    pub fn new(overall_secs: u64, cfg: &Config) -> Self {
+        Self {
+            connect: Duration::from_secs(cfg.connect_timeout_secs),
+            read: cfg.read_timeout_secs.map(Duration::from_secs),
+            overall: Duration::from_secs(overall_secs),
+        }
+    }
+
+    pub fn client(&self) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect.min(self.overall))
+            .timeout(self.overall);
+        if let Some(read) = self.read {
+            builder = builder.read_timeout(read);
+        }
+        builder.build()
+    }
+}
+
+/// The provider for a run, wrapped in the retry layer. With `provider_chain`
+/// set in config, its entries (`kind:model`) replace `kind`/`model` and are
+/// tried in order, each with its own retries.
 pub fn make_provider(
     kind: ProviderKind,
     model: String,
     timeout_secs: u64,
-    _ollama_url: Option<String>,
+    openai_messages: OpenAIMessages,
+    endpoint: Endpoint,
+    cfg: &Config,
+) -> Result<DynProvider> {
+    let prov = make_chain(kind, model, timeout_secs, openai_messages, endpoint, cfg)?;
+    Ok(match cfg.wire_format {
+        WireFormat::Json => prov,
+        WireFormat::Yaml => yaml::YamlWire::wrap(prov),
+    })
+}
+
+fn make_chain(
+    kind: ProviderKind,
+    model: String,
+    timeout_secs: u64,
+    openai_messages: OpenAIMessages,
+    endpoint: Endpoint,
+    cfg: &Config,
+) -> Result<DynProvider> {
+    if cfg.provider_chain.is_empty() {
+        let label = provider_label(&kind);
+        let metered_model = model.clone();
+        let prov = make_budgeted(kind, model, timeout_secs, openai_messages, endpoint, cfg)?;
+        let prov = ratelimit::RateLimited::wrap(prov, &label, cfg.rate_limit.clone());
+        return Ok(usage::Metered::wrap(retry::Retrying::wrap(prov, cfg.retry.clone()), &metered_model));
+    }
+    let links = make_links(&cfg.provider_chain, "provider_chain", (&kind, &endpoint), timeout_secs, openai_messages, cfg)?;
+    Ok(Box::new(chain::Chain::new(links)))
+}
+
+/// `kind:model` / `@profile` entries as labelled providers, each with its
+/// own rate limit, retries and metering.
+fn make_links(
+    entries: &[String],
+    setting: &str,
+    run: (&ProviderKind, &Endpoint),
+    timeout_secs: u64,
+    openai_messages: OpenAIMessages,
+    cfg: &Config,
+) -> Result<Vec<(String, DynProvider)>> {
+    let mut links = Vec::new();
+    for entry in entries {
+        let (kind, model, endpoint) = resolve_chain_entry(entry, run, cfg)?;
+        let label = provider_label(&kind);
+        let metered_model = model.clone();
+        let prov = make_budgeted(kind, model, timeout_secs, openai_messages, endpoint, cfg)
+            .with_context(|| format!("{} entry '{}'", setting, entry))?;
+        let prov = ratelimit::RateLimited::wrap(prov, &label, cfg.rate_limit.clone());
+        let prov = retry::Retrying::wrap(prov, cfg.retry.clone());
+        links.push((entry.clone(), usage::Metered::wrap(prov, &metered_model)));
+    }
+    Ok(links)
+}
+
+/// PLAN provider racing the `plan_race` entries (see `race::Race`); `run` is
+/// the run's own provider and endpoint, see `resolve_chain_entry`.
+pub fn make_race(
+    run: (&ProviderKind, &Endpoint),
+    timeout_secs: u64,
+    openai_messages: OpenAIMessages,
+    cfg: &Config,
+) -> Result<DynProvider> {
+    if cfg.plan_race.len() < 2 {
+        return Err(anyhow!("plan_race needs at least two <provider>:<model> entries"));
+    }
+    let links = make_links(&cfg.plan_race, "plan_race", run, timeout_secs, openai_messages, cfg)?;
+    let prov: DynProvider = Box::new(race::Race::new(links));
+    Ok(match cfg.wire_format {
+        WireFormat::Json => prov,
+        WireFormat::Yaml => yaml::YamlWire::wrap(prov),
+    })
+}
+
+/// Provider name as on the command line (`openai`, `anthropic`...).
+fn provider_label(kind: &ProviderKind) -> String {
+    clap::ValueEnum::to_possible_value(kind).map(|v| v.get_name().to_string()).unwrap_or_default()
+}
+
+/// A `provider_chain`/`plan_race` entry with the key and base URL to use:
+/// `@name` takes provider, model and endpoint from that provider profile;
+/// `kind:model` on the run's own provider (`run`) shares its endpoint
+/// (`--api-base`, `--profile-provider`), other providers use their env vars.
+pub fn resolve_chain_entry(entry: &str, run: (&ProviderKind, &Endpoint), cfg: &Config) -> Result<(ProviderKind, String, Endpoint)> {
+    if let Some(name) = entry.trim().strip_prefix('@') {
+        let p = cfg.provider_profile(name)?;
+        let model = p.model.clone().ok_or_else(|| anyhow!("entry '{}': provider profile '{}' has no model", entry, name))?;
+        let endpoint = Endpoint { api_key: p.resolve_api_key(), api_base: p.api_base.clone() };
+        return Ok((p.provider.clone(), model, endpoint));
+    }
+    let (kind, model) = parse_chain_entry(entry)?;
+    let endpoint = if provider_label(&kind) == provider_label(run.0) { run.1.clone() } else { Endpoint::default() };
+    Ok((kind, model, endpoint))
+}
+
+/// `openai:gpt-4.1` -> (OpenAI, "gpt-4.1"); the model may contain ':' (`ollama:llama3:8b`).
+pub fn parse_chain_entry(entry: &str) -> Result<(ProviderKind, String)> {
+    let (kind, model) = entry
+        .split_once(':')
+        .ok_or_else(|| anyhow!("provider_chain entry '{}' must be <provider>:<model>", entry))?;
+    let kind = <ProviderKind as clap::ValueEnum>::from_str(kind.trim(), true)
+        .map_err(|_| anyhow!("provider_chain entry '{}': unknown provider '{}'", entry, kind))?;
+    Ok((kind, model.trim().to_string()))
+}
+
+/// `make_single` behind the token budget check for that model.
+fn make_budgeted(
+    kind: ProviderKind,
+    model: String,
+    timeout_secs: u64,
+    openai_messages: OpenAIMessages,
+    endpoint: Endpoint,
+    cfg: &Config,
+) -> Result<DynProvider> {
+    let budget_model = model.clone();
+    let prov = make_single(kind, model, timeout_secs, openai_messages, endpoint, cfg)?;
+    Ok(tokens::Budgeted::wrap(prov, &budget_model, cfg.context_window, cfg.trim_snapshot_to_fit))
+}
+
+fn make_single(
+    kind: ProviderKind,
+    model: String,
+    timeout_secs: u64,
+    openai_messages: OpenAIMessages,
+    endpoint: Endpoint,
+    cfg: &Config,
 ) -> Result<DynProvider> {
+    let azure = &cfg.azure;
+    let Endpoint { api_key, api_base } = endpoint;
+    let timeouts = Timeouts::new(timeout_secs, cfg);
+    let caps = capabilities::resolve(&kind, &model, cfg);
+    let structured = capabilities::strategy(&caps, cfg);
+    let learn = capabilities::Learn::new(&cfg.root, &kind, &model);
     match kind {
-        ProviderKind::OpenAI => Ok(Box::new(openai::OpenAIProvider::new(
-            model,
-            timeout_secs,
-        ))),
-
-        // Keep these as explicit errors for now so the binary compiles even if
-        // Anthropic/Ollama adapters are not implemented in your workspace.
-        ProviderKind::Anthropic => Err(anyhow!("Anthropic provider not implemented in this build")),
-        ProviderKind::Ollama => Err(anyhow!("Ollama provider not implemented in this build")),
+        ProviderKind::OpenAI => Ok(Box::new(
+            openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                .with_endpoint(api_key, api_base)
+                .with_structured(structured, learn),
+        )),
+        // llama.cpp server, LM Studio, vLLM, LiteLLM...: base URL required, key optional
+        ProviderKind::OpenAICompatible => {
+            let base = api_base.ok_or_else(|| anyhow!("--provider openai-compatible requires --api-base <url>"))?;
+            Ok(Box::new(
+                openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                    .with_endpoint(api_key, Some(base))
+                    .with_optional_key()
+                    .with_structured(structured, learn),
+            ))
+        }
+        ProviderKind::OpenRouter => Ok(Box::new(
+            openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                .with_endpoint(api_key, Some(api_base.unwrap_or_else(|| openai::OPENROUTER_API_BASE.to_string())))
+                .with_key_var("OPENROUTER_API_KEY")
+                .with_structured(structured, learn),
+        )),
+        ProviderKind::Groq => Ok(Box::new(
+            openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                .with_endpoint(api_key, Some(api_base.unwrap_or_else(|| openai::GROQ_API_BASE.to_string())))
+                .with_key_var("GROQ_API_KEY")
+                .with_structured(structured, learn),
+        )),
+        ProviderKind::Azure => {
+            let target = azure::AzureTarget::resolve(azure, api_base, &model)?;
+            Ok(Box::new(
+                openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                    .with_endpoint(api_key, None)
+                    .with_structured(structured, learn)
+                    .with_azure(target),
+            ))
+        }
+        ProviderKind::Bedrock => Ok(Box::new(
+            bedrock::Bedrock::from_env(model, timeouts, api_base)?.with_prompt_cache(cfg.prompt_cache),
+        )),
+        ProviderKind::Anthropic => Ok(Box::new(
+            anthropic::Anthropic::from_env(model, timeouts, api_key, api_base)?
+                .with_tool_use(cfg.anthropic_tool_use && caps.tools != Some(false))
+                .with_prompt_cache(cfg.prompt_cache),
+        )),
+        ProviderKind::Mistral => Ok(Box::new(
+            mistral::Mistral::from_env(model, timeouts, api_key, api_base)?
+                .with_json_mode(structured != capabilities::StructuredOutput::Prompt),
+        )),
+        ProviderKind::Ollama => Ok(Box::new(
+            ollama::Ollama::new(model, timeouts, cfg.ollama_url.clone().or(api_base)).with_structured(structured),
+        )),
+        ProviderKind::Vllm | ProviderKind::Tgi => {
+            let server = if matches!(kind, ProviderKind::Vllm) { selfhosted::Server::Vllm } else { selfhosted::Server::Tgi };
+            Ok(Box::new(
+                selfhosted::SelfHosted::new(server, model, timeouts, api_key, api_base)
+                    .with_guided(cfg.guided_decoding && structured == capabilities::StructuredOutput::JsonSchema),
+            ))
+        }
+        ProviderKind::LmStudio => Ok(Box::new(
+            openai::OpenAIProvider::new(model, timeouts, openai_messages)
+                .with_endpoint(api_key, Some(lmstudio::api_base(api_base)))
+                .with_key_var("LM_STUDIO_API_KEY")
+                .with_optional_key()
+                .with_structured(structured, learn),
+        )),
+        ProviderKind::Replay => {
+            let tx = cfg.replay_tx.as_deref().ok_or_else(|| anyhow!("--provider replay requires --replay-tx <uuid>"))?;
+            let tx = uuid::Uuid::parse_str(tx).with_context(|| format!("invalid --replay-tx '{}'", tx))?;
+            let stages: Vec<String> = cfg.stages.iter().map(|s| s.name.clone()).collect();
+            Ok(Box::new(replay::Replay::load(std::path::Path::new(&cfg.root), tx, &stages)?))
+        }
     }
 }
@@ -1,16 +1,36 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
 
-use crate::wire::{Instruction, LlmRequest, LlmResponse};
-use super::Provider;
+use crate::errors::VibeError;
+use crate::wire::{Instruction, LlmRequest, LlmResponse, WireFormat};
+use super::capabilities::StructuredOutput;
+use super::{Provider, Timeouts};
+
+pub const DEFAULT_URL: &str = "http://localhost:11434";
 
 pub struct Ollama {
     pub model: String,
     pub url: String,
-    pub timeout: Duration,
+    pub timeouts: Timeouts,
+    /// `json-schema` sends the wire schema as `format` (Ollama 0.5+).
+    pub structured: StructuredOutput,
+}
+
+impl Ollama {
+    /// URL from `--ollama-url`/config, then `OLLAMA_HOST`, then localhost.
+    pub fn new(model: String, timeouts: Timeouts, url: Option<String>) -> Self {
+        let url = url
+            .or_else(|| std::env::var("OLLAMA_HOST").ok())
+            .map(|u| if u.starts_with("http") { u } else { format!("http://{}", u) })
+            .unwrap_or_else(|| DEFAULT_URL.to_string());
+        Self { model, url, timeouts, structured: StructuredOutput::JsonObject }
+    }
+
+    pub fn with_structured(mut self, s: StructuredOutput) -> Self {
+        self.structured = s;
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -18,18 +38,26 @@ struct ChatRequest<'a> {
     model: &'a str,
     messages: Vec<Msg>,
     stream: bool,
+    /// Constrain output to valid JSON or to a schema (off for YAML replies).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
     options: OllamaOptions,
 }
 
 #[derive(Serialize)]
 struct OllamaOptions {
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// Ollama's name for max output tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
 }
 
 #[derive(Serialize)]
-struct Msg {
-    role: String,
-    content: String,
+pub(super) struct Msg {
+    pub(super) role: String,
+    pub(super) content: String,
 }
 
 #[derive(Deserialize)]
@@ -39,32 +67,55 @@ struct ChatResponse {
 
 #[derive(Deserialize)]
 struct MsgOut {
-    role: String,
     content: String,
 }
 
-fn to_messages(ins: &Instruction) -> Vec<Msg> {
+/// System prompt (+ developer notes) and a user turn carrying the user
+/// instruction plus the request payload (task, context, limits); a follow-up
+/// adds the reply and its text as further turns.
+pub(super) fn to_messages(req: &LlmRequest) -> Result<Vec<Msg>> {
+    if let Some(f) = &req.follow_up {
+        let mut msgs = to_messages(&f.previous)?;
+        msgs.push(Msg { role: "assistant".into(), content: f.reply.clone() });
+        msgs.push(Msg { role: "user".into(), content: f.text.clone() });
+        return Ok(msgs);
+    }
+    let ins: &Instruction = &req.instruction;
     let mut sys = ins.system.clone();
     if let Some(dev) = &ins.developer {
         sys.push_str("\n\nDeveloper notes:\n");
         sys.push_str(dev);
     }
-    vec![
+    let mut payload = serde_json::to_value(req)?;
+    if let Some(obj) = payload.as_object_mut() {
+        obj.remove("instruction");
+    }
+    let user = format!("{}\n\nRequest:\n{}", ins.user, serde_json::to_string(&payload)?);
+    Ok(vec![
         Msg { role: "system".into(), content: sys },
-        Msg { role: "user".into(), content: ins.user.clone() },
-    ]
+        Msg { role: "user".into(), content: user },
+    ])
 }
 
 #[async_trait]
 impl Provider for Ollama {
     async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
         let url = format!("{}/api/chat", self.url.trim_end_matches('/'));
-        let client = Client::builder().timeout(self.timeout).build()?;
+        let client = self.timeouts.client()?;
         let body = ChatRequest {
             model: &self.model,
-            messages: to_messages(&req.instruction),
+            messages: to_messages(req)?,
             stream: false,
-            options: OllamaOptions { temperature: 0.1 },
+            format: match (req.wire_format, self.structured) {
+                (WireFormat::Json, StructuredOutput::JsonSchema) => Some(crate::wire::response_json_schema()),
+                (WireFormat::Json, StructuredOutput::JsonObject) => Some(serde_json::json!("json")),
+                _ => None,
+            },
+            options: OllamaOptions {
+                temperature: req.sampling.temperature.unwrap_or(0.1),
+                top_p: req.sampling.top_p,
+                num_predict: req.sampling.max_output_tokens,
+            },
         };
 
         if debug {
@@ -78,12 +129,18 @@ impl Provider for Ollama {
             .await
             .context("ollama request failed")?;
 
+        let status = resp.status();
         let text = resp.text().await.context("ollama read body failed")?;
 
         if debug {
             eprintln!("debug/ollama: raw body:\n{}\n", text);
         }
 
+        if !status.is_success() {
+            return Err(VibeError::Http { provider: "Ollama", status, body: text }.into());
+        }
+        super::usage::record_body(&text);
+
         // Try to parse to standard ollama response first
         let parsed: Result<ChatResponse, _> = serde_json::from_str(&text);
         let content = match parsed {
@@ -91,9 +148,9 @@ impl Provider for Ollama {
             Err(_) => text,
         };
 
-        let llm_resp: LlmResponse = serde_json::from_str(&content)
-            .map_err(|e| anyhow!("failed to parse LLM JSON: {}.\nContent was:\n{}", e, content))?;
-
-        Ok(llm_resp)
+        if req.wire_format == WireFormat::Yaml {
+            return super::yaml::parse(&content);
+        }
+        super::jsonfix::parse_response(&content)
     }
 }
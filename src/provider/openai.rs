@@ -2,25 +2,124 @@ use async_trait::async_trait;
 use anyhow::{anyhow, Result};
 use reqwest::Client;
 use serde::Deserialize;
-use serde_json::json;
-use std::time::Duration;
-
-use crate::wire::{LlmRequest, LlmResponse};
-
-/// OpenAI provider that sends the ENTIRE LlmRequest as a single user message,
-/// with no extra system/developer messages.
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+use crate::cli::OpenAIMessages;
+use super::azure::AzureTarget;
+use super::capabilities::{Learn, StructuredOutput};
+use super::Timeouts;
+use crate::errors::VibeError;
+use crate::wire::{LlmRequest, LlmResponse, WireFormat};
+
+const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+pub const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
+pub const GROQ_API_BASE: &str = "https://api.groq.com/openai/v1";
+
+/// OpenAI provider. By default it sends the ENTIRE LlmRequest as a single user
+/// message, with no extra system/developer messages; `OpenAIMessages::Roles`
+/// maps the instruction onto real system/developer roles instead.
 pub struct OpenAIProvider {
     model: String,
     client: Client,
-    timeout_secs: u64,
+    messages: OpenAIMessages,
+    /// Overrides `OPENAI_API_KEY` (e.g. from a provider profile).
+    api_key: Option<String>,
+    api_base: String,
+    /// Env var holding the key when none is configured.
+    key_var: &'static str,
+    /// Send without auth when no key is configured (local servers).
+    key_optional: bool,
+    /// How JSON replies are requested; weakened for the rest of the run
+    /// when the endpoint rejects it.
+    structured: Mutex<StructuredOutput>,
+    /// Remembers such rejections for later runs.
+    learn: Option<Learn>,
+    /// Send to an Azure OpenAI deployment (api-key header, deployment URL).
+    azure: Option<AzureTarget>,
 }
 
 impl OpenAIProvider {
-    pub fn new(model: String, timeout_secs: u64) -> Self {
+    pub fn new(model: String, timeouts: Timeouts, messages: OpenAIMessages) -> Self {
         Self {
             model,
-            client: Client::new(),
-            timeout_secs,
+            // building only fails when the TLS backend can't start, where Client::new() would panic too
+            client: timeouts.client().unwrap_or_else(|_| Client::new()),
+            messages,
+            api_key: None,
+            api_base: DEFAULT_API_BASE.to_string(),
+            key_var: "OPENAI_API_KEY",
+            key_optional: false,
+            structured: Mutex::new(StructuredOutput::JsonObject),
+            learn: None,
+            azure: None,
+        }
+    }
+
+    /// Read the key from `var` instead of `OPENAI_API_KEY` (OpenAI-compatible hosts).
+    pub fn with_key_var(mut self, var: &'static str) -> Self {
+        self.key_var = var;
+        self
+    }
+
+    pub fn with_optional_key(mut self) -> Self {
+        self.key_optional = true;
+        self
+    }
+
+    /// Request JSON with `s` (see `capabilities::strategy`), recording a
+    /// rejection of it through `learn`.
+    pub fn with_structured(mut self, s: StructuredOutput, learn: Learn) -> Self {
+        self.structured = Mutex::new(s);
+        self.learn = Some(learn);
+        self
+    }
+
+    pub fn with_azure(mut self, target: AzureTarget) -> Self {
+        self.azure = Some(target);
+        self
+    }
+
+    pub fn with_endpoint(mut self, api_key: Option<String>, api_base: Option<String>) -> Self {
+        self.api_key = api_key;
+        if let Some(b) = api_base {
+            self.api_base = b;
+        }
+        self
+    }
+}
+
+/// Build chat messages for `req` according to the message mode.
+/// A follow-up appends the reply and its text to the previous request's messages.
+fn build_messages(req: &LlmRequest, mode: OpenAIMessages) -> Result<Value> {
+    if let Some(f) = &req.follow_up {
+        let mut msgs = build_messages(&f.previous, mode)?;
+        if let Value::Array(a) = &mut msgs {
+            a.push(json!({ "role": "assistant", "content": f.reply }));
+            a.push(json!({ "role": "user", "content": f.text }));
+        }
+        return Ok(msgs);
+    }
+    match mode {
+        // Single user message, no system messages or added scaffolding.
+        OpenAIMessages::Single => {
+            let request_json_str = serde_json::to_string(req)?;
+            Ok(json!([{ "role": "user", "content": request_json_str }]))
+        }
+        // System/developer prompts as roles; the user message carries the
+        // user instruction plus task/context (the request minus `instruction`).
+        OpenAIMessages::Roles => {
+            let mut payload = serde_json::to_value(req)?;
+            if let Some(obj) = payload.as_object_mut() {
+                obj.remove("instruction");
+            }
+            let user = format!("{}\n\nRequest:\n{}", req.instruction.user, serde_json::to_string(&payload)?);
+            let mut msgs = vec![json!({ "role": "system", "content": req.instruction.system })];
+            if let Some(dev) = &req.instruction.developer {
+                msgs.push(json!({ "role": "developer", "content": dev }));
+            }
+            msgs.push(json!({ "role": "user", "content": user }));
+            Ok(Value::Array(msgs))
         }
     }
 }
@@ -28,54 +127,99 @@ impl OpenAIProvider {
 #[async_trait]
 impl super::Provider for OpenAIProvider {
     async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| anyhow!("OPENAI_API_KEY env var is not set"))?;
-
-        // Serialize the WHOLE request exactly as we want the model to see it.
-        let request_json_str = serde_json::to_string(req)?;
-
-        // Single user message, no system messages or added scaffolding.
-        let body = json!({
+        let (url, key_var) = match &self.azure {
+            Some(a) => (a.chat_url(), "AZURE_OPENAI_API_KEY"),
+            None => (format!("{}/chat/completions", self.api_base.trim_end_matches('/')), self.key_var),
+        };
+        let api_key = match (&self.api_key, crate::credentials::api_key(key_var)) {
+            (Some(k), _) => Some(k.clone()),
+            (None, Some(k)) => Some(k),
+            (None, None) if self.key_optional => None,
+            (None, None) => return Err(crate::credentials::missing(key_var)),
+        };
+
+        let messages = build_messages(req, self.messages)?;
+
+        let mut body = json!({
             "model": self.model,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": request_json_str
-                }
-            ],
-            "temperature": 0.0,
-            "top_p": 1.0,
-            // Force a valid JSON object in the response.
-            "response_format": { "type": "json_object" }
+            "messages": messages,
+            "temperature": req.sampling.temperature.unwrap_or(0.0),
+            "top_p": req.sampling.top_p.unwrap_or(1.0),
         });
-
-        if debug {
-            eprintln!(
-                "debug[openai]: HTTP POST /v1/chat/completions body:\n{}",
-                serde_json::to_string_pretty(&body)?
-            );
+        if let Some(n) = req.sampling.max_output_tokens {
+            // OpenAI's reasoning models only accept the newer name; compatible servers the older
+            let key = if self.azure.is_some() || self.api_base == DEFAULT_API_BASE { "max_completion_tokens" } else { "max_tokens" };
+            body[key] = json!(n);
         }
+        let (status, text) = loop {
+            let structured = *self.structured.lock().unwrap_or_else(|e| e.into_inner());
+            match (req.wire_format, structured) {
+                // Structured outputs: the reply is guaranteed to match the wire schema.
+                (WireFormat::Json, StructuredOutput::JsonSchema) => {
+                    body["response_format"] = json!({
+                        "type": "json_schema",
+                        "json_schema": { "name": "llm_response", "strict": true, "schema": crate::wire::response_json_schema() }
+                    });
+                }
+                // Force a valid JSON object in the response.
+                (WireFormat::Json, StructuredOutput::JsonObject) => body["response_format"] = json!({ "type": "json_object" }),
+                _ => {
+                    if let Some(obj) = body.as_object_mut() {
+                        obj.remove("response_format");
+                    }
+                }
+            }
 
-        let resp = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .bearer_auth(api_key)
-            .timeout(Duration::from_secs(self.timeout_secs))
-            .json(&body)
-            .send()
-            .await?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if debug {
-            eprintln!("debug[openai]: raw status: {}", status);
-            eprintln!("debug[openai]: raw response:\n{}", &text);
-        }
+            if debug {
+                eprintln!(
+                    "debug[openai]: HTTP POST {} body:\n{}",
+                    url,
+                    serde_json::to_string_pretty(&body)?
+                );
+            }
+
+            let builder = self.client.post(&url);
+            let builder = match &api_key {
+                Some(k) if self.azure.is_some() => builder.header("api-key", k),
+                Some(k) => builder.bearer_auth(k),
+                None => builder,
+            };
+            let resp = builder
+                .json(&body)
+                .send()
+                .await?;
+
+            let status = resp.status();
+            let text = resp.text().await?;
+
+            if debug {
+                eprintln!("debug[openai]: raw status: {}", status);
+                eprintln!("debug[openai]: raw response:\n{}", &text);
+            }
+
+            // the endpoint doesn't take this response_format: step down and resend
+            let weaker = structured.weaker().filter(|_| req.wire_format == WireFormat::Json);
+            if let (Some(weaker), true) = (weaker, super::capabilities::rejects_response_format(status, &text)) {
+                let model = self.learn.as_ref().map(|l| l.key().to_string()).unwrap_or_else(|| self.model.clone());
+                eprintln!(
+                    "warning: {} rejected {} replies; retrying with {} (remembered in .vibe/capabilities.json)",
+                    model,
+                    structured.as_str(),
+                    weaker.as_str()
+                );
+                if let Some(l) = &self.learn {
+                    l.rejected(structured);
+                }
+                *self.structured.lock().unwrap_or_else(|e| e.into_inner()) = weaker;
+                continue;
+            }
+            break (status, text);
+        };
 
         if !status.is_success() {
-            return Err(anyhow!("OpenAI API error ({}): {}", status, text));
+            return Err(VibeError::Http { provider: "OpenAI", status, body: text }.into());
         }
+        super::usage::record_body(&text);
 
         // Minimal structs to parse the chat response
         #[derive(Deserialize)]
@@ -101,50 +245,10 @@ impl super::Provider for OpenAIProvider {
             .map(|c| c.message.content.clone())
             .unwrap_or_default();
 
-        // Try strict parse first
-        match serde_json::from_str::<LlmResponse>(&content) {
-            Ok(ok) => return Ok(ok),
-            Err(_e) => {
-                // Fallback: extract first {...} JSON object from the text, then parse it.
-                if let Some(obj) = extract_first_json_object(&content) {
-                    if let Ok(resp) = serde_json::from_str::<LlmResponse>(&obj) {
-                        return Ok(resp);
-                    }
-                }
-            }
+        if req.wire_format == WireFormat::Yaml {
+            return super::yaml::parse(&content);
         }
 
-        Err(anyhow!(
-            "Model did not return a valid JSON response body.\n--- content start ---\n{}\n--- content end ---",
-            content
-        ))
-    }
-}
-
-/// Extracts the first top-level JSON object substring from a string.
-/// Handles nested braces; returns None if not found.
-fn extract_first_json_object(s: &str) -> Option<String> {
-    let bytes = s.as_bytes();
-    let mut start = None;
-    let mut depth = 0usize;
-
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == b'{' {
-            if start.is_none() {
-                start = Some(i);
-            }
-            depth += 1;
-        } else if b == b'}' {
-            if depth > 0 {
-                depth -= 1;
-                if depth == 0 {
-                    if let Some(st) = start {
-                        let slice = &s[st..=i];
-                        return Some(slice.to_string());
-                    }
-                }
-            }
-        }
+        super::jsonfix::parse_response(&content)
     }
-    None
 }
@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::time::Instant;
+
+use crate::wire::{Kind, LlmRequest, LlmResponse};
+use super::{DynProvider, Provider};
+
+/// `plan_race`: send the request to every provider at once and keep the
+/// first reply that is a non-empty `kind:"plan"`; the others are dropped
+/// (and their requests cancelled) as soon as it arrives. If no racer
+/// produces a plan, the first parsed reply (e.g. an answer) is used, then
+/// the last error.
+pub struct Race {
+    links: Vec<(String, DynProvider)>,
+}
+
+impl Race {
+    pub fn new(links: Vec<(String, DynProvider)>) -> Self {
+        Self { links }
+    }
+}
+
+fn is_plan(resp: &LlmResponse) -> bool {
+    matches!(resp.kind, Kind::Plan) && resp.plan.as_ref().map(|p| !p.steps.is_empty()).unwrap_or(false)
+}
+
+#[async_trait]
+impl Provider for Race {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let started = Instant::now();
+        let mut racers: FuturesUnordered<_> = self
+            .links
+            .iter()
+            .map(|(label, prov)| async move { (label.as_str(), prov.send(req, debug).await) })
+            .collect();
+        let mut fallback: Option<(&str, LlmResponse)> = None;
+        let mut last_err = None;
+        let mut finished: Vec<&str> = Vec::new();
+        while let Some((label, result)) = racers.next().await {
+            finished.push(label);
+            match result {
+                Ok(resp) if is_plan(&resp) => {
+                    let losers: Vec<&str> = self.links.iter().map(|(l, _)| l.as_str()).filter(|l| !finished.contains(l)).collect();
+                    crate::ux::print_race_winner(label, started.elapsed(), &losers);
+                    return Ok(resp);
+                }
+                Ok(resp) => {
+                    if debug {
+                        eprintln!("debug/race: {} replied without a plan", label);
+                    }
+                    fallback.get_or_insert((label, resp));
+                }
+                Err(e) => {
+                    eprintln!("warning: racing provider {} failed: {:#}", label, e);
+                    last_err = Some(e.context(format!("provider {} failed", label)));
+                }
+            }
+        }
+        if let Some((label, resp)) = fallback {
+            crate::ux::print_race_winner(label, started.elapsed(), &[]);
+            return Ok(resp);
+        }
+        Err(last_err.unwrap_or_else(|| anyhow!("plan_race is empty")))
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::wire::{LlmRequest, LlmResponse};
+use super::{DynProvider, Provider};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Client-side limits per provider (`[rate_limit]` in config); unset means
+/// unlimited. Tokens are the request estimate from `tokens::request_tokens`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+/// Requests sent in the last minute: (sent at, estimated tokens).
+type Window = Arc<Mutex<VecDeque<(Instant, usize)>>>;
+
+/// One window per provider label, so the PLAN and CODEGEN providers (and
+/// chain entries on the same provider) share a budget.
+fn window_for(label: &str) -> Window {
+    static WINDOWS: OnceLock<Mutex<HashMap<String, Window>>> = OnceLock::new();
+    let mut map = WINDOWS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    map.entry(label.to_string()).or_default().clone()
+}
+
+/// Delays requests that would exceed the configured RPM/TPM instead of
+/// letting the provider reject them.
+pub struct RateLimited {
+    inner: DynProvider,
+    label: String,
+    limit: RateLimit,
+    window: Window,
+}
+
+impl RateLimited {
+    pub fn wrap(inner: DynProvider, label: &str, limit: RateLimit) -> DynProvider {
+        if limit.requests_per_minute.is_none() && limit.tokens_per_minute.is_none() {
+            return inner;
+        }
+        Box::new(Self { inner, label: label.to_string(), window: window_for(label), limit })
+    }
+
+    /// How long to wait before a request of `tokens` fits; records it when none.
+    fn reserve(&self, tokens: usize) -> Option<Duration> {
+        let mut w = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        while w.front().is_some_and(|(t, _)| now.duration_since(*t) >= WINDOW) {
+            w.pop_front();
+        }
+        let over_rpm = self.limit.requests_per_minute.is_some_and(|rpm| w.len() >= rpm as usize);
+        let used: usize = w.iter().map(|(_, n)| n).sum();
+        // a single request over the TPM still goes out once the window is empty
+        let over_tpm = self.limit.tokens_per_minute.is_some_and(|tpm| !w.is_empty() && used + tokens > tpm as usize);
+        if over_rpm || over_tpm {
+            let (oldest, _) = w.front().copied()?;
+            return Some(WINDOW.saturating_sub(now.duration_since(oldest)) + Duration::from_millis(50));
+        }
+        w.push_back((now, tokens));
+        None
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimited {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let tokens = super::tokens::request_tokens(req);
+        while let Some(wait) = self.reserve(tokens) {
+            crate::ux::print_rate_limit_wait(&self.label, wait);
+            tokio::time::sleep(wait).await;
+        }
+        if debug {
+            eprintln!("debug/ratelimit: {} sending ~{} tokens", self.label, tokens);
+        }
+        self.inner.send(req, debug).await
+    }
+}
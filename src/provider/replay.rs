@@ -0,0 +1,75 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::log;
+use crate::wire::{LlmRequest, LlmResponse, Mode};
+use super::Provider;
+
+/// `--provider replay --replay-tx <uuid>`: answers from the responses saved
+/// in a previous transaction instead of calling a model. Responses are handed
+/// out in the order the run made its calls (plan, widen/strict retries,
+/// stages, codegen, repairs, dependency migration), skipping the ones that
+/// tx never saved.
+pub struct Replay {
+    tx: Uuid,
+    responses: Vec<(String, Mode, LlmResponse)>,
+    next: Mutex<usize>,
+}
+
+impl Replay {
+    pub fn load(root: &Path, tx: Uuid, stage_names: &[String]) -> Result<Self> {
+        let dir = log::tx_dir(root, tx);
+        if !dir.is_dir() {
+            bail!("replay: no transaction {} under {}", tx, log::tx_root(root).display());
+        }
+        let mut order: Vec<(String, Mode)> = ["plan", "plan.widen"].iter().map(|s| (s.to_string(), Mode::Plan)).collect();
+        let mut n = 2;
+        while log::read_artifact(&dir, &format!("plan.sample.{}.response.json", n))?.is_some() {
+            order.push((format!("plan.sample.{}", n), Mode::Plan));
+            n += 1;
+        }
+        order.push(("plan.strict".to_string(), Mode::Plan));
+        order.extend(stage_names.iter().map(|n| (format!("stage.{}", n), Mode::Plan)));
+        order.extend(["codegen", "codegen.widen", "codegen.repair"].iter().map(|s| (s.to_string(), Mode::Codegen)));
+        // later repair attempts: codegen.repair.2, .3, ...
+        let mut n = 2;
+        while log::read_artifact(&dir, &format!("codegen.repair.{}.response.json", n))?.is_some() {
+            order.push((format!("codegen.repair.{}", n), Mode::Codegen));
+            n += 1;
+        }
+        // dependency migration, after the codegen reply is final
+        order.push(("codegen.deps".to_string(), Mode::Codegen));
+
+        let mut responses = Vec::new();
+        for (stage, mode) in order {
+            let name = format!("{}.response.json", stage);
+            let Some(text) = log::read_artifact(&dir, &name)? else { continue };
+            let resp: LlmResponse = serde_json::from_str(&text).with_context(|| format!("replay: parsing {}", name))?;
+            responses.push((stage, mode, resp));
+        }
+        if responses.is_empty() {
+            bail!("replay: transaction {} has no saved responses (was it run with --save-response?)", tx);
+        }
+        Ok(Self { tx, responses, next: Mutex::new(0) })
+    }
+}
+
+#[async_trait]
+impl Provider for Replay {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let mut next = self.next.lock().map_err(|_| anyhow!("replay state poisoned"))?;
+        let want = std::mem::discriminant(&req.mode);
+        let i = (*next..self.responses.len())
+            .find(|&i| std::mem::discriminant(&self.responses[i].1) == want)
+            .ok_or_else(|| anyhow!("replay: transaction {} has no saved response left for this {:?} request", self.tx, req.mode))?;
+        *next = i + 1;
+        let (stage, _, resp) = &self.responses[i];
+        if debug {
+            eprintln!("debug/replay: answering with {}.response.json from tx {}", stage, self.tx);
+        }
+        Ok(resp.clone())
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::errors::VibeError;
+use crate::wire::{LlmRequest, LlmResponse};
+use super::{DynProvider, Provider};
+
+/// Retry settings for transient provider failures (`[retry]` in config).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries.
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 4, initial_backoff_ms: 1_000, max_backoff_ms: 30_000 }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff before attempt `next` (2-based), with up to 25% jitter.
+    fn backoff(&self, next: u32) -> Duration {
+        let base = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << (next - 2).min(16))
+            .min(self.max_backoff_ms);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0);
+        Duration::from_millis(base + nanos % (base / 4 + 1))
+    }
+}
+
+/// Wraps a provider and re-sends on rate limits, 5xx replies, timeouts and
+/// dropped connections. Other errors (bad key, unparseable JSON) fail at once.
+pub struct Retrying {
+    inner: DynProvider,
+    policy: RetryPolicy,
+}
+
+impl Retrying {
+    pub fn wrap(inner: DynProvider, policy: RetryPolicy) -> DynProvider {
+        if policy.max_attempts <= 1 {
+            return inner;
+        }
+        Box::new(Self { inner, policy })
+    }
+}
+
+/// Short reason if `err` is worth retrying.
+fn transient_reason(err: &anyhow::Error) -> Option<String> {
+    for cause in err.chain() {
+        if let Some(VibeError::Http { status, .. }) = cause.downcast_ref::<VibeError>() {
+            return match status.as_u16() {
+                408 | 409 | 425 | 429 | 500 | 502 | 503 | 504 | 529 => Some(status.to_string()),
+                _ => None,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return Some("timeout".into());
+            }
+            if e.is_connect() || e.is_request() || e.is_body() {
+                return Some("connection error".into());
+            }
+        }
+    }
+    None
+}
+
+#[async_trait]
+impl Provider for Retrying {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let mut attempt = 1;
+        loop {
+            match self.inner.send(req, debug).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) if attempt < self.policy.max_attempts => {
+                    let Some(reason) = transient_reason(&e) else { return Err(e) };
+                    attempt += 1;
+                    super::usage::record_retry();
+                    let delay = self.policy.backoff(attempt);
+                    crate::ux::print_retry_status(attempt, self.policy.max_attempts, &reason, delay);
+                    if debug {
+                        eprintln!("debug/retry: {:#}", e);
+                    }
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::errors::VibeError;
+use crate::wire::{LlmRequest, LlmResponse, WireFormat};
+use super::{Provider, Timeouts};
+
+pub const VLLM_DEFAULT_URL: &str = "http://localhost:8000";
+pub const TGI_DEFAULT_URL: &str = "http://localhost:8080";
+
+/// Which inference server is on the other end; each is driven through its
+/// native guided-decoding API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Server {
+    /// vLLM: chat route with `structured_outputs.json` (xgrammar/outlines),
+    /// falling back to the pre-0.10 `guided_json` field.
+    Vllm,
+    /// Text Generation Inference: `/generate` with a `grammar` of type
+    /// `json`, compiled to a token-level grammar.
+    Tgi,
+}
+
+impl Server {
+    fn name(self) -> &'static str {
+        match self {
+            Server::Vllm => "vLLM",
+            Server::Tgi => "TGI",
+        }
+    }
+
+    fn url_var(self) -> &'static str {
+        match self {
+            Server::Vllm => "VLLM_BASE_URL",
+            Server::Tgi => "TGI_URL",
+        }
+    }
+
+    /// Token sent as bearer auth when set (`vllm serve --api-key`, gated
+    /// Hugging Face endpoints); neither server requires one by default.
+    pub fn key_var(self) -> &'static str {
+        match self {
+            Server::Vllm => "VLLM_API_KEY",
+            Server::Tgi => "HF_TOKEN",
+        }
+    }
+}
+
+/// Self-hosted vLLM or TGI server. Unlike `openai-compatible`, JSON replies
+/// are grammar-constrained to `wire::response_json_schema()`, so small local
+/// models can't drift out of the wire format.
+pub struct SelfHosted {
+    pub server: Server,
+    pub model: String,
+    pub url: String,
+    pub api_key: Option<String>,
+    pub timeouts: Timeouts,
+    /// Constrain JSON replies to the wire schema (config `guided_decoding`).
+    pub guided: bool,
+}
+
+impl SelfHosted {
+    /// URL from `--api-base`/a profile, then `VLLM_BASE_URL`/`TGI_URL`, then
+    /// the server's default local port. A trailing `/v1` is accepted.
+    pub fn new(server: Server, model: String, timeouts: Timeouts, api_key: Option<String>, api_base: Option<String>) -> Self {
+        let url = api_base
+            .or_else(|| std::env::var(server.url_var()).ok())
+            .unwrap_or_else(|| match server {
+                Server::Vllm => VLLM_DEFAULT_URL.to_string(),
+                Server::Tgi => TGI_DEFAULT_URL.to_string(),
+            });
+        let url = url.trim_end_matches('/').trim_end_matches("/v1").to_string();
+        let api_key = api_key.or_else(|| crate::credentials::api_key(server.key_var()));
+        Self { server, model, url, api_key, timeouts, guided: true }
+    }
+
+    pub fn with_guided(mut self, on: bool) -> Self {
+        self.guided = on;
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// TGI `/generate` reply; Inference Endpoints wrap it in a one-element array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GenerateResponse {
+    One(Generated),
+    Many(Vec<Generated>),
+}
+
+#[derive(Deserialize)]
+struct Generated {
+    generated_text: String,
+}
+
+/// `/generate` takes a raw prompt, so the chat turns are flattened into a
+/// role-tagged transcript that ends on an open assistant turn.
+fn transcript(req: &LlmRequest) -> Result<String> {
+    let mut out = String::new();
+    for m in super::ollama::to_messages(req)? {
+        out.push_str(&format!("### {}\n{}\n\n", m.role, m.content));
+    }
+    out.push_str("### assistant\n");
+    Ok(out)
+}
+
+impl SelfHosted {
+    fn vllm_body(&self, req: &LlmRequest, guided_field: Option<&str>) -> Result<Value> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": super::ollama::to_messages(req)?,
+            "temperature": req.sampling.temperature.unwrap_or(0.0),
+            "top_p": req.sampling.top_p.unwrap_or(1.0),
+            "stream": false,
+        });
+        if let Some(n) = req.sampling.max_output_tokens {
+            body["max_tokens"] = json!(n);
+        }
+        match guided_field {
+            Some("structured_outputs") => body["structured_outputs"] = json!({ "json": crate::wire::response_json_schema() }),
+            Some(field) => body[field] = crate::wire::response_json_schema(),
+            None => {}
+        }
+        Ok(body)
+    }
+
+    fn tgi_body(&self, req: &LlmRequest, guided: bool) -> Result<Value> {
+        let temperature = req.sampling.temperature.unwrap_or(0.0);
+        let mut parameters = json!({
+            "max_new_tokens": req.sampling.max_output_tokens.unwrap_or(4096),
+            "do_sample": temperature > 0.0,
+            "return_full_text": false,
+            "details": true,
+        });
+        // TGI rejects a zero temperature and a top_p outside (0, 1).
+        if temperature > 0.0 {
+            parameters["temperature"] = json!(temperature);
+        }
+        if let Some(p) = req.sampling.top_p.filter(|p| *p > 0.0 && *p < 1.0) {
+            parameters["top_p"] = json!(p);
+        }
+        if guided {
+            parameters["grammar"] = json!({ "type": "json", "value": crate::wire::response_json_schema() });
+        }
+        Ok(json!({ "inputs": transcript(req)?, "parameters": parameters }))
+    }
+
+    async fn post(&self, url: &str, body: &Value, debug: bool) -> Result<(reqwest::StatusCode, String)> {
+        let name = self.server.name();
+        if debug {
+            eprintln!("debug[{}]: POST {} body:\n{}", name, url, serde_json::to_string_pretty(body)?);
+        }
+        let builder = self.timeouts.client()?.post(url);
+        let builder = match &self.api_key {
+            Some(k) => builder.bearer_auth(k),
+            None => builder,
+        };
+        let resp = builder.json(body).send().await.with_context(|| format!("{} request failed", name))?;
+        let status = resp.status();
+        let text = resp.text().await.with_context(|| format!("{} read body failed", name))?;
+        if debug {
+            eprintln!("debug[{}]: raw status: {}", name, status);
+            eprintln!("debug[{}]: raw response:\n{}", name, &text);
+        }
+        Ok((status, text))
+    }
+}
+
+#[async_trait]
+impl Provider for SelfHosted {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let name = self.server.name();
+        let guided = req.wire_format == WireFormat::Json && self.guided;
+
+        let content = match self.server {
+            Server::Vllm => {
+                let url = format!("{}/v1/chat/completions", self.url);
+                let (mut status, mut text) =
+                    self.post(&url, &self.vllm_body(req, guided.then_some("structured_outputs"))?, debug).await?;
+                // Servers older than 0.10 reject `structured_outputs`; they
+                // take the same schema as `guided_json`.
+                if guided && status == reqwest::StatusCode::BAD_REQUEST {
+                    (status, text) = self.post(&url, &self.vllm_body(req, Some("guided_json"))?, debug).await?;
+                }
+                if !status.is_success() {
+                    return Err(VibeError::Http { provider: name, status, body: text }.into());
+                }
+                super::usage::record_body(&text);
+                let parsed: ChatResponse = serde_json::from_str(&text)
+                    .map_err(|e| anyhow!("Failed to parse {} response: {e}\nRaw: {text}", name))?;
+                parsed.choices.into_iter().next().and_then(|c| c.message.content).unwrap_or_default()
+            }
+            Server::Tgi => {
+                let url = format!("{}/generate", self.url);
+                let (status, text) = self.post(&url, &self.tgi_body(req, guided)?, debug).await?;
+                if !status.is_success() {
+                    return Err(VibeError::Http { provider: name, status, body: text }.into());
+                }
+                super::usage::record_body(&text);
+                let parsed: GenerateResponse = serde_json::from_str(&text)
+                    .map_err(|e| anyhow!("Failed to parse {} response: {e}\nRaw: {text}", name))?;
+                match parsed {
+                    GenerateResponse::One(g) => g.generated_text,
+                    GenerateResponse::Many(v) => v.into_iter().next().map(|g| g.generated_text).unwrap_or_default(),
+                }
+            }
+        };
+
+        if req.wire_format == WireFormat::Yaml {
+            return super::yaml::parse(&content);
+        }
+        super::jsonfix::parse_response(&content)
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::wire::{LlmRequest, LlmResponse};
+use super::{DynProvider, Provider};
+
+/// Tokens kept free for the model's reply unless `max_output_tokens` is set.
+const OUTPUT_RESERVE: usize = 8_192;
+
+/// Rough token count in the style of tiktoken's cl100k: split on the same
+/// pre-token classes, then charge words ~4 chars/token, symbol runs ~2
+/// chars/token, digits up to 3 per token and 1 per whitespace run. Errs
+/// high on code/JSON so the budget check is conservative.
+pub fn estimate(text: &str) -> usize {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"(?i:'s|'t|'re|'ve|'m|'ll|'d)| ?\p{L}+| ?\p{N}{1,3}| ?[^\s\p{L}\p{N}]+|\s+").unwrap()
+    });
+    re.find_iter(text)
+        .map(|m| {
+            let s = m.as_str().trim_start_matches(' ');
+            let chars = s.chars().count();
+            match s.chars().next() {
+                None => 1, // a lone space
+                Some(c) if c.is_whitespace() => 1,
+                Some(c) if c.is_alphabetic() => chars.div_ceil(4).max(1),
+                Some(c) if c.is_numeric() => 1,
+                Some(_) => chars.div_ceil(2).max(1),
+            }
+        })
+        .sum()
+}
+
+/// Context window (tokens) for a model name; unknown models get a
+/// conservative 32k. `context_window` in config overrides this.
+pub fn context_window(model: &str) -> usize {
+    let m = model.to_ascii_lowercase();
+    let m = m.rsplit('/').next().unwrap_or(&m); // openrouter-style `vendor/model`
+    const TABLE: &[(&str, usize)] = &[
+        ("gpt-4.1", 1_000_000),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4", 200_000),
+        ("gpt-4", 8_192),
+        ("gpt-3.5", 16_385),
+        ("claude", 200_000),
+        ("anthropic.claude", 200_000),
+        ("codestral", 256_000),
+        ("mistral-large", 128_000),
+        ("mistral", 32_000),
+        ("llama-3.1", 128_000),
+        ("llama-3.3", 128_000),
+        ("llama3.1", 128_000),
+        ("llama3", 8_192),
+        ("qwen2.5-coder", 32_768),
+        ("deepseek", 64_000),
+    ];
+    TABLE
+        .iter()
+        .find(|(prefix, _)| m.starts_with(prefix))
+        .map(|(_, w)| *w)
+        .unwrap_or(32_000)
+}
+
+/// Serialized request size as the adapters send it.
+pub fn request_tokens(req: &LlmRequest) -> usize {
+    estimate(&serde_json::to_string(req).unwrap_or_default())
+}
+
+/// Checks each request against the model's window before sending. Over
+/// budget (or when the provider still rejects it as too long), it either
+/// compacts `files_snapshot` and retries, or refuses.
+pub struct Budgeted {
+    inner: DynProvider,
+    model: String,
+    window: usize,
+    trim: bool,
+}
+
+impl Budgeted {
+    pub fn wrap(inner: DynProvider, model: &str, window: Option<usize>, trim: bool) -> DynProvider {
+        Box::new(Self {
+            inner,
+            model: model.to_string(),
+            window: window.unwrap_or_else(|| context_window(model)),
+            trim,
+        })
+    }
+}
+
+/// Extensions whose snapshots can be stripped/reduced as code.
+const CODE_EXTS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "css", "scss"];
+
+/// Provider errors that mean the prompt was over the model's window.
+const OVERFLOW_HINTS: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "context window",
+    "prompt is too long",
+    "too many tokens",
+    "input is too long",
+];
+
+/// Retries after a provider-side overflow, each at 3/4 of the last size.
+const OVERFLOW_RETRIES: usize = 2;
+
+fn is_code(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, e)| CODE_EXTS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Code without blank lines and whole-line `//` / `/* */` comments (comments
+/// sharing a line with code are kept; nothing inside strings is touched).
+fn strip_comments(src: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_block = false;
+    for line in src.lines() {
+        let t = line.trim();
+        if in_block {
+            in_block = !t.contains("*/");
+            continue;
+        }
+        if t.is_empty() || t.starts_with("//") {
+            continue;
+        }
+        if t.starts_with("/*") {
+            in_block = !t.contains("*/");
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n")
+}
+
+/// Only the lines that declare something: imports, exports, functions,
+/// classes, types and directives, with a note that bodies were left out.
+fn signatures(src: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r#"^\s*(?:import\b|export\b|(?:async\s+)?function\b|class\b|interface\b|type\s+\w+|enum\b|(?:const|let)\s+\w+\s*=\s*(?:async\s*)?\(|['"]use (?:client|server)['"])"#,
+        )
+        .unwrap()
+    });
+    let mut out: Vec<&str> = src.lines().filter(|l| re.is_match(l)).collect();
+    out.push("// ... bodies omitted to fit the context window");
+    out.join("\n")
+}
+
+/// Shrink `files_snapshot` until `req` fits in `limit`, least damaging step
+/// first: strip comments/blank lines from code, reduce code files to their
+/// signatures (lowest-ranked first; snapshots are in relevance order), then
+/// drop files from the bottom. Returns what was done, or None if the request
+/// is still over with no snapshots left.
+fn compact(req: &mut LlmRequest, limit: usize) -> Option<Vec<String>> {
+    let mut report = Vec::new();
+    if request_tokens(req) <= limit {
+        return Some(report);
+    }
+    let mut stripped = 0;
+    for blob in req.context.files_snapshot.iter_mut().filter(|b| is_code(&b.path)) {
+        let s = strip_comments(&blob.content);
+        if s.len() < blob.content.len() {
+            blob.content = s;
+            blob.truncated = true;
+            stripped += 1;
+        }
+    }
+    if stripped > 0 {
+        report.push(format!("stripped comments/blank lines from {} file(s)", stripped));
+    }
+    for i in (0..req.context.files_snapshot.len()).rev() {
+        if request_tokens(req) <= limit {
+            return Some(report);
+        }
+        let blob = &mut req.context.files_snapshot[i];
+        // stylesheets have no signatures worth keeping; they go with the drops
+        if is_code(&blob.path) && !blob.path.ends_with("css") {
+            blob.content = signatures(&blob.content);
+            blob.truncated = true;
+            report.push(format!("reduced {} to signatures", blob.path));
+        }
+    }
+    while request_tokens(req) > limit {
+        let blob = req.context.files_snapshot.pop()?;
+        report.push(format!("dropped {}", blob.path));
+    }
+    Some(report)
+}
+
+fn is_overflow(e: &anyhow::Error) -> bool {
+    let msg = format!("{:#}", e).to_ascii_lowercase();
+    OVERFLOW_HINTS.iter().any(|h| msg.contains(h))
+}
+
+#[async_trait]
+impl Provider for Budgeted {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let reserve = req.sampling.max_output_tokens.map(|n| n as usize).unwrap_or(OUTPUT_RESERVE);
+        let mut limit = self.window.saturating_sub(reserve);
+        let tokens = request_tokens(req);
+        if debug {
+            eprintln!("debug/tokens: ~{} tokens (window {} for {})", tokens, self.window, self.model);
+        }
+        if tokens > limit && !self.trim {
+            bail!(
+                "request is ~{} tokens, over the ~{} available for {} (window {}, {} reserved for the reply); \
+                 narrow the task or set trim_snapshot_to_fit = true",
+                tokens, limit, self.model, self.window, reserve
+            );
+        }
+        let mut req = req.clone();
+        let mut retries = 0;
+        loop {
+            let before = request_tokens(&req);
+            if before > limit {
+                let Some(report) = compact(&mut req, limit) else {
+                    bail!(
+                        "request is ~{} tokens even without file snapshots; {} allows ~{}",
+                        request_tokens(&req), self.model, limit
+                    );
+                };
+                eprintln!(
+                    "warning: request was ~{} tokens, over the budget for {}; compacted file snapshots to ~{}",
+                    before, self.model, request_tokens(&req)
+                );
+                if debug {
+                    for r in &report {
+                        eprintln!("debug/tokens: {}", r);
+                    }
+                }
+            }
+            match self.inner.send(&req, debug).await {
+                Err(e) if self.trim && retries < OVERFLOW_RETRIES && is_overflow(&e) && !req.context.files_snapshot.is_empty() => {
+                    retries += 1;
+                    // the estimate was off for this model; aim well under what was rejected
+                    limit = request_tokens(&req) * 3 / 4;
+                    eprintln!("warning: {} rejected the request as too long; compacting and retrying", self.model);
+                    if debug {
+                        eprintln!("debug/tokens: provider error: {:#}", e);
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
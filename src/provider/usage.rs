@@ -0,0 +1,70 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::time::Instant;
+
+use crate::wire::{LlmRequest, LlmResponse, Usage};
+use super::{DynProvider, Provider};
+
+tokio::task_local! {
+    /// Usage of the request `Metered` is currently timing; adapters and the
+    /// retry layer below it fill it in.
+    static CURRENT: RefCell<Usage>;
+}
+
+fn count(v: &Value, keys: &[&str]) -> Option<u64> {
+    keys.iter().find_map(|k| v.pointer(k).and_then(Value::as_u64))
+}
+
+/// Record the model and token counts from a raw response body: OpenAI-style
+/// `usage.prompt_tokens`, Anthropic-style `usage.input_tokens` or Ollama's
+/// `prompt_eval_count` (TGI's `/generate` reports only
+/// `details.generated_tokens`). A no-op outside `Metered`.
+pub fn record_body(text: &str) {
+    let Ok(v) = serde_json::from_str::<Value>(text) else { return };
+    let _ = CURRENT.try_with(|u| {
+        let mut u = u.borrow_mut();
+        if let Some(m) = v.get("model").and_then(Value::as_str) {
+            u.model = m.to_string();
+        }
+        u.prompt_tokens = count(&v, &["/usage/prompt_tokens", "/usage/input_tokens", "/prompt_eval_count"]);
+        u.completion_tokens = count(&v, &["/usage/completion_tokens", "/usage/output_tokens", "/eval_count", "/details/generated_tokens"]);
+    });
+}
+
+/// Count a re-send by the retry layer.
+pub fn record_retry() {
+    let _ = CURRENT.try_with(|u| u.borrow_mut().retries += 1);
+}
+
+/// Attaches `Usage` to every response: wall time across retries and
+/// rate-limit waits, plus what the adapter reported.
+pub struct Metered {
+    inner: DynProvider,
+    model: String,
+}
+
+impl Metered {
+    pub fn wrap(inner: DynProvider, model: &str) -> DynProvider {
+        Box::new(Self { inner, model: model.to_string() })
+    }
+}
+
+#[async_trait]
+impl Provider for Metered {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let started = Instant::now();
+        let initial = Usage { model: self.model.clone(), ..Default::default() };
+        let (result, mut usage) = CURRENT
+            .scope(RefCell::new(initial), async {
+                let result = self.inner.send(req, debug).await;
+                (result, CURRENT.with(|u| u.take()))
+            })
+            .await;
+        usage.duration_ms = started.elapsed().as_millis() as u64;
+        let mut resp = result?;
+        resp.usage = usage;
+        Ok(resp)
+    }
+}
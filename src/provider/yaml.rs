@@ -0,0 +1,53 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::wire::{LlmRequest, LlmResponse, WireFormat};
+use super::{DynProvider, Provider};
+
+const YAML_OVERRIDE: &str = "OUTPUT FORMAT OVERRIDE: wherever these instructions say to return a JSON object, \
+return the same structure as ONE YAML document instead (same keys and nesting, \
+`null` for empty values, multi-line file contents as `|` block scalars). \
+No code fences, no prose before or after the document.";
+
+/// `--wire-format yaml`: asks for YAML in place of JSON and marks the request
+/// so the adapter skips JSON-only modes and parses the reply as YAML.
+pub struct YamlWire {
+    inner: DynProvider,
+}
+
+impl YamlWire {
+    pub fn wrap(inner: DynProvider) -> DynProvider {
+        Box::new(Self { inner })
+    }
+}
+
+#[async_trait]
+impl Provider for YamlWire {
+    async fn send(&self, req: &LlmRequest, debug: bool) -> Result<LlmResponse> {
+        let mut req = req.clone();
+        req.wire_format = WireFormat::Yaml;
+        let dev = req.instruction.developer.take().unwrap_or_default();
+        req.instruction.developer = Some(format!("{}\n\n{}", dev, YAML_OVERRIDE).trim_start().to_string());
+        self.inner.send(&req, debug).await
+    }
+}
+
+/// Parse a YAML reply into an LlmResponse, tolerating a ```yaml fence.
+pub(super) fn parse(content: &str) -> Result<LlmResponse> {
+    let body = content.trim();
+    let body = match body.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.split_once('\n').map(|(_, r)| r).unwrap_or("");
+            rest.trim_end().strip_suffix("```").unwrap_or(rest)
+        }
+        None => body,
+    };
+    let body = body.strip_prefix("---\n").unwrap_or(body);
+    serde_yaml::from_str::<LlmResponse>(body).map_err(|e| {
+        anyhow!(
+            "Model did not return a valid YAML response body: {}\n--- content start ---\n{}\n--- content end ---",
+            e,
+            content
+        )
+    })
+}
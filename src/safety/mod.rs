@@ -1,9 +1,100 @@
 use std::path::{Component, Path};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::Config;
+use crate::wire::Plan;
+
+pub mod policy;
+
+/// Normalize a project-relative path for comparisons: NFC (macOS hands out
+/// NFD names), forward slashes, no leading `./`.
+pub fn normalize_rel(p: &str) -> String {
+    let s: String = p.nfc().collect();
+    let s = s.replace('\\', "/");
+    s.trim_start_matches("./").to_string()
+}
+
+/// Check a plan against the project's approval policy (`policy_file`):
+/// denied steps reject the plan, and the review says which steps still need
+/// the final confirmation. Without a policy file everything is confirmed.
+pub fn validate(plan: &Plan, cfg: &Config) -> anyhow::Result<policy::Review> {
+    match policy::Policy::load(Path::new(&cfg.root), &cfg.policy_file)? {
+        Some(p) => p.review(&plan.steps),
+        None => Ok(policy::Review::default()),
+    }
+}
+
+/// Files/dirs that mark a directory as a project root.
+pub const PROJECT_MARKERS: &[&str] = &["package.json", "Cargo.toml", ".git"];
+
+/// Refuse to scan or modify a directory that doesn't look like a project
+/// (e.g. `--root /` or a home directory) unless `force` is set.
+pub fn ensure_project_root(root: &Path, force: bool) -> anyhow::Result<()> {
+    if !root.is_dir() {
+        anyhow::bail!("root {} is not a directory", root.display());
+    }
+    if force || PROJECT_MARKERS.iter().any(|m| root.join(m).exists()) {
+        return Ok(());
+    }
+    let shown = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    anyhow::bail!(
+        "{} does not look like a project root (none of {} found).\n\
+         Point --root at your project, or pass --force-root if this is intentional.",
+        shown.display(),
+        PROJECT_MARKERS.join(", ")
+    )
+}
+
+/// First symlink on the way from `root` to `rel` that resolves outside the
+/// project (or dangles), as a relative path. Links listed in `link_allowlist`
+/// (or under a listed dir) are intentional, e.g. pnpm workspace packages.
+pub fn escaping_symlink(root: &Path, rel: &str, link_allowlist: &[String]) -> Option<String> {
+    let root_abs = root.canonicalize().ok()?;
+    let mut cur = String::new();
+    for seg in normalize_rel(rel).split('/').filter(|s| !s.is_empty() && *s != ".") {
+        if !cur.is_empty() {
+            cur.push('/');
+        }
+        cur.push_str(seg);
+        let p = root.join(&cur);
+        match std::fs::symlink_metadata(&p) {
+            Ok(m) if m.file_type().is_symlink() => {
+                if rel_matches_allowlist(&cur, link_allowlist) {
+                    return None;
+                }
+                match p.canonicalize() {
+                    Ok(target) if target.starts_with(&root_abs) => {}
+                    _ => return Some(cur),
+                }
+            }
+            Ok(_) => {}
+            // nothing on disk past this point, so no more links to follow
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+/// Unicode-aware case-insensitive equality of two path strings.
+pub fn path_eq_ignore_case(a: &str, b: &str) -> bool {
+    normalize_rel(a).to_lowercase() == normalize_rel(b).to_lowercase()
+}
+
+/// True if `rel` equals an allowlist entry or lies under it as a directory
+/// (segment-aware: "src" allows "src/x" but not "srcfoo/x").
+pub fn rel_matches_allowlist(rel: &str, allowlist: &[String]) -> bool {
+    let rel = normalize_rel(rel);
+    allowlist.iter().any(|p| {
+        let p = normalize_rel(p);
+        let p = p.trim_end_matches('/');
+        rel == p || (rel.starts_with(p) && rel[p.len()..].starts_with('/'))
+    })
+}
 
 /// Ensure `candidate` is within `project_root` or matches an allowlisted file.
 pub fn path_is_allowed(candidate: &str, project_root: &str, allowlist: &[String]) -> bool {
     // Direct allow for specific files listed
-    if allowlist.iter().any(|p| p.eq_ignore_ascii_case(candidate)) {
+    if allowlist.iter().any(|p| path_eq_ignore_case(p, candidate)) {
         return true;
     }
 
@@ -11,7 +102,7 @@ pub fn path_is_allowed(candidate: &str, project_root: &str, allowlist: &[String]
     if let Some(first) = Path::new(candidate).components().next() {
         if let Component::Normal(seg) = first {
             let seg = seg.to_string_lossy().to_string();
-            if allowlist.iter().any(|allowed| allowed.eq_ignore_ascii_case(&seg)) {
+            if allowlist.iter().any(|allowed| path_eq_ignore_case(allowed, &seg)) {
                 // also ensure it doesn't escape the root via .. segments
                 return is_within_root(candidate, project_root);
             }
@@ -61,3 +152,46 @@ pub fn command_is_allowed(cmd: &str, allowlist: &[String]) -> bool {
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Path segments mixing ASCII with accented (precomposed), CJK and emoji names.
+    fn segment() -> impl Strategy<Value = String> {
+        "[a-zA-Z0-9._ -]{0,3}[a-zA-Zéüçñåø日本語한글👍][a-zA-Zéüçñåø日本語한글👍._ -]{0,6}"
+    }
+
+    fn rel_path() -> impl Strategy<Value = String> {
+        prop::collection::vec(segment(), 1..4).prop_map(|s| s.join("/"))
+    }
+
+    proptest! {
+        #[test]
+        fn normalize_rel_is_idempotent_and_form_independent(p in rel_path()) {
+            let n = normalize_rel(&p);
+            prop_assert_eq!(&normalize_rel(&n), &n);
+            prop_assert_eq!(&normalize_rel(&p.nfd().collect::<String>()), &n);
+            prop_assert_eq!(&normalize_rel(&p.replace('/', "\\")), &n);
+            prop_assert!(!n.contains('\\'));
+        }
+
+        #[test]
+        fn allowlist_matches_children_in_either_normal_form(dir in rel_path(), tail in rel_path()) {
+            let allow = vec![format!("{}/", dir)];
+            let child = format!("{}/{}", dir, tail);
+            prop_assert!(rel_matches_allowlist(&child, &allow));
+            prop_assert!(rel_matches_allowlist(&child.nfd().collect::<String>(), &allow));
+            prop_assert!(rel_matches_allowlist(&dir, &[dir.nfd().collect::<String>()]));
+        }
+
+        #[test]
+        fn allowlist_does_not_match_sibling_prefixes(dir in rel_path(), suffix in segment(), tail in rel_path()) {
+            let sibling = format!("{}{}/{}", dir, suffix, tail);
+            prop_assume!(normalize_rel(&sibling) != normalize_rel(&dir));
+            prop_assume!(!normalize_rel(&sibling).starts_with(&format!("{}/", normalize_rel(&dir))));
+            prop_assert!(!rel_matches_allowlist(&sibling, &[dir]));
+        }
+    }
+}
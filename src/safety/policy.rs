@@ -0,0 +1,196 @@
+use anyhow::{anyhow, bail, Context, Result};
+use fs_err as fs;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::wire::Step;
+
+/// What a rule says about a matching step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Applied without asking.
+    Allow,
+    /// Applied only after the final confirmation prompt.
+    Confirm,
+    /// The plan is rejected.
+    Deny,
+}
+
+impl Verdict {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "allow" => Some(Verdict::Allow),
+            "require-confirm" | "confirm" => Some(Verdict::Confirm),
+            "deny" => Some(Verdict::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Step kinds a rule can name; `write` is create or update, `*` any step.
+const ACTIONS: &[&str] = &["create", "update", "delete", "command", "test", "write", "*"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    verdict: Verdict,
+    action: String,
+    pattern: glob::Pattern,
+    source: String,
+}
+
+/// `.vibe/policy.yaml`:
+///
+/// ```yaml
+/// default: require-confirm
+/// rules:
+///   - allow update src/app/**
+///   - deny delete **
+///   - require-confirm command *
+/// ```
+///
+/// A rule is `<allow|deny|require-confirm> <action> <glob>`; several may
+/// share a line separated by `;`. Globs match the path of file steps and the
+/// command line of command/test steps. The first matching rule wins; steps
+/// no rule matches get `default` (require-confirm unless set).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PolicyFile {
+    default: Option<String>,
+    rules: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Policy {
+    rules: Vec<Rule>,
+    default: Verdict,
+}
+
+/// A step that is not auto-approved, with the rule that decided it.
+#[derive(Debug, Clone)]
+pub struct Flagged {
+    pub id: String,
+    pub target: String,
+    pub rule: String,
+}
+
+/// Policy decisions for a plan (see `safety::validate`).
+#[derive(Debug, Clone, Default)]
+pub struct Review {
+    /// A policy file was found; without one every apply is confirmed.
+    pub active: bool,
+    pub confirm: Vec<Flagged>,
+}
+
+impl Review {
+    /// Every step was allowed outright, so the final prompt can be skipped
+    /// (in a trusted workspace only; the policy comes with the project).
+    pub fn auto_approved(&self) -> bool {
+        self.active && self.confirm.is_empty()
+    }
+}
+
+fn parse_rule(src: &str) -> Result<Rule> {
+    let mut parts = src.split_whitespace();
+    let (Some(verdict), Some(action)) = (parts.next(), parts.next()) else {
+        bail!("policy rule '{}' must be `<verdict> <action> <glob>`", src);
+    };
+    let verdict = Verdict::parse(verdict)
+        .ok_or_else(|| anyhow!("policy rule '{}': unknown verdict '{}' (allow, deny, require-confirm)", src, verdict))?;
+    let action = action.to_ascii_lowercase();
+    if !ACTIONS.contains(&action.as_str()) {
+        bail!("policy rule '{}': unknown action '{}' ({})", src, action, ACTIONS.join(", "));
+    }
+    // commands contain spaces: the glob is the rest of the rule
+    let glob = parts.collect::<Vec<_>>().join(" ");
+    let glob = if glob.is_empty() { "*".to_string() } else { glob };
+    let pattern = glob::Pattern::new(&glob).with_context(|| format!("policy rule '{}'", src))?;
+    Ok(Rule { verdict, action, pattern, source: src.to_string() })
+}
+
+fn action_of(step: &Step) -> &'static str {
+    match step {
+        Step::Create { .. } => "create",
+        Step::Update { .. } => "update",
+        Step::Delete { .. } => "delete",
+        Step::Command { .. } => "command",
+        Step::Test { .. } => "test",
+    }
+}
+
+/// Path of a file step, command line of a command/test step.
+fn target_of(step: &Step) -> String {
+    match step {
+        Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. } => super::normalize_rel(path),
+        Step::Command { command, .. } | Step::Test { command, .. } => command.trim().to_string(),
+    }
+}
+
+impl Policy {
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: PolicyFile = serde_yaml::from_str(text)?;
+        let default = match &file.default {
+            Some(d) => Verdict::parse(d).ok_or_else(|| anyhow!("policy default '{}' is not allow, deny or require-confirm", d))?,
+            None => Verdict::Confirm,
+        };
+        let rules = file
+            .rules
+            .iter()
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(parse_rule)
+            .collect::<Result<_>>()?;
+        Ok(Self { rules, default })
+    }
+
+    /// The policy at `file` (relative to `root`), if there is one.
+    pub fn load(root: &Path, file: &str) -> Result<Option<Self>> {
+        let path = root.join(file);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path)?;
+        Self::parse(&text).with_context(|| format!("parsing {}", path.display())).map(Some)
+    }
+
+    /// Verdict for `step` and the rule that gave it (`None` for the default).
+    pub fn verdict(&self, step: &Step) -> (Verdict, Option<&str>) {
+        let action = action_of(step);
+        let target = target_of(step);
+        let file_step = !matches!(step, Step::Command { .. } | Step::Test { .. });
+        let opts = glob::MatchOptions { require_literal_separator: file_step, ..Default::default() };
+        self.rules
+            .iter()
+            .find(|r| {
+                let kind = r.action == "*" || r.action == action || (r.action == "write" && matches!(action, "create" | "update"));
+                kind && r.pattern.matches_with(&target, opts)
+            })
+            .map(|r| (r.verdict, Some(r.source.as_str())))
+            .unwrap_or((self.default, None))
+    }
+
+    /// Denied steps fail the whole plan; the rest are split into allowed and
+    /// needing confirmation.
+    pub fn review(&self, steps: &[Step]) -> Result<Review> {
+        let mut review = Review { active: true, confirm: vec![] };
+        let mut denied = Vec::new();
+        for s in steps {
+            let (verdict, rule) = self.verdict(s);
+            let flagged = || Flagged {
+                id: s.id().to_string(),
+                target: format!("{} {}", action_of(s), target_of(s)),
+                rule: rule.unwrap_or("default").to_string(),
+            };
+            match verdict {
+                Verdict::Allow => {}
+                Verdict::Confirm => review.confirm.push(flagged()),
+                Verdict::Deny => denied.push(flagged()),
+            }
+        }
+        if !denied.is_empty() {
+            let lines: Vec<String> = denied.iter().map(|f| format!("  step {} {} (rule: {})", f.id, f.target, f.rule)).collect();
+            bail!("policy rejected {} step(s):\n{}", denied.len(), lines.join("\n"));
+        }
+        Ok(review)
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::{context, deps, doctor, exec, log};
+
+/// Task list, under the project root.
+pub const SCHEDULE_FILE: &str = ".vibe/schedule.yaml";
+const STATE_FILE: &str = ".vibe/schedule.state.json";
+/// The last consolidated report, for CI to pick up.
+pub const REPORT_FILE: &str = ".vibe/schedule.report.json";
+
+/// What a scheduled task does.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Job {
+    /// Re-chunk stale files of the embedding index (or run `reindex_command`).
+    Reindex,
+    /// Dependencies that aren't installed or that nothing imports.
+    Deps,
+    /// `vibe doctor` against the configured providers.
+    Doctor,
+    /// Delete old `.vibe/tx` directories.
+    Prune {
+        #[serde(default = "default_keep_last")]
+        keep_last: usize,
+        #[serde(default = "default_older_than")]
+        older_than: String,
+    },
+}
+
+fn default_keep_last() -> usize {
+    50
+}
+
+fn default_older_than() -> String {
+    "30d".to_string()
+}
+
+/// One entry of `.vibe/schedule.yaml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Task {
+    /// Defaults to the kind; must be unique.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Skip the task until this long (`6h`, `1d`) after its last run.
+    #[serde(default)]
+    pub every: Option<String>,
+    #[serde(flatten)]
+    pub job: Job,
+}
+
+impl Task {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            match self.job {
+                Job::Reindex => "reindex",
+                Job::Deps => "deps",
+                Job::Doctor => "doctor",
+                Job::Prune { .. } => "prune",
+            }
+            .to_string()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Schedule {
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Ran and found something to look at.
+    Warn,
+    Failed,
+    /// Not due yet (`every`) or not selected.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskReport {
+    pub name: String,
+    pub status: Status,
+    pub summary: String,
+    /// Findings, one line each.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    pub duration_ms: u128,
+}
+
+/// What one `vibe schedule` invocation did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub started: DateTime<Utc>,
+    pub tasks: Vec<TaskReport>,
+}
+
+impl Report {
+    pub fn failed(&self) -> bool {
+        self.tasks.iter().any(|t| t.status == Status::Failed)
+    }
+}
+
+pub fn load(root: &Path) -> Result<Schedule> {
+    let path = root.join(SCHEDULE_FILE);
+    let text = fs::read_to_string(&path).with_context(|| format!("no task list at {} (see `vibe schedule --help`)", path.display()))?;
+    let schedule: Schedule = serde_yaml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    let mut names: Vec<String> = schedule.tasks.iter().map(Task::name).collect();
+    names.sort();
+    if let Some(w) = names.windows(2).find(|w| w[0] == w[1]) {
+        anyhow::bail!("{}: two tasks are named '{}'; give one a `name`", path.display(), w[0]);
+    }
+    Ok(schedule)
+}
+
+fn state_path(root: &Path) -> PathBuf {
+    root.join(STATE_FILE)
+}
+
+fn load_state(root: &Path) -> BTreeMap<String, DateTime<Utc>> {
+    fs::read_to_string(state_path(root)).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+fn due(task: &Task, last: Option<&DateTime<Utc>>) -> Result<bool> {
+    let (Some(every), Some(last)) = (&task.every, last) else { return Ok(true) };
+    let every = crate::ux::input::parse_duration(every).map_err(|e| anyhow::anyhow!("task '{}': every: {}", task.name(), e))?;
+    Ok((Utc::now() - *last).to_std().map(|since| since >= every).unwrap_or(false))
+}
+
+/// Findings become `Warn`, a task error `Failed`.
+type Outcome = (Status, String, Vec<String>);
+
+fn reindex(root: &Path, vibe_out: &Path, cfg: &Config) -> Result<Outcome> {
+    if let Some(cmd) = &cfg.reindex_command {
+        if !cfg.trusted {
+            anyhow::bail!("reindex_command needs a trusted workspace; {}", crate::trust::HOW_TO_TRUST);
+        }
+        let res = exec::run_command_allowlisted(cmd, cfg, Some(&cfg.root), cfg.timeout_secs)?;
+        return Ok(if res.status == 0 {
+            (Status::Ok, format!("ran `{}`", cmd), vec![])
+        } else {
+            (Status::Failed, format!("`{}` exited with {}", cmd, res.status), vec![])
+        });
+    }
+    let Some(index) = context::embeddings::EmbeddingIndex::load(vibe_out).ok().filter(|i| !i.chunks.is_empty()) else {
+        return Ok((Status::Skipped, format!("no index in {}", vibe_out.display()), vec![]));
+    };
+    let fresh = index.freshness(root);
+    if fresh.stale_files.is_empty() {
+        return Ok((Status::Ok, format!("{} chunk(s), all fresh", fresh.chunks), vec![]));
+    }
+    reindex_stale(vibe_out, root, &fresh.stale_files)
+}
+
+#[cfg(feature = "watch")]
+fn reindex_stale(vibe_out: &Path, root: &Path, stale: &[String]) -> Result<Outcome> {
+    let n = context::embeddings::reindex_files(vibe_out, root, stale)?;
+    Ok((Status::Ok, format!("re-chunked {} stale file(s) into {} chunk(s)", stale.len(), n), stale.to_vec()))
+}
+
+#[cfg(not(feature = "watch"))]
+fn reindex_stale(_vibe_out: &Path, _root: &Path, stale: &[String]) -> Result<Outcome> {
+    Ok((
+        Status::Warn,
+        format!("{} stale file(s); set reindex_command or build with the `watch` feature to re-index", stale.len()),
+        stale.to_vec(),
+    ))
+}
+
+fn vet_deps(root: &Path) -> Result<Outcome> {
+    let all = deps::vet(root)?;
+    let node_modules = root.join("node_modules").is_dir();
+    let mut details = Vec::new();
+    for d in &all {
+        if node_modules && d.installed.is_none() {
+            details.push(format!("{} {} is not installed", d.package, d.declared));
+        }
+        if !d.dev && !d.imported {
+            details.push(format!("{} is a dependency but nothing imports it", d.package));
+        }
+    }
+    let summary = format!("{} package(s), {} finding(s)", all.len(), details.len());
+    Ok((if details.is_empty() { Status::Ok } else { Status::Warn }, summary, details))
+}
+
+async fn check_providers(args: &Args, cfg: &Config) -> Result<Outcome> {
+    let report = doctor::run(args, cfg).await?;
+    let failing: Vec<String> = report
+        .iter()
+        .filter(|h| !h.ok)
+        .map(|h| format!("{}: {}", h.target, h.note.as_deref().unwrap_or("failed")))
+        .collect();
+    let summary = format!("{}/{} provider(s) reachable", report.len() - failing.len(), report.len());
+    Ok((if failing.is_empty() { Status::Ok } else { Status::Failed }, summary, failing))
+}
+
+fn prune(root: &Path, keep_last: usize, older_than: &str) -> Result<Outcome> {
+    let age = crate::ux::input::parse_duration(older_than).map_err(|e| anyhow::anyhow!("older_than: {}", e))?;
+    let removed = log::prune_tx(root, keep_last, age)?;
+    Ok((Status::Ok, format!("removed {} transaction(s) older than {}", removed.len(), older_than), removed))
+}
+
+/// Run the tasks of `.vibe/schedule.yaml` that are due (all with `force`),
+/// or only those named in `only`. Meant for cron/CI: one task failing
+/// doesn't stop the others, and the report is saved to `REPORT_FILE`.
+pub async fn run(args: &Args, cfg: &Config, only: &[String], force: bool) -> Result<Report> {
+    let root = Path::new(&cfg.root);
+    let vibe_out = Path::new(&args.vibe_out);
+    let schedule = load(root)?;
+    let mut state = load_state(root);
+    let mut report = Report { started: Utc::now(), tasks: Vec::new() };
+
+    for task in &schedule.tasks {
+        let name = task.name();
+        let started = Instant::now();
+        let selected = only.is_empty() || only.contains(&name);
+        // a bad `every` fails this task, not the whole run
+        let is_due = if force { Ok(true) } else { due(task, state.get(&name)) };
+        let outcome = match (selected, is_due) {
+            (false, _) => Ok((Status::Skipped, "not selected".to_string(), vec![])),
+            (true, Err(e)) => Err(e),
+            (true, Ok(false)) => {
+                let last = state.get(&name).map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string()).unwrap_or_default();
+                Ok((Status::Skipped, format!("not due (last run {}, every {})", last, task.every.as_deref().unwrap_or("")), vec![]))
+            }
+            (true, Ok(true)) => {
+                let ran = match &task.job {
+                    Job::Reindex => reindex(root, vibe_out, cfg),
+                    Job::Deps => vet_deps(root),
+                    Job::Doctor => check_providers(args, cfg).await,
+                    Job::Prune { keep_last, older_than } => prune(root, *keep_last, older_than),
+                };
+                state.insert(name.clone(), Utc::now());
+                ran
+            }
+        };
+        let (status, summary, details) = outcome.unwrap_or_else(|e| (Status::Failed, format!("{:#}", e), vec![]));
+        report.tasks.push(TaskReport { name, status, summary, details, duration_ms: started.elapsed().as_millis() });
+    }
+
+    fs::create_dir_all(root.join(".vibe"))?;
+    fs::write(state_path(root), serde_json::to_string_pretty(&state)?)?;
+    fs::write(root.join(REPORT_FILE), serde_json::to_string_pretty(&report)?)?;
+    Ok(report)
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::log;
+use crate::prefs::Preferences;
+
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// One task run (transaction) with every JSON artifact saved for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub tx: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub task: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// File name (e.g. `plan.request.json`) -> parsed JSON content.
+    pub artifacts: BTreeMap<String, Value>,
+}
+
+/// Portable session state: turn history, applied tx ids and learned preferences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub root: String,
+    pub turns: Vec<Turn>,
+    pub applied_tx: Vec<Uuid>,
+    pub memory: Preferences,
+}
+
+fn read_turn(dir: &Path, tx: Uuid) -> Result<Turn> {
+    let mut artifacts = BTreeMap::new();
+    for (name, _) in log::list_artifacts(dir)? {
+        let Some(s) = log::read_artifact(dir, &name)? else { continue };
+        if let Ok(v) = serde_json::from_str::<Value>(&s) {
+            artifacts.insert(name, v);
+        }
+    }
+    let req = artifacts.get("plan.request.json");
+    let task = req
+        .and_then(|v| v.get("task"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let timestamp = req
+        .and_then(|v| v.pointer("/transaction/timestamp"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+    let summary = ["codegen.response.json", "plan.strict.response.json", "plan.response.json"]
+        .iter()
+        .find_map(|n| artifacts.get(*n)?.pointer("/plan/summary")?.as_str().map(|s| s.to_string()));
+    Ok(Turn { tx, timestamp, task, summary, artifacts })
+}
+
+/// Collect every transaction under `.vibe/tx`, oldest first.
+pub fn load_turns(root: &Path) -> Result<Vec<Turn>> {
+    let base = log::tx_root(root);
+    let mut turns = Vec::new();
+    if !base.exists() {
+        return Ok(turns);
+    }
+    for entry in fs::read_dir(&base)? {
+        let entry = entry?;
+        let Ok(tx) = Uuid::parse_str(&entry.file_name().to_string_lossy()) else { continue };
+        if entry.path().is_dir() {
+            turns.push(read_turn(&entry.path(), tx)?);
+        }
+    }
+    turns.sort_by_key(|t| t.timestamp);
+    Ok(turns)
+}
+
+pub fn export(root: &Path, out: &Path) -> Result<SessionBundle> {
+    let turns = load_turns(root)?;
+    let applied_tx = turns
+        .iter()
+        .filter(|t| t.artifacts.contains_key("apply.json"))
+        .map(|t| t.tx)
+        .collect();
+    let bundle = SessionBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()).display().to_string(),
+        turns,
+        applied_tx,
+        memory: Preferences::load(root).unwrap_or_default(),
+    };
+    fs::write(out, serde_json::to_string_pretty(&bundle)?)?;
+    Ok(bundle)
+}
+
+/// A plain `*.json` file name: no separators, `..` or root.
+fn is_artifact_name(name: &str) -> bool {
+    let mut parts = Path::new(name).components();
+    matches!((parts.next(), parts.next()), (Some(std::path::Component::Normal(_)), None))
+        && !name.contains(['/', '\\', ':'])
+        && name.len() > ".json".len()
+        && name.ends_with(".json")
+}
+
+/// Restore a bundle into `root`: missing tx directories are recreated and
+/// preferences are merged (local entries win on conflicts).
+pub fn import(root: &Path, file: &Path) -> Result<SessionBundle> {
+    let s = fs::read_to_string(file)?;
+    let bundle: SessionBundle =
+        serde_json::from_str(&s).with_context(|| format!("parsing session bundle {}", file.display()))?;
+    if bundle.version > BUNDLE_VERSION {
+        anyhow::bail!("session bundle version {} is newer than supported ({})", bundle.version, BUNDLE_VERSION);
+    }
+
+    // names are joined under .vibe/tx/<id>/: nothing that could leave it
+    if let Some(bad) = bundle.turns.iter().flat_map(|t| t.artifacts.keys()).find(|n| !is_artifact_name(n)) {
+        anyhow::bail!("session bundle has an invalid artifact name {:?} (expected a plain *.json file name)", bad);
+    }
+
+    for t in &bundle.turns {
+        let dir = log::tx_dir(root, t.tx);
+        fs::create_dir_all(&dir)?;
+        for (name, v) in &t.artifacts {
+            if log::read_artifact(&dir, name)?.is_none() {
+                log::crypt::write(&dir.join(name), serde_json::to_string_pretty(v)?.as_bytes())?;
+            }
+        }
+    }
+
+    let mut prefs = Preferences::load(root).unwrap_or_default();
+    for n in &bundle.memory.notes {
+        if !prefs.notes.contains(n) {
+            prefs.notes.push(n.clone());
+        }
+    }
+    for (k, c) in &bundle.memory.decisions {
+        prefs.decisions.entry(k.clone()).or_insert_with(|| c.clone());
+    }
+    prefs.save(root)?;
+    Ok(bundle)
+}
@@ -0,0 +1,147 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::context::routes;
+use crate::exec::managed::ManagedProcess;
+use crate::exec::policy::{detect_package_manager, PackageManager};
+
+/// Console patterns that indicate a hydration/render problem for a route.
+const HYDRATION_MARKERS: &[&str] = &[
+    "Hydration failed",
+    "hydration mismatch",
+    "Text content does not match",
+    "did not match. Server:",
+    "There was an error while hydrating",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteHealth {
+    pub route: String,
+    pub file: String,
+    /// HTTP status; `None` if skipped (dynamic) or the request failed.
+    pub status: Option<u16>,
+    pub duration_ms: u128,
+    /// Hydration/error lines printed by the dev server while serving this route.
+    pub console_errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl RouteHealth {
+    pub fn healthy(&self) -> bool {
+        self.note.as_deref() == Some("dynamic")
+            || (matches!(self.status, Some(s) if s < 400) && self.console_errors.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmokeReport {
+    pub command: String,
+    pub base_url: String,
+    pub routes: Vec<RouteHealth>,
+    pub healthy: bool,
+}
+
+/// Dev server command: `smoke_command` or `<package manager> run dev`.
+fn dev_command(root: &Path, cfg: &Config) -> String {
+    if let Some(c) = &cfg.smoke_command {
+        return c.clone();
+    }
+    match detect_package_manager(root) {
+        PackageManager::Npm => "npm run dev".into(),
+        PackageManager::Pnpm => "pnpm dev".into(),
+        PackageManager::Yarn | PackageManager::YarnBerry => "yarn dev".into(),
+        PackageManager::Bun => "bun run dev".into(),
+    }
+}
+
+fn console_errors(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|l| {
+            let t = l.trim_start();
+            HYDRATION_MARKERS.iter().any(|m| l.contains(m))
+                || t.starts_with("Error:")
+                || t.starts_with("⨯")
+                || t.contains("Unhandled Runtime Error")
+        })
+        .map(|l| l.trim().to_string())
+        .collect()
+}
+
+async fn wait_ready(client: &reqwest::Client, base: &str, server: &mut ManagedProcess, secs: u64) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        if client.get(base).send().await.is_ok() {
+            return Ok(());
+        }
+        if !server.is_running() {
+            bail!("dev server exited before becoming ready:\n{}", server.output_since(0));
+        }
+        if started.elapsed() >= Duration::from_secs(secs) {
+            bail!("dev server not ready on {} after {}s", base, secs);
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Start the dev server, request every static route from the routes index,
+/// and collect status codes plus hydration errors from the server output.
+pub async fn run(root: &Path, cfg: &Config) -> Result<SmokeReport> {
+    let command = dev_command(root, cfg);
+    if !cfg.trusted {
+        bail!("workspace is not trusted; refusing to start `{}`", command);
+    }
+    if !crate::safety::command_is_allowed(&command, &cfg.command_allowlist) {
+        bail!("dev server command not in command_allowlist: {}", command);
+    }
+
+    let base_url = format!("http://localhost:{}", cfg.smoke_port);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(cfg.timeout_secs.min(120)))
+        .build()?;
+
+    let mut server = ManagedProcess::spawn(&command, &root.to_string_lossy())?;
+    wait_ready(&client, &base_url, &mut server, cfg.smoke_startup_secs).await?;
+
+    let mut results = Vec::new();
+    for r in routes::discover(root) {
+        let mut h = RouteHealth {
+            route: r.route.clone(),
+            file: r.file.clone(),
+            status: None,
+            duration_ms: 0,
+            console_errors: vec![],
+            note: None,
+        };
+        if r.dynamic {
+            h.note = Some("dynamic".into());
+            results.push(h);
+            continue;
+        }
+        let mark = server.output_len();
+        let started = Instant::now();
+        match client.get(format!("{}{}", base_url, r.route)).send().await {
+            Ok(resp) => {
+                h.status = Some(resp.status().as_u16());
+                let body = resp.text().await.unwrap_or_default();
+                if body.contains("data-next-error-message") {
+                    h.console_errors.push("error overlay rendered in response".into());
+                }
+            }
+            Err(e) => h.note = Some(format!("request failed: {}", e)),
+        }
+        h.duration_ms = started.elapsed().as_millis();
+        // give the server a moment to flush logs for this request
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        h.console_errors.extend(console_errors(&server.output_since(mark)));
+        results.push(h);
+    }
+    server.stop();
+
+    let healthy = results.iter().all(|r| r.healthy());
+    Ok(SmokeReport { command, base_url, routes: results, healthy })
+}
@@ -0,0 +1,196 @@
+use regex::Regex;
+use std::collections::BTreeSet;
+
+use crate::merge::has_use_client_top;
+
+/// A component pulled out of an oversized page into a colocated file.
+#[derive(Debug, Clone)]
+pub struct Extracted {
+    pub name: String,
+    /// Project-relative path, e.g. `src/app/about/_components/Hero.tsx`.
+    pub path: String,
+    pub content: String,
+}
+
+/// Line span `[start, end]` of a top-level declaration.
+struct Decl {
+    name: String,
+    start: usize,
+    end: usize,
+    /// Capitalized, non-exported function/const: a candidate for extraction.
+    component: bool,
+    is_arrow: bool,
+}
+
+fn line_depth(line: &str) -> i64 {
+    let opens = line.matches('{').count() + line.matches('(').count();
+    let closes = line.matches('}').count() + line.matches(')').count();
+    opens as i64 - closes as i64
+}
+
+fn top_level_decls(lines: &[&str]) -> Vec<Decl> {
+    let re = Regex::new(
+        r"^(export\s+)?(default\s+)?(async\s+)?(function|const|let|var|type|interface|enum|class)\s+([A-Za-z_$][\w$]*)",
+    )
+    .expect("static regex");
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(c) = re.captures(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let exported = c.get(1).is_some();
+        let kw = c.get(4).map(|m| m.as_str()).unwrap_or("");
+        let name = c.get(5).map(|m| m.as_str().to_string()).unwrap_or_default();
+
+        // walk to the line where braces/parens balance again
+        let mut depth = 0i64;
+        let mut opened = false;
+        let mut end = i;
+        for (j, l) in lines.iter().enumerate().skip(i) {
+            depth += line_depth(l);
+            opened |= l.contains('{') || l.contains('(');
+            end = j;
+            if opened && depth <= 0 {
+                break;
+            }
+            if !opened && l.trim_end().ends_with(';') {
+                break;
+            }
+        }
+        out.push(Decl {
+            component: !exported
+                && matches!(kw, "function" | "const")
+                && name.chars().next().map(|ch| ch.is_ascii_uppercase()).unwrap_or(false),
+            is_arrow: kw == "const",
+            name,
+            start: i,
+            end,
+        });
+        i = end + 1;
+    }
+    out
+}
+
+/// Import statements (possibly multi-line) with the index of their last line.
+fn import_statements(lines: &[&str]) -> Vec<(String, usize)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !lines[i].trim_start().starts_with("import ") {
+            i += 1;
+            continue;
+        }
+        let mut stmt = lines[i].to_string();
+        let mut j = i;
+        while !(stmt.contains(" from ") || stmt.trim_end().ends_with(';') || stmt.contains("import \"") || stmt.contains("import '"))
+            && j + 1 < lines.len()
+        {
+            j += 1;
+            stmt.push('\n');
+            stmt.push_str(lines[j]);
+        }
+        out.push((stmt, j));
+        i = j + 1;
+    }
+    out
+}
+
+fn mentions(text: &str, ident: &str) -> bool {
+    Regex::new(&format!(r"\b{}\b", regex::escape(ident)))
+        .map(|r| r.is_match(text))
+        .unwrap_or(false)
+}
+
+/// Deterministically split `page` into colocated `_components/<Name>.tsx`
+/// files. Only self-contained, non-exported components are moved (ones that
+/// reference other top-level declarations of the page stay put). Returns the
+/// lean page and the extracted files, or `None` if nothing could be moved.
+pub fn split_page(path: &str, page: &str) -> Option<(String, Vec<Extracted>)> {
+    let path = path.replace('\\', "/");
+    let dir = path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+    let lines: Vec<&str> = page.lines().collect();
+    let decls = top_level_decls(&lines);
+    let imports = import_statements(&lines);
+    let client = has_use_client_top(page);
+
+    let mut moved: BTreeSet<usize> = BTreeSet::new();
+    let mut extracted = Vec::new();
+    for (k, d) in decls.iter().enumerate() {
+        if !d.component {
+            continue;
+        }
+        let body = lines[d.start..=d.end].join("\n");
+        let self_contained = decls
+            .iter()
+            .filter(|o| o.name != d.name)
+            .all(|o| !mentions(&body, &o.name));
+        if !self_contained {
+            continue;
+        }
+
+        let mut content = String::new();
+        if client {
+            content.push_str("'use client'\n\n");
+        }
+        for (imp, _) in &imports {
+            // keep imports whose bindings the component uses (side-effect imports stay in the page)
+            let bindings = imp.split(" from ").next().unwrap_or("");
+            let used = bindings
+                .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .filter(|w| !w.is_empty() && !matches!(*w, "import" | "type" | "as"))
+                .any(|w| mentions(&body, w));
+            if used && imp.contains(" from ") {
+                content.push_str(imp);
+                content.push('\n');
+            }
+        }
+        content.push('\n');
+        if d.is_arrow {
+            content.push_str(&body);
+            content.push_str(&format!("\n\nexport default {};\n", d.name));
+        } else {
+            content.push_str(&format!("export default {}\n", body.trim_start()));
+        }
+        extracted.push(Extracted {
+            name: d.name.clone(),
+            path: format!("{}/_components/{}.tsx", dir, d.name),
+            content,
+        });
+        moved.insert(k);
+    }
+    if extracted.is_empty() {
+        return None;
+    }
+
+    let mut skip = vec![false; lines.len()];
+    for k in &moved {
+        let d = &decls[*k];
+        skip[d.start..=d.end].iter_mut().for_each(|s| *s = true);
+    }
+    let last_import = imports.last().map(|(_, end)| *end);
+    let mut out: Vec<String> = Vec::new();
+    if last_import.is_none() {
+        out.extend(extracted.iter().map(|e| format!("import {} from \"./_components/{}\";", e.name, e.name)));
+    }
+    for (i, l) in lines.iter().enumerate() {
+        if !skip[i] {
+            out.push(l.to_string());
+        }
+        if Some(i) == last_import {
+            out.extend(extracted.iter().map(|e| format!("import {} from \"./_components/{}\";", e.name, e.name)));
+        }
+    }
+    // collapse blank runs left behind by removed blocks
+    let mut page_out = String::new();
+    let mut blank = 0;
+    for l in out {
+        blank = if l.trim().is_empty() { blank + 1 } else { 0 };
+        if blank <= 1 {
+            page_out.push_str(&l);
+            page_out.push('\n');
+        }
+    }
+    Some((page_out, extracted))
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::wire::{Instruction, LlmRequest, LlmResponse, Plan};
+
+/// A user-defined pipeline stage run between PLAN and CODEGEN (e.g. a design
+/// review). Templates may use `{task}`, `{plan_summary}`, `{plan_steps}`,
+/// `{plan_json}` and `{previous_stages}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageConfig {
+    pub name: String,
+    pub system: String,
+    pub user_template: String,
+    #[serde(default)]
+    pub developer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageOutput {
+    pub name: String,
+    pub content: String,
+}
+
+fn plan_steps_text(plan: &Plan) -> String {
+    let mut s = String::new();
+    for (i, step) in plan.steps.iter().enumerate() {
+        let line = serde_json::to_string(step).unwrap_or_default();
+        s.push_str(&format!("{}. {}\n", i + 1, line));
+    }
+    s
+}
+
+fn previous_text(previous: &[StageOutput]) -> String {
+    if previous.is_empty() {
+        return "(none)".to_string();
+    }
+    previous
+        .iter()
+        .map(|o| format!("[{}]\n{}", o.name, o.content))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render(template: &str, task: &str, plan: &Plan, previous: &[StageOutput]) -> String {
+    template
+        .replace("{task}", task)
+        .replace("{plan_summary}", &plan.summary)
+        .replace("{plan_steps}", &plan_steps_text(plan))
+        .replace("{plan_json}", &serde_json::to_string_pretty(plan).unwrap_or_default())
+        .replace("{previous_stages}", &previous_text(previous))
+}
+
+/// Build the request for `stage` from the PLAN request (same context/snapshot),
+/// swapping in the stage's own rendered prompts.
+pub fn build_request(
+    stage: &StageConfig,
+    base: &LlmRequest,
+    task: &str,
+    plan: &Plan,
+    previous: &[StageOutput],
+) -> LlmRequest {
+    let mut req = base.clone();
+    req.instruction = Instruction {
+        system: format!("{}\n\n{}", render(&stage.system, task, plan, previous), crate::prompt::stage_wire_contract()),
+        user: render(&stage.user_template, task, plan, previous),
+        developer: stage.developer.as_ref().map(|d| render(d, task, plan, previous)),
+    };
+    req
+}
+
+/// Text a stage contributes: the answer content, or the plan summary if the
+/// model answered with a plan anyway.
+pub fn output_text(resp: &LlmResponse) -> String {
+    if let Some(a) = &resp.answer {
+        return a.content.clone();
+    }
+    resp.plan.as_ref().map(|p| p.summary.clone()).unwrap_or_default()
+}
+
+/// Block appended to the CODEGEN user prompt.
+pub fn context_block(outputs: &[StageOutput]) -> String {
+    if outputs.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\nAdditional pipeline stage outputs (apply their guidance):\n{}",
+        previous_text(outputs)
+    )
+}
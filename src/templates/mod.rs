@@ -0,0 +1,188 @@
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::wire::Step;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateAction {
+    Create,
+    Delete,
+    Command,
+    Test,
+}
+
+/// A step the user can inject while editing a plan: `add <name> <args...>`.
+/// `{param}` placeholders in title/path/command/content are substituted from
+/// the positional args; the last param takes the rest of the line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepTemplate {
+    pub name: String,
+    pub action: TemplateAction,
+    #[serde(default)]
+    pub params: Vec<String>,
+    pub title: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+pub fn default_step_templates() -> Vec<StepTemplate> {
+    let t = |name: &str, action, params: &[&str], title: &str, command: Option<&str>| StepTemplate {
+        name: name.to_string(),
+        action,
+        params: params.iter().map(|p| p.to_string()).collect(),
+        title: title.to_string(),
+        path: None,
+        command: command.map(|c| c.to_string()),
+        content: None,
+        group: None,
+    };
+    vec![
+        t("test", TemplateAction::Test, &["path"], "Run tests for {path}", Some("npm test -- {path}")),
+        t("command", TemplateAction::Command, &["cmd"], "Run {cmd}", Some("{cmd}")),
+        StepTemplate {
+            path: Some("{path}".into()),
+            ..t("delete", TemplateAction::Delete, &["path"], "Delete {path}", None)
+        },
+    ]
+}
+
+/// Whether `command` is what one of the Test templates renders to: the
+/// template's literal text with each `{param}` filled by arguments without
+/// shell syntax. The plan editor lets such test steps run without being in
+/// `command_allowlist`.
+pub fn is_test_command(templates: &[StepTemplate], command: &str) -> bool {
+    let command = command.trim();
+    templates
+        .iter()
+        .filter(|t| t.action == TemplateAction::Test)
+        .filter_map(|t| t.command.as_deref())
+        .any(|c| renders_to(c.trim(), command))
+}
+
+/// A template that starts with a placeholder could render to any command,
+/// so it never matches.
+fn renders_to(template: &str, command: &str) -> bool {
+    if template.starts_with('{') {
+        return false;
+    }
+    let mut pattern = String::from("^");
+    let mut rest = template;
+    while let Some((open, close)) = rest.find('{').and_then(|o| Some((o, o + rest[o..].find('}')?))) {
+        pattern.push_str(&regex::escape(&rest[..open]));
+        pattern.push_str(r"[^;&|$`<>()\\\n\r]+");
+        rest = &rest[close + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(command))
+}
+
+fn bind(params: &[String], args: &str) -> Result<Vec<(String, String)>> {
+    let mut rest = args.trim();
+    let mut out = Vec::new();
+    for (i, p) in params.iter().enumerate() {
+        if rest.is_empty() {
+            bail!("missing argument <{}>", p);
+        }
+        let value = if i + 1 == params.len() {
+            std::mem::take(&mut rest)
+        } else {
+            let (v, r) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = r.trim_start();
+            v
+        };
+        out.push((p.clone(), value.to_string()));
+    }
+    Ok(out)
+}
+
+fn render(s: &str, bound: &[(String, String)]) -> String {
+    bound
+        .iter()
+        .fold(s.to_string(), |acc, (k, v)| acc.replace(&format!("{{{}}}", k), v))
+}
+
+/// Build a step from template `t` with `args`; `id` must be unique in the plan.
+pub fn instantiate(t: &StepTemplate, args: &str, id: String) -> Result<Step> {
+    let bound = bind(&t.params, args)?;
+    let r = |o: &Option<String>| o.as_deref().map(|s| render(s, &bound));
+    let title = render(&t.title, &bound);
+    let group = t.group.clone();
+    let need = |v: Option<String>, field: &str| match v {
+        Some(v) if !v.trim().is_empty() => Ok(v),
+        _ => Err(anyhow::anyhow!("template '{}' has no {}", t.name, field)),
+    };
+    Ok(match t.action {
+        TemplateAction::Create => Step::Create {
+            id,
+            title,
+            group,
+            path: need(r(&t.path), "path")?,
+            language: None,
+            content: Some(r(&t.content).unwrap_or_default()),
+        },
+        TemplateAction::Delete => Step::Delete { id, title, group, path: need(r(&t.path), "path")? },
+        TemplateAction::Command => Step::Command { id, title, group, command: need(r(&t.command), "command")?, cwd: None },
+        TemplateAction::Test => Step::Test { id, title, group, command: need(r(&t.command), "command")? },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_template(command: &str) -> StepTemplate {
+        StepTemplate {
+            name: "t".into(),
+            action: TemplateAction::Test,
+            params: vec!["path".into()],
+            title: "t".into(),
+            path: None,
+            command: Some(command.into()),
+            content: None,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn default_test_template_matches_its_rendering() {
+        let templates = default_step_templates();
+        assert!(is_test_command(&templates, "npm test -- src/app/page.tsx"));
+        assert!(is_test_command(&templates, "  npm test -- src/app/page.tsx  "));
+        assert!(!is_test_command(&templates, "npm test -- "));
+        assert!(!is_test_command(&templates, "npm run build"));
+        // `command` is a Command template, not a Test one
+        assert!(!is_test_command(&templates, "rm -rf src"));
+    }
+
+    #[test]
+    fn shell_syntax_in_arguments_is_rejected() {
+        let templates = default_step_templates();
+        for arg in ["a; rm -rf ~", "a && curl x", "a | sh", "$(whoami)", "`id`", "a > /etc/passwd", "a < x", "(a)", "a\\b", "a\nrm x", "a\rb"] {
+            assert!(!is_test_command(&templates, &format!("npm test -- {}", arg)), "{:?}", arg);
+        }
+    }
+
+    #[test]
+    fn literal_text_after_a_placeholder_must_match() {
+        let templates = vec![test_template("npx vitest run {path} --reporter=dot")];
+        assert!(is_test_command(&templates, "npx vitest run src/a.test.ts --reporter=dot"));
+        assert!(!is_test_command(&templates, "npx vitest run src/a.test.ts"));
+        assert!(!is_test_command(&templates, "npx evil --reporter=dot"));
+    }
+
+    #[test]
+    fn template_starting_with_a_placeholder_never_matches() {
+        let templates = vec![test_template("{cmd}"), test_template("{runner} test {path}")];
+        assert!(!is_test_command(&templates, "npm test src"));
+        assert!(!is_test_command(&templates, "curl evil.sh"));
+    }
+}
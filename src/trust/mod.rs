@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::ux;
+
+/// One trusted project, keyed by the hash of its canonical root path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedEntry {
+    pub hash: String,
+    pub trusted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    pub trusted: Vec<TrustedEntry>,
+}
+
+/// How a user trusts a workspace, for errors about untrusted ones.
+pub const HOW_TO_TRUST: &str = "answer yes to the trust prompt of an interactive vibe run there (kept in ~/.config/vibe/trusted.json)";
+
+/// `~/.config/vibe/trusted.json` (HOME on *nix, USERPROFILE on Windows).
+pub fn store_path() -> Option<PathBuf> {
+    Some(crate::config::user_config_dir()?.join("trusted.json"))
+}
+
+/// Stable identifier for a project root: sha256 of its canonical path.
+pub fn root_hash(root: &Path) -> String {
+    let canon = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = Sha256::digest(canon.to_string_lossy().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_store(path: &Path) -> Result<TrustStore> {
+    if !path.exists() {
+        return Ok(TrustStore::default());
+    }
+    let s = fs::read_to_string(path)?;
+    serde_json::from_str(&s).with_context(|| format!("parsing {}", path.display()))
+}
+
+pub fn is_trusted(root: &Path) -> Result<bool> {
+    let Some(path) = store_path() else { return Ok(false) };
+    let store = load_store(&path)?;
+    let hash = root_hash(root);
+    Ok(store.trusted.iter().any(|e| e.hash == hash))
+}
+
+pub fn trust(root: &Path) -> Result<()> {
+    let path = store_path().ok_or_else(|| anyhow::anyhow!("cannot locate home directory for trust store"))?;
+    let mut store = load_store(&path)?;
+    let hash = root_hash(root);
+    if !store.trusted.iter().any(|e| e.hash == hash) {
+        store.trusted.push(TrustedEntry { hash, trusted_at: Utc::now() });
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&store)?)?;
+    Ok(())
+}
+
+/// Returns whether the project at `root` is trusted, asking the user the first
+/// time vibe runs there. Untrusted projects run with commands disabled and
+/// deletes blocked. A `--confirm-timeout` running out leaves the project
+/// untrusted, even with `--confirm-default apply`.
+pub fn ensure_trusted(root: &Path) -> Result<bool> {
+    if is_trusted(root)? {
+        return Ok(true);
+    }
+    println!(
+        "\nThis is the first time vibe runs in {}.\nUntrusted projects run with commands disabled and deletes blocked.",
+        root.canonicalize().unwrap_or_else(|_| root.to_path_buf()).display()
+    );
+    if ux::confirm_declining_on_timeout("Trust this project?") {
+        trust(root)?;
+        Ok(true)
+    } else {
+        println!("Continuing in untrusted mode (commands disabled, deletes blocked).");
+        Ok(false)
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::patch::{self, ChangeKind, Preview};
+use crate::{backup, git, log};
+
+/// One file a past transaction changed, before and after it (`None`: absent).
+#[derive(Debug, Clone)]
+pub struct TxFile {
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The files `tx` changed: from its commits when `--git-commit` made them
+/// (hook changes included), else from the copies in its backup.
+pub fn files(root: &Path, tx: Uuid) -> Result<Vec<TxFile>> {
+    let tx_git: Option<git::TxGit> = log::read_artifact(&log::tx_dir(root, tx), "git.json")?
+        .and_then(|s| serde_json::from_str(&s).ok());
+    let commits = tx_git
+        .map(|g| if g.commits.is_empty() { g.commit.into_iter().collect() } else { g.commits })
+        .filter(|c: &Vec<String>| !c.is_empty() && git::is_repo(root));
+    if let Some(commits) = commits {
+        let parent = format!("{}^", commits[0]);
+        let last = &commits[commits.len() - 1];
+        let mut paths: Vec<String> = Vec::new();
+        for c in &commits {
+            for p in git::files_in_commit(root, c)? {
+                if !paths.contains(&p) {
+                    paths.push(p);
+                }
+            }
+        }
+        return Ok(paths
+            .into_iter()
+            .map(|path| TxFile { before: git::show_file(root, &parent, &path), after: git::show_file(root, last, &path), path })
+            .collect());
+    }
+    let entries = match backup::load_manifest(root, tx) {
+        Ok(e) => e,
+        Err(_) => bail!("transaction {} has neither a commit (git.json) nor a backup to diff", tx),
+    };
+    let mut out = Vec::new();
+    for e in entries {
+        let before = backup::original_content(root, tx, &e.path)?;
+        let after = backup::applied_content(root, tx, &e.path)?;
+        out.push(TxFile { path: e.path, before, after });
+    }
+    Ok(out)
+}
+
+/// `files` as previews carrying their whole diff, for `patch::colorize_preview`.
+pub fn previews(files: &[TxFile]) -> Vec<Preview> {
+    files
+        .iter()
+        .filter(|f| f.before != f.after)
+        .map(|f| {
+            let kind = match (&f.before, &f.after) {
+                (None, _) => ChangeKind::Create,
+                (_, None) => ChangeKind::Delete,
+                _ => ChangeKind::Update,
+            };
+            let diff = (!matches!(kind, ChangeKind::Delete))
+                .then(|| patch::short_diff(f.before.as_deref().unwrap_or(""), f.after.as_deref().unwrap_or(""), usize::MAX));
+            Preview {
+                kind,
+                path: Some(PathBuf::from(&f.path)),
+                bytes_before: f.before.as_ref().map(|s| s.len() as u64),
+                bytes_after: f.after.as_ref().map(|s| s.len() as u64),
+                diff_snippet: diff,
+                command: None,
+                group: None,
+                metrics: None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use fs_err as fs;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::backup::{self, After};
+use crate::{git, log};
+
+/// Where a file stands relative to what the transaction left behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Still exactly what the transaction wrote (or still deleted): safe to revert.
+    AsApplied,
+    /// Edited since; reverting would throw that work away.
+    ChangedSince,
+    /// Already back to its content before the transaction.
+    Reverted,
+    /// Backup from before post-apply hashes were recorded; can't tell.
+    Unknown,
+}
+
+/// One file the transaction changed and the content to put back.
+#[derive(Debug, Clone)]
+pub struct UndoFile {
+    pub path: String,
+    /// Content before the transaction; `None` when it created the file.
+    pub original: Option<String>,
+    pub state: State,
+}
+
+fn state(current: &Option<String>, original: &Option<String>, after: Option<After>) -> State {
+    if current == original {
+        return State::Reverted;
+    }
+    match after {
+        Some(a) if a == After::of(current.as_deref()) => State::AsApplied,
+        Some(_) => State::ChangedSince,
+        None => State::Unknown,
+    }
+}
+
+/// The files `tx` changed, from its commits when `--git-commit` made them
+/// (the first one's parent holds the originals), else from its backup.
+pub fn plan(root: &Path, tx: Uuid) -> Result<Vec<UndoFile>> {
+    let tx_git: Option<git::TxGit> = log::read_artifact(&log::tx_dir(root, tx), "git.json")?
+        .and_then(|s| serde_json::from_str(&s).ok());
+    let commits = tx_git
+        .map(|g| if g.commits.is_empty() { g.commit.into_iter().collect() } else { g.commits })
+        .filter(|c: &Vec<String>| !c.is_empty() && git::is_repo(root));
+    if let Some(commits) = commits {
+        let parent = format!("{}^", commits[0]);
+        let last = &commits[commits.len() - 1];
+        let mut paths = Vec::new();
+        for c in &commits {
+            for p in git::files_in_commit(root, c)? {
+                if !paths.contains(&p) {
+                    paths.push(p);
+                }
+            }
+        }
+        return Ok(paths
+            .into_iter()
+            .map(|path| {
+                let original = git::show_file(root, &parent, &path);
+                let after = git::show_file(root, last, &path);
+                let current = fs::read_to_string(root.join(&path)).ok();
+                let state = state(&current, &original, Some(After::of(after.as_deref())));
+                UndoFile { path, original, state }
+            })
+            .collect());
+    }
+    let entries = match backup::load_manifest(root, tx) {
+        Ok(e) => e,
+        Err(_) => bail!("transaction {} has neither a commit (git.json) nor a backup to undo from", tx),
+    };
+    let mut out = Vec::new();
+    for e in entries {
+        let original = backup::original_content(root, tx, &e.path)?;
+        let current = fs::read_to_string(root.join(&e.path)).ok();
+        let state = state(&current, &original, e.after);
+        out.push(UndoFile { path: e.path, original, state });
+    }
+    Ok(out)
+}
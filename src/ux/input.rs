@@ -0,0 +1,121 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// What a confirmation nobody answers in time resolves to.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmDefault {
+    #[default]
+    Abort,
+    Apply,
+}
+
+impl ConfirmDefault {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfirmDefault::Abort => "abort",
+            ConfirmDefault::Apply => "apply",
+        }
+    }
+}
+
+/// A prompt the timeout answered instead of the user.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedOut {
+    pub prompt: String,
+    pub approved: bool,
+    pub after_secs: u64,
+}
+
+pub(super) enum Input {
+    Line(String),
+    Eof,
+    TimedOut,
+}
+
+static TIMEOUT: OnceLock<(Duration, ConfirmDefault)> = OnceLock::new();
+static LINES: OnceLock<Mutex<Receiver<Option<String>>>> = OnceLock::new();
+static DECISIONS: Mutex<Vec<TimedOut>> = Mutex::new(Vec::new());
+
+/// Make confirmations resolve to `default` after `timeout`; call once at
+/// startup. Without a timeout prompts wait forever.
+pub fn init(timeout: Option<Duration>, default: ConfirmDefault) {
+    if let Some(t) = timeout.filter(|t| !t.is_zero()) {
+        let _ = TIMEOUT.set((t, default));
+    }
+}
+
+pub(super) fn timeout() -> Option<(Duration, ConfirmDefault)> {
+    TIMEOUT.get().copied()
+}
+
+/// `120s`, `2m`, `1h`, `7d` or plain seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = num.parse().map_err(|_| format!("'{}' is not a duration like 120s or 2m", s))?;
+    let secs = match unit.trim() {
+        "" | "s" | "sec" | "secs" => n,
+        "m" | "min" | "mins" => n * 60,
+        "h" => n * 3600,
+        "d" => n * 86_400,
+        u => return Err(format!("unknown duration unit '{}' (s, m, h, d)", u)),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// Stdin as a channel, read on a thread so a prompt can stop waiting. A
+/// line typed after a timeout is delivered to the next prompt, not lost.
+fn lines() -> &'static Mutex<Receiver<Option<String>>> {
+    LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut s = String::new();
+            let line = match io::stdin().lock().read_line(&mut s) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(s),
+            };
+            let eof = line.is_none();
+            if tx.send(line).is_err() || eof {
+                break;
+            }
+        });
+        Mutex::new(rx)
+    })
+}
+
+/// The next line of input, waiting at most `wait`.
+pub(super) fn read_line(wait: Option<Duration>) -> Input {
+    // plain blocking read until a timed prompt has started the reader thread
+    if wait.is_none() && LINES.get().is_none() {
+        let mut s = String::new();
+        return match io::stdin().read_line(&mut s) {
+            Ok(0) | Err(_) => Input::Eof,
+            Ok(_) => Input::Line(s),
+        };
+    }
+    let rx = lines().lock().unwrap_or_else(|e| e.into_inner());
+    let got = match wait {
+        Some(t) => rx.recv_timeout(t),
+        None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match got {
+        Ok(Some(l)) => Input::Line(l),
+        Ok(None) | Err(RecvTimeoutError::Disconnected) => Input::Eof,
+        Err(RecvTimeoutError::Timeout) => Input::TimedOut,
+    }
+}
+
+pub(super) fn record(prompt: &str, approved: bool, after: Duration) {
+    let mut d = DECISIONS.lock().unwrap_or_else(|e| e.into_inner());
+    d.push(TimedOut { prompt: prompt.to_string(), approved, after_secs: after.as_secs() });
+}
+
+/// Every prompt the timeout has answered so far in this run.
+pub fn timed_out() -> Vec<TimedOut> {
+    DECISIONS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -1,9 +1,32 @@
 use colored::Colorize;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 
 use crate::apply::ApplySummary;
+use crate::lint::Violation;
+use crate::verify::{VerifyReport, VerifyStatus};
 use crate::patch;
-use crate::wire::{Plan, Step};
+use crate::doctor::ProviderHealth;
+use crate::smoke::SmokeReport;
+use crate::templates::{self, StepTemplate};
+use crate::wire::{Answer, Plan, Step};
+
+pub mod input;
+pub mod theme;
+use input::Input;
+use theme::Role;
+
+/// Plans with at least this many steps are shown as collapsible groups.
+pub const GROUP_THRESHOLD: usize = 15;
+
+fn step_line(s: &Step) -> String {
+    match s {
+        Step::Create { title, path, .. } => format!("{}  {} — {}", theme::label(Role::Add, "[CREATE]"), path, title),
+        Step::Update { title, path, .. } => format!("{}  {} — {}", theme::label(Role::Change, "[UPDATE]"), path, title),
+        Step::Delete { title, path, .. } => format!("{}  {} — {}", theme::label(Role::Remove, "[DELETE]"), path, title),
+        Step::Command { title, command, .. } => format!("{}  {} — {}", theme::label(Role::Command, "[COMMAND]"), command, title),
+        Step::Test { title, command, .. } => format!("{}  {} — {}", theme::label(Role::Test, "[TEST]"), command, title),
+    }
+}
 
 pub fn show_plan(plan: &Plan) {
     println!("\n=== PLAN ===");
@@ -12,45 +35,358 @@ pub fn show_plan(plan: &Plan) {
         println!("(no steps)");
         return;
     }
+    if plan.steps.len() >= GROUP_THRESHOLD {
+        // large plan: collapsed groups, expanded on demand in review_groups
+        for (g, idxs) in crate::plan::group_steps(&plan.steps) {
+            println!("{} {}  ({} steps: {}–{})", "▸".bold(), g.bold(), idxs.len(), idxs[0] + 1, idxs[idxs.len() - 1] + 1);
+        }
+        println!();
+        return;
+    }
     for (i, s) in plan.steps.iter().enumerate() {
-        match s {
-            Step::Create { title, path, .. } => {
-                println!("{}. {}  {}", i + 1, "[CREATE]".green().bold(), format!("{} — {}", path, title));
+        println!("{}. {}", i + 1, step_line(s));
+    }
+    println!();
+}
+
+/// Explanation that came with a hybrid answer+plan response, shown above the plan.
+pub fn print_plan_note(ans: &Answer) {
+    println!("\n=== NOTE ===");
+    println!("{}", ans.title.bold());
+    println!("{}", ans.content);
+}
+
+/// Lowercased answer to a menu prompt, `None` on EOF. When
+/// `--confirm-timeout` runs out, `defaults` gives the answer for
+/// `--confirm-default` abort and apply, in that order.
+fn read_answer(prompt: &str, defaults: [&str; 2]) -> Option<String> {
+    let timeout = input::timeout();
+    match timeout {
+        Some((t, default)) => {
+            let pick = defaults[(default == input::ConfirmDefault::Apply) as usize];
+            print!("{} [{} in {}s]: ", prompt, pick, t.as_secs())
+        }
+        None => print!("{}: ", prompt),
+    }
+    let _ = io::stdout().flush();
+    match input::read_line(timeout.map(|(t, _)| t)) {
+        Input::Line(s) => Some(s.trim().to_lowercase()),
+        Input::Eof => None,
+        Input::TimedOut => {
+            let (t, default) = timeout.unwrap_or_default();
+            let pick = defaults[(default == input::ConfirmDefault::Apply) as usize];
+            timed_out(prompt, t, default, &format!("answering '{}'", pick));
+            Some(pick.to_string())
+        }
+    }
+}
+
+/// Say that `--confirm-default` answered `prompt` after `t` and record it;
+/// whether the default approves.
+fn timed_out(prompt: &str, t: std::time::Duration, default: input::ConfirmDefault, what: &str) -> bool {
+    let approved = default == input::ConfirmDefault::Apply;
+    println!();
+    println!("{}", theme::paint(Role::Warn, &format!("No answer within {}s; {} (--confirm-default).", t.as_secs(), what)));
+    input::record(prompt, approved, t);
+    approved
+}
+
+/// Group-level approve/skip for large plans. Each group can be viewed
+/// (expanded) before deciding; Enter approves, other answers ask again and
+/// EOF skips the group. A `--confirm-timeout` running out skips the group,
+/// or approves it with `--confirm-default apply`.
+pub fn review_groups(plan: Plan) -> Plan {
+    if plan.steps.len() < GROUP_THRESHOLD {
+        return plan;
+    }
+    let groups = crate::plan::group_steps(&plan.steps);
+    let mut keep = vec![true; plan.steps.len()];
+    for (g, idxs) in &groups {
+        loop {
+            let ans = read_answer(&format!("Group '{}' ({} steps) — [a]pprove, [s]kip, [v]iew", g, idxs.len()), ["s", "a"]);
+            match ans.as_deref().unwrap_or("s") {
+                "v" | "view" => {
+                    for i in idxs {
+                        println!("   {}. {}", i + 1, step_line(&plan.steps[*i]));
+                    }
+                }
+                "s" | "skip" => {
+                    for i in idxs {
+                        keep[*i] = false;
+                    }
+                    break;
+                }
+                "" | "a" | "approve" => break,
+                other => println!("unknown answer '{}'; a, s or v", other),
             }
-            Step::Update { title, path, .. } => {
-                println!("{}. {}  {}", i + 1, "[UPDATE]".yellow().bold(), format!("{} — {}", path, title));
+        }
+    }
+    let steps = plan
+        .steps
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(s, k)| if k { Some(s) } else { None })
+        .collect();
+    Plan { summary: plan.summary, steps, requires: plan.requires }
+}
+
+/// Answer to a y/N prompt; `by_timeout` when `--confirm-timeout` ran out
+/// and `--confirm-default` decided.
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub approved: bool,
+    pub by_timeout: bool,
+}
+
+pub fn confirm(prompt: &str) -> bool {
+    confirm_decision(prompt).approved
+}
+
+pub fn confirm_decision(prompt: &str) -> Decision {
+    decide(prompt, "y/N", |_| false)
+}
+
+/// y/N prompt whose `--confirm-timeout` always declines, whatever
+/// `--confirm-default` says: for answers that outlive the run, like
+/// trusting a project.
+pub fn confirm_declining_on_timeout(prompt: &str) -> bool {
+    decide_with(prompt, "y/N", declining(input::timeout()), input::read_line, |_| false).approved
+}
+
+fn declining(timeout: Option<(std::time::Duration, input::ConfirmDefault)>) -> Option<(std::time::Duration, input::ConfirmDefault)> {
+    timeout.map(|(t, _)| (t, input::ConfirmDefault::Abort))
+}
+
+/// The plan approval prompt; `peek <n>` shows the file step n targets
+/// (head and tail) and asks again.
+pub fn confirm_plan(prompt: &str, plan: &Plan, root: &std::path::Path) -> Decision {
+    decide(prompt, "y/N, peek <n>", |ans| match ans.strip_prefix("peek") {
+        Some(n) => {
+            peek_step(plan, n.trim(), root);
+            true
+        }
+        None => false,
+    })
+}
+
+/// The apply prompt; `c <file>` copies that file's preview diff and `c msg`
+/// the commit message to the clipboard, then asks again.
+pub fn confirm_apply(prompt: &str, previews: &[patch::Preview], commit_message: &str) -> bool {
+    decide(prompt, "y/N, c <file>|msg", |ans| {
+        let Some(what) = ans.strip_prefix("copy").or_else(|| ans.strip_prefix('c')).filter(|w| w.is_empty() || w.starts_with(' ')) else {
+            return false;
+        };
+        let what = what.trim();
+        let text = if what == "msg" {
+            Some(commit_message.to_string())
+        } else {
+            let hits: Vec<&patch::Preview> = previews
+                .iter()
+                .filter(|p| !what.is_empty() && p.diff_snippet.is_some())
+                .filter(|p| p.path.as_ref().is_some_and(|path| path.display().to_string().to_lowercase().ends_with(what)))
+                .collect();
+            match hits.as_slice() {
+                [one] => one.diff_snippet.clone(),
+                [] => {
+                    println!("usage: c msg | c <file with a preview diff, or the end of its path>");
+                    None
+                }
+                _ => {
+                    println!("{} files match '{}'; give more of the path", hits.len(), what);
+                    None
+                }
             }
-            Step::Delete { title, path, .. } => {
-                println!("{}. {}  {}", i + 1, "[DELETE]".red().bold(), format!("{} — {}", path, title));
+        };
+        if let Some(text) = text {
+            match crate::clipboard::copy(&text) {
+                Ok(()) => println!("Copied {} line(s).", text.lines().count()),
+                Err(e) => println!("{}", theme::paint(Role::Warn, &format!("{:#}", e))),
             }
-            Step::Command { title, command, .. } => {
-                println!("{}. {}  {}", i + 1, "[COMMAND]".cyan().bold(), format!("{} — {}", command, title));
+        }
+        true
+    })
+    .approved
+}
+
+/// y/N prompt with the `--confirm-timeout` default; answers `other`
+/// handles (returns true for) re-prompt instead of deciding.
+fn decide(prompt: &str, choices: &str, other: impl FnMut(&str) -> bool) -> Decision {
+    decide_with(prompt, choices, input::timeout(), input::read_line, other)
+}
+
+fn decide_with(
+    prompt: &str,
+    choices: &str,
+    timeout: Option<(std::time::Duration, input::ConfirmDefault)>,
+    mut read: impl FnMut(Option<std::time::Duration>) -> Input,
+    mut other: impl FnMut(&str) -> bool,
+) -> Decision {
+    loop {
+        match timeout {
+            Some((t, default)) => print!("{} [{}, {} in {}s]: ", prompt, choices, default.as_str(), t.as_secs()),
+            None => print!("{} [{}]: ", prompt, choices),
+        }
+        let _ = io::stdout().flush();
+        match read(timeout.map(|(t, _)| t)) {
+            Input::Line(s) => {
+                let ans = s.trim().to_lowercase();
+                if other(&ans) {
+                    continue;
+                }
+                return Decision { approved: ans == "y" || ans == "yes", by_timeout: false };
             }
-            Step::Test { title, command, .. } => {
-                println!("{}. {}  {}", i + 1, "[TEST]".magenta().bold(), format!("{} — {}", command, title));
+            Input::Eof => return Decision { approved: false, by_timeout: false },
+            Input::TimedOut => {
+                let (t, default) = timeout.unwrap_or_default();
+                let what = if default == input::ConfirmDefault::Apply { "applying" } else { "aborting" };
+                let approved = timed_out(prompt, t, default, what);
+                return Decision { approved, by_timeout: true };
             }
         }
     }
-    println!();
 }
 
-pub fn confirm(prompt: &str) -> bool {
-    print!("{} [y/N]: ", prompt);
-    let _ = io::stdout().flush();
-    let mut s = String::new();
-    if io::stdin().read_line(&mut s).is_ok() {
-        let ans = s.trim().to_lowercase();
-        ans == "y" || ans == "yes"
+const PEEK_HEAD: usize = 15;
+const PEEK_TAIL: usize = 8;
+
+/// Head and tail of the file plan step `n` (1-based) targets as it is on
+/// disk now, so a plan aimed at the wrong file is caught before CODEGEN.
+pub fn peek_step(plan: &Plan, n: &str, root: &std::path::Path) {
+    let step = match n.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= plan.steps.len() => &plan.steps[n - 1],
+        _ => {
+            println!("usage: peek <step number 1-{}>", plan.steps.len());
+            return;
+        }
+    };
+    let path = match step {
+        Step::Update { path, .. } | Step::Delete { path, .. } | Step::Create { path, .. } => path,
+        Step::Command { .. } | Step::Test { .. } => {
+            println!("step {} runs a command; nothing to peek", n);
+            return;
+        }
+    };
+    let rel = crate::safety::normalize_rel(path);
+    if std::path::Path::new(&rel).is_absolute() || rel.split('/').any(|s| s == "..") {
+        println!("{}{} is outside the project", theme::mark(Role::Error), path);
+        return;
+    }
+    let content = match std::fs::read_to_string(root.join(&rel)) {
+        Ok(c) => c,
+        Err(_) if matches!(step, Step::Create { .. }) => {
+            println!("{} does not exist yet (created by this step)", rel);
+            return;
+        }
+        Err(_) => {
+            println!("{}{} does not exist; this step targets a missing file", theme::mark(Role::Error), rel);
+            return;
+        }
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let warn = if matches!(step, Step::Create { .. }) { "  (exists already; CREATE overwrites it)" } else { "" };
+    println!("{}  {} lines, {} bytes{}", rel.bold(), lines.len(), content.len(), theme::paint(Role::Warn, warn));
+    let print = |from: usize, to: usize| {
+        for (i, l) in lines[from..to].iter().enumerate() {
+            println!("  {:>4} | {}", from + i + 1, l);
+        }
+    };
+    if lines.len() <= PEEK_HEAD + PEEK_TAIL {
+        print(0, lines.len());
     } else {
-        false
+        print(0, PEEK_HEAD);
+        println!("  {:>4} | ... {} lines ...", "", lines.len() - PEEK_HEAD - PEEK_TAIL);
+        print(lines.len() - PEEK_TAIL, lines.len());
     }
 }
 
-/// Minimal inline editor hook. For now, returns the same plan (user may decline and re-run).
-/// You can enhance to open $EDITOR or present a TUI later.
-pub fn edit_plan(plan: Plan) -> Plan {
-    println!("\n(no inline editor configured; returning plan unchanged)\n");
-    plan
+/// Numbered pick from `items`; `None` on EOF or an out-of-range answer.
+pub fn choose(what: &str, items: &[String]) -> Option<usize> {
+    println!("{}", format!("Choose {}:", what).bold());
+    for (i, item) in items.iter().enumerate() {
+        println!("  {:>2}. {}", i + 1, item);
+    }
+    let ans = read_line_raw(&format!("{} [1-{}]: ", what, items.len()))?;
+    let n: usize = ans.parse().ok()?;
+    (1..=items.len()).contains(&n).then(|| n - 1)
+}
+
+/// A trimmed line; `None` on EOF or when `--confirm-timeout` runs out.
+fn read_line_raw(prompt: &str) -> Option<String> {
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+    let timeout = input::timeout();
+    match input::read_line(timeout.map(|(t, _)| t)) {
+        Input::Line(s) => Some(s.trim().to_string()),
+        Input::Eof => None,
+        Input::TimedOut => {
+            println!();
+            let secs = timeout.map(|(t, _)| t.as_secs()).unwrap_or_default();
+            println!("{}", theme::paint(Role::Warn, &format!("No answer within {}s (--confirm-timeout).", secs)));
+            None
+        }
+    }
+}
+
+/// One line of secret input such as an API key; not echoed when typed at a
+/// terminal, read as a plain line when piped in.
+pub fn read_secret(prompt: &str) -> String {
+    if io::stdin().is_terminal() {
+        return rpassword::prompt_password(format!("{}: ", prompt)).map(|s| s.trim().to_string()).unwrap_or_default();
+    }
+    read_line_raw(&format!("{}: ", prompt)).unwrap_or_default()
+}
+
+/// Minimal inline plan editor: inject steps from templates
+/// (`add test src/app/page.tsx`, `add command npm run lint`) or drop steps
+/// (`rm 3`), or look at a step's target (`peek 2`); an empty line or `done`
+/// finishes. Also returns the commands of the test steps added from test
+/// templates that are still in the plan.
+pub fn edit_plan(mut plan: Plan, templates: &[StepTemplate], root: &std::path::Path) -> (Plan, Vec<String>) {
+    let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+    println!("\nEdit plan: add <{}> <args...> | rm <n> | peek <n> | list | done", names.join("|"));
+    let mut added = 0usize;
+    let mut tests: Vec<String> = Vec::new();
+    while let Some(line) = read_line_raw("edit> ") {
+        let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line.as_str(), ""));
+        match cmd {
+            "" | "done" | "q" => break,
+            "list" | "ls" => show_plan(&plan),
+            "peek" => peek_step(&plan, rest.trim(), root),
+            "rm" => match rest.trim().parse::<usize>() {
+                Ok(n) if n >= 1 && n <= plan.steps.len() => {
+                    let s = plan.steps.remove(n - 1);
+                    println!("removed: {}", step_line(&s));
+                }
+                _ => println!("usage: rm <step number>"),
+            },
+            "add" => {
+                let (name, args) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                let Some(t) = templates.iter().find(|t| t.name == name) else {
+                    println!("unknown template '{}' (available: {})", name, names.join(", "));
+                    continue;
+                };
+                added += 1;
+                match templates::instantiate(t, args, format!("user-{}", added)) {
+                    Ok(mut step) => {
+                        if step.group().is_none() {
+                            step.set_group("user".to_string());
+                        }
+                        println!("added: {}", step_line(&step));
+                        if let Step::Test { command, .. } = &step {
+                            if templates::is_test_command(templates, command) {
+                                tests.push(command.trim().to_string());
+                            }
+                        }
+                        plan.steps.push(step);
+                    }
+                    Err(e) => println!("{:#}", e),
+                }
+            }
+            _ => println!("unknown command '{}'", cmd),
+        }
+    }
+    tests.retain(|t| plan.steps.iter().any(|s| matches!(s, Step::Test { command, .. } if command.trim() == t)));
+    (plan, tests)
 }
 
 /// Render a compact preview dashboard using patch previews.
@@ -77,14 +413,48 @@ pub fn print_preview_dashboard(previews: &[patch::Preview]) {
     );
     println!(
         "  {}: {}   {}: {}   {}: {}   {}: {}   {}: {}",
-        "Create".green().bold(), create,
-        "Update".yellow().bold(), update,
-        "Delete".red().bold(), delete,
-        "Command".cyan().bold(), command,
-        "Test".magenta().bold(), test
+        theme::label(Role::Add, "Create"), create,
+        theme::label(Role::Change, "Update"), update,
+        theme::label(Role::Remove, "Delete"), delete,
+        theme::label(Role::Command, "Command"), command,
+        theme::label(Role::Test, "Test"), test
     );
+    let analyzed: Vec<&crate::metrics::FileMetrics> = previews.iter().filter_map(|p| p.metrics.as_ref()).collect();
+    if !analyzed.is_empty() {
+        let client = analyzed.iter().filter(|m| m.client).count();
+        let large = analyzed.iter().filter(|m| m.loc > crate::metrics::LARGE_FILE_LOC).count();
+        println!(
+            "  Generated: {} LOC   client: {}   server: {}   large files: {}",
+            analyzed.iter().map(|m| m.loc).sum::<usize>(),
+            client,
+            analyzed.len() - client,
+            if large > 0 { theme::paint(Role::Warn, &large.to_string()).bold() } else { large.to_string().normal() }
+        );
+    }
     println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
 
+    if previews.len() >= GROUP_THRESHOLD {
+        let mut order: Vec<&str> = Vec::new();
+        for p in previews {
+            let g = p.group.as_deref().unwrap_or("ungrouped");
+            if !order.contains(&g) {
+                order.push(g);
+            }
+        }
+        for g in order {
+            let members: Vec<&patch::Preview> = previews
+                .iter()
+                .filter(|p| p.group.as_deref().unwrap_or("ungrouped") == g)
+                .collect();
+            println!("{} {}  ({} changes)", "▾".bold(), g.bold(), members.len());
+            for p in members {
+                println!("{}", patch::colorize_preview(p));
+                println!();
+            }
+        }
+        return;
+    }
+
     for p in previews {
         let rendered = patch::colorize_preview(p);
         println!("{}", rendered);
@@ -92,6 +462,33 @@ pub fn print_preview_dashboard(previews: &[patch::Preview]) {
     }
 }
 
+pub fn print_lint_report(violations: &[Violation]) {
+    if violations.is_empty() {
+        return;
+    }
+    println!("\nConvention lint:");
+    for v in violations {
+        let tag = if v.fixed { theme::label(Role::Ok, "[FIXED]") } else { theme::label(Role::Error, "[UNFIXED]") };
+        println!(" {} {} ({}): {}", tag, v.path, v.rule, v.message);
+    }
+}
+
+/// Files that still import a package the plan removes.
+pub fn print_dependency_stragglers(items: &[crate::deps::Straggler]) {
+    println!("\nRemoved packages still imported:");
+    for s in items {
+        println!("  {}{}:{} imports {}", theme::mark(Role::Warn), s.path, s.line, theme::paint(Role::Warn, &s.package));
+    }
+}
+
+/// Removals postponed because of remaining imports, and how the plan changed.
+pub fn print_held_back_removals(packages: &[String], notes: &[String]) {
+    println!("{}Keeping {} until nothing imports it:", theme::mark(Role::Warn), packages.join(", "));
+    for n in notes {
+        println!("  - {}", n);
+    }
+}
+
 pub fn print_apply_dashboard(sum: &ApplySummary) {
     println!(
         "\n{}",
@@ -99,16 +496,47 @@ pub fn print_apply_dashboard(sum: &ApplySummary) {
     );
     println!(
         "  {}: {}   {}: {}   {}: {}   {}: {}   {}: {}   {}: {}   {}: {}B",
-        "Created".green().bold(), sum.created,
-        "Updated".yellow().bold(), sum.updated,
-        "Deleted".red().bold(), sum.deleted,
-        "Commands".cyan().bold(), sum.commands,
-        "Tests".magenta().bold(), sum.tests,
+        theme::label(Role::Add, "Created"), sum.created,
+        theme::label(Role::Change, "Updated"), sum.updated,
+        theme::label(Role::Remove, "Deleted"), sum.deleted,
+        theme::label(Role::Command, "Commands"), sum.commands,
+        theme::label(Role::Test, "Tests"), sum.tests,
         "Skipped".bold(), sum.skipped,
         "Bytes".bold(), sum.bytes
     );
     println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
 
+    let areas = crate::apply::diffstat::by_area(&sum.changes);
+    if !areas.is_empty() {
+        println!("{}", "\nChanges by area:".bold());
+        let width = areas.iter().map(|a| a.name.len()).max().unwrap_or(0);
+        for a in &areas {
+            println!(
+                "  {:<width$}  {} file{}, {} {}",
+                a.name,
+                a.files,
+                if a.files == 1 { "" } else { "s" },
+                theme::paint(Role::Add, &format!("+{}", a.added)),
+                theme::paint(Role::Remove, &format!("−{}", a.removed)),
+                width = width
+            );
+        }
+    }
+
+    if !sum.skipped_steps.is_empty() {
+        println!("{}", "\nSkipped steps:".bold());
+        for s in &sum.skipped_steps {
+            println!("  {}{} {}  ({})", theme::mark(Role::Warn), s.id, s.target, s.reason.describe());
+        }
+    }
+
+    if !sum.follow_ups.is_empty() {
+        println!("{}", "\nFollow-ups left in the code:".bold());
+        for f in &sum.follow_ups {
+            println!("  {}{}:{}  {} {}", theme::mark(Role::Warn), f.path, f.line, theme::paint(Role::Warn, &f.marker), f.text);
+        }
+    }
+
     if !sum.command_outputs.is_empty() {
         println!("{}", "\nCommand outputs:".bold());
         for (i, o) in sum.command_outputs.iter().enumerate() {
@@ -133,6 +561,400 @@ pub fn print_apply_dashboard(sum: &ApplySummary) {
     }
 }
 
+pub fn print_verify_matrix(report: &VerifyReport) {
+    println!(
+        "\n{}",
+        "┏━━━━━━━━━━━━━━━━━━━━━━━━ Verification ━━━━━━━━━━━━━━━━━━━━┓".bold()
+    );
+    for r in &report.results {
+        let (role, text) = match r.status {
+            VerifyStatus::Pass => (Role::Ok, "PASS"),
+            VerifyStatus::Fail => (Role::Error, "FAIL"),
+            VerifyStatus::Timeout => (Role::Error, "TIMEOUT"),
+            VerifyStatus::Denied => (Role::Warn, "DENIED"),
+        };
+        println!(
+            "  {}{:<8} {:<12} {:<9} {:>7}ms  {}",
+            theme::mark(role),
+            theme::paint(role, text).bold(),
+            r.name,
+            if r.required { "required" } else { "optional" },
+            r.duration_ms,
+            r.command.dimmed()
+        );
+    }
+    println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
+    for r in report.results.iter().filter(|r| r.output_tail.is_some()) {
+        println!("{} output (tail):\n{}", r.name.bold(), indent(r.output_tail.as_deref().unwrap_or(""), 2));
+    }
+    if report.verified {
+        println!("{}", theme::label(Role::Ok, "Transaction verified: all required checks passed."));
+    } else {
+        println!("{}", theme::label(Role::Error, "Transaction NOT verified: a required check did not pass."));
+    }
+}
+
+/// One compact line per provider round-trip: model, tokens, time, retries.
+pub fn print_phase_usage(stage: &str, u: &crate::wire::Usage) {
+    let tokens = match (u.prompt_tokens, u.completion_tokens) {
+        (Some(p), Some(c)) => format!("{} in / {} out", p, c),
+        (Some(p), None) => format!("{} in", p),
+        _ => "tokens n/a".to_string(),
+    };
+    let retries = if u.retries > 0 { format!(", {} retr{}", u.retries, if u.retries == 1 { "y" } else { "ies" }) } else { String::new() };
+    println!(
+        "{}",
+        format!("  [{}] {} · {} · {:.1}s{}", stage, u.model, tokens, u.duration_ms as f64 / 1000.0, retries).dimmed()
+    );
+}
+
+/// One line per provider retry, e.g. `503 Service Unavailable — attempt 2/4 in 1.2s`.
+pub fn print_retry_status(attempt: u32, max: u32, reason: &str, delay: std::time::Duration) {
+    eprintln!(
+        "{}{} — attempt {}/{} in {:.1}s",
+        theme::mark(Role::Warn),
+        theme::paint(Role::Warn, reason),
+        attempt,
+        max,
+        delay.as_secs_f32()
+    );
+}
+
+/// Shown while a request waits for the client-side rate limit window.
+pub fn print_rate_limit_wait(provider: &str, wait: std::time::Duration) {
+    eprintln!(
+        "{}waiting for rate limit ({}) — {:.1}s",
+        theme::mark(Role::Warn),
+        theme::paint(Role::Warn, provider),
+        wait.as_secs_f32()
+    );
+}
+
+/// Shown when a `provider_chain` entry gives up and the next one takes over.
+pub fn print_fallback_status(failed: &str, next: &str, err: &anyhow::Error) {
+    let first_line = err.to_string().lines().next().unwrap_or_default().to_string();
+    eprintln!(
+        "{}{} failed ({}); falling back to {}",
+        theme::mark(Role::Warn),
+        theme::paint(Role::Warn, failed),
+        first_line,
+        next
+    );
+}
+
+/// Which `plan_race` entry produced the plan, and which were cancelled.
+pub fn print_race_winner(winner: &str, elapsed: std::time::Duration, cancelled: &[&str]) {
+    let rest = if cancelled.is_empty() { String::new() } else { format!("; cancelled {}", cancelled.join(", ")) };
+    println!("{}PLAN race won by {} in {:.1}s{}", theme::mark(Role::Ok), theme::paint(Role::Ok, winner), elapsed.as_secs_f32(), rest);
+}
+
+/// One line on how context files were picked, with a hint when no index was used.
+pub fn print_retrieval_mode(mode: crate::context::RetrievalMode, vibe_out: &std::path::Path, reindex_command: Option<&str>) {
+    use crate::context::RetrievalMode;
+    let hint = match reindex_command {
+        Some(cmd) => format!("run `{}`", cmd),
+        None => "run the indexer".to_string(),
+    };
+    match mode {
+        RetrievalMode::Embeddings => println!("Context retrieval: embeddings ({})", vibe_out.display()),
+        RetrievalMode::Lexical => println!(
+            "{}Context retrieval: lexical (no vectors.sqlite/manifest in {}); {} for better context",
+            theme::mark(Role::Warn),
+            vibe_out.display(),
+            hint
+        ),
+        RetrievalMode::BaselineOnly => println!(
+            "{}Context retrieval: baseline-only (no index in {}), using a few fixed files; {} to build one",
+            theme::mark(Role::Warn),
+            vibe_out.display(),
+            hint
+        ),
+    }
+}
+
+/// Files the plan writes that have uncommitted changes.
+pub fn print_dirty_targets(paths: &[String], mode: crate::git::DirtyWorktree) {
+    let what = match mode {
+        crate::git::DirtyWorktree::Stash => "stashing them until the apply is done",
+        _ => "refusing to apply over them",
+    };
+    println!("{}Uncommitted changes in files the plan writes ({}):", theme::mark(Role::Warn), what);
+    for p in paths {
+        println!("  - {}", p);
+    }
+}
+
+/// Files the plan writes that the upstream branch changed since HEAD.
+pub fn print_upstream_divergence(upstream: &str, diverged: &[crate::git::Diverged]) {
+    println!(
+        "{}{} changed {} file(s) the plan writes; pushing these changes will conflict:",
+        theme::mark(Role::Warn),
+        upstream,
+        diverged.len()
+    );
+    for d in diverged {
+        println!("  - {}", d.path);
+        for c in d.commits.iter().take(3) {
+            println!("      {}", c.dimmed());
+        }
+        if d.commits.len() > 3 {
+            println!("      {}", format!("... and {} more", d.commits.len() - 3).dimmed());
+        }
+    }
+    println!("  Pull or rebase first to generate against the latest code.");
+}
+
+/// Files the plan writes that changed on disk since codegen read them; asks
+/// whether to re-merge, overwrite or abort (the default, also on EOF). A
+/// `--confirm-timeout` running out aborts, or merges with `--confirm-default apply`.
+pub fn confirm_drift(drifted: &[crate::apply::drift::Drift]) -> crate::apply::drift::Resolution {
+    use crate::apply::drift::Resolution;
+    println!("{}Files changed on disk since they were snapshotted for codegen:", theme::mark(Role::Warn));
+    for d in drifted {
+        println!("  - {}{}", d.path, if d.deleted { " (deleted)" } else { "" });
+    }
+    match read_answer("[m]erge your edits into the generated changes, [o]verwrite them, or [A]bort", ["a", "m"]).as_deref() {
+        Some("m" | "merge") => Resolution::Remerge,
+        Some("o" | "overwrite") => Resolution::Overwrite,
+        _ => Resolution::Abort,
+    }
+}
+
+/// What the startup cleanup removed.
+pub fn print_gc_report(report: &crate::gc::Report) {
+    if !report.temp_files.is_empty() {
+        println!("Removed {} leftover temp file(s) from an interrupted apply:", report.temp_files.len());
+        for f in &report.temp_files {
+            println!("  - {}", f);
+        }
+    }
+    if let Some(l) = &report.stale_lock {
+        println!("Removed stale .vibe/lock (pid {}, tx {}, started {}).", l.pid, l.tx, l.started.format("%Y-%m-%d %H:%M:%S UTC"));
+    }
+}
+
+/// What `vibe undo` will do with each file of the transaction.
+pub fn print_undo_plan(files: &[crate::undo::UndoFile], force: bool) {
+    use crate::undo::State;
+    for f in files {
+        let (mark, note) = match f.state {
+            State::AsApplied => (theme::mark(Role::Ok), ""),
+            State::Unknown => (theme::mark(Role::Warn), "  (no post-apply hash; reverting)"),
+            State::ChangedSince if force => (theme::mark(Role::Warn), "  (edited since; --force reverts anyway)"),
+            State::ChangedSince => (theme::mark(Role::Error), "  (edited since; kept, use --force)"),
+            State::Reverted => (theme::mark(Role::Ok), "  (already reverted)"),
+        };
+        let action = if f.original.is_none() { "delete" } else { "restore" };
+        println!("  {}{} {}{}", mark, action, f.path, note);
+    }
+}
+
+/// Task breadth and the retrieval budget it bought (`adaptive_top_k`).
+pub fn print_task_breadth(b: &crate::context::breadth::Breadth) {
+    let why = if b.reasons.is_empty() { String::new() } else { format!(" ({})", b.reasons.join("; ")) };
+    println!(
+        "Task breadth {:.2}{}: top_k {}, {} KiB per file in the plan snapshot",
+        b.score,
+        why,
+        b.top_k,
+        b.plan_file_bytes / 1024
+    );
+}
+
+/// One line on the git history and diff going out with the context.
+pub fn print_git_context(g: &crate::wire::GitContext) {
+    let diff_lines = g.diff.lines().filter(|l| l.starts_with(['+', '-']) && !l.starts_with("+++") && !l.starts_with("---")).count();
+    println!(
+        "Git context: {} recent commit(s), {} changed line(s) uncommitted{}",
+        g.log.len(),
+        diff_lines,
+        if g.diff_truncated { " (diff truncated)" } else { "" }
+    );
+}
+
+/// The `blame_context` summary going out with the task.
+pub fn print_blame_context(files: &[crate::context::history::FileBlame]) {
+    for f in files {
+        let commits: std::collections::BTreeSet<&str> = f.regions.iter().map(|r| r.commit.as_str()).collect();
+        println!("Recent changes in {}: {} region(s) from {} commit(s)", f.path, f.regions.len(), commits.len());
+    }
+}
+
+/// Scores of the `--plan-samples` candidates and which one was kept.
+pub fn print_plan_samples(scores: &[Option<crate::plan::samples::Score>], best: Option<usize>) {
+    println!("\nPlan samples:");
+    for (i, s) in scores.iter().enumerate() {
+        let kept = if Some(i) == best { theme::paint(Role::Ok, "  kept").to_string() } else { String::new() };
+        match s {
+            Some(s) => println!(
+                " #{}  score {:>5}  ({} outside allowlists, {} inconsistent with the project){}",
+                i + 1,
+                s.total,
+                s.disallowed,
+                s.inconsistent,
+                kept
+            ),
+            None => println!(" #{}  no plan", i + 1),
+        }
+    }
+}
+
+/// Plan steps on paths CODEOWNERS assigns to other teams.
+pub fn print_foreign_owners(steps: &[crate::owners::ForeignStep]) {
+    if steps.is_empty() {
+        return;
+    }
+    println!("Owned by other teams:");
+    for s in steps {
+        println!(
+            "{}step {} {} — {}",
+            theme::mark(Role::Warn),
+            s.id,
+            s.path,
+            theme::paint(Role::Warn, &s.owners.join(" "))
+        );
+    }
+    println!();
+}
+
+/// Steps the approval policy wants confirmed, and by which rule.
+pub fn print_policy_review(review: &crate::safety::policy::Review) {
+    if !review.active || review.confirm.is_empty() {
+        return;
+    }
+    println!("\nNeeds confirmation (policy):");
+    for f in &review.confirm {
+        println!("{}step {} {} — {}", theme::mark(Role::Warn), f.id, f.target, f.rule.dimmed());
+    }
+}
+
+pub fn print_preflight_problems(problems: &[crate::exec::preflight::Problem]) {
+    println!("\nPreflight:");
+    for p in problems {
+        println!(" {} {}: {}", theme::label(Role::Error, "[MISSING]"), p.requirement, p.message);
+    }
+}
+
+pub fn print_doctor_report(report: &[ProviderHealth]) {
+    println!("\n{}", "┏━━━━━━━━━━━━━━━━━━━━━━━━━ Doctor ━━━━━━━━━━━━━━━━━━━━━━━━━┓".bold());
+    for h in report {
+        let role = if h.ok { Role::Ok } else { Role::Error };
+        let key_role = if h.key == "missing" { Role::Warn } else { Role::Ok };
+        println!(
+            "  {}{:<4} {:<40} key {:<8} {:>7}ms  {}",
+            theme::mark(role),
+            theme::paint(role, if h.ok { "OK" } else { "FAIL" }).bold(),
+            h.target,
+            theme::paint(key_role, &h.key),
+            h.latency_ms,
+            h.structured.dimmed()
+        );
+        if let Some(n) = &h.note {
+            println!("         {}", theme::paint(if h.ok { Role::Warn } else { Role::Error }, n));
+        }
+    }
+    println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
+}
+
+/// `vibe schedule`: one row per task, findings indented below.
+pub fn print_schedule_report(report: &crate::schedule::Report) {
+    use crate::schedule::Status;
+    println!("\n{}", "┏━━━━━━━━━━━━━━━━━━━━━━━━ Schedule ━━━━━━━━━━━━━━━━━━━━━━━━┓".bold());
+    for t in &report.tasks {
+        let (role, label) = match t.status {
+            Status::Ok => (Role::Ok, "OK"),
+            Status::Warn => (Role::Warn, "WARN"),
+            Status::Failed => (Role::Error, "FAIL"),
+            Status::Skipped => (Role::Command, "SKIP"),
+        };
+        println!("  {}{:<4} {:<12} {:>7}ms  {}", theme::mark(role), theme::paint(role, label).bold(), t.name, t.duration_ms, t.summary);
+        for d in t.details.iter().take(20) {
+            println!("         {}", d);
+        }
+        if t.details.len() > 20 {
+            println!("         ... and {} more", t.details.len() - 20);
+        }
+    }
+    println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
+}
+
+/// `vibe compare`: per-model stats, then every path either model touched.
+pub fn print_comparison(cmp: &crate::compare::Comparison) {
+    println!("\n{}", "┏━━━━━━━━━━━━━━━━━━━━━━━━ Compare ━━━━━━━━━━━━━━━━━━━━━━━━━┓".bold());
+    println!("  {:<6} {:<36} {:>5} {:>5} {:>9} {:>9} {:>9} {:>8}", "", "model", "steps", "files", "tok in", "tok out", "cost", "time");
+    for (tag, o) in [("A", &cmp.a), ("B", &cmp.b)] {
+        if let Some(e) = &o.error {
+            println!("  {}{:<4} {:<36} {}", theme::mark(Role::Error), tag, o.target, theme::paint(Role::Error, e));
+            continue;
+        }
+        println!(
+            "  {}{:<4} {:<36} {:>5} {:>5} {:>9} {:>9} {:>9} {:>7.1}s",
+            theme::mark(Role::Ok),
+            tag,
+            o.target,
+            o.plan_steps,
+            o.files.len(),
+            o.tokens_in,
+            o.tokens_out,
+            o.cost_usd.map(|c| format!("${:.4}", c)).unwrap_or_else(|| "-".into()),
+            o.latency_ms as f64 / 1000.0
+        );
+    }
+    println!();
+    for f in &cmp.files {
+        let side = |a: &Option<String>| a.clone().unwrap_or_else(|| "-".into());
+        let (role, note) = match (&f.a, &f.b, f.similarity) {
+            (Some(_), None, _) => (Role::Warn, "A only".to_string()),
+            (None, Some(_), _) => (Role::Warn, "B only".to_string()),
+            (_, _, Some(s)) if s >= 0.999 => (Role::Ok, "identical".to_string()),
+            (_, _, Some(s)) => (Role::Change, format!("{:.0}% similar", s * 100.0)),
+            _ => (Role::Change, "both".to_string()),
+        };
+        println!("  {:<8} {:<8} {:<48} {}", side(&f.a), side(&f.b), f.path, theme::paint(role, &note));
+    }
+    if cmp.a.commands != cmp.b.commands {
+        println!("\n  commands A: {}", if cmp.a.commands.is_empty() { "-".into() } else { cmp.a.commands.join("; ") });
+        println!("  commands B: {}", if cmp.b.commands.is_empty() { "-".into() } else { cmp.b.commands.join("; ") });
+    }
+    println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
+}
+
+pub fn print_smoke_table(report: &SmokeReport) {
+    println!(
+        "\n{} {}",
+        "┏━━━━━━━━━━━━━━━━━━━━━━━━━━ Smoke ━━━━━━━━━━━━━━━━━━━━━━━━━┓".bold(),
+        report.base_url.dimmed()
+    );
+    for r in &report.routes {
+        let (role, text) = match (r.status, r.note.as_deref()) {
+            (_, Some("dynamic")) => (Role::Warn, "SKIP".to_string()),
+            (Some(s), _) if r.healthy() => (Role::Ok, s.to_string()),
+            (Some(s), _) => (Role::Error, s.to_string()),
+            (None, _) => (Role::Error, "ERR".to_string()),
+        };
+        println!(
+            "  {}{:<6} {:<32} {:>7}ms  {}",
+            theme::mark(role),
+            theme::paint(role, &text).bold(),
+            r.route,
+            r.duration_ms,
+            r.file.dimmed()
+        );
+        if let Some(n) = r.note.as_deref().filter(|n| *n != "dynamic") {
+            println!("         {}", theme::paint(Role::Error, n));
+        }
+        for e in &r.console_errors {
+            println!("         {}", theme::paint(Role::Error, e));
+        }
+    }
+    println!("{}", "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛".bold());
+    if report.healthy {
+        println!("{}", theme::label(Role::Ok, "All routes healthy."));
+    } else {
+        println!("{}", theme::label(Role::Error, "Some routes are unhealthy."));
+    }
+}
+
 fn indent(s: &str, n: usize) -> String {
     let pad = " ".repeat(n);
     s.lines()
@@ -140,3 +962,27 @@ fn indent(s: &str, n: usize) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn declining_prompt_times_out_to_no_even_with_apply_default() {
+        let timeout = Some((Duration::from_secs(5), input::ConfirmDefault::Apply));
+        let d = decide_with("Trust this project?", "y/N", declining(timeout), |_| Input::TimedOut, |_| false);
+        assert!(!d.approved);
+        assert!(d.by_timeout);
+        // a plain confirmation under the same flags applies
+        assert!(decide_with("Apply?", "y/N", timeout, |_| Input::TimedOut, |_| false).approved);
+    }
+
+    #[test]
+    fn declining_prompt_still_takes_an_answer() {
+        let timeout = Some((Duration::from_secs(5), input::ConfirmDefault::Apply));
+        let d = decide_with("Trust this project?", "y/N", declining(timeout), |_| Input::Line("y\n".into()), |_| false);
+        assert!(d.approved);
+        assert!(!d.by_timeout);
+    }
+}
@@ -0,0 +1,178 @@
+use colored::{Color, ColoredString, Colorize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Semantic role of a piece of output; themes map roles to colors/symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Add,
+    Remove,
+    Change,
+    Command,
+    Test,
+    Ok,
+    Error,
+    Warn,
+}
+
+impl Role {
+    fn key(self) -> &'static str {
+        match self {
+            Role::Add => "add",
+            Role::Remove => "remove",
+            Role::Change => "change",
+            Role::Command => "command",
+            Role::Test => "test",
+            Role::Ok => "ok",
+            Role::Error => "error",
+            Role::Warn => "warn",
+        }
+    }
+
+    /// Prefix that carries the meaning without relying on color.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Role::Add => "+",
+            Role::Remove => "-",
+            Role::Change => "~",
+            Role::Command => "$",
+            Role::Test => "?",
+            Role::Ok => "✓",
+            Role::Error => "✗",
+            Role::Warn => "!",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// `None` renders the role without color (mono).
+    palette: BTreeMap<&'static str, Option<Color>>,
+    pub symbols: bool,
+}
+
+const ROLES: [Role; 8] = [
+    Role::Add,
+    Role::Remove,
+    Role::Change,
+    Role::Command,
+    Role::Test,
+    Role::Ok,
+    Role::Error,
+    Role::Warn,
+];
+
+fn rgb(r: u8, g: u8, b: u8) -> Option<Color> {
+    Some(Color::TrueColor { r, g, b })
+}
+
+/// `#rrggbb`, a color name understood by `colored`, or `none`.
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("none") {
+        return None;
+    }
+    if let Some(hex) = s.strip_prefix('#').filter(|h| h.len() == 6 && h.bytes().all(|b| b.is_ascii_hexdigit())) {
+        let c = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        if let (Some(r), Some(g), Some(b)) = (c(0), c(2), c(4)) {
+            return rgb(r, g, b);
+        }
+    }
+    s.parse::<Color>().ok()
+}
+
+impl Theme {
+    /// Built-in themes: `default`, `colorblind` (Okabe-Ito palette) and `mono`.
+    pub fn builtin(name: &str) -> Self {
+        let colors: [Option<Color>; 8] = match name {
+            "colorblind" | "cb" => [
+                rgb(0, 114, 178),   // add: blue
+                rgb(213, 94, 0),    // remove: vermillion
+                rgb(240, 228, 66),  // change: yellow
+                rgb(86, 180, 233),  // command: sky blue
+                rgb(204, 121, 167), // test: reddish purple
+                rgb(0, 114, 178),
+                rgb(213, 94, 0),
+                rgb(230, 159, 0), // warn: orange
+            ],
+            "mono" | "none" => [None; 8],
+            _ => [
+                Some(Color::Green),
+                Some(Color::Red),
+                Some(Color::Yellow),
+                Some(Color::Cyan),
+                Some(Color::Magenta),
+                Some(Color::Green),
+                Some(Color::Red),
+                Some(Color::Yellow),
+            ],
+        };
+        Self {
+            palette: ROLES.iter().map(|r| r.key()).zip(colors).collect(),
+            symbols: true,
+        }
+    }
+
+    /// Apply per-role overrides from config (`add = "#0072b2"`, `warn = "none"`).
+    pub fn with_overrides(mut self, overrides: &BTreeMap<String, String>) -> Self {
+        for r in ROLES {
+            if let Some(v) = overrides.get(r.key()) {
+                self.palette.insert(r.key(), parse_color(v));
+            }
+        }
+        self
+    }
+
+    pub fn paint(&self, role: Role, text: &str) -> ColoredString {
+        match self.palette.get(role.key()).copied().flatten() {
+            Some(c) => text.color(c),
+            None => text.normal(),
+        }
+    }
+
+    /// `text` painted and, when symbols are on, prefixed with the role symbol.
+    pub fn label(&self, role: Role, text: &str) -> String {
+        if self.symbols {
+            format!("{} {}", role.symbol(), self.paint(role, text).bold())
+        } else {
+            self.paint(role, text).bold().to_string()
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Select the theme once at startup: `VIBE_THEME` wins over the config name.
+pub fn init(config_name: Option<&str>, overrides: &BTreeMap<String, String>, symbols: bool) {
+    let name = std::env::var("VIBE_THEME").ok().or_else(|| config_name.map(|s| s.to_string()));
+    let mut t = Theme::builtin(name.as_deref().unwrap_or("default")).with_overrides(overrides);
+    t.symbols = symbols;
+    let _ = THEME.set(t);
+}
+
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(|| Theme::builtin(std::env::var("VIBE_THEME").as_deref().unwrap_or("default")))
+}
+
+pub fn paint(role: Role, text: &str) -> ColoredString {
+    current().paint(role, text)
+}
+
+pub fn label(role: Role, text: &str) -> String {
+    current().label(role, text)
+}
+
+/// `s` without ANSI color codes (for the clipboard, PR descriptions).
+pub fn strip(s: &str) -> String {
+    static ANSI: OnceLock<regex::Regex> = OnceLock::new();
+    ANSI.get_or_init(|| regex::Regex::new(r"\x1b\[[0-9;]*m").expect("valid regex")).replace_all(s, "").into_owned()
+}
+
+/// Symbol plus a space for table rows, or nothing when symbols are off.
+pub fn mark(role: Role) -> String {
+    if current().symbols {
+        format!("{} ", role.symbol())
+    } else {
+        String::new()
+    }
+}
@@ -0,0 +1,107 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::exec::run_command_allowlisted;
+
+/// One entry of the post-apply verification matrix (typecheck, lint, tests, build...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyEntry {
+    pub name: String,
+    pub command: String,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    /// Per-entry timeout; falls back to `Config::timeout_secs`.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyStatus {
+    Pass,
+    Fail,
+    Timeout,
+    /// Command is not in the command allowlist (or the workspace is untrusted).
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub name: String,
+    pub command: String,
+    pub required: bool,
+    pub status: VerifyStatus,
+    pub duration_ms: u128,
+    /// Last lines of stderr (or stdout) for failed entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub results: Vec<VerifyResult>,
+    /// True only when every required entry passed.
+    pub verified: bool,
+}
+
+fn tail(s: &str, n: usize) -> String {
+    let lines: Vec<&str> = s.lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+fn run_entry(e: &VerifyEntry, cfg: &Config) -> VerifyResult {
+    let mut r = VerifyResult {
+        name: e.name.clone(),
+        command: e.command.clone(),
+        required: e.required,
+        status: VerifyStatus::Denied,
+        duration_ms: 0,
+        output_tail: None,
+    };
+    if !cfg.trusted || !crate::safety::command_is_allowed(&e.command, &cfg.command_allowlist) {
+        return r;
+    }
+    let timeout = e.timeout_secs.unwrap_or(cfg.timeout_secs);
+    match run_command_allowlisted(&e.command, cfg, e.cwd.as_deref(), timeout) {
+        Ok(out) => {
+            r.duration_ms = out.duration_ms;
+            r.status = if out.timed_out {
+                VerifyStatus::Timeout
+            } else if out.status_code == 0 {
+                VerifyStatus::Pass
+            } else {
+                VerifyStatus::Fail
+            };
+            if r.status != VerifyStatus::Pass {
+                let src = if out.stderr.trim().is_empty() { &out.stdout } else { &out.stderr };
+                r.output_tail = Some(tail(src, 15));
+            }
+        }
+        Err(e) => {
+            r.status = VerifyStatus::Fail;
+            r.output_tail = Some(tail(&format!("{:#}", e), 15));
+        }
+    }
+    r
+}
+
+/// Run the configured verification matrix, in parallel when `cfg.verify_parallel`.
+pub fn run_matrix(cfg: &Config) -> VerifyReport {
+    let results: Vec<VerifyResult> = if cfg.verify_parallel {
+        cfg.verify.par_iter().map(|e| run_entry(e, cfg)).collect()
+    } else {
+        cfg.verify.iter().map(|e| run_entry(e, cfg)).collect()
+    };
+    let verified = results
+        .iter()
+        .filter(|r| r.required)
+        .all(|r| r.status == VerifyStatus::Pass);
+    VerifyReport { results, verified }
+}
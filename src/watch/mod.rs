@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::context::embeddings;
+
+/// Directories whose changes never reach the index.
+const IGNORED_DIRS: &[&str] = &[".git", ".vibe", ".next", "node_modules", "target", "dist", "build", "out"];
+
+/// Source files the retrieval layer indexes.
+const INDEXED_EXTS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "json", "css", "scss", "md", "mdx"];
+
+/// Root-relative path if `abs` is a file the index should track.
+fn indexed_rel(root: &Path, vibe_out: &Path, abs: &Path) -> Option<String> {
+    if abs.starts_with(vibe_out) {
+        return None;
+    }
+    let rel = abs.strip_prefix(root).ok()?;
+    if rel.components().any(|c| IGNORED_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref())) {
+        return None;
+    }
+    let ext = rel.extension()?.to_str()?;
+    INDEXED_EXTS.contains(&ext).then(|| crate::safety::normalize_rel(&rel.to_string_lossy()))
+}
+
+/// `vibe watch`: keep `embeddings.jsonl` in step with the working tree,
+/// re-chunking files (debounced by `watch_debounce_ms`) as they are saved or
+/// deleted. Runs until interrupted.
+pub fn run(root: &Path, vibe_out: &Path, cfg: &Config) -> Result<()> {
+    let root = root.canonicalize().with_context(|| format!("resolving {}", root.display()))?;
+    let vibe_out = if vibe_out.is_absolute() { vibe_out.to_path_buf() } else { std::env::current_dir()?.join(vibe_out) };
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(Duration::from_millis(cfg.watch_debounce_ms), tx)?;
+    debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+    println!("Watching {} (index: {}); Ctrl-C to stop.", root.display(), vibe_out.join("embeddings.jsonl").display());
+
+    for batch in rx {
+        let events = match batch {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("warning: watch error: {}", e);
+                continue;
+            }
+        };
+        let changed: BTreeSet<String> = events.iter().filter_map(|e| indexed_rel(&root, &vibe_out, &e.path)).collect();
+        if changed.is_empty() {
+            continue;
+        }
+        let changed: Vec<String> = changed.into_iter().collect();
+        match embeddings::reindex_files(&vibe_out, &root, &changed) {
+            Ok(n) => println!("reindexed {} ({} chunk(s))", changed.join(", "), n),
+            Err(e) => eprintln!("warning: reindex failed: {:#}", e),
+        }
+    }
+    Ok(())
+}
@@ -75,6 +75,30 @@ pub struct ContextSlice {
     /// NEW: actual file contents provided to the model
     #[serde(default)]
     pub files_snapshot: Vec<FileBlob>,
+    /// Recent commits and uncommitted changes (config `git_context`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitContext>,
+}
+
+/// Where the repository stands: what was done lately and what is in flight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitContext {
+    /// `git log --oneline`, newest first.
+    pub log: Vec<String>,
+    /// `git diff HEAD` of the snapshot files: uncommitted work the snapshot already contains.
+    pub diff: String,
+    #[serde(default)]
+    pub diff_truncated: bool,
+}
+
+/// Format the model is asked to reply in; `yaml` suits some local models
+/// better. Responses are parsed into the same `LlmResponse` either way.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    Yaml,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +112,50 @@ pub struct LlmRequest {
     pub capabilities: Vec<String>,
     pub safety: Safety,
     pub instruction: Instruction,
+    /// Transport detail for the provider adapter; never sent to the model.
+    #[serde(skip)]
+    pub wire_format: WireFormat,
+    #[serde(skip)]
+    pub sampling: Sampling,
+    /// Send as a continuation of an earlier exchange (see `FollowUp`).
+    #[serde(skip)]
+    pub follow_up: Option<FollowUp>,
+}
+
+/// A re-ask continuing an earlier request's conversation. Chat adapters send
+/// `previous` exactly as before, its reply as the assistant turn and `text`
+/// as a new user turn, so the unchanged prefix is served from provider
+/// prompt caches (and local servers' prefix caches) instead of being paid
+/// for again. Adapters without turns send the request itself.
+#[derive(Debug, Clone)]
+pub struct FollowUp {
+    pub previous: Box<LlmRequest>,
+    pub reply: String,
+    pub text: String,
+}
+
+/// Sampling knobs (`--temperature`, `--top-p`, `--max-output-tokens`); unset
+/// fields keep each adapter's own default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+}
+
+impl Sampling {
+    /// Sampling for repair attempt `attempt` (0-based) of `attempts`: the
+    /// first keeps the configured temperature, later ones step it down
+    /// linearly so the last attempt runs at 0.
+    pub fn for_attempt(self, attempt: usize, attempts: usize) -> Self {
+        if attempt == 0 {
+            return self;
+        }
+        let base = self.temperature.unwrap_or(0.0);
+        let left = attempts.saturating_sub(attempt + 1) as f32;
+        let steps = attempts.saturating_sub(1).max(1) as f32;
+        Self { temperature: Some(base * left / steps), ..self }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,6 +168,9 @@ pub struct Answer {
 pub struct Plan {
     pub summary: String,
     pub steps: Vec<Step>,
+    /// Tools the plan needs, e.g. `node>=18`, `git`, `docker`; checked before apply.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
 }
 
 impl Default for Plan {
@@ -107,6 +178,7 @@ impl Default for Plan {
         Self {
             summary: String::new(),
             steps: Vec::new(),
+            requires: Vec::new(),
         }
     }
 }
@@ -118,6 +190,8 @@ pub enum Step {
     Create {
         id: String,
         title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
         path: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         language: Option<String>,
@@ -127,6 +201,8 @@ pub enum Step {
     Update {
         id: String,
         title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
         path: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         patch: Option<String>,
@@ -136,11 +212,15 @@ pub enum Step {
     Delete {
         id: String,
         title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
         path: String,
     },
     Command {
         id: String,
         title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
         command: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         cwd: Option<String>,
@@ -148,10 +228,63 @@ pub enum Step {
     Test {
         id: String,
         title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
         command: String,
     },
 }
 
+impl Step {
+    pub fn id(&self) -> &str {
+        match self {
+            Step::Create { id, .. }
+            | Step::Update { id, .. }
+            | Step::Delete { id, .. }
+            | Step::Command { id, .. }
+            | Step::Test { id, .. } => id,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            Step::Create { title, .. }
+            | Step::Update { title, .. }
+            | Step::Delete { title, .. }
+            | Step::Command { title, .. }
+            | Step::Test { title, .. } => title,
+        }
+    }
+
+    /// File path for file steps, command line for command/test steps.
+    pub fn target(&self) -> &str {
+        match self {
+            Step::Create { path, .. } | Step::Update { path, .. } | Step::Delete { path, .. } => path,
+            Step::Command { command, .. } | Step::Test { command, .. } => command,
+        }
+    }
+
+    /// Optional feature/phase label used to group large plans.
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            Step::Create { group, .. }
+            | Step::Update { group, .. }
+            | Step::Delete { group, .. }
+            | Step::Command { group, .. }
+            | Step::Test { group, .. } => group.as_deref(),
+        }
+    }
+
+    pub fn set_group(&mut self, g: String) {
+        match self {
+            Step::Create { group, .. }
+            | Step::Update { group, .. }
+            | Step::Delete { group, .. }
+            | Step::Command { group, .. }
+            | Step::Test { group, .. } => *group = Some(g),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmResponse {
     pub schema_version: String,
@@ -160,4 +293,224 @@ pub struct LlmResponse {
     pub plan: Option<Plan>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub answer: Option<Answer>,
+    /// Filled in by the provider layer, never by the model.
+    #[serde(skip)]
+    pub usage: Usage,
+}
+
+/// Provider-reported metadata for one request (see `provider::usage`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    /// Model that answered, as reported by the provider when it says.
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    /// Wall time including retries and rate-limit waits.
+    pub duration_ms: u64,
+    pub retries: u32,
+}
+
+impl LlmResponse {
+    /// An explanation plus a non-empty plan ("you already have X; here's a
+    /// small tweak"), whichever `kind` the model set.
+    pub fn is_hybrid(&self) -> bool {
+        self.answer.is_some() && self.plan.as_ref().is_some_and(|p| !p.steps.is_empty())
+    }
+}
+
+/// JSON Schema for `Plan` in the closed, all-required form strict structured
+/// outputs accept: optional fields as `[T, "null"]`, `Step` as an `anyOf`
+/// over its `action` tags. Mirrors the types above; keep them in sync.
+pub fn plan_json_schema() -> Value {
+    use serde_json::json;
+    let nullable_str = json!({ "type": ["string", "null"] });
+    let step = |action: &str, fields: &[(&str, Value)]| {
+        let mut props = serde_json::Map::new();
+        props.insert("id".into(), json!({ "type": "string" }));
+        props.insert("title".into(), json!({ "type": "string" }));
+        props.insert("action".into(), json!({ "type": "string", "enum": [action] }));
+        props.insert("group".into(), nullable_str.clone());
+        for (k, v) in fields {
+            props.insert((*k).into(), v.clone());
+        }
+        let required: Vec<&String> = props.keys().collect();
+        json!({ "type": "object", "properties": props, "required": required, "additionalProperties": false })
+    };
+    let s = json!({ "type": "string" });
+    let steps = json!([
+        step("create", &[("path", s.clone()), ("language", nullable_str.clone()), ("content", nullable_str.clone())]),
+        step("update", &[("path", s.clone()), ("patch", nullable_str.clone()), ("content", nullable_str.clone())]),
+        step("delete", &[("path", s.clone())]),
+        step("command", &[("command", s.clone()), ("cwd", nullable_str.clone())]),
+        step("test", &[("command", s)]),
+    ]);
+    json!({
+        "type": "object",
+        "properties": {
+            "summary": { "type": "string" },
+            "steps": { "type": "array", "items": { "anyOf": steps } },
+            "requires": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["summary", "steps", "requires"],
+        "additionalProperties": false
+    })
+}
+
+pub fn answer_json_schema() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": { "title": { "type": "string" }, "content": { "type": "string" } },
+        "required": ["title", "content"],
+        "additionalProperties": false
+    })
+}
+
+/// JSON Schema for `LlmResponse` (strict structured-output form, see
+/// `plan_json_schema`).
+pub fn response_json_schema() -> Value {
+    use serde_json::json;
+    json!({
+        "type": "object",
+        "properties": {
+            "schema_version": { "type": "string" },
+            "kind": { "type": "string", "enum": ["plan", "answer"] },
+            "plan": { "anyOf": [plan_json_schema(), { "type": "null" }] },
+            "answer": { "anyOf": [answer_json_schema(), { "type": "null" }] }
+        },
+        "required": ["schema_version", "kind", "plan", "answer"],
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// The subset of JSON Schema `response_json_schema` uses: `type`,
+    /// `enum`, `properties`/`required`/`additionalProperties`, `items`, `anyOf`.
+    fn conforms(schema: &Value, v: &Value, at: &str) -> Result<(), String> {
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            return match any.iter().any(|s| conforms(s, v, at).is_ok()) {
+                true => Ok(()),
+                false => Err(format!("{}: matches no anyOf branch: {}", at, v)),
+            };
+        }
+        if let Some(t) = schema.get("type") {
+            let types: Vec<&str> = match t {
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                t => t.as_str().into_iter().collect(),
+            };
+            let is = |t: &str| match t {
+                "object" => v.is_object(),
+                "array" => v.is_array(),
+                "string" => v.is_string(),
+                "null" => v.is_null(),
+                _ => false,
+            };
+            if !types.iter().any(|t| is(t)) {
+                return Err(format!("{}: {} is not {:?}", at, v, types));
+            }
+        }
+        if let Some(e) = schema.get("enum").and_then(Value::as_array) {
+            if !e.contains(v) {
+                return Err(format!("{}: {} not in {:?}", at, v, e));
+            }
+        }
+        if let (Some(props), Some(obj)) = (schema.get("properties").and_then(Value::as_object), v.as_object()) {
+            for r in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !obj.contains_key(r) {
+                    return Err(format!("{}: missing required '{}'", at, r));
+                }
+            }
+            for (k, val) in obj {
+                match props.get(k) {
+                    Some(s) => conforms(s, val, &format!("{}.{}", at, k))?,
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        return Err(format!("{}: unexpected property '{}'", at, k))
+                    }
+                    None => {}
+                }
+            }
+        }
+        if let (Some(items), Some(arr)) = (schema.get("items"), v.as_array()) {
+            for (i, item) in arr.iter().enumerate() {
+                conforms(items, item, &format!("{}[{}]", at, i))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn every_step() -> Vec<Step> {
+        let s = |v: &str| v.to_string();
+        let g = Some(s("ui"));
+        vec![
+            Step::Create { id: s("1"), title: s("t"), group: g.clone(), path: s("a.tsx"), language: Some(s("tsx")), content: Some(s("x")) },
+            Step::Update { id: s("2"), title: s("t"), group: g.clone(), path: s("b.tsx"), patch: Some(s("@@")), content: Some(s("y")) },
+            Step::Delete { id: s("3"), title: s("t"), group: g.clone(), path: s("c.tsx") },
+            Step::Command { id: s("4"), title: s("t"), group: g.clone(), command: s("npm i zod"), cwd: Some(s(".")) },
+            Step::Test { id: s("5"), title: s("t"), group: g, command: s("npm test") },
+        ]
+    }
+
+    #[test]
+    fn response_with_every_field_conforms_to_schema() {
+        let resp = LlmResponse {
+            schema_version: "v1".into(),
+            kind: Kind::Plan,
+            plan: Some(Plan { summary: "s".into(), steps: every_step(), requires: vec!["node>=18".into()] }),
+            answer: Some(Answer { title: "t".into(), content: "c".into() }),
+            usage: Usage::default(),
+        };
+        let v = serde_json::to_value(&resp).unwrap();
+        conforms(&response_json_schema(), &v, "$").unwrap();
+    }
+
+    #[test]
+    fn strict_reply_with_nulls_parses_and_round_trips() {
+        let reply = json!({
+            "schema_version": "v1",
+            "kind": "plan",
+            "plan": {
+                "summary": "s",
+                "steps": [
+                    { "id": "1", "title": "t", "action": "create", "group": null, "path": "a.tsx", "language": null, "content": "x" },
+                    { "id": "2", "title": "t", "action": "update", "group": null, "path": "b.tsx", "patch": null, "content": "y" },
+                    { "id": "3", "title": "t", "action": "delete", "group": null, "path": "c.tsx" },
+                    { "id": "4", "title": "t", "action": "command", "group": null, "command": "npm i", "cwd": null },
+                    { "id": "5", "title": "t", "action": "test", "group": null, "command": "npm test" }
+                ],
+                "requires": []
+            },
+            "answer": null
+        });
+        conforms(&response_json_schema(), &reply, "$").unwrap();
+        let resp: LlmResponse = serde_json::from_value(reply).unwrap();
+        assert_eq!(resp.plan.as_ref().map(|p| p.steps.len()), Some(5));
+        let again: LlmResponse = serde_json::from_value(serde_json::to_value(&resp).unwrap()).unwrap();
+        assert_eq!(again.plan.map(|p| p.steps.len()), Some(5));
+    }
+
+    #[test]
+    fn schema_rejects_unknown_actions_and_fields() {
+        let schema = response_json_schema();
+        let mut v = serde_json::to_value(LlmResponse {
+            schema_version: "v1".into(),
+            kind: Kind::Plan,
+            plan: Some(Plan { summary: "s".into(), steps: every_step(), requires: vec![] }),
+            answer: None,
+            usage: Usage::default(),
+        })
+        .unwrap();
+        v["answer"] = Value::Null;
+        v["plan"]["requires"] = json!([]);
+        conforms(&schema, &v, "$").unwrap();
+        v["plan"]["steps"][4]["action"] = json!("deploy");
+        assert!(conforms(&schema, &v, "$").is_err());
+        v["plan"]["steps"][4]["action"] = json!("test");
+        v["plan"]["steps"][4]["extra"] = json!(1);
+        assert!(conforms(&schema, &v, "$").is_err());
+    }
 }